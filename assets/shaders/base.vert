@@ -2,18 +2,22 @@
 layout (location = 0) in vec3 pos;
 layout (location = 1) in vec2 uv;
 layout (location = 2) in vec3 normal;
+layout (location = 5) in mat4 instance_model;
 
 uniform mat4 model;
+uniform bool instanced;
 uniform mat4 view;
 uniform mat4 projection;
+uniform vec2 uv_offset;
 
 out vec3 v_pos;
 out vec2 v_uv;
 out vec3 v_normal;
 
 void main() {
-    v_pos = (model * vec4(pos, 1.0)).xyz;
-    v_uv = uv;
-    v_normal = mat3(transpose(inverse(model))) * normal;  
-    gl_Position = projection * view * model * vec4(pos, 1.0);
+    mat4 world = instanced ? instance_model : model;
+    v_pos = (world * vec4(pos, 1.0)).xyz;
+    v_uv = uv + uv_offset;
+    v_normal = mat3(transpose(inverse(world))) * normal;  
+    gl_Position = projection * view * world * vec4(pos, 1.0);
 }
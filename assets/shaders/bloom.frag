@@ -0,0 +1,12 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform sampler2D bloom;
+uniform float intensity;
+
+out vec4 f_color;
+
+void main() {
+	f_color = vec4(texture(tex, uv).rgb + texture(bloom, uv).rgb * intensity, 1.0);
+}
@@ -0,0 +1,14 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform float threshold;
+
+out vec4 f_color;
+
+void main() {
+	vec3 color = texture(tex, uv).rgb;
+	float brightness = dot(color, vec3(0.2126, 0.7152, 0.0722));
+	// soft knee so highlights fade in rather than pop
+	f_color = vec4(color * max(brightness - threshold, 0.0) / max(brightness, 0.0001), 1.0);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform bool horizontal;
+
+out vec4 f_color;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+	vec2 texel_size = 1.0 / textureSize(tex, 0);
+	vec2 dir = horizontal ? vec2(texel_size.x, 0.0) : vec2(0.0, texel_size.y);
+	vec3 color = texture(tex, uv).rgb * weights[0];
+	for (int i = 1; i < 5; i++) {
+		color += texture(tex, uv + dir * i).rgb * weights[i];
+		color += texture(tex, uv - dir * i).rgb * weights[i];
+	}
+	f_color = vec4(color, 1.0);
+}
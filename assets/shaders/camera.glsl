@@ -0,0 +1,8 @@
+// written once per view by the scene renderer, laid out like CameraUniforms
+layout (std140) uniform camera {
+	mat4 view;
+	mat4 projection;
+	vec3 cam_pos;
+	// scales everything written to the hdr target
+	float exposure;
+};
@@ -0,0 +1,35 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+// 1 protanopia, 2 deuteranopia, 3 tritanopia
+uniform int mode;
+uniform float strength;
+
+out vec4 f_color;
+
+// daltonization, simulates what's lost in lms space then moves that difference into the channels
+// that can still be seen
+void main() {
+	vec3 c = texture(tex, uv).rgb;
+	vec3 lms = vec3(
+		dot(c, vec3(17.8824, 43.5161, 4.11935)),
+		dot(c, vec3(3.45565, 27.1554, 3.86714)),
+		dot(c, vec3(0.0299566, 0.184309, 1.46709))
+	);
+	if (mode == 1) {
+		lms.x = 2.02344 * lms.y - 2.52581 * lms.z;
+	} else if (mode == 2) {
+		lms.y = 0.494207 * lms.x + 1.24827 * lms.z;
+	} else {
+		lms.z = -0.395913 * lms.x + 0.801109 * lms.y;
+	}
+	vec3 sim = vec3(
+		dot(lms, vec3(0.0809444479, -0.130504409, 0.116721066)),
+		dot(lms, vec3(-0.0102485335, 0.0540193266, -0.113614708)),
+		dot(lms, vec3(-0.000365296938, -0.00412161469, 0.693511405))
+	);
+	vec3 err = c - sim;
+	vec3 shift = vec3(0.0, 0.7 * err.r + err.g, 0.7 * err.r + err.b);
+	f_color = vec4(max(c + shift * strength, 0.0), 1.0);
+}
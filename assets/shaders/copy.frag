@@ -0,0 +1,10 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+
+out vec4 f_color;
+
+void main() {
+	f_color = vec4(texture(tex, uv).rgb, 1.0);
+}
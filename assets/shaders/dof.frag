@@ -0,0 +1,51 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform sampler2D galbedo;
+uniform sampler2D gposition;
+uniform vec3 cam_pos;
+uniform float focus;
+// circle of confusion in pixels for something infinitely far away
+uniform float coc_scale;
+uniform float max_radius;
+
+out vec4 f_color;
+
+const float GOLDEN_ANGLE = 2.39996323;
+// spacing between rings of samples in pixels, lower is smoother and slower
+const float RING_STEP = 1.5;
+
+float depth(vec2 at) {
+	// the sky is infinitely far away
+	return texture(galbedo, at).a > 0 ? distance(cam_pos, texture(gposition, at).xyz) : 1e6;
+}
+
+float coc(float d) {
+	return min(coc_scale * abs(d - focus) / d, max_radius);
+}
+
+// gathers a spiral of samples, each one counts if its own blur reaches this pixel. samples behind
+// are kept from bleeding over sharper ones in front
+void main() {
+	vec2 texel_size = 1.0 / textureSize(tex, 0);
+	float center_depth = depth(uv);
+	float center_coc = coc(center_depth);
+	vec3 color = texture(tex, uv).rgb;
+	float total = 1.0;
+	float radius = RING_STEP;
+	for (float angle = 0.0; radius < max_radius; angle += GOLDEN_ANGLE) {
+		vec2 at = uv + vec2(cos(angle), sin(angle)) * texel_size * radius;
+		vec3 sample_color = texture(tex, at).rgb;
+		float sample_depth = depth(at);
+		float sample_coc = coc(sample_depth);
+		if (sample_depth > center_depth) {
+			sample_coc = min(sample_coc, center_coc * 2.0);
+		}
+		float m = smoothstep(radius - 0.5, radius + 0.5, sample_coc);
+		color += mix(color / total, sample_color, m);
+		total += 1.0;
+		radius += RING_STEP / radius;
+	}
+	f_color = vec4(color / total, 1.0);
+}
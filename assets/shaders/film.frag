@@ -0,0 +1,34 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform float vignette;
+uniform float vignette_radius;
+uniform float grain;
+uniform float aberration;
+uniform float seed;
+
+out vec4 f_color;
+
+float hash(vec3 p) {
+	return fract(sin(dot(p, vec3(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+void main() {
+	vec2 size = textureSize(tex, 0);
+	// red and blue split outwards from the center, further toward the edges
+	vec2 offset = (uv - 0.5) * 2.0 * aberration / size;
+	vec3 color = vec3(
+		texture(tex, uv - offset).r,
+		texture(tex, uv).g,
+		texture(tex, uv + offset).b
+	);
+	// 0 at the center and 1 in the corners
+	float d = distance(uv, vec2(0.5)) * sqrt(2.0);
+	color *= 1.0 - vignette * smoothstep(1.0 - vignette_radius, 1.0, d);
+	// strongest in the midtones, like film
+	float luma = dot(color, vec3(0.299, 0.587, 0.114));
+	float noise = hash(vec3(gl_FragCoord.xy, seed)) - 0.5;
+	color += noise * grain * (1.0 - abs(luma * 2.0 - 1.0));
+	f_color = vec4(max(color, 0.0), 1.0);
+}
@@ -0,0 +1,37 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+
+out vec4 f_color;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+	return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// blends across the local edge direction, rejecting samples that overshoot the neighbourhood
+void main() {
+	vec2 texel_size = 1.0 / textureSize(tex, 0);
+	vec3 color = texture(tex, uv).rgb;
+	float m = luma(color);
+	float nw = luma(texture(tex, uv + vec2(-1.0, -1.0) * texel_size).rgb);
+	float ne = luma(texture(tex, uv + vec2(1.0, -1.0) * texel_size).rgb);
+	float sw = luma(texture(tex, uv + vec2(-1.0, 1.0) * texel_size).rgb);
+	float se = luma(texture(tex, uv + vec2(1.0, 1.0) * texel_size).rgb);
+	float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+	float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+	vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+	float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+	float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+	dir = clamp(dir * scale, -SPAN_MAX, SPAN_MAX) * texel_size;
+
+	vec3 a = 0.5 * (texture(tex, uv + dir * (1.0 / 3.0 - 0.5)).rgb + texture(tex, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+	vec3 b = a * 0.5 + 0.25 * (texture(tex, uv - dir * 0.5).rgb + texture(tex, uv + dir * 0.5).rgb);
+	float luma_b = luma(b);
+	f_color = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b, 1.0);
+}
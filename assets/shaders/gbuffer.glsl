@@ -0,0 +1,63 @@
+in vec3 v_pos;
+in vec2 v_uv;
+
+uniform vec3 albedo;
+uniform float metallic;
+uniform float roughness;
+uniform float ao;
+uniform float emissive;
+uniform sampler2D albedo_tex;
+uniform sampler2D metallic_tex;
+uniform sampler2D roughness_tex;
+uniform sampler2D normal_tex;
+uniform sampler2D ao_tex;
+uniform bool use_albedo_tex;
+uniform bool use_metallic_tex;
+uniform bool use_roughness_tex;
+uniform bool use_ao_tex;
+// lod cross-fade, only fragments whose dither value falls in the range are kept
+uniform bool use_lod_fade;
+uniform vec2 lod_fade;
+
+layout(location = 0) out vec4 galbedo;
+layout(location = 1) out vec4 gposition;
+layout(location = 2) out vec4 gnormal;
+layout(location = 3) out vec4 gmaterial;
+
+// textures are loaded as srgb, undo it for non color data
+vec3 linear_sample(sampler2D tex) {
+	return pow(texture(tex, v_uv).rgb, vec3(1.0 / 2.2));
+}
+
+float bayer4(ivec2 p) {
+	const float m[16] = float[](0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+	return (m[(p.y & 3) * 4 + (p.x & 3)] + 0.5) / 16.0;
+}
+
+void write_gbuffer(vec3 normal) {
+	if (use_lod_fade) {
+		float d = bayer4(ivec2(gl_FragCoord.xy));
+		if (d < lod_fade.x || d >= lod_fade.y) {
+			discard;
+		}
+	}
+	galbedo = vec4(albedo, 1.0);
+	if (use_albedo_tex) {
+		galbedo *= texture(albedo_tex, v_uv);
+	}
+	float m = metallic;
+	if (use_metallic_tex) {
+		m *= linear_sample(metallic_tex).r;
+	}
+	float r = roughness;
+	if (use_roughness_tex) {
+		r *= linear_sample(roughness_tex).r;
+	}
+	float o = ao;
+	if (use_ao_tex) {
+		o *= linear_sample(ao_tex).r;
+	}
+	gposition = vec4(v_pos, 1.0);
+	gnormal = vec4(normal, 1.0);
+	gmaterial = vec4(r, m, o, emissive);
+}
@@ -7,24 +7,62 @@ uniform sampler2D gnormal;
 uniform sampler2D gmaterial;
 uniform sampler2D ssao_tex;
 uniform sampler2D shadow_map;
-uniform mat4 view;
-uniform mat4 projection;
-uniform vec3 cam_pos;
+#include "camera.glsl"
 uniform vec3 sun_dir;
-uniform mat4 sun_view;
-uniform mat4 sun_projection;
-uniform int tonemap;
+uniform mat4 cascades[4];
+uniform float cascade_far[4];
+uniform float cascade_bias[4];
+uniform int num_cascades;
+uniform bool debug_cascades;
 
-struct light_t {
-	vec3 pos;
-	vec3 color;
-	float strength;
-};
-uniform light_t lights[100];
-uniform int num_lights;
+#include "pbr.glsl"
+// only the deferred pass binds the cubemaps
+#define POINT_SHADOWS
+#include "lights.glsl"
 
 out vec4 f_color;
 
+const vec3 cascade_colors[4] = vec3[](
+	vec3(1.0, 0.3, 0.3),
+	vec3(0.3, 1.0, 0.3),
+	vec3(0.3, 0.3, 1.0),
+	vec3(1.0, 1.0, 0.3)
+);
+
+// the first cascade reaching `depth`, num_cascades past the last one
+int cascade_index(float depth) {
+	for (int i = 0; i < num_cascades; i++) {
+		if (depth < cascade_far[i]) {
+			return i;
+		}
+	}
+	return num_cascades;
+}
+
+float sun_shadow(vec3 pos, int cascade) {
+	if (cascade >= num_cascades) {
+		return 0.0;
+	}
+	vec4 light_pos = cascades[cascade] * vec4(pos, 1.0);
+	light_pos = light_pos * 0.5 + 0.5;
+	if (light_pos.z > 1.0) {
+		return 0.0;
+	}
+	// each cascade is a quarter of the atlas, samples are kept inside its tile
+	vec2 tile = vec2(cascade % 2, cascade / 2) * 0.5;
+	vec2 texel_size = 1.0 / textureSize(shadow_map, 0);
+	float current = light_pos.z - cascade_bias[cascade];
+	float shadow = 0.0;
+	for (int x = -1; x <= 1; ++x) {
+		for (int y = -1; y <= 1; ++y) {
+			vec2 offset = clamp(light_pos.xy * 0.5 + vec2(x, y) * texel_size, texel_size, 0.5 - texel_size);
+			float closest = texture(shadow_map, tile + offset).r;
+			shadow += current > closest ? 1.0 : 0.0;
+		}
+	}
+	return shadow / 9.0;
+}
+
 vec2 raymarch(vec3 pos, vec3 dir) {
 	dir *= 0.05;
 	vec4 projected;
@@ -57,62 +95,32 @@ vec2 raymarch(vec3 pos, vec3 dir) {
 	return projected.xy;
 }
 
-vec3 calc_light(vec3 pos, vec3 normal, vec3 dir, vec3 color, float spec, float atten) {
- 	float diffuse = max(dot(normal, dir), 0.0);
-	float specular = pow(max(dot(normalize(cam_pos - pos), normalize(-reflect(dir, normal))), 0.0), 16.0) * spec;
-	diffuse *= atten;
-	specular *= atten;
-	return vec3(diffuse + specular) * color;
-}
-
-vec3 uncharted2(vec3 x) {
-	const float A = 0.15;
-	const float B = 0.50;
-	const float C = 0.10;
-	const float D = 0.20;
-	const float E = 0.02;
-	const float F = 0.30;
-	const float W = 11.2;
-	return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
-}
-
 void main(){
 	vec4 albedo = texture(galbedo, uv);
-	vec3 color = albedo.rgb;
+	// the sky, scaled like the ambient
+	vec3 color = albedo.rgb * sun_intensity / PI;
 	if (albedo.a > 0) {
 		vec3 pos = texture(gposition,uv).xyz;
 		vec3 view_pos =  (view * vec4(pos, 1.0)).xyz;
 		vec3 normal = normalize(texture(gnormal, uv).xyz);
 		vec3 view_normal = (mat3(view) * normal).xyz;
 		vec4 material = texture(gmaterial, uv);
-		float spec = material.x;
+		float roughness = material.x;
 		float metallic = material.y;
+		float ao = material.z;
 		vec3 reflected = normalize(reflect(normalize(view_pos), view_normal));
+		vec3 v = normalize(cam_pos - pos);
 
-		vec3 light = calc_light(pos, normal, sun_dir, albedo.rgb, spec, 1.0);
-		for (int i = 0; i < num_lights; i++) {
-			vec3 dir = lights[i].pos - pos;
-			float distance = length(dir);
-			light += calc_light(pos,normal,dir, lights[i].color * albedo.rgb, spec, 1.0 / (pow(distance / lights[i].strength, 2.0) + 1.0));
-		}
-		
-		vec4 light_pos = sun_projection * sun_view * vec4(pos, 1.0);
-	    light_pos = light_pos * 0.5 + 0.5;
-	    float current = light_pos.z - 0.0015;
-	    float shadow = 0.0;
-	    float closest = texture(shadow_map, light_pos.xy).r;
-	    vec2 texel_size = 1.0 / textureSize(shadow_map, 0);
-	    for (int x = -1; x <= 1; ++x) {
-	    	for (int y = -1; y <= 1; ++y) {
-	    		float closest = texture(shadow_map, light_pos.xy + vec2(x, y) * texel_size).r;
-	    		shadow += current > closest ? 1.0 : 0.0;
-	    	}
-	    }
-		shadow /= 9;
+		vec3 light = pbr_light(albedo.rgb, metallic, roughness, normal, v, normalize(sun_dir), vec3(sun_intensity));
+		int cascade = cascade_index(-view_pos.z);
+		float shadow = sun_shadow(pos, cascade);
 		light *= (1 - vec3(shadow));
+		// point lights have their own shadows, the sun's shouldn't darken them
+		light += point_lights(albedo.rgb, metallic, roughness, pos, normal, v);
 		
-		light += albedo.rgb * 0.1;
-		texel_size = 1.0 / textureSize(ssao_tex, 0);
+		// ambient follows the sun so a sun in lux stays balanced
+		light += albedo.rgb * ambient * ao * sun_intensity / PI;
+		vec2 texel_size = 1.0 / textureSize(ssao_tex, 0);
 		float ssao = 0.0;
 		for (int x = -2; x <= 2; ++x) {
 			for (int y = -2; y <= 2; ++y) {
@@ -122,29 +130,14 @@ void main(){
 		ssao /= 25.0;
 		light *= ssao;
 		vec2 coords = raymarch(view_pos, reflected);
-		float reflection_multiplier = clamp(pow(metallic, 3) * -reflected.z, 0.0, 0.9);
+		float reflection_multiplier = clamp(pow(metallic, 3) * (1.0 - roughness) * -reflected.z, 0.0, 0.9);
 		color = light + texture(galbedo, coords).rgb * reflection_multiplier;
+		// emission isn't darkened by shadows or ssao
+		color += albedo.rgb * material.w;
+		color = apply_fog(color, pos);
+		if (debug_cascades && cascade < num_cascades) {
+			color *= cascade_colors[cascade];
+		}
 	}
-	switch (tonemap) {
-		case 0: // aces
-			const float a = 2.51;
-			const float b = 0.03;
-			const float c = 2.43;
-			const float d = 0.59;
-			const float e = 0.14;
-			color = clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
-			break;
-		case 1: // filmic
-			color = max(vec3(0.0), color - 0.004);
-			color = (color * (6.2 * color + 0.5)) / (color * (6.2 * color + 1.7) + 0.06);
-			color = pow(color, vec3(2.2));
-			break;
-		case 2: // reinhard
-			color = color / (1.0 + color);
-			break;
-		case 3: // uncharted2
-			color = uncharted2(color * 2.0) / uncharted2(vec3(11.2));
-			break;
-	}
-	f_color = vec4(color, 1.0);
+	f_color = vec4(color * exposure, 1.0);
 }
@@ -0,0 +1,74 @@
+struct light_t {
+	vec3 pos;
+	vec3 color;
+	float strength;
+	// index into point_shadows plus one, 0 when unshadowed
+	int shadow;
+	float shadow_far;
+};
+// written once per view by the scene renderer, laid out like LightUniforms
+layout (std140) uniform light_data {
+	light_t lights[100];
+	int num_lights;
+	// the sun's illuminance, pi unless it's set in lux
+	float sun_intensity;
+	// scaled by the sun like the sky, see WorldEnvironment
+	vec3 ambient;
+	float fog_density;
+	vec3 fog_color;
+	float fog_start;
+};
+
+// exponential past fog_start, lit like the sky
+vec3 apply_fog(vec3 color, vec3 pos) {
+	float d = max(length(pos - cam_pos) - fog_start, 0.0);
+	return mix(color, fog_color * sun_intensity / PI, 1.0 - exp(-d * fog_density));
+}
+
+#ifdef POINT_SHADOWS
+uniform samplerCube point_shadows[4];
+
+const vec3 point_shadow_offsets[6] = vec3[](
+	vec3(1.0, 0.0, 0.0), vec3(-1.0, 0.0, 0.0),
+	vec3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0),
+	vec3(0.0, 0.0, 1.0), vec3(0.0, 0.0, -1.0)
+);
+
+// sampler arrays can only be indexed by constants in 330
+float point_shadow_depth(int i, vec3 dir) {
+	if (i == 0) return texture(point_shadows[0], dir).r;
+	if (i == 1) return texture(point_shadows[1], dir).r;
+	if (i == 2) return texture(point_shadows[2], dir).r;
+	return texture(point_shadows[3], dir).r;
+}
+
+// 0 lit to 1 shadowed, `dir` points from the light
+float point_shadow(int i, vec3 dir, float far) {
+	float distance = length(dir);
+	float current = (distance - 0.05) / far;
+	float radius = distance * 0.01;
+	float shadow = current > point_shadow_depth(i, dir) ? 1.0 : 0.0;
+	for (int j = 0; j < 6; j++) {
+		shadow += current > point_shadow_depth(i, dir + point_shadow_offsets[j] * radius) ? 1.0 : 0.0;
+	}
+	return shadow / 7.0;
+}
+#endif
+
+// point light contribution at pos, shared by the deferred and forward paths
+vec3 point_lights(vec3 albedo, float metallic, float roughness, vec3 pos, vec3 n, vec3 v) {
+	vec3 light = vec3(0.0);
+	for (int i = 0; i < num_lights; i++) {
+		vec3 dir = lights[i].pos - pos;
+		float distance = length(dir);
+		float atten = 1.0 / (pow(distance / lights[i].strength, 2.0) + 1.0);
+		vec3 radiance = lights[i].color * PI * atten;
+#ifdef POINT_SHADOWS
+		if (lights[i].shadow > 0) {
+			radiance *= 1.0 - point_shadow(lights[i].shadow - 1, -dir, lights[i].shadow_far);
+		}
+#endif
+		light += pbr_light(albedo, metallic, roughness, n, v, normalize(dir), radiance);
+	}
+	return light;
+}
@@ -0,0 +1,27 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform sampler2D velocity;
+uniform float strength;
+uniform int samples;
+
+out vec4 f_color;
+
+// fast turns are capped to this much of the screen
+const float MAX_LENGTH = 0.05;
+
+void main() {
+	vec2 v = texture(velocity, uv).xy * strength;
+	float len = length(v);
+	if (len > MAX_LENGTH) {
+		v *= MAX_LENGTH / len;
+	}
+	vec3 color = vec3(0.0);
+	for (int i = 0; i < samples; i++) {
+		// centered on the pixel, half the shutter either side
+		float t = float(i) / float(max(samples - 1, 1)) - 0.5;
+		color += texture(tex, uv - v * t).rgb;
+	}
+	f_color = vec4(color / float(samples), 1.0);
+}
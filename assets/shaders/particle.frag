@@ -0,0 +1,29 @@
+#version 330 core
+in vec3 v_pos;
+in vec2 v_uv;
+in vec4 v_color;
+
+uniform sampler2D tex;
+uniform bool use_tex;
+uniform sampler2D galbedo;
+uniform sampler2D gposition;
+#include "camera.glsl"
+uniform float softness;
+
+out vec4 f_color;
+
+void main() {
+	vec4 color = v_color;
+	if (use_tex) {
+		color *= texture(tex, v_uv);
+	} else {
+		color.a *= 1.0 - smoothstep(0.0, 1.0, length(v_uv * 2.0 - 1.0));
+	}
+	// fade out near geometry behind the particle, this also hides particles behind it
+	vec2 screen_uv = gl_FragCoord.xy / textureSize(galbedo, 0);
+	if (texture(galbedo, screen_uv).a > 0) {
+		float scene = distance(cam_pos, texture(gposition, screen_uv).xyz);
+		color.a *= clamp((scene - distance(cam_pos, v_pos)) / softness, 0.0, 1.0);
+	}
+	f_color = vec4(color.rgb * exposure, color.a);
+}
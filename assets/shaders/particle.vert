@@ -0,0 +1,23 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 uv;
+// position and size, color, rotation, packed by the particle renderer
+layout (location = 5) in mat4 instance;
+
+#include "camera.glsl"
+
+out vec3 v_pos;
+out vec2 v_uv;
+out vec4 v_color;
+
+void main() {
+    float rot = instance[2].x;
+    vec2 offset = mat2(cos(rot), sin(rot), -sin(rot), cos(rot)) * pos.xy * instance[0].w * 0.5;
+    // camera right and up from the view matrix, so the quad always faces the screen
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    v_pos = instance[0].xyz + right * offset.x + up * offset.y;
+    v_uv = uv;
+    v_color = instance[1];
+    gl_Position = projection * view * vec4(v_pos, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+#ifdef HAS_NORMAL_MAP
+in mat3 v_tbn;
+#else
+in vec3 v_normal;
+#endif
+
+#include "gbuffer.glsl"
+
+void main() {
+#ifdef HAS_NORMAL_MAP
+	write_gbuffer(normalize(v_tbn * (linear_sample(normal_tex) * 2.0 - 1.0)));
+#else
+	write_gbuffer(normalize(v_normal));
+#endif
+}
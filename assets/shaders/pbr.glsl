@@ -0,0 +1,33 @@
+#define PI 3.141592
+
+float distribution_ggx(vec3 n, vec3 h, float roughness) {
+	float a = roughness * roughness;
+	float a2 = a * a;
+	float ndoth = max(dot(n, h), 0.0);
+	float denom = ndoth * ndoth * (a2 - 1.0) + 1.0;
+	return a2 / (PI * denom * denom);
+}
+
+float geometry_schlick_ggx(float ndotv, float roughness) {
+	float r = roughness + 1.0;
+	float k = (r * r) / 8.0;
+	return ndotv / (ndotv * (1.0 - k) + k);
+}
+
+float geometry_smith(vec3 n, vec3 v, vec3 l, float roughness) {
+	return geometry_schlick_ggx(max(dot(n, v), 0.0), roughness) * geometry_schlick_ggx(max(dot(n, l), 0.0), roughness);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+	return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 pbr_light(vec3 albedo, float metallic, float roughness, vec3 n, vec3 v, vec3 l, vec3 radiance) {
+	vec3 h = normalize(v + l);
+	vec3 f0 = mix(vec3(0.04), albedo, metallic);
+	vec3 f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+	float ndotl = max(dot(n, l), 0.0);
+	vec3 specular = distribution_ggx(n, h, roughness) * geometry_smith(n, v, l, roughness) * f / (4.0 * max(dot(n, v), 0.0) * ndotl + 0.0001);
+	vec3 kd = (vec3(1.0) - f) * (1.0 - metallic);
+	return (kd * albedo / PI + specular) * radiance * ndotl;
+}
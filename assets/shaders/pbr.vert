@@ -0,0 +1,41 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 uv;
+layout (location = 2) in vec3 normal;
+#ifdef HAS_NORMAL_MAP
+layout (location = 3) in vec3 tangent;
+layout (location = 4) in vec3 bitangent;
+#endif
+#ifdef INSTANCED
+layout (location = 5) in mat4 instance_model;
+#else
+uniform mat4 model;
+#endif
+
+#include "camera.glsl"
+uniform vec2 uv_offset;
+
+out vec3 v_pos;
+out vec2 v_uv;
+#ifdef HAS_NORMAL_MAP
+out mat3 v_tbn;
+#else
+out vec3 v_normal;
+#endif
+
+void main() {
+#ifdef INSTANCED
+    mat4 world = instance_model;
+#else
+    mat4 world = model;
+#endif
+    v_pos = (world * vec4(pos, 1.0)).xyz;
+    v_uv = uv + uv_offset;
+    mat3 normal_mat = mat3(transpose(inverse(world)));
+#ifdef HAS_NORMAL_MAP
+    v_tbn = mat3(normalize(normal_mat * tangent), normalize(normal_mat * bitangent), normalize(normal_mat * normal));
+#else
+    v_normal = normal_mat * normal;
+#endif
+    gl_Position = projection * view * world * vec4(pos, 1.0);
+}
@@ -0,0 +1,10 @@
+#version 330 core
+in vec3 world_pos;
+
+uniform vec3 light_pos;
+uniform float far;
+
+void main() {
+    // linear distance so it can be compared from any face
+    gl_FragDepth = length(world_pos - light_pos) / far;
+}
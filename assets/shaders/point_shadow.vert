@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 5) in mat4 instance_model;
+
+uniform mat4 model;
+uniform bool instanced;
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 world_pos;
+
+void main() {
+    mat4 world = instanced ? instance_model : model;
+    vec4 p = world * vec4(pos, 1.0);
+    world_pos = p.xyz;
+    gl_Position = projection * view * p;
+}
@@ -1,10 +1,13 @@
 #version 330 core
 layout (location = 0) in vec3 pos;
+layout (location = 5) in mat4 instance_model;
 
 uniform mat4 model;
+uniform bool instanced;
 uniform mat4 view;
 uniform mat4 projection;
 
 void main() {
-    gl_Position = projection * view * model * vec4(pos, 1.0);
+    mat4 world = instanced ? instance_model : model;
+    gl_Position = projection * view * world * vec4(pos, 1.0);
 }
@@ -2,6 +2,10 @@
 in vec3 v_pos;
 
 layout(location = 0) out vec4 galbedo;
+// written as the clear left them, outputs a shader leaves out are undefined
+layout(location = 1) out vec4 gposition;
+layout(location = 2) out vec4 gnormal;
+layout(location = 3) out vec4 gmaterial;
 
 uniform vec3 sun_dir;
 
@@ -130,4 +134,7 @@ void main() {
     0.758                           // Mie preferred scattering direction
   );
   galbedo = vec4(color, 0.0);
+  gposition = vec4(0.0, 0.0, 0.0, 1.0);
+  gnormal = vec4(0.0, 0.0, 0.0, 1.0);
+  gmaterial = vec4(0.0, 0.0, 0.0, 1.0);
 }
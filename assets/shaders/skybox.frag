@@ -0,0 +1,18 @@
+#version 330 core
+in vec3 v_pos;
+
+layout(location = 0) out vec4 galbedo;
+// written as the clear left them, outputs a shader leaves out are undefined
+layout(location = 1) out vec4 gposition;
+layout(location = 2) out vec4 gnormal;
+layout(location = 3) out vec4 gmaterial;
+
+uniform samplerCube skybox;
+uniform float intensity;
+
+void main() {
+  galbedo = vec4(texture(skybox, normalize(v_pos)).rgb * intensity, 0.0);
+  gposition = vec4(0.0, 0.0, 0.0, 1.0);
+  gnormal = vec4(0.0, 0.0, 0.0, 1.0);
+  gmaterial = vec4(0.0, 0.0, 0.0, 1.0);
+}
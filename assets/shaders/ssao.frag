@@ -6,8 +6,7 @@ uniform sampler2D gposition;
 uniform sampler2D gnormal;
 uniform sampler2D noise;
 uniform vec3 samples[64];
-uniform mat4 view;
-uniform mat4 projection;
+#include "camera.glsl"
 
 float radius = 0.5;
 float bias = 0.025;
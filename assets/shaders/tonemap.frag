@@ -0,0 +1,12 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+
+#include "tonemap.glsl"
+
+out vec4 f_color;
+
+void main() {
+	f_color = vec4(apply_tonemap(texture(tex, uv).rgb), 1.0);
+}
@@ -0,0 +1,37 @@
+uniform int tonemap;
+
+vec3 uncharted2(vec3 x) {
+	const float A = 0.15;
+	const float B = 0.50;
+	const float C = 0.10;
+	const float D = 0.20;
+	const float E = 0.02;
+	const float F = 0.30;
+	const float W = 11.2;
+	return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 apply_tonemap(vec3 color) {
+	switch (tonemap) {
+		case 0: // aces
+			const float a = 2.51;
+			const float b = 0.03;
+			const float c = 2.43;
+			const float d = 0.59;
+			const float e = 0.14;
+			color = clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+			break;
+		case 1: // filmic
+			color = max(vec3(0.0), color - 0.004);
+			color = (color * (6.2 * color + 0.5)) / (color * (6.2 * color + 1.7) + 0.06);
+			color = pow(color, vec3(2.2));
+			break;
+		case 2: // reinhard
+			color = color / (1.0 + color);
+			break;
+		case 3: // uncharted2
+			color = uncharted2(color * 2.0) / uncharted2(vec3(11.2));
+			break;
+	}
+	return color;
+}
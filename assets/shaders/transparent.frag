@@ -0,0 +1,50 @@
+#version 330 core
+in vec3 v_pos;
+in vec2 v_uv;
+in vec3 v_normal;
+
+uniform vec3 albedo;
+uniform float alpha;
+uniform float metallic;
+uniform float roughness;
+uniform float ao;
+uniform float emissive;
+uniform sampler2D albedo_tex;
+uniform sampler2D metallic_tex;
+uniform sampler2D roughness_tex;
+uniform sampler2D ao_tex;
+uniform bool use_albedo_tex;
+uniform bool use_metallic_tex;
+uniform bool use_roughness_tex;
+uniform bool use_ao_tex;
+#include "camera.glsl"
+uniform vec3 sun_dir;
+
+#include "pbr.glsl"
+#include "lights.glsl"
+
+out vec4 f_color;
+
+float linear_sample(sampler2D tex) {
+	return pow(texture(tex, v_uv).r, 1.0 / 2.2);
+}
+
+// forward shaded, blended over the already lit scene
+void main() {
+	vec4 base = vec4(albedo, alpha);
+	if (use_albedo_tex) {
+		base *= texture(albedo_tex, v_uv);
+	}
+	float m = use_metallic_tex ? metallic * linear_sample(metallic_tex) : metallic;
+	float r = use_roughness_tex ? roughness * linear_sample(roughness_tex) : roughness;
+	float o = use_ao_tex ? ao * linear_sample(ao_tex) : ao;
+
+	vec3 n = normalize(v_normal);
+	vec3 v = normalize(cam_pos - v_pos);
+	vec3 light = pbr_light(base.rgb, m, r, n, v, normalize(sun_dir), vec3(sun_intensity));
+	light += point_lights(base.rgb, m, r, v_pos, n, v);
+	light += base.rgb * ambient * o * sun_intensity / PI;
+	light += base.rgb * emissive;
+	light = apply_fog(light, v_pos);
+	f_color = vec4(light * exposure, base.a);
+}
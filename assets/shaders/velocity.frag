@@ -0,0 +1,29 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D galbedo;
+uniform sampler2D gposition;
+uniform vec3 cam_pos;
+uniform mat4 inv_view_proj;
+uniform mat4 prev_view_proj;
+
+out vec4 f_color;
+
+// how far each pixel moved across the screen since last frame, from the camera moving
+void main() {
+	vec3 pos;
+	if (texture(galbedo, uv).a > 0) {
+		pos = texture(gposition, uv).xyz;
+	} else {
+		// the sky is far enough away that only turning moves it
+		vec4 far = inv_view_proj * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+		pos = cam_pos + normalize(far.xyz / far.w - cam_pos) * 10000.0;
+	}
+	vec4 prev = prev_view_proj * vec4(pos, 1.0);
+	// behind the last view, there's nothing sensible to blur towards
+	if (prev.w <= 0.0) {
+		f_color = vec4(0.0, 0.0, 0.0, 1.0);
+		return;
+	}
+	f_color = vec4(uv - (prev.xy / prev.w * 0.5 + 0.5), 0.0, 1.0);
+}
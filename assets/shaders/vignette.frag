@@ -0,0 +1,12 @@
+#version 330 core
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform float strength;
+
+out vec4 f_color;
+
+void main() {
+	float d = distance(uv, vec2(0.5));
+	f_color = vec4(texture(tex, uv).rgb * mix(1.0, smoothstep(0.8, 0.2, d), strength), 1.0);
+}
@@ -1,39 +1,47 @@
 #![allow(clippy::redundant_pattern_matching)]
-use phosphor::{Engine, Result, DeltaTime};
+use phosphor::{Engine, Result, cvar};
+use phosphor::input::Input;
 use phosphor::ecs::{World, stage};
-use phosphor::log::LevelFilter;
-use phosphor::math::Vec3;
+use phosphor::log::{LevelFilter, error};
+use phosphor::math::{Vec3, Vec4};
 use phosphor::assets::Assets;
 use phosphor::scene::Scene;
-use phosphor::gfx::{Renderer, Mesh, Query};
-use phosphor::glfw::{CursorMode, Key, MouseButton, Action};
+use phosphor::profiling::Profiler;
+use phosphor::gfx::{Renderer, Fullscreen, Mesh, Query};
+use phosphor::glfw::{CursorMode, Key, MouseButton};
 use phosphor_3d::{
-  Transform, Camera, Model, Material, Light, SkySettings, ScenePerf, Tonemap, scenerenderer_plugin,
+  Transform, Camera, Model, Material, Light, SkySettings, ScenePerf, Tonemap, PostProcess,
+  PostEffect, SceneRendererOptions, AntiAliasing, BenchScene, CameraController, ParticleEmitter,
+  ParticleBlend, Curve, LevelStreamer, scenerenderer_plugin, bench_plugin,
+  camera_controller_plugin, level_streaming_plugin,
 };
-use phosphor_imgui::imgui_plugin;
+use phosphor_imgui::{imgui_plugin, console_plugin, cvar_tweaker, Console};
 use phosphor_imgui::imgui::{Ui, Condition, Drag};
 use phosphor_fmod::{AudioSource, fmod_plugin};
 use phosphor_rapier::rapier3d::prelude::*;
 use phosphor_rapier::{RigidBodyBuilder, ColliderBuilder, Gravity, rapier_plugin};
 use puffin_imgui::ProfilerUi;
-use dolly::rig::CameraRig;
-use dolly::handedness::RightHanded;
-use dolly::drivers::{Position, YawPitch, Smooth};
 use rand::Rng;
 
-struct LastPos(f32, f32);
+cvar!(move_speed: f32 = 12.0);
+cvar!(mouse_sensitivity: f32 = 0.2);
 
 fn main() -> Result {
   ezlogger::init(LevelFilter::Debug)?;
   Engine::new()
     .add_resource(ProfilerUi::default())
+    .add_resource(Profiler::new())
     .add_resource(DebugRenderPipeline::new(
       DebugRenderStyle::default(),
       DebugRenderMode::empty(),
     ))
     .add_system(stage::INIT, scenerenderer_plugin)
+    .add_system(stage::INIT, bench_plugin)
+    .add_system(stage::INIT, camera_controller_plugin)
+    .add_system(stage::INIT, level_streaming_plugin)
     .add_system(stage::INIT, fmod_plugin)
     .add_system(stage::INIT, imgui_plugin)
+    .add_system(stage::INIT, console_plugin)
     .add_system(stage::INIT, rapier_plugin)
     .add_system(stage::INIT, phosphor_rapier::rapier_debug_plugin)
     .add_system(stage::INIT, start)
@@ -43,22 +51,19 @@ fn main() -> Result {
 }
 
 fn start(world: &mut World) -> Result {
+  world.get_resource::<Console>().unwrap().register(
+    "bench",
+    "bench [meshes] [lights] [particles] [frames]",
+    bench,
+  );
   let renderer = world.get_resource::<Renderer>().unwrap();
   renderer.window.set_cursor_mode(CursorMode::Disabled);
-  let pos = renderer.window.get_cursor_pos();
-  world.add_resource(LastPos(pos.0 as f32, pos.1 as f32));
   let assets = world.get_resource::<Assets>().unwrap();
   world
     .spawn("cam")
-    .insert(Transform::new())
+    .insert(Transform::new().pos(Vec3::new(0.0, 1.0, -10.0)))
     .insert(Camera::new(80.0, [0.1, 100.0]))
-    .insert(
-      CameraRig::<RightHanded>::builder()
-        .with(Position::new(Vec3::new(0.0, 1.0, -10.0)))
-        .with(YawPitch::new().yaw_degrees(180.0))
-        .with(Smooth::new_position_rotation(1.0, 0.5))
-        .build(),
-    );
+    .insert(CameraController::fly().yaw_pitch(180.0, 0.0));
   let garf_rb = RigidBodyBuilder::dynamic().build(world);
   let garf_mesh = assets.load::<Mesh>("garfield.obj")?;
   world
@@ -66,10 +71,10 @@ fn start(world: &mut World) -> Result {
     .insert(Transform::new().pos(Vec3::new(0.0, 0.0, 2.0)))
     .insert(Model::new(garf_mesh.clone()))
     .insert(Material {
-      color: Vec3::ONE,
-      tex: Some(assets.load("garfield.png")?),
-      spec: 0.5,
+      albedo: Vec3::ONE,
+      albedo_tex: Some(assets.load("garfield.png")?),
       metallic: 0.5,
+      ..Material::DEFAULT
     })
     .insert(AudioSource::new(assets.load("portal-radio.mp3")?))
     .insert(
@@ -83,12 +88,17 @@ fn start(world: &mut World) -> Result {
     .insert(Transform::new().scale(Vec3::new(10.0, 0.01, 10.0)))
     .insert(Model::new(assets.load("cube.obj")?))
     .insert(Material {
-      color: Vec3::splat(0.75),
-      tex: None,
-      spec: 0.5,
+      albedo: Vec3::splat(0.75),
       metallic: 0.5,
+      ..Material::DEFAULT
     })
     .insert(ColliderBuilder::cuboid(10.0, 0.01, 10.0).build(world));
+  let mut sparks = ParticleEmitter::new();
+  sparks.spread = 1.0;
+  sparks.gravity = Vec3::new(0.0, -2.0, 0.0);
+  sparks.size = Curve::linear(0.05, 0.0);
+  sparks.color = Curve::linear(Vec4::new(4.0, 0.0, 4.0, 1.0), Vec4::new(1.0, 0.0, 1.0, 0.0));
+  sparks.blend = ParticleBlend::Additive;
   world
     .spawn("light")
     .insert(
@@ -97,51 +107,58 @@ fn start(world: &mut World) -> Result {
         .scale(Vec3::splat(0.1)),
     )
     .insert(Model::new(assets.load("sphere.obj")?))
-    .insert(Light::new(Vec3::new(1.0, 0.0, 1.0)));
+    .insert(Light::new(Vec3::new(1.0, 0.0, 1.0)))
+    .insert(sparks);
 
   Ok(())
 }
 
+fn bench(world: &mut World, args: &[&str]) -> Result<String> {
+  let arg = |i: usize, default: usize| -> Result<usize> {
+    Ok(match args.get(i) {
+      Some(a) => a.parse()?,
+      None => default,
+    })
+  };
+  let scene = BenchScene {
+    meshes: arg(0, BenchScene::DEFAULT.meshes)?,
+    lights: arg(1, BenchScene::DEFAULT.lights)?,
+    particles: arg(2, BenchScene::DEFAULT.particles)?,
+    ..BenchScene::DEFAULT
+  };
+  let frames = arg(3, 600)?;
+  scene.run(world, frames)?;
+  Ok(format!(
+    "Benchmarking {} frames, results go to the log.",
+    frames
+  ))
+}
+
+// movement itself is handled by the CameraController, this only manages cursor capture
 fn camera(world: &mut World) -> Result {
   let renderer = world.get_resource::<Renderer>().unwrap();
-  let cam = world.get_name("cam").unwrap();
-  let cam_t = cam.get_one::<Transform>().unwrap();
+  // missing once a bench scene replaces the world
+  let cam = match world.get_name("cam") {
+    Some(cam) => cam,
+    None => return Ok(()),
+  };
+  let controller = cam.get_one::<CameraController>().unwrap();
+  controller.speed = move_speed.get();
+  controller.sensitivity = mouse_sensitivity.get();
 
-  let rig = cam.get_one::<CameraRig>().unwrap();
-  let t = rig.update(world.get_resource::<DeltaTime>().unwrap().0);
-  cam_t.position = t.position;
-  cam_t.rotation = t.rotation;
-
-  let last_pos = world.get_resource::<LastPos>().unwrap();
-  let pos = renderer.window.get_cursor_pos();
-  let pos = (pos.0 as f32, pos.1 as f32);
-  if renderer.window.get_cursor_mode() == CursorMode::Disabled {
-    let r = rig.driver_mut::<YawPitch>();
-    r.yaw_degrees -= (pos.0 - last_pos.0) * 0.2;
-    r.pitch_degrees -= (pos.1 - last_pos.1) * 0.2;
-    r.pitch_degrees = r.pitch_degrees.clamp(-89.0, 89.0);
-
-    let pos = rig.driver_mut::<Position>();
-    if renderer.window.get_key(Key::W) == Action::Press {
-      pos.translate(t.forward() * 0.2);
-    }
-    if renderer.window.get_key(Key::A) == Action::Press {
-      pos.translate(t.right() * -0.2);
-    }
-    if renderer.window.get_key(Key::S) == Action::Press {
-      pos.translate(t.forward() * -0.2);
-    }
-    if renderer.window.get_key(Key::D) == Action::Press {
-      pos.translate(t.right() * 0.2);
-    }
-  }
-  *last_pos = LastPos(pos.0, pos.1);
-  if renderer.window.get_key(Key::Escape) == Action::Press {
+  let input = world.get_resource::<Input>().unwrap();
+  if input.key_down(Key::Escape) {
     renderer.window.set_cursor_mode(CursorMode::Normal);
   }
-  if renderer.window.get_mouse_button(MouseButton::Button1) == Action::Press {
+  if input.button_down(MouseButton::Button1) {
     renderer.window.set_cursor_mode(CursorMode::Disabled);
   }
+  if input.key_pressed(Key::F11) {
+    renderer.set_fullscreen(match renderer.fullscreen() {
+      Fullscreen::Windowed => Fullscreen::Borderless(0),
+      _ => Fullscreen::Windowed,
+    })?;
+  }
   Ok(())
 }
 
@@ -192,14 +209,13 @@ fn ui(world: &mut World) -> Result {
               .insert(Model::new(assets.load(mesh).unwrap()))
               .insert(collider.attach_rb(rb).build(world))
               .insert(Material {
-                color: Vec3::new(
+                albedo: Vec3::new(
                   rng.gen_range(0.0..1.0),
                   rng.gen_range(0.0..1.0),
                   rng.gen_range(0.0..1.0),
                 ),
-                tex: None,
-                spec: 0.5,
                 metallic: 0.5,
+                ..Material::DEFAULT
               })
               .insert(rb);
           }
@@ -207,6 +223,17 @@ fn ui(world: &mut World) -> Result {
           if ui.button("Save scene") {
             Scene::save(world, "test.scene".into()).unwrap();
           }
+
+          let streamer = world.get_resource::<LevelStreamer>().unwrap();
+          ui.checkbox("Show streaming volumes", &mut streamer.debug);
+          for (scene, state, entities) in streamer.volumes() {
+            ui.text(format!(
+              "{}: {} ({} entities)",
+              scene,
+              state.name(),
+              entities
+            ));
+          }
         }
         if let Some(_) = ui.tab_item("Graphics") {
           let scene_perf = world.get_resource::<ScenePerf>().unwrap();
@@ -214,11 +241,37 @@ fn ui(world: &mut World) -> Result {
           pass(ui, "geometry", &mut scene_perf.geometry_pass);
           pass(ui, "ssao", &mut scene_perf.ssao_pass);
           pass(ui, "lighting", &mut scene_perf.lighting_pass);
-          let tonemap = world.get_resource::<Tonemap>().unwrap();
-          if let Some(_) = ui.begin_combo("Tonemap", tonemap.name()) {
-            for t in Tonemap::ALL {
-              if ui.selectable(t.name()) {
-                *tonemap = t;
+          pass(ui, "transparent", &mut scene_perf.transparent_pass);
+          pass(ui, "particles", &mut scene_perf.particle_pass);
+          pass(ui, "post", &mut scene_perf.post_pass);
+          ui.text(format!(
+            "{} drawn, {} culled, {} particles",
+            scene_perf.drawn, scene_perf.culled, scene_perf.particles
+          ));
+          let options = world.get_resource::<SceneRendererOptions>().unwrap();
+          if let Some(_) = ui.begin_combo("Anti-aliasing", options.aa.name()) {
+            for aa in AntiAliasing::ALL {
+              if ui.selectable(aa.name()) {
+                options.aa = aa;
+              }
+            }
+          }
+          ui.checkbox("GPU culling", &mut options.gpu_culling);
+          ui.checkbox("Occlusion culling", &mut options.occlusion_culling);
+          ui.slider("Shadow cascades", 1, 4, &mut options.shadow_cascades);
+          Drag::new("Shadow distance")
+            .range(1.0, f32::MAX)
+            .build(ui, &mut options.shadow_distance);
+          ui.checkbox("Show cascades", &mut options.debug_cascades);
+          let post = world.get_resource::<PostProcess>().unwrap();
+          for effect in &mut post.effects {
+            if let PostEffect::Tonemap(tonemap) = effect {
+              if let Some(_) = ui.begin_combo("Tonemap", tonemap.name()) {
+                for t in Tonemap::ALL {
+                  if ui.selectable(t.name()) {
+                    *tonemap = t;
+                  }
+                }
               }
             }
           }
@@ -234,7 +287,21 @@ fn ui(world: &mut World) -> Result {
             DebugRenderMode::empty()
           }
         }
+        if let Some(_) = ui.tab_item("Tweaks") {
+          cvar_tweaker(ui);
+        }
         if let Some(_) = ui.tab_item("Profiler") {
+          let profiler = world.get_resource::<Profiler>().unwrap();
+          if ui.button("Export trace") {
+            if let Err(e) = profiler.export_chrome_trace("profile.json") {
+              error!("Couldn't export profile. {}", e);
+            }
+          }
+          ui.same_line();
+          ui.text_disabled(format!(
+            "{} frames, open in ui.perfetto.dev",
+            profiler.frames()
+          ));
           world.get_resource::<ProfilerUi>().unwrap().ui(ui);
         }
       }
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+use crate::cvar;
+
+// multiplies the size of imgui and should be read by any ui the game draws itself
+cvar!(pub ui_scale: f32 = 1.0);
+// shifts colors so they're easier to tell apart, 0 is off, 1 protanopia, 2 deuteranopia and
+// 3 tritanopia
+cvar!(pub colorblind_filter: i32 = 0);
+cvar!(pub colorblind_strength: f32 = 1.0);
+cvar!(pub subtitles: bool = false);
+// pressing a key turns `Input::held` on until it's pressed again
+cvar!(pub hold_to_toggle: bool = false);
+
+// lines older than this are dropped when another is shown
+const MAX_SUBTITLES: usize = 3;
+
+struct Subtitle {
+  text: String,
+  until: Instant,
+}
+
+// captions for sounds, filled by audio plugins and drawn by the ui plugin while `subtitles` is on
+pub struct Subtitles {
+  lines: Vec<Subtitle>,
+}
+
+impl Subtitles {
+  pub fn new() -> Self {
+    Self { lines: vec![] }
+  }
+
+  pub fn show(&mut self, text: &str, duration: f32) {
+    if self.lines.len() == MAX_SUBTITLES {
+      self.lines.remove(0);
+    }
+    self.lines.push(Subtitle {
+      text: text.to_string(),
+      until: Instant::now() + Duration::from_secs_f32(duration.max(0.0)),
+    });
+  }
+
+  // lines still on screen, oldest first
+  pub fn current(&mut self) -> Vec<&str> {
+    let now = Instant::now();
+    self.lines.retain(|l| l.until > now);
+    self.lines.iter().map(|l| l.text.as_str()).collect()
+  }
+}
@@ -2,28 +2,40 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use std::ops::Deref;
 use std::any::Any;
+use std::sync::Mutex;
+use std::mem;
 use log::{error, trace};
 use linkme::distributed_slice;
-use serde::{Serialize, Deserialize, Deserializer};
+use serde::{de, Serialize, Deserialize, Deserializer};
 use crate::ecs::World;
 use crate::{Result, TypeIdNamed, WORLD};
 
 pub struct AssetLoader {
   pub id: TypeIdNamed,
   pub loader: fn(&mut World, &str) -> Result<Rc<dyn Any>>,
+  pub unloader: Option<fn(&mut World, &dyn Any)>,
 }
 
 #[distributed_slice]
 pub static ASSET_LOADERS: [AssetLoader] = [..];
 
+// addresses of assets whose last handle outside the registry was dropped, for `Assets::update`
+static DROPPED: Mutex<Vec<usize>> = Mutex::new(vec![]);
+
 pub struct Assets {
   pub handles: HashMap<TypeIdNamed, Vec<Handle<dyn Any>>>,
+  // directory asset names are relative to, engine shaders and fonts stay in assets/
+  pub root: String,
+  // held so they aren't unloaded, see `keep`
+  kept: Vec<Handle<dyn Any>>,
 }
 
 impl Assets {
   pub fn new() -> Self {
     Self {
       handles: HashMap::new(),
+      root: "assets".to_string(),
+      kept: vec![],
     }
   }
 
@@ -46,7 +58,7 @@ impl Assets {
           name: path.to_string(),
           data: (loader.loader)(
             unsafe { WORLD.get_mut().unwrap() },
-            &format!("assets/{}", path),
+            &format!("{}/{}", self.root, path),
           )?,
         };
         v.push(h.clone());
@@ -55,6 +67,94 @@ impl Assets {
     })
   }
 
+  // an asset made or edited at runtime, replacing any loaded under the same name. handles that
+  // were already given out keep the old one
+  pub fn insert<T: Any>(&mut self, name: &str, data: T) -> Handle<T> {
+    let h = Handle {
+      name: name.to_string(),
+      data: Rc::new(data) as Rc<dyn Any>,
+    };
+    let v = self.handles.entry(TypeIdNamed::of::<T>()).or_insert(vec![]);
+    v.retain(|h| h.name != name);
+    v.push(h.clone());
+    h.downcast()
+  }
+
+  // keeps an asset loaded with nothing else holding it, eg one imported in the editor to be
+  // picked later
+  pub fn keep<T: Any>(&mut self, h: Handle<T>) {
+    if !self.kept.iter().any(|k| k.addr() == h.addr()) {
+      self.kept.push(Handle {
+        name: h.name.clone(),
+        data: h.data.clone() as Rc<dyn Any>,
+      });
+    }
+  }
+
+  // stops keeping everything `keep` was given, eg when another project is opened
+  pub fn release(&mut self) {
+    self.kept.clear();
+  }
+
+  // unloads the assets whose last handle was dropped since the last call, unless one was taken
+  // again since. runs at the end of every frame
+  pub fn update(&mut self) -> Vec<String> {
+    let mut unloaded = vec![];
+    loop {
+      // unloading a material drops the handles to its textures, so they're done in turn
+      let dropped = mem::take(&mut *DROPPED.lock().unwrap());
+      if dropped.is_empty() {
+        break;
+      }
+      unloaded.extend(self.unload(|h| dropped.contains(&h.addr())));
+    }
+    unloaded
+  }
+
+  // drops every asset nothing outside the registry has a handle to, including ones loaded but
+  // never held, and returns their names
+  pub fn collect(&mut self) -> Vec<String> {
+    let mut unloaded = vec![];
+    loop {
+      let v = self.unload(|_| true);
+      if v.is_empty() {
+        break;
+      }
+      unloaded.extend(v);
+    }
+    // everything that was waiting for `update` is gone or held again
+    DROPPED.lock().unwrap().clear();
+    unloaded
+  }
+
+  // runs each unreferenced asset `f` picks through its type's unloader. anything keeping a copy
+  // of an asset's contents rather than a handle, eg a texture id, must not use it after
+  fn unload<F: Fn(&Handle<dyn Any>) -> bool>(&mut self, f: F) -> Vec<String> {
+    let mut unloaded = vec![];
+    let mut dropped = vec![];
+    for (t, v) in self.handles.iter_mut() {
+      v.retain(|h| match h.refs() == 0 && f(h) {
+        true => {
+          dropped.push((*t, h.clone()));
+          false
+        }
+        false => true,
+      });
+    }
+    for (t, h) in dropped {
+      let unloader = ASSET_LOADERS
+        .iter()
+        .find(|l| l.id == t)
+        .and_then(|l| l.unloader);
+      if let Some(unload) = unloader {
+        unload(unsafe { WORLD.get_mut().unwrap() }, &*h.data);
+      }
+      trace!("Unloaded '{}' '{}'.", t.name, h.name);
+      unloaded.push(h.name.clone());
+    }
+    unloaded
+  }
+
   pub fn get<T: Any>(&self) -> Vec<Handle<T>> {
     match self.handles.get(&TypeIdNamed::of::<T>()) {
       Some(l) => l.iter().map(|h| h.downcast()).collect(),
@@ -73,15 +173,33 @@ pub struct Handle<T: ?Sized> {
 impl<'de, T: Any> Deserialize<'de> for Handle<T> {
   fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
     let name: String = Deserialize::deserialize(deserializer)?;
-    Ok(unsafe {
-      WORLD
-        .get_mut()
-        .unwrap()
-        .get_resource::<Assets>()
-        .unwrap()
-        .load(&name)
-        .unwrap()
-    })
+    // a missing asset fails the component holding it rather than the whole program
+    unsafe { WORLD.get_mut().unwrap() }
+      .get_resource::<Assets>()
+      .unwrap()
+      .load(&name)
+      .map_err(|e| de::Error::custom(format!("Couldn't load '{}'. {}", name, e)))
+  }
+}
+
+impl<T: ?Sized> Handle<T> {
+  // handles to the asset other than the registry's, it can be collected at 0
+  pub fn refs(&self) -> usize {
+    Rc::strong_count(&self.data) - 1
+  }
+
+  // the same for every handle to the asset, whatever type it's viewed as
+  fn addr(&self) -> usize {
+    Rc::as_ptr(&self.data) as *const () as usize
+  }
+}
+
+impl<T: ?Sized> Drop for Handle<T> {
+  fn drop(&mut self) {
+    // this and the registry's
+    if Rc::strong_count(&self.data) == 2 {
+      DROPPED.lock().unwrap().push(self.addr());
+    }
   }
 }
 
@@ -0,0 +1,201 @@
+use std::fs;
+use crate::gfx::{Texture, TextureOptions, gl};
+use crate::vram::{self, GpuKind};
+use crate::Result;
+
+// from EXT_texture_compression_s3tc and EXT_texture_sRGB, neither made it into core
+const COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83f0;
+const COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83f1;
+const COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83f3;
+const COMPRESSED_SRGB_S3TC_DXT1: u32 = 0x8c4c;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: u32 = 0x8c4d;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: u32 = 0x8c4f;
+
+const KTX2_MAGIC: [u8; 12] = [
+  0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+#[derive(Copy, Clone)]
+enum Format {
+  Bc1,
+  Bc1Alpha,
+  Bc3,
+  Bc7,
+}
+
+impl Format {
+  fn block_bytes(self) -> usize {
+    match self {
+      Self::Bc1 | Self::Bc1Alpha => 8,
+      Self::Bc3 | Self::Bc7 => 16,
+    }
+  }
+
+  fn gl(self, srgb: bool) -> u32 {
+    match (self, srgb) {
+      (Self::Bc1, false) => COMPRESSED_RGB_S3TC_DXT1,
+      (Self::Bc1, true) => COMPRESSED_SRGB_S3TC_DXT1,
+      (Self::Bc1Alpha, false) => COMPRESSED_RGBA_S3TC_DXT1,
+      (Self::Bc1Alpha, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+      (Self::Bc3, false) => COMPRESSED_RGBA_S3TC_DXT5,
+      (Self::Bc3, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+      (Self::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+      (Self::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+    }
+  }
+
+  fn level_bytes(self, width: u32, height: u32) -> usize {
+    let blocks = |n: u32| n.div_ceil(4).max(1) as usize;
+    blocks(width) * blocks(height) * self.block_bytes()
+  }
+}
+
+struct Compressed<'a> {
+  format: Format,
+  srgb: bool,
+  width: u32,
+  height: u32,
+  levels: Vec<&'a [u8]>,
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(
+    data.get(offset..offset + 4)?.try_into().ok()?,
+  ))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<usize> {
+  Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?) as _)
+}
+
+// only 2d textures, dxt1 and dxt5 fourccs are taken as srgb like other images are
+fn parse_dds(data: &[u8]) -> Option<Result<Compressed>> {
+  if data.get(..4)? != b"DDS " {
+    return None;
+  }
+  let height = u32_at(data, 12)?;
+  let width = u32_at(data, 16)?;
+  let mips = u32_at(data, 28)?.max(1);
+  let (format, srgb, mut offset) = match data.get(84..88)? {
+    b"DXT1" => (Format::Bc1Alpha, true, 128),
+    b"DXT5" => (Format::Bc3, true, 128),
+    b"DX10" => {
+      let (format, srgb) = match u32_at(data, 128)? {
+        71 => (Format::Bc1Alpha, false),
+        72 => (Format::Bc1Alpha, true),
+        77 => (Format::Bc3, false),
+        78 => (Format::Bc3, true),
+        98 => (Format::Bc7, false),
+        99 => (Format::Bc7, true),
+        n => return Some(Err(format!("Unsupported DXGI format {}.", n).into())),
+      };
+      (format, srgb, 148)
+    }
+    cc => {
+      let cc = String::from_utf8_lossy(cc);
+      return Some(Err(format!("Unsupported DDS format '{}'.", cc).into()));
+    }
+  };
+  let mut levels = vec![];
+  for i in 0..mips {
+    let len = format.level_bytes(width >> i, height >> i);
+    levels.push(data.get(offset..offset + len)?);
+    offset += len;
+  }
+  Some(Ok(Compressed {
+    format,
+    srgb,
+    width,
+    height,
+    levels,
+  }))
+}
+
+// supercompressed files would need basis or zstd, so they're rejected
+fn parse_ktx2(data: &[u8]) -> Option<Result<Compressed>> {
+  if data.get(..12)? != KTX2_MAGIC {
+    return None;
+  }
+  let (format, srgb) = match u32_at(data, 12)? {
+    131 => (Format::Bc1, false),
+    132 => (Format::Bc1, true),
+    133 => (Format::Bc1Alpha, false),
+    134 => (Format::Bc1Alpha, true),
+    137 => (Format::Bc3, false),
+    138 => (Format::Bc3, true),
+    145 => (Format::Bc7, false),
+    146 => (Format::Bc7, true),
+    n => return Some(Err(format!("Unsupported Vulkan format {}.", n).into())),
+  };
+  let width = u32_at(data, 20)?;
+  let height = u32_at(data, 24)?;
+  if u32_at(data, 28)? > 1 || u32_at(data, 32)? > 1 || u32_at(data, 36)? > 1 {
+    return Some(Err("Only 2D KTX2 textures are supported.".into()));
+  }
+  if u32_at(data, 44)? != 0 {
+    return Some(Err(
+      "Supercompressed KTX2 textures aren't supported.".into(),
+    ));
+  }
+  let mut levels = vec![];
+  for i in 0..u32_at(data, 40)?.max(1) as usize {
+    let offset = u64_at(data, 80 + i * 24)?;
+    let len = u64_at(data, 88 + i * 24)?;
+    levels.push(data.get(offset..offset.checked_add(len)?)?);
+  }
+  Some(Ok(Compressed {
+    format,
+    srgb,
+    width,
+    height,
+    levels,
+  }))
+}
+
+// uploads the blocks as stored, unlike other images they aren't flipped so they should be exported
+// with the first row at the bottom (`texconv -vflip`, `toktx --lower_left_maps_to_s0t0`). mips
+// come from the file and aren't streamed
+pub(crate) fn load_compressed(path: &str, options: TextureOptions) -> Result<Texture> {
+  let data = fs::read(path)?;
+  let tex = match parse_dds(&data).or_else(|| parse_ktx2(&data)) {
+    Some(tex) => tex?,
+    None => return Err(format!("'{}' is truncated or not a DDS or KTX2 file.", path).into()),
+  };
+  let levels = match options.mipmaps {
+    true => &tex.levels[..],
+    false => &tex.levels[..1],
+  };
+  let iformat = tex.format.gl(tex.srgb);
+  unsafe {
+    let mut id = 0;
+    gl::GenTextures(1, &mut id);
+    gl::BindTexture(gl::TEXTURE_2D, id);
+    options.mipmaps(levels.len() > 1).apply();
+    gl::TexParameteri(
+      gl::TEXTURE_2D,
+      gl::TEXTURE_MAX_LEVEL,
+      levels.len() as i32 - 1,
+    );
+    for (i, level) in levels.iter().enumerate() {
+      gl::CompressedTexImage2D(
+        gl::TEXTURE_2D,
+        i as _,
+        iformat,
+        (tex.width >> i).max(1) as _,
+        (tex.height >> i).max(1) as _,
+        0,
+        level.len() as _,
+        level.as_ptr() as _,
+      );
+    }
+    vram::track(GpuKind::Texture, id, levels.iter().map(|l| l.len()).sum());
+    Ok(Texture {
+      id,
+      width: tex.width,
+      height: tex.height,
+      iformat,
+      format: gl::RGBA,
+      typ: gl::UNSIGNED_BYTE,
+    })
+  }
+}
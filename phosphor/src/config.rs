@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use log::{info, error};
+use crate::Result;
+use crate::ecs::World;
+
+// seconds between checking the files
+const INTERVAL: f32 = 0.5;
+
+pub type Reload = fn(&mut World, &Path) -> Result;
+
+// settings files applied again whenever they're saved, so bindings and quality can be tweaked
+// while the game is running. the engine watches cvars and input bindings, plugins add their own
+pub struct ConfigFiles {
+  files: Vec<(PathBuf, Reload, Option<SystemTime>)>,
+  checked: Instant,
+}
+
+impl ConfigFiles {
+  pub fn new() -> Self {
+    Self {
+      files: vec![],
+      checked: Instant::now(),
+    }
+  }
+
+  // `reload` runs when the file next changes or is created, it's expected to be loaded at
+  // startup already
+  pub fn watch<P: Into<PathBuf>>(&mut self, path: P, reload: Reload) {
+    let path = path.into();
+    let modified = modified(&path);
+    self.files.push((path, reload, modified));
+  }
+
+  pub(crate) fn update(world: &mut World) {
+    let files = world.get_resource::<Self>().unwrap();
+    if files.checked.elapsed().as_secs_f32() < INTERVAL {
+      return;
+    }
+    files.checked = Instant::now();
+    let mut changed = vec![];
+    for (path, reload, last) in &mut files.files {
+      let modified = modified(path);
+      // deleted files keep what they last set
+      if modified.is_some() && modified != *last {
+        changed.push((path.clone(), *reload));
+      }
+      *last = modified;
+    }
+    for (path, reload) in changed {
+      match reload(world, &path) {
+        Ok(()) => info!("Reloaded '{}'.", path.display()),
+        Err(e) => error!("Couldn't reload '{}'. {}", path.display(), e),
+      }
+    }
+  }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
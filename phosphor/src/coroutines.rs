@@ -0,0 +1,159 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::mem;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use log::error;
+use crate::{Result, DeltaTime};
+use crate::ecs::World;
+
+type Coroutine = Pin<Box<dyn Future<Output = Result>>>;
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct CoroutineId(usize);
+
+// gameplay sequences written as async code, each one is resumed once a frame after PRE_DRAW until
+// it finishes, so it sees this frame's game events and moves things before transforms propagate:
+//
+//   coroutines.start(world, |world| async move {
+//     wait_seconds(world, 2.0).await;
+//     wait_for_event(world, "door_opened").await;
+//     move_to(world, camera, Vec3::new(0.0, 2.0, -5.0), 1.5).await;
+//     Ok(())
+//   });
+pub struct Coroutines {
+  running: Vec<(CoroutineId, Coroutine)>,
+  next: usize,
+  // taken out of `running` while they're polled, and those stopped meanwhile
+  polling: Vec<CoroutineId>,
+  stopped: Vec<CoroutineId>,
+}
+
+impl Coroutines {
+  pub fn new() -> Self {
+    Self {
+      running: vec![],
+      next: 0,
+      polling: vec![],
+      stopped: vec![],
+    }
+  }
+
+  // runs up to its first wait on the next update
+  pub fn start<C, F>(&mut self, world: &World, coroutine: C) -> CoroutineId
+  where
+    C: FnOnce(&'static World) -> F,
+    F: Future<Output = Result> + 'static,
+  {
+    let id = CoroutineId(self.next);
+    self.next += 1;
+    self.running.push((id, Box::pin(coroutine(world.g()))));
+    id
+  }
+
+  pub fn stop(&mut self, id: CoroutineId) {
+    self.running.retain(|c| c.0 != id);
+    if self.polling.contains(&id) && !self.stopped.contains(&id) {
+      self.stopped.push(id);
+    }
+  }
+
+  pub fn is_running(&self, id: CoroutineId) -> bool {
+    self.running.iter().any(|c| c.0 == id)
+      || self.polling.contains(&id) && !self.stopped.contains(&id)
+  }
+
+  pub fn len(&self) -> usize {
+    self.running.len() + self.polling.len() - self.stopped.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn clear(&mut self) {
+    self.running.clear();
+    self.stopped = self.polling.clone();
+  }
+
+  pub(crate) fn update(world: &mut World) {
+    // taken out so coroutines can start and stop others while they run
+    let coroutines = world.get_resource::<Self>().unwrap();
+    let mut running = mem::take(&mut coroutines.running);
+    coroutines.polling = running.iter().map(|c| c.0).collect();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    running.retain_mut(|(id, c)| {
+      // stopped by one polled before it
+      if world.get_resource::<Self>().unwrap().stopped.contains(id) {
+        return false;
+      }
+      let done = match c.as_mut().poll(&mut cx) {
+        Poll::Ready(Ok(())) => true,
+        Poll::Ready(Err(e)) => {
+          error!("Error in coroutine: {}", e);
+          true
+        }
+        Poll::Pending => false,
+      };
+      // so the rest see it as stopped
+      if done {
+        world.get_resource::<Self>().unwrap().stop(*id);
+      }
+      !done
+    });
+    let coroutines = world.get_resource::<Self>().unwrap();
+    running.retain(|c| !coroutines.stopped.contains(&c.0));
+    coroutines.polling.clear();
+    coroutines.stopped.clear();
+    // started while running
+    running.append(&mut coroutines.running);
+    coroutines.running = running;
+  }
+}
+
+// coroutines are polled every frame rather than woken
+fn noop_waker() -> Waker {
+  const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+  const RAW: RawWaker = RawWaker::new(ptr::null(), &VTABLE);
+  unsafe { Waker::from_raw(RAW) }
+}
+
+struct NextFrame(bool);
+
+impl Future for NextFrame {
+  type Output = ();
+
+  fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+    match mem::replace(&mut self.0, true) {
+      true => Poll::Ready(()),
+      false => Poll::Pending,
+    }
+  }
+}
+
+pub fn next_frame() -> impl Future<Output = ()> {
+  NextFrame(false)
+}
+
+pub async fn wait_frames(n: usize) {
+  for _ in 0..n {
+    next_frame().await;
+  }
+}
+
+// in game time, so waits stop while paused and follow the time scale
+pub async fn wait_seconds(world: &World, seconds: f32) {
+  let mut elapsed = 0.0;
+  while elapsed < seconds {
+    next_frame().await;
+    elapsed += world.get_resource::<DeltaTime>().unwrap().0;
+  }
+}
+
+// checked once a frame, starting with this one
+pub async fn wait_until<F: FnMut() -> bool>(mut f: F) {
+  while !f() {
+    next_frame().await;
+  }
+}
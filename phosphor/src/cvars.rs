@@ -0,0 +1,169 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::fs;
+use std::path::Path;
+use linkme::distributed_slice;
+use log::{info, warn};
+use crate::Result;
+
+pub const CVARS_PATH: &str = "cvars.txt";
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CVarValue {
+  Bool(bool),
+  I32(i32),
+  F32(f32),
+}
+
+pub trait CVarType: Copy + Send + Sync + FromStr + Display + 'static {
+  fn into_value(self) -> CVarValue;
+  fn from_value(value: CVarValue) -> Option<Self>;
+}
+
+macro_rules! cvar_type {
+  ($t:ty, $variant:ident) => {
+    impl CVarType for $t {
+      fn into_value(self) -> CVarValue {
+        CVarValue::$variant(self)
+      }
+
+      fn from_value(value: CVarValue) -> Option<Self> {
+        match value {
+          CVarValue::$variant(v) => Some(v),
+          _ => None,
+        }
+      }
+    }
+  };
+}
+
+cvar_type!(bool, Bool);
+cvar_type!(i32, I32);
+cvar_type!(f32, F32);
+
+pub struct CVar<T> {
+  pub name: &'static str,
+  pub default: T,
+  value: Mutex<T>,
+}
+
+impl<T: CVarType> CVar<T> {
+  pub const fn new(name: &'static str, default: T) -> Self {
+    Self {
+      name,
+      default,
+      value: Mutex::new(default),
+    }
+  }
+
+  pub fn get(&self) -> T {
+    *self.value.lock().unwrap()
+  }
+
+  pub fn set(&self, value: T) {
+    *self.value.lock().unwrap() = value;
+  }
+}
+
+// type erased access for the console and tweaker
+pub trait AnyCVar: Sync {
+  fn name(&self) -> &'static str;
+  fn value(&self) -> CVarValue;
+  fn set_value(&self, value: CVarValue);
+  fn parse(&self, s: &str) -> Result;
+  fn reset(&self);
+}
+
+impl<T: CVarType> AnyCVar for CVar<T> {
+  fn name(&self) -> &'static str {
+    self.name
+  }
+
+  fn value(&self) -> CVarValue {
+    self.get().into_value()
+  }
+
+  fn set_value(&self, value: CVarValue) {
+    if let Some(v) = T::from_value(value) {
+      self.set(v);
+    }
+  }
+
+  fn parse(&self, s: &str) -> Result {
+    match s.parse::<T>() {
+      Ok(v) => {
+        self.set(v);
+        Ok(())
+      }
+      Err(_) => Err(format!("Invalid value '{}' for '{}'.", s, self.name).into()),
+    }
+  }
+
+  fn reset(&self) {
+    self.set(self.default);
+  }
+}
+
+impl Display for CVarValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      Self::Bool(v) => v.fmt(f),
+      Self::I32(v) => v.fmt(f),
+      Self::F32(v) => v.fmt(f),
+    }
+  }
+}
+
+#[distributed_slice]
+pub static CVARS: [&'static dyn AnyCVar] = [..];
+
+// declares a tunable static, eg `cvar!(player_speed: f32 = 5.0)` then `player_speed.get()`
+#[macro_export]
+macro_rules! cvar {
+  ($vis:vis $name:ident: $t:ty = $default:expr) => {
+    #[allow(non_upper_case_globals)]
+    $vis static $name: $crate::cvars::CVar<$t> = $crate::cvars::CVar::new(stringify!($name), $default);
+    const _: () = {
+      #[$crate::linkme::distributed_slice($crate::cvars::CVARS)]
+      static CVAR: &'static dyn $crate::cvars::AnyCVar = &$name;
+    };
+  };
+}
+
+pub fn all() -> Vec<&'static dyn AnyCVar> {
+  let mut cvars = CVARS.to_vec();
+  cvars.sort_by_key(|c| c.name());
+  cvars
+}
+
+pub fn find(name: &str) -> Option<&'static dyn AnyCVar> {
+  CVARS.iter().find(|c| c.name() == name).copied()
+}
+
+// `name = value` lines in the project root
+pub fn load<P: AsRef<Path>>(path: P) -> Result {
+  for line in fs::read_to_string(path)?.lines() {
+    if let Some((k, v)) = line.split_once(" = ") {
+      match find(k.trim()) {
+        Some(cvar) => {
+          if let Err(e) = cvar.parse(v.trim()) {
+            warn!("{}", e);
+          }
+        }
+        None => warn!("Unknown cvar '{}'.", k.trim()),
+      }
+    }
+  }
+  Ok(())
+}
+
+pub fn save<P: AsRef<Path>>(path: P) -> Result {
+  let s = all()
+    .iter()
+    .map(|c| format!("{} = {}\n", c.name(), c.value()))
+    .collect::<String>();
+  fs::write(path.as_ref(), s)?;
+  info!("Saved cvars to '{}'.", path.as_ref().display());
+  Ok(())
+}
@@ -1,5 +1,8 @@
 use std::collections::{HashMap, BTreeMap};
 use std::any::{Any, type_name};
+use std::marker::PhantomData;
+use std::time::{Instant, Duration};
+use std::mem;
 use log::error;
 use serde::{Serialize, Deserialize};
 use crate::{Result, HashMapExt, TypeIdNamed, component, WORLD};
@@ -18,7 +21,24 @@ pub mod stage {
 pub struct World {
   pub components: HashMap<TypeIdNamed, Vec<(usize, Box<dyn Any>)>>,
   resources: HashMap<TypeIdNamed, Box<dyn Any>>,
-  systems: HashMap<usize, Vec<(&'static dyn System, &'static str)>>,
+  // by stage, each with an id for `last_run`
+  systems: HashMap<usize, Vec<(usize, &'static dyn System, &'static str)>>,
+  pub system_times: HashMap<&'static str, Duration>,
+  frame_times: HashMap<&'static str, Duration>,
+  // when each component was added and last changed, by type then entity
+  pub(crate) ticks: HashMap<TypeIdNamed, HashMap<usize, Ticks>>,
+  // counts up once per system run
+  tick: u64,
+  // the tick each system last ran at, by its id, and the running one's
+  last_run: HashMap<usize, u64>,
+  since: Option<u64>,
+  commands: Commands,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct Ticks {
+  added: u64,
+  changed: u64,
 }
 
 impl World {
@@ -27,10 +47,17 @@ impl World {
       components: HashMap::new(),
       resources: HashMap::new(),
       systems: HashMap::new(),
+      system_times: HashMap::new(),
+      frame_times: HashMap::new(),
+      ticks: HashMap::new(),
+      tick: 0,
+      last_run: HashMap::new(),
+      since: None,
+      commands: Commands(vec![]),
     }
   }
 
-  fn g(&self) -> &'static mut Self {
+  pub(crate) fn g(&self) -> &'static mut Self {
     unsafe { WORLD.get_mut().unwrap() }
   }
 
@@ -54,6 +81,60 @@ impl World {
     }
   }
 
+  // only entities `F` matches, eg `query_filtered::<Transform, Without<Camera>>()`
+  pub fn query_filtered<T: Any, F: Filter>(&self) -> Vec<(Entity, &mut T)> {
+    let mut q = self.query::<T>();
+    q.retain(|(e, _)| F::matches(self, e.id));
+    q
+  }
+
+  pub(crate) fn has(&self, t: TypeIdNamed, id: usize) -> bool {
+    self
+      .components
+      .get(&t)
+      .is_some_and(|v| v.iter().any(|c| c.0 == id))
+  }
+
+  // adds a boxed component, like `Entity::insert` when the type is only known at runtime
+  pub fn insert_id(&self, t: TypeIdNamed, id: usize, c: Box<dyn Any>) {
+    let w = self.g();
+    w.components.push_or_insert(t, (id, c));
+    let tick = w.tick;
+    w.ticks.entry(t).or_default().insert(
+      id,
+      Ticks {
+        added: tick,
+        changed: tick,
+      },
+    );
+  }
+
+  // for `Changed` filters, when a component was changed without `Entity::modify`
+  pub fn set_changed(&self, t: TypeIdNamed, id: usize) {
+    let tick = self.tick;
+    self.ticks(t, id).changed = tick;
+  }
+
+  fn ticks(&self, t: TypeIdNamed, id: usize) -> &mut Ticks {
+    let tick = self.tick;
+    // anything pushed straight into `components` counts as just added
+    self
+      .g()
+      .ticks
+      .entry(t)
+      .or_default()
+      .entry(id)
+      .or_insert(Ticks {
+        added: tick,
+        changed: tick,
+      })
+  }
+
+  // whether something done at `tick` happened since the running system last ran
+  fn is_new(&self, tick: u64) -> bool {
+    self.since.is_none_or(|s| tick > s)
+  }
+
   pub fn get_name(&self, name: &str) -> Option<Entity> {
     puffin::profile_function!(name);
     self
@@ -68,6 +149,9 @@ impl World {
     if let Some(v) = self.g().components.get_mut(&t) {
       v.retain(|c| c.0 != id);
     }
+    if let Some(ticks) = self.g().ticks.get_mut(&t) {
+      ticks.remove(&id);
+    }
   }
 
   pub fn add_resource<T: Any>(&self, resource: T) {
@@ -93,18 +177,56 @@ impl World {
   }
 
   pub fn add_system<S: System + 'static>(&mut self, stage: usize, sys: S) {
+    // not the leaked box's address, zero sized systems like fn items all get the same one
+    let id = self.systems.values().map(Vec::len).sum();
     self
       .systems
-      .push_or_insert(stage, (Box::leak(Box::new(sys)), type_name::<S>()));
+      .push_or_insert(stage, (id, Box::leak(Box::new(sys)), type_name::<S>()));
+  }
+
+  pub fn systems(&self) -> Vec<(usize, &'static str)> {
+    let mut systems = vec![];
+    for (stage, vec) in self.systems.iter() {
+      for (_, _, name) in vec {
+        systems.push((*stage, *name));
+      }
+    }
+    systems.sort_by_key(|s| s.0);
+    systems
+  }
+
+  // recorded changes to make after the running system, see `Commands`
+  pub fn commands(&self) -> &mut Commands {
+    &mut self.g().commands
+  }
+
+  // runs everything recorded so far, and anything those record in turn
+  pub fn apply_commands(&mut self) {
+    while !self.commands.is_empty() {
+      for c in mem::take(&mut self.commands.0) {
+        c(self);
+      }
+    }
+  }
+
+  pub(crate) fn new_frame(&self) {
+    let w = self.g();
+    w.system_times = mem::take(&mut w.frame_times);
   }
 
   pub fn run_system(&self, stage: usize) {
     if let Some(vec) = self.systems.get(&stage) {
-      for (sys, name) in vec.clone() {
+      for (id, sys, name) in vec.clone() {
         puffin::profile_scope!(name);
+        let start = Instant::now();
+        let w = self.g();
+        w.tick += 1;
+        w.since = w.last_run.insert(id, w.tick);
         if let Err(e) = sys(self.g()) {
           error!("Error in system '{}': {}", name, e);
         }
+        self.g().apply_commands();
+        *self.g().frame_times.entry(name).or_default() += start.elapsed();
       }
     }
   }
@@ -114,6 +236,131 @@ impl World {
 #[component]
 pub struct Name(pub String);
 
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Parent(pub usize);
+
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Children(pub Vec<usize>);
+
+// narrows a query by the other components an entity has. a tuple needs all of its filters to
+// match, and zero sized components like `struct Enemy;` can be inserted just to be filtered on
+pub trait Filter {
+  fn matches(world: &World, id: usize) -> bool;
+}
+
+pub struct With<T>(PhantomData<T>);
+
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Any> Filter for With<T> {
+  fn matches(world: &World, id: usize) -> bool {
+    world.has(TypeIdNamed::of::<T>(), id)
+  }
+}
+
+impl<T: Any> Filter for Without<T> {
+  fn matches(world: &World, id: usize) -> bool {
+    !world.has(TypeIdNamed::of::<T>(), id)
+  }
+}
+
+macro_rules! filter_tuple {
+  ($($f:ident),*) => {
+    impl<$($f: Filter),*> Filter for ($($f,)*) {
+      fn matches(world: &World, id: usize) -> bool {
+        $($f::matches(world, id))&&*
+      }
+    }
+  };
+}
+
+// components added since the running system last ran, or the first time it runs
+pub struct Added<T>(PhantomData<T>);
+
+// components added, or changed through `Entity::modify` or `set_changed`, since the running
+// system last ran. writes through other references aren't seen
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Any> Filter for Added<T> {
+  fn matches(world: &World, id: usize) -> bool {
+    let t = TypeIdNamed::of::<T>();
+    world.has(t, id) && world.is_new(world.ticks(t, id).added)
+  }
+}
+
+impl<T: Any> Filter for Changed<T> {
+  fn matches(world: &World, id: usize) -> bool {
+    let t = TypeIdNamed::of::<T>();
+    world.has(t, id) && world.is_new(world.ticks(t, id).changed)
+  }
+}
+
+filter_tuple!(A, B);
+filter_tuple!(A, B, C);
+filter_tuple!(A, B, C, D);
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+// changes recorded to be made once the running system returns, so entities can be spawned,
+// despawned and given components while iterating a query or from ui code that only has a
+// `&World`:
+//
+//   for (e, health) in world.query::<Health>() {
+//     if health.0 <= 0.0 {
+//       world.commands().despawn(e);
+//     }
+//   }
+pub struct Commands(Vec<Command>);
+
+impl Commands {
+  // the id can be used right away, the entity's components arrive with the other commands
+  pub fn spawn(&mut self, name: &str) -> Entity {
+    let e = Entity { id: rand::random() };
+    self.insert(e, Name(name.to_string()));
+    e
+  }
+
+  pub fn insert<T: Any>(&mut self, e: Entity, component: T) {
+    self.add(move |_| {
+      e.insert(component);
+    });
+  }
+
+  pub fn insert_id(&mut self, t: TypeIdNamed, id: usize, c: Box<dyn Any>) {
+    self.add(move |world| world.insert_id(t, id, c));
+  }
+
+  pub fn remove<T: Any>(&mut self, e: Entity) {
+    self.add(move |_| {
+      e.remove::<T>();
+    });
+  }
+
+  pub fn remove_id(&mut self, t: TypeIdNamed, id: usize) {
+    self.add(move |world| world.remove_id(t, id));
+  }
+
+  // along with its children
+  pub fn despawn(&mut self, e: Entity) {
+    self.add(move |_| e.despawn());
+  }
+
+  // anything else, run in the order recorded
+  pub fn add<F: FnOnce(&mut World) + 'static>(&mut self, f: F) {
+    self.0.push(Box::new(f));
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+}
+
 #[derive(Clone, Copy)]
 pub struct Entity {
   pub id: usize,
@@ -121,16 +368,43 @@ pub struct Entity {
 
 impl Entity {
   pub fn insert<T: Any>(self, component: T) -> Self {
-    unsafe {
-      WORLD
-        .get_mut()
-        .unwrap()
-        .components
-        .push_or_insert(TypeIdNamed::of::<T>(), (self.id, Box::new(component)));
-    }
+    unsafe { WORLD.get_mut().unwrap() }.insert_id(
+      TypeIdNamed::of::<T>(),
+      self.id,
+      Box::new(component),
+    );
     self
   }
 
+  // every component of the type, a `Parent` is removed through `set_parent` so the parent's
+  // `Children` stays in sync
+  pub fn remove<T: Any>(self) -> Self {
+    let t = TypeIdNamed::of::<T>();
+    match t == TypeIdNamed::of::<Parent>() {
+      true => self.set_parent(None),
+      false => {
+        unsafe { WORLD.get_mut().unwrap() }.remove_id(t, self.id);
+        self
+      }
+    }
+  }
+
+  // removes all its components and its children's, don't despawn while iterating a query that
+  // could include them, use `Commands::despawn` for that
+  pub fn despawn(self) {
+    for child in self.children() {
+      child.despawn();
+    }
+    self.set_parent(None);
+    let world = unsafe { WORLD.get_mut().unwrap() };
+    for v in world.components.values_mut() {
+      v.retain(|c| c.0 != self.id);
+    }
+    for ticks in world.ticks.values_mut() {
+      ticks.remove(&self.id);
+    }
+  }
+
   pub fn get<T: Any>(&self) -> Vec<&mut T> {
     let t = TypeIdNamed::of::<T>();
     puffin::profile_function!(t.name);
@@ -149,6 +423,72 @@ impl Entity {
     self.get().pop()
   }
 
+  // like `get_one` but marks it changed, for writes that `Changed` filters should see
+  pub fn modify<T: Any>(&self) -> Option<&mut T> {
+    let c = self.get_one();
+    if c.is_some() {
+      self.set_changed::<T>();
+    }
+    c
+  }
+
+  pub fn set_changed<T: Any>(&self) {
+    unsafe { WORLD.get_mut().unwrap() }.set_changed(TypeIdNamed::of::<T>(), self.id);
+  }
+
+  pub fn has<T: Any>(&self) -> bool {
+    unsafe { WORLD.get_mut().unwrap() }.has(TypeIdNamed::of::<T>(), self.id)
+  }
+
+  pub fn parent(&self) -> Option<Entity> {
+    self.get_one::<Parent>().map(|p| Entity { id: p.0 })
+  }
+
+  pub fn children(&self) -> Vec<Entity> {
+    match self.get_one::<Children>() {
+      Some(c) => c.0.iter().map(|id| Entity { id: *id }).collect(),
+      None => vec![],
+    }
+  }
+
+  pub fn set_parent(self, parent: Option<Entity>) -> Self {
+    let world = unsafe { WORLD.get_mut().unwrap() };
+    if let Some(old) = self.parent() {
+      if let Some(c) = old.get_one::<Children>() {
+        c.0.retain(|id| *id != self.id);
+      }
+      world.remove_id(TypeIdNamed::of::<Parent>(), self.id);
+    }
+    if let Some(parent) = parent {
+      // refuse to create cycles
+      let mut p = Some(parent);
+      while let Some(e) = p {
+        if e.id == self.id {
+          return self;
+        }
+        p = e.parent();
+      }
+      match parent.get_one::<Children>() {
+        Some(c) => c.0.push(self.id),
+        None => {
+          parent.insert(Children(vec![self.id]));
+        }
+      }
+      self.insert(Parent(parent.id));
+    }
+    // whatever depends on where it sits, like its global transform, needs redoing
+    let types = world
+      .components
+      .iter()
+      .filter(|(_, v)| v.iter().any(|c| c.0 == self.id))
+      .map(|(t, _)| *t)
+      .collect::<Vec<_>>();
+    for t in types {
+      world.set_changed(t, self.id);
+    }
+    self
+  }
+
   pub fn get_all(&self) -> BTreeMap<TypeIdNamed, Vec<&mut Box<dyn Any>>> {
     puffin::profile_function!();
     let mut components = BTreeMap::new();
@@ -166,3 +506,104 @@ impl Entity {
     components
   }
 }
+
+// keeps released entities' component boxes and puts them back on the next acquire, for things
+// spawned and despawned often like bullets or hit effects. only pool entities without children
+pub struct EntityPool {
+  // entities kept for reuse, any released past this are dropped
+  pub max: usize,
+  free: Vec<(usize, Vec<(TypeIdNamed, Box<dyn Any>)>)>,
+}
+
+impl EntityPool {
+  pub fn new(max: usize) -> Self {
+    Self { max, free: vec![] }
+  }
+
+  pub fn len(&self) -> usize {
+    self.free.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.free.is_empty()
+  }
+
+  // a released entity with the components it had, reset what you need, or a new one from `spawn`
+  pub fn acquire<F: FnOnce(&World) -> Entity>(&mut self, world: &World, spawn: F) -> Entity {
+    match self.free.pop() {
+      Some((id, components)) => {
+        for (t, c) in components {
+          world.insert_id(t, id, c);
+        }
+        Entity { id }
+      }
+      None => spawn(world),
+    }
+  }
+
+  // takes the entity out of the world, keeping its components for the next acquire
+  pub fn release(&mut self, world: &World, e: Entity) {
+    e.set_parent(None);
+    let mut components = vec![];
+    for (t, v) in world.g().components.iter_mut() {
+      // removed in place so query order, eg which camera comes first, doesn't change
+      while let Some(i) = v.iter().position(|c| c.0 == e.id) {
+        components.push((*t, v.remove(i).1));
+      }
+    }
+    for ticks in world.g().ticks.values_mut() {
+      ticks.remove(&e.id);
+    }
+    if self.free.len() < self.max {
+      self.free.push((e.id, components));
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.free.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct Counter(u32);
+
+  struct Seen(usize);
+
+  fn count(world: &mut World) -> Result {
+    for (e, _) in world.query::<Counter>() {
+      e.modify::<Counter>().unwrap().0 += 1;
+    }
+    Ok(())
+  }
+
+  fn watch(world: &mut World) -> Result {
+    world.get_resource::<Seen>().unwrap().0 =
+      world.query_filtered::<Counter, Changed<Counter>>().len();
+    Ok(())
+  }
+
+  #[test]
+  fn changed_between_systems() {
+    let _ = unsafe { WORLD.set(World::new()) };
+    let world = unsafe { WORLD.get_mut().unwrap() };
+    world.add_resource(Seen(0));
+    world.spawn("counter").insert(Counter(0));
+    world.add_system(stage::PRE_DRAW, count);
+    world.add_system(stage::DRAW, watch);
+    let seen = || world.get_resource::<Seen>().unwrap().0;
+
+    world.run_system(stage::PRE_DRAW);
+    world.run_system(stage::DRAW);
+    assert_eq!(seen(), 1);
+    // `count` changed it since `watch` last ran
+    world.run_system(stage::PRE_DRAW);
+    world.run_system(stage::DRAW);
+    assert_eq!(seen(), 1);
+    // nothing did
+    world.run_system(stage::DRAW);
+    assert_eq!(seen(), 0);
+  }
+}
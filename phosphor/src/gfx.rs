@@ -1,61 +1,511 @@
-use std::ptr;
+use std::{mem, ptr};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::marker::PhantomData;
+use std::collections::{VecDeque, HashMap};
 use std::fs::{self, File};
+use std::path::Path;
 use std::io::BufReader;
-use std::ffi::{CStr, CString};
+use std::ffi::{CStr, CString, c_void};
 use std::sync::mpsc::Receiver;
-use glfw::{Context, WindowHint, WindowEvent, WindowMode};
-use glam::{Mat4, Vec3};
-use image::imageops;
+use glfw::{
+  Context, ContextCreationApi, WindowHint, WindowEvent, WindowMode, PixelImage, StandardCursor,
+};
+use glam::{Mat4, Vec4, Vec3, Vec2, BVec3};
+use image::{imageops, ImageFormat, RgbaImage};
+use raw_window_handle::{HasRawWindowHandle, HasRawDisplayHandle, RawWindowHandle, RawDisplayHandle};
 use obj::{Obj, TexturedVertex};
-use log::{debug, trace, error};
+use once_cell::unsync::OnceCell;
+use log::{debug, info, trace, warn, error};
 use shader_prepper::{ResolvedInclude, ResolvedIncludePath};
 use crate::ecs::World;
-use crate::{Result, asset};
+use crate::streaming::TextureStreamer;
+use crate::compressed::load_compressed;
+use crate::raycast::Bvh;
+use crate::vram::{self, GpuKind};
+use crate::{Result, asset, cvar};
 
 pub use gl;
 
+// asks for a debug context and logs what the driver reports, read when the window is created
+cvar!(pub gl_debug: bool = false);
+// lowest severity logged, 0 is notifications, 1 low, 2 medium and 3 high
+cvar!(pub gl_debug_severity: i32 = 1);
+// frames waited between buffer swaps, 0 disables vsync and -1 is adaptive sync, tearing frames that
+// miss the refresh instead of waiting for the next one. changes apply on the next frame
+cvar!(pub swap_interval: i32 = 1);
+
 pub struct Renderer {
   pub glfw: glfw::Glfw,
   pub window: glfw::Window,
   pub events: Receiver<(f64, WindowEvent)>,
   pub version: &'static str,
   pub renderer: &'static str,
+  interval: i32,
+  fullscreen: Fullscreen,
+  // where the window was before leaving windowed mode, to go back to
+  windowed: (i32, i32, i32, i32),
+  // sent along with the window's own events on the next frame
+  pub(crate) queued: Vec<WindowEvent>,
+  // what `set_cursor` asked for and what the window last got
+  cursor: Option<Cursor>,
+  applied_cursor: Option<Cursor>,
+  // what was last applied, gl state should only be changed through `apply_state`
+  state: Cell<RenderState>,
+}
+
+// the native window, for other crates to draw into it or parent their own windows to it, eg video
+// players, file dialogs or a wgpu surface. only valid while the renderer is alive
+unsafe impl HasRawWindowHandle for Renderer {
+  fn raw_window_handle(&self) -> RawWindowHandle {
+    self.window.raw_window_handle()
+  }
+}
+
+unsafe impl HasRawDisplayHandle for Renderer {
+  fn raw_display_handle(&self) -> RawDisplayHandle {
+    self.window.raw_display_handle()
+  }
+}
+
+// blend factors for color then alpha
+#[derive(Copy, Clone, PartialEq)]
+pub struct BlendFunc(pub u32, pub u32, pub u32, pub u32);
+
+impl BlendFunc {
+  // over what's already there
+  pub const ALPHA: Self = Self(
+    gl::SRC_ALPHA,
+    gl::ONE_MINUS_SRC_ALPHA,
+    gl::ONE,
+    gl::ONE_MINUS_SRC_ALPHA,
+  );
+  pub const ADDITIVE: Self = Self(gl::SRC_ALPHA, gl::ONE, gl::SRC_ALPHA, gl::ONE);
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum CullFace {
+  Front,
+  Back,
+}
+
+// fixed function state for a draw. change a copy of `Renderer::state` and put it back afterwards,
+// so nothing leaks into whatever draws next
+#[derive(Copy, Clone, PartialEq)]
+pub struct RenderState {
+  pub depth_test: bool,
+  pub depth_write: bool,
+  pub blend: Option<BlendFunc>,
+  pub cull: Option<CullFace>,
+  // x, y, width and height from the bottom left
+  pub scissor: Option<[i32; 4]>,
+}
+
+impl RenderState {
+  pub const DEFAULT: Self = Self {
+    depth_test: true,
+    depth_write: true,
+    blend: None,
+    cull: None,
+    scissor: None,
+  };
+}
+
+unsafe fn set_enabled(cap: u32, enabled: bool) {
+  match enabled {
+    true => gl::Enable(cap),
+    false => gl::Disable(cap),
+  }
+}
+
+// glfw's images are rgba bytes packed into u32s
+fn pixel_image(image: &RgbaImage) -> PixelImage {
+  PixelImage {
+    width: image.width(),
+    height: image.height(),
+    pixels: image.pixels().map(|p| u32::from_ne_bytes(p.0)).collect(),
+  }
+}
+
+// a mouse cursor kept on the cpu, the window's own is made from it when it's set. clones share the
+// image and compare equal
+#[derive(Clone)]
+pub enum Cursor {
+  Standard(StandardCursor),
+  // hotspot in pixels from the top left
+  Image(Rc<RgbaImage>, (u32, u32)),
+}
+
+impl Cursor {
+  pub fn from_image(image: RgbaImage, hotspot: (u32, u32)) -> Self {
+    Self::Image(Rc::new(image), hotspot)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P, hotspot: (u32, u32)) -> Result<Self> {
+    Ok(Self::from_image(image::open(path)?.to_rgba8(), hotspot))
+  }
+
+  fn create(&self) -> glfw::Cursor {
+    match self {
+      Self::Standard(c) => glfw::Cursor::standard(*c),
+      Self::Image(img, (x, y)) => glfw::Cursor::create_from_pixels(pixel_image(img), *x, *y),
+    }
+  }
+}
+
+impl PartialEq for Cursor {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Standard(a), Self::Standard(b)) => a == b,
+      (Self::Image(a, x), Self::Image(b, y)) => Rc::ptr_eq(a, b) && x == y,
+      _ => false,
+    }
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Fullscreen {
+  Windowed,
+  // an undecorated window covering the monitor at the desktop's video mode, switches instantly
+  Borderless(usize),
+  // takes the monitor over at its current video mode
+  Exclusive(usize),
+}
+
+// read once when the engine creates the window, add it as a resource before running
+#[derive(Copy, Clone)]
+pub struct WindowOptions {
+  pub title: &'static str,
+  // an image file shown for the window and taskbar, glfw's own when none
+  pub icon: Option<&'static str>,
+  pub size: (u32, u32),
+  pub min_size: Option<(u32, u32)>,
+  pub resizable: bool,
+  pub maximized: bool,
+  pub fullscreen: Fullscreen,
+  // hidden windows still render and can be read back, eg for ci or thumbnails
+  pub visible: bool,
+  // egl or osmesa contexts can be used where there's no gpu driver for the window system, osmesa
+  // renders in software. glfw needs a display server to make any window, under ci use xvfb
+  pub context: ContextCreationApi,
+  // 3.3 is the lowest the renderer works with
+  pub gl_version: (u32, u32),
+}
+
+impl WindowOptions {
+  pub const DEFAULT: Self = Self {
+    title: "phosphor",
+    icon: None,
+    size: (1400, 800),
+    min_size: None,
+    resizable: true,
+    maximized: false,
+    fullscreen: Fullscreen::Windowed,
+    visible: true,
+    context: ContextCreationApi::Native,
+    gl_version: (3, 3),
+  };
+
+  pub fn title(mut self, title: &'static str) -> Self {
+    self.title = title;
+    self
+  }
+
+  pub fn icon(mut self, path: &'static str) -> Self {
+    self.icon = Some(path);
+    self
+  }
+
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.size = (width, height);
+    self
+  }
+
+  pub fn min_size(mut self, width: u32, height: u32) -> Self {
+    self.min_size = Some((width, height));
+    self
+  }
+
+  pub fn resizable(mut self, resizable: bool) -> Self {
+    self.resizable = resizable;
+    self
+  }
+
+  pub fn maximized(mut self, maximized: bool) -> Self {
+    self.maximized = maximized;
+    self
+  }
+
+  pub fn fullscreen(mut self, fullscreen: Fullscreen) -> Self {
+    self.fullscreen = fullscreen;
+    self
+  }
+
+  pub fn visible(mut self, visible: bool) -> Self {
+    self.visible = visible;
+    self
+  }
+
+  pub fn context(mut self, context: ContextCreationApi) -> Self {
+    self.context = context;
+    self
+  }
+
+  pub fn gl_version(mut self, major: u32, minor: u32) -> Self {
+    self.gl_version = (major, minor);
+    self
+  }
 }
 
 impl Renderer {
-  pub fn new() -> Result<Self> {
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
-    glfw.window_hint(WindowHint::ContextVersion(3, 3));
+  // hidden window for rendering without showing anything, eg thumbnails
+  pub fn new_headless(width: u32, height: u32) -> Result<Self> {
+    Self::new(&WindowOptions::DEFAULT.size(width, height).visible(false))
+  }
+
+  pub fn new(options: &WindowOptions) -> Result<Self> {
+    let visible = options.visible;
+    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS)
+      .map_err(|e| format!("Couldn't initialize GLFW, is there a display? {:?}", e))?;
+    let (major, minor) = options.gl_version;
+    glfw.window_hint(WindowHint::ContextVersion(major, minor));
     glfw.window_hint(WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
     glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+    // shown once it's in the right mode
+    glfw.window_hint(WindowHint::Visible(
+      visible && options.fullscreen == Fullscreen::Windowed,
+    ));
+    glfw.window_hint(WindowHint::Resizable(options.resizable));
+    glfw.window_hint(WindowHint::Maximized(options.maximized && visible));
+    glfw.window_hint(WindowHint::OpenGlDebugContext(gl_debug.get()));
+    glfw.window_hint(WindowHint::ContextCreationApi(options.context));
+    let (w, h) = options.size;
     let (mut window, events) = glfw
-      .create_window(1400, 800, "phosphor", WindowMode::Windowed)
-      .unwrap();
+      .create_window(w, h, options.title, WindowMode::Windowed)
+      .ok_or("Couldn't create window.")?;
+    if let Some((w, h)) = options.min_size {
+      window.set_size_limits(Some(w), Some(h), None, None);
+    }
     window.make_current();
     window.set_all_polling(true);
     gl::load_with(|s| window.get_proc_address(s));
     unsafe {
       gl::Enable(gl::FRAMEBUFFER_SRGB);
       gl::Enable(gl::LINE_SMOOTH);
-      gl::Enable(gl::DEPTH_TEST);
-      gl::Enable(gl::SCISSOR_TEST);
       let version = CStr::from_ptr(gl::GetString(gl::VERSION) as _).to_str()?;
       let renderer = CStr::from_ptr(gl::GetString(gl::RENDERER) as _).to_str()?;
       debug!("Initialized OpenGL {} renderer on '{}'.", version, renderer);
-      Ok(Self {
+      if gl_debug.get() {
+        // KHR_debug is core from 4.3, older drivers usually have it as an extension
+        match gl::DebugMessageCallback::is_loaded() {
+          true => {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            gl::DebugMessageCallback(Some(debug_callback), ptr::null());
+            debug!("Enabled OpenGL debug output.");
+          }
+          false => warn!("OpenGL debug output isn't supported."),
+        }
+      }
+      let mut renderer = Self {
         glfw,
         window,
         events,
         version,
         renderer,
-      })
+        interval: 0,
+        fullscreen: Fullscreen::Windowed,
+        windowed: (0, 0, 0, 0),
+        queued: vec![],
+        cursor: None,
+        applied_cursor: None,
+        // what a new context starts with
+        state: Cell::new(RenderState {
+          depth_test: false,
+          ..RenderState::DEFAULT
+        }),
+      };
+      renderer.apply_state(RenderState::DEFAULT);
+      renderer.apply_swap_interval(swap_interval.get());
+      if let Some(icon) = options.icon {
+        match image::open(icon) {
+          Ok(img) => renderer.set_icon(&img.to_rgba8()),
+          Err(e) => warn!("Couldn't load icon '{}'. {}", icon, e),
+        }
+      }
+      if visible && options.fullscreen != Fullscreen::Windowed {
+        renderer.set_fullscreen(options.fullscreen)?;
+        renderer.window.show();
+      }
+      Ok(renderer)
+    }
+  }
+
+  pub fn set_swap_interval(&mut self, interval: i32) {
+    swap_interval.set(interval);
+    if interval != self.interval {
+      self.apply_swap_interval(interval);
+    }
+  }
+
+  fn apply_swap_interval(&mut self, interval: i32) {
+    // kept as asked for so an unsupported value only warns once
+    self.interval = interval;
+    let tear = self.glfw.extension_supported("WGL_EXT_swap_control_tear")
+      || self.glfw.extension_supported("GLX_EXT_swap_control_tear");
+    self.glfw.set_swap_interval(match interval {
+      i if i < 0 && tear => glfw::SwapInterval::Adaptive,
+      i if i < 0 => {
+        warn!("Adaptive sync isn't supported, using vsync.");
+        glfw::SwapInterval::Sync(1)
+      }
+      0 => glfw::SwapInterval::None,
+      i => glfw::SwapInterval::Sync(i as _),
+    });
+  }
+
+  // the viewport follows straight away, plugins see a FramebufferSize event on the next frame
+  pub fn set_fullscreen(&mut self, fullscreen: Fullscreen) -> Result {
+    if fullscreen == self.fullscreen {
+      return Ok(());
+    }
+    if self.fullscreen == Fullscreen::Windowed {
+      let (x, y) = self.window.get_pos();
+      let (w, h) = self.window.get_size();
+      self.windowed = (x, y, w, h);
     }
+    let window = &mut self.window;
+    let (x, y, w, h) = self.windowed;
+    self.glfw.with_connected_monitors(|_, monitors| -> Result {
+      let monitor = |i: usize| monitors.get(i).ok_or(format!("There's no monitor {}.", i));
+      match fullscreen {
+        Fullscreen::Windowed => {
+          window.set_decorated(true);
+          window.set_monitor(WindowMode::Windowed, x, y, w as _, h as _, None);
+        }
+        Fullscreen::Borderless(i) => {
+          let m = monitor(i)?;
+          let (x, y) = m.get_pos();
+          let mode = m
+            .get_video_mode()
+            .ok_or("Couldn't get the monitor's video mode.")?;
+          window.set_decorated(false);
+          window.set_monitor(WindowMode::Windowed, x, y, mode.width, mode.height, None);
+        }
+        Fullscreen::Exclusive(i) => {
+          let m = monitor(i)?;
+          let mode = m
+            .get_video_mode()
+            .ok_or("Couldn't get the monitor's video mode.")?;
+          window.set_monitor(
+            WindowMode::FullScreen(m),
+            0,
+            0,
+            mode.width,
+            mode.height,
+            Some(mode.refresh_rate),
+          );
+        }
+      }
+      Ok(())
+    })?;
+    self.fullscreen = fullscreen;
+    let (w, h) = self.window.get_framebuffer_size();
+    self.resize(w as _, h as _);
+    self.queued.push(WindowEvent::FramebufferSize(w, h));
+    Ok(())
+  }
+
+  pub fn fullscreen(&self) -> Fullscreen {
+    self.fullscreen
+  }
+
+  pub fn set_title(&mut self, title: &str) {
+    self.window.set_title(title);
+  }
+
+  // scaled by the system to the sizes it needs, ignored on macos where the bundle's icon is used
+  pub fn set_icon(&mut self, image: &RgbaImage) {
+    self.window.set_icon_from_pixels(vec![pixel_image(image)]);
+  }
+
+  // shown over the window instead of the default and ui cursors until set back to none. it's
+  // cheap to set every frame, the window only changes at the end of a frame where it differs
+  pub fn set_cursor(&mut self, cursor: Option<Cursor>) {
+    self.cursor = cursor;
+  }
+
+  pub fn cursor(&self) -> Option<&Cursor> {
+    self.cursor.as_ref()
+  }
+
+  pub(crate) fn apply_cursor(&mut self) {
+    if self.cursor != self.applied_cursor {
+      self
+        .window
+        .set_cursor(self.cursor.as_ref().map(|c| c.create()));
+      self.applied_cursor = self.cursor.clone();
+    }
+  }
+
+  pub fn set_vsync(&mut self, vsync: bool) {
+    self.set_swap_interval(vsync as _);
+  }
+
+  pub fn swap_interval(&self) -> i32 {
+    self.interval
+  }
+
+  // saves the whole window as a png, call it after drawing and before the buffers are swapped.
+  // `Framebuffer::DEFAULT.read_pixels` reads it back without saving
+  pub fn screenshot<P: AsRef<Path>>(&self, path: P) -> Result {
+    let (w, h) = self.window.get_framebuffer_size();
+    let img = Framebuffer::DEFAULT.read_pixels((0, 0, w as _, h as _));
+    img.save_with_format(path.as_ref(), ImageFormat::Png)?;
+    debug!("Saved screenshot '{}'.", path.as_ref().display());
+    Ok(())
   }
 
   pub fn resize(&self, w: u32, h: u32) {
     unsafe {
       gl::Viewport(0, 0, w as _, h as _);
-      gl::Scissor(0, 0, w as _, h as _);
+    }
+  }
+
+  pub fn state(&self) -> RenderState {
+    self.state.get()
+  }
+
+  // only sets what changed since the last state
+  pub fn apply_state(&self, state: RenderState) {
+    let old = self.state.replace(state);
+    unsafe {
+      if state.depth_test != old.depth_test {
+        set_enabled(gl::DEPTH_TEST, state.depth_test);
+      }
+      if state.depth_write != old.depth_write {
+        gl::DepthMask(state.depth_write as _);
+      }
+      if state.blend != old.blend {
+        set_enabled(gl::BLEND, state.blend.is_some());
+        if let Some(BlendFunc(src, dst, src_alpha, dst_alpha)) = state.blend {
+          gl::BlendFuncSeparate(src, dst, src_alpha, dst_alpha);
+        }
+      }
+      if state.cull != old.cull {
+        set_enabled(gl::CULL_FACE, state.cull.is_some());
+        match state.cull {
+          Some(CullFace::Front) => gl::CullFace(gl::FRONT),
+          Some(CullFace::Back) => gl::CullFace(gl::BACK),
+          None => {}
+        }
+      }
+      if state.scissor != old.scissor {
+        set_enabled(gl::SCISSOR_TEST, state.scissor.is_some());
+        if let Some([x, y, w, h]) = state.scissor {
+          gl::Scissor(x, y, w, h);
+        }
+      }
     }
   }
 
@@ -67,6 +517,62 @@ impl Renderer {
   }
 }
 
+extern "system" fn debug_callback(
+  source: u32,
+  typ: u32,
+  id: u32,
+  severity: u32,
+  _: i32,
+  message: *const gl::types::GLchar,
+  _: *mut c_void,
+) {
+  let level = match severity {
+    gl::DEBUG_SEVERITY_HIGH => 3,
+    gl::DEBUG_SEVERITY_MEDIUM => 2,
+    gl::DEBUG_SEVERITY_LOW => 1,
+    _ => 0,
+  };
+  if level < gl_debug_severity.get() {
+    return;
+  }
+  let source = match source {
+    gl::DEBUG_SOURCE_API => "API",
+    gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+    gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+    gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+    gl::DEBUG_SOURCE_APPLICATION => "application",
+    _ => "other",
+  };
+  let typ = match typ {
+    gl::DEBUG_TYPE_ERROR => "error",
+    gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+    gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+    gl::DEBUG_TYPE_PORTABILITY => "portability",
+    gl::DEBUG_TYPE_PERFORMANCE => "performance",
+    gl::DEBUG_TYPE_MARKER => "marker",
+    _ => "other",
+  };
+  let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+  let message = format!("OpenGL {} {} {}: {}", source, typ, id, message.trim_end());
+  match level {
+    3 => error!("{}", message),
+    2 => warn!("{}", message),
+    1 => info!("{}", message),
+    _ => debug!("{}", message),
+  }
+}
+
+// names an object in debug output, graphics debuggers and the vram list, `identifier` is its
+// kind eg gl::TEXTURE. only reaches gl with gl_debug
+pub fn object_label(identifier: u32, id: u32, label: &str) {
+  vram::label(identifier, id, label);
+  if gl_debug.get() && gl::ObjectLabel::is_loaded() {
+    unsafe {
+      gl::ObjectLabel(identifier, id, label.len() as _, label.as_ptr() as _);
+    }
+  }
+}
+
 struct FileIncludeProvider;
 impl shader_prepper::IncludeProvider for FileIncludeProvider {
   type IncludeContext = ();
@@ -87,7 +593,7 @@ impl shader_prepper::IncludeProvider for FileIncludeProvider {
   }
 }
 
-unsafe fn compile_shader(path: &str, ty: u32) -> Result<u32> {
+unsafe fn compile_shader(path: &str, ty: u32, defines: &[&str]) -> Result<u32> {
   trace!("Compiling shader '{}'.", path);
   let shader = gl::CreateShader(ty);
   let src = shader_prepper::process_file(path, &mut FileIncludeProvider, ())?
@@ -95,6 +601,17 @@ unsafe fn compile_shader(path: &str, ty: u32) -> Result<u32> {
     .map(|c| c.source)
     .collect::<Vec<String>>()
     .join("");
+  // defines have to come after the version
+  let defines = defines
+    .iter()
+    .map(|d| format!("#define {}\n", d))
+    .collect::<String>();
+  let src = match src.split_once('\n') {
+    Some((version, rest)) if version.starts_with("#version") => {
+      format!("{}\n{}{}", version, defines, rest)
+    }
+    _ => defines + &src,
+  };
   gl::ShaderSource(
     shader,
     1,
@@ -112,20 +629,112 @@ unsafe fn compile_shader(path: &str, ty: u32) -> Result<u32> {
   Ok(shader)
 }
 
+// the linked binary's size is the closest thing to a program's footprint
+#[track_caller]
+unsafe fn track_program(program: u32) {
+  let mut len = 0;
+  gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut len);
+  vram::track(GpuKind::Program, program, len.max(0) as _);
+}
+
 #[derive(Copy, Clone)]
 pub struct Shader(pub u32);
 
 impl Shader {
+  #[track_caller]
   pub fn new(vert_path: &str, frag_path: &str) -> Result<Self> {
+    Self::with_defines(vert_path, frag_path, &[])
+  }
+
+  // each define is added as `#define NAME` to both stages
+  #[track_caller]
+  pub fn with_defines(vert_path: &str, frag_path: &str, defines: &[&str]) -> Result<Self> {
+    Self::link(
+      &[
+        (vert_path, gl::VERTEX_SHADER),
+        (frag_path, gl::FRAGMENT_SHADER),
+      ],
+      defines,
+    )
+  }
+
+  // a geometry shader between the vertex and fragment stages, eg for extruding grass or drawing
+  // normals and wide lines
+  #[track_caller]
+  pub fn with_stages(vert_path: &str, geom_path: &str, frag_path: &str) -> Result<Self> {
+    Self::link(
+      &[
+        (vert_path, gl::VERTEX_SHADER),
+        (geom_path, gl::GEOMETRY_SHADER),
+        (frag_path, gl::FRAGMENT_SHADER),
+      ],
+      &[],
+    )
+  }
+
+  // needs opengl 4.0, meshes are drawn with `draw_patches`
+  #[track_caller]
+  pub fn with_tessellation(
+    vert_path: &str,
+    control_path: &str,
+    eval_path: &str,
+    frag_path: &str,
+  ) -> Result<Self> {
+    Self::link(
+      &[
+        (vert_path, gl::VERTEX_SHADER),
+        (control_path, gl::TESS_CONTROL_SHADER),
+        (eval_path, gl::TESS_EVALUATION_SHADER),
+        (frag_path, gl::FRAGMENT_SHADER),
+      ],
+      &[],
+    )
+  }
+
+  #[track_caller]
+  fn link(stages: &[(&str, u32)], defines: &[&str]) -> Result<Self> {
     unsafe {
-      let vert = compile_shader(vert_path, gl::VERTEX_SHADER)?;
-      let frag = compile_shader(frag_path, gl::FRAGMENT_SHADER)?;
       let program = gl::CreateProgram();
-      gl::AttachShader(program, vert);
-      gl::AttachShader(program, frag);
+      let mut shaders = vec![];
+      for (path, ty) in stages {
+        let shader = compile_shader(path, *ty, defines)?;
+        gl::AttachShader(program, shader);
+        shaders.push(shader);
+      }
       gl::LinkProgram(program);
-      gl::DeleteShader(vert);
-      gl::DeleteShader(frag);
+      for shader in shaders {
+        gl::DeleteShader(shader);
+      }
+      let paths = stages.iter().map(|s| s.0).collect::<Vec<_>>().join(" ");
+      // mismatched stages usually only fail here
+      let mut success = 0;
+      gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+      if success == 0 {
+        let err = CString::from_vec_unchecked(vec![0; 1024]);
+        gl::GetProgramInfoLog(program, 1024, ptr::null_mut(), err.as_ptr() as _);
+        error!("Failed to link '{}':\n{}", paths, err.to_str()?);
+      }
+      track_program(program);
+      let label = match defines.is_empty() {
+        true => paths,
+        false => format!("{} {}", paths, defines.join(" ")),
+      };
+      object_label(gl::PROGRAM, program, &label);
+      Ok(Self(program))
+    }
+  }
+
+  // needs opengl 4.3
+  #[track_caller]
+  pub fn new_compute(path: &str) -> Result<Self> {
+    unsafe {
+      let comp = compile_shader(path, gl::COMPUTE_SHADER, &[])?;
+      let program = gl::CreateProgram();
+      gl::AttachShader(program, comp);
+      gl::LinkProgram(program);
+      gl::DeleteShader(comp);
+      track_program(program);
+      object_label(gl::PROGRAM, program, path);
       Ok(Self(program))
     }
   }
@@ -134,6 +743,18 @@ impl Shader {
     unsafe { gl::UseProgram(self.0) }
   }
 
+  // points the uniform block `name` at a UniformBuffer's binding, blocks the shader doesn't
+  // have are ignored
+  pub fn bind_block(&self, name: &str, binding: u32) {
+    let c = CString::new(name).unwrap();
+    unsafe {
+      let index = gl::GetUniformBlockIndex(self.0, c.as_ptr() as _);
+      if index != gl::INVALID_INDEX {
+        gl::UniformBlockBinding(self.0, index, binding);
+      }
+    }
+  }
+
   fn get_loc(&self, name: &str) -> i32 {
     let c = CString::new(name).unwrap();
     unsafe { gl::GetUniformLocation(self.0, c.as_ptr() as _) }
@@ -151,10 +772,18 @@ impl Shader {
     }
   }
 
+  pub fn set_vec2(&self, name: &str, val: &Vec2) {
+    unsafe { gl::ProgramUniform2fv(self.0 as _, self.get_loc(name), 1, val.to_array().as_ptr()) }
+  }
+
   pub fn set_vec3(&self, name: &str, val: &Vec3) {
     unsafe { gl::ProgramUniform3fv(self.0 as _, self.get_loc(name), 1, val.to_array().as_ptr()) }
   }
 
+  pub fn set_vec4(&self, name: &str, val: &Vec4) {
+    unsafe { gl::ProgramUniform4fv(self.0 as _, self.get_loc(name), 1, val.to_array().as_ptr()) }
+  }
+
   pub fn set_i32(&self, name: &str, val: &i32) {
     unsafe {
       gl::ProgramUniform1i(self.0 as _, self.get_loc(name), *val);
@@ -166,44 +795,424 @@ impl Shader {
       gl::ProgramUniform1f(self.0 as _, self.get_loc(name), *val);
     }
   }
+
+  // uniforms the compiler kept, arrays, struct members and unsupported types are left out
+  pub fn uniforms(&self) -> Vec<Uniform> {
+    let mut uniforms = vec![];
+    unsafe {
+      let mut count = 0;
+      gl::GetProgramiv(self.0, gl::ACTIVE_UNIFORMS, &mut count);
+      for i in 0..count {
+        let mut name = [0u8; 256];
+        let (mut len, mut size, mut typ) = (0, 0, 0);
+        gl::GetActiveUniform(
+          self.0,
+          i as _,
+          name.len() as _,
+          &mut len,
+          &mut size,
+          &mut typ,
+          name.as_mut_ptr() as _,
+        );
+        let name = String::from_utf8_lossy(&name[..len as usize]).to_string();
+        if size > 1 || name.contains(['[', '.']) {
+          continue;
+        }
+        if let Some(typ) = UniformType::from_gl(typ) {
+          uniforms.push(Uniform { name, typ });
+        }
+      }
+    }
+    uniforms
+  }
+}
+
+// a shader pair compiled once for each set of defines it's asked for, so features are
+// switched at compile time rather than with uniforms
+pub struct ShaderVariants {
+  vert: String,
+  frag: String,
+  // uniform blocks bound on every variant
+  blocks: Vec<(String, u32)>,
+  variants: HashMap<Vec<String>, Shader>,
 }
 
+impl ShaderVariants {
+  pub fn new(vert_path: &str, frag_path: &str) -> Self {
+    Self {
+      vert: vert_path.to_string(),
+      frag: frag_path.to_string(),
+      blocks: vec![],
+      variants: HashMap::new(),
+    }
+  }
+
+  pub fn block(mut self, name: &str, binding: u32) -> Self {
+    self.blocks.push((name.to_string(), binding));
+    self
+  }
+
+  // the order of `defines` doesn't matter
+  pub fn get(&mut self, defines: &[&str]) -> Result<Shader> {
+    let mut key = defines.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+    key.sort();
+    key.dedup();
+    if let Some(shader) = self.variants.get(&key) {
+      return Ok(*shader);
+    }
+    debug!("Compiling '{}' with [{}].", self.frag, key.join(", "));
+    let shader = Shader::with_defines(
+      &self.vert,
+      &self.frag,
+      &key.iter().map(|d| d.as_str()).collect::<Vec<_>>(),
+    )?;
+    for (name, binding) in &self.blocks {
+      shader.bind_block(name, *binding);
+    }
+    self.variants.insert(key, shader);
+    Ok(shader)
+  }
+
+  // variants compiled so far
+  pub fn len(&self) -> usize {
+    self.variants.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.variants.is_empty()
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum UniformType {
+  Float,
+  Vec2,
+  Vec3,
+  Vec4,
+  Int,
+  Bool,
+  Mat4,
+  Sampler2D,
+}
+
+impl UniformType {
+  fn from_gl(typ: u32) -> Option<Self> {
+    Some(match typ {
+      gl::FLOAT => Self::Float,
+      gl::FLOAT_VEC2 => Self::Vec2,
+      gl::FLOAT_VEC3 => Self::Vec3,
+      gl::FLOAT_VEC4 => Self::Vec4,
+      gl::INT => Self::Int,
+      gl::BOOL => Self::Bool,
+      gl::FLOAT_MAT4 => Self::Mat4,
+      gl::SAMPLER_2D => Self::Sampler2D,
+      _ => return None,
+    })
+  }
+}
+
+pub struct Uniform {
+  pub name: String,
+  pub typ: UniformType,
+}
+
+// laid out like `VertexLayout::standard`, what models are loaded as and the pbr shaders read
 #[repr(C)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Vertex {
   pub pos: [f32; 3],
   pub uv: [f32; 2],
   pub normal: [f32; 3],
+  pub tangent: [f32; 3],
+  pub bitangent: [f32; 3],
 }
 
-#[asset(load_mesh)]
-#[derive(Clone)]
-pub struct Mesh {
-  pub vert_arr: u32,
-  pub vert_buf: u32,
-  pub idx_buf: u32,
-  pub vertices: Vec<Vertex>,
-  pub indices: Vec<u32>,
+// plain data that's uploaded byte for byte. only implement it for #[repr(C)] types with no
+// padding, pointers or references, whose every bit pattern is valid
+#[allow(clippy::missing_safety_doc)]
+pub unsafe trait Pod {}
+
+unsafe impl Pod for Vertex {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+fn as_bytes<T: Pod>(v: &[T]) -> &[u8] {
+  unsafe { std::slice::from_raw_parts(v.as_ptr() as *const u8, mem::size_of_val(v)) }
 }
 
-fn load_mesh(_: &mut World, path: &str) -> Result<Mesh> {
-  let obj: Obj<TexturedVertex, u32> = obj::load_obj(BufReader::new(File::open(path)?))?;
-  Ok(Mesh::new(
-    &obj
-      .vertices
+#[derive(Copy, Clone, PartialEq)]
+pub enum AttribType {
+  F32,
+  // 0 to 255 read as 0 to 1, eg colors
+  U8Norm,
+  // read as integers with ivec or uvec, eg joint indices
+  U8,
+  U16,
+  I32,
+}
+
+impl AttribType {
+  pub fn size(&self) -> usize {
+    match self {
+      Self::F32 | Self::I32 => 4,
+      Self::U8Norm | Self::U8 => 1,
+      Self::U16 => 2,
+    }
+  }
+
+  fn gl(&self) -> u32 {
+    match self {
+      Self::F32 => gl::FLOAT,
+      Self::U8Norm | Self::U8 => gl::UNSIGNED_BYTE,
+      Self::U16 => gl::UNSIGNED_SHORT,
+      Self::I32 => gl::INT,
+    }
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct VertexAttrib {
+  pub location: u32,
+  pub components: u32,
+  pub kind: AttribType,
+  // bytes from the start of the vertex
+  pub offset: usize,
+}
+
+// attributes are packed in the order they're added, use `pad` to match a struct's alignment.
+// locations 5 to 8 are taken by the instance matrix
+#[derive(Clone, PartialEq)]
+pub struct VertexLayout {
+  attribs: Vec<VertexAttrib>,
+  stride: usize,
+}
+
+impl VertexLayout {
+  pub fn new() -> Self {
+    Self {
+      attribs: vec![],
+      stride: 0,
+    }
+  }
+
+  // position, uv, normal, tangent and bitangent, see `Vertex`
+  pub fn standard() -> Self {
+    Self::new()
+      .attrib(0, 3, AttribType::F32)
+      .attrib(1, 2, AttribType::F32)
+      .attrib(2, 3, AttribType::F32)
+      .attrib(3, 3, AttribType::F32)
+      .attrib(4, 3, AttribType::F32)
+  }
+
+  pub fn attrib(mut self, location: u32, components: u32, kind: AttribType) -> Self {
+    self.attribs.push(VertexAttrib {
+      location,
+      components,
+      kind,
+      offset: self.stride,
+    });
+    self.stride += components as usize * kind.size();
+    self
+  }
+
+  pub fn pad(mut self, bytes: usize) -> Self {
+    self.stride += bytes;
+    self
+  }
+
+  pub fn attribs(&self) -> &[VertexAttrib] {
+    &self.attribs
+  }
+
+  pub fn stride(&self) -> usize {
+    self.stride
+  }
+
+  // points the bound vertex array's attributes at vertices `offset` bytes into the bound array
+  // buffer, for vertices streamed into a shared buffer
+  pub fn bind(&self, offset: usize) {
+    unsafe {
+      for a in &self.attribs {
+        gl::EnableVertexAttribArray(a.location);
+        let ptr = (offset + a.offset) as *const c_void;
+        match a.kind {
+          AttribType::F32 | AttribType::U8Norm => gl::VertexAttribPointer(
+            a.location,
+            a.components as _,
+            a.kind.gl(),
+            (a.kind == AttribType::U8Norm) as _,
+            self.stride as _,
+            ptr,
+          ),
+          _ => gl::VertexAttribIPointer(
+            a.location,
+            a.components as _,
+            a.kind.gl(),
+            self.stride as _,
+            ptr,
+          ),
+        }
+      }
+    }
+  }
+
+  // location 0 is taken as the position, when it's floats
+  fn positions(&self, data: &[u8]) -> Vec<Vec3> {
+    let pos = match self
+      .attribs
       .iter()
-      .map(|v| Vertex {
-        pos: v.position,
-        uv: [v.texture[0], v.texture[1]],
-        normal: v.normal,
+      .find(|a| a.location == 0 && a.kind == AttribType::F32)
+    {
+      Some(a) => a,
+      None => return vec![],
+    };
+    data
+      .chunks_exact(self.stride)
+      .map(|v| {
+        let mut p = [0.0; 3];
+        for (i, c) in p.iter_mut().enumerate().take(pos.components as usize) {
+          let at = pos.offset + i * 4;
+          *c = f32::from_ne_bytes(v[at..at + 4].try_into().unwrap());
+        }
+        Vec3::from(p)
       })
-      .collect::<Vec<_>>(),
-    &obj.indices,
-  ))
+      .collect()
+  }
 }
 
-impl Mesh {
-  pub fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
+#[derive(Copy, Clone, PartialEq)]
+pub enum IndexType {
+  U16,
+  U32,
+}
+
+impl IndexType {
+  // the smallest that fits every index
+  pub fn fitting(indices: &[u32]) -> Self {
+    match indices.iter().max() {
+      Some(i) if *i > u16::MAX as u32 => Self::U32,
+      _ => Self::U16,
+    }
+  }
+
+  pub fn size(&self) -> usize {
+    match self {
+      Self::U16 => 2,
+      Self::U32 => 4,
+    }
+  }
+
+  fn gl(&self) -> u32 {
+    match self {
+      Self::U16 => gl::UNSIGNED_SHORT,
+      Self::U32 => gl::UNSIGNED_INT,
+    }
+  }
+
+  fn encode(&self, indices: &[u32]) -> Vec<u8> {
+    match self {
+      Self::U16 => indices
+        .iter()
+        .flat_map(|i| (*i as u16).to_ne_bytes())
+        .collect(),
+      Self::U32 => indices.iter().flat_map(|i| i.to_ne_bytes()).collect(),
+    }
+  }
+}
+
+// how a mesh's indices are put together when it's drawn
+#[derive(Copy, Clone, PartialEq)]
+pub enum Topology {
+  Triangles,
+  TriangleStrip,
+  Lines,
+  LineStrip,
+  Points,
+}
+
+impl Topology {
+  pub const ALL: [Self; 5] = [
+    Self::Triangles,
+    Self::TriangleStrip,
+    Self::Lines,
+    Self::LineStrip,
+    Self::Points,
+  ];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Triangles => "Triangles",
+      Self::TriangleStrip => "Triangle Strip",
+      Self::Lines => "Lines",
+      Self::LineStrip => "Line Strip",
+      Self::Points => "Points",
+    }
+  }
+
+  fn gl(&self) -> u32 {
+    match self {
+      Self::Triangles => gl::TRIANGLES,
+      Self::TriangleStrip => gl::TRIANGLE_STRIP,
+      Self::Lines => gl::LINES,
+      Self::LineStrip => gl::LINE_STRIP,
+      Self::Points => gl::POINTS,
+    }
+  }
+}
+
+// for meshes with other vertex formats, eg positions only, vertex colors or skinning weights
+pub struct MeshBuilder {
+  layout: VertexLayout,
+  data: Vec<u8>,
+  indices: Option<Vec<u32>>,
+  topology: Topology,
+}
+
+impl MeshBuilder {
+  pub fn new(layout: VertexLayout) -> Self {
+    Self {
+      layout,
+      data: vec![],
+      indices: None,
+      topology: Topology::Triangles,
+    }
+  }
+
+  // #[repr(C)] vertices the size of the layout's stride
+  pub fn vertices<T: Pod>(mut self, vertices: &[T]) -> Self {
+    assert_eq!(
+      mem::size_of::<T>(),
+      self.layout.stride(),
+      "Vertices don't match the layout's stride."
+    );
+    self.data = as_bytes(vertices).to_vec();
+    self
+  }
+
+  // without any the vertices are drawn in order
+  pub fn indices(mut self, indices: &[u32]) -> Self {
+    self.indices = Some(indices.to_vec());
+    self
+  }
+
+  pub fn topology(mut self, topology: Topology) -> Self {
+    self.topology = topology;
+    self
+  }
+
+  #[track_caller]
+  pub fn build(self) -> Mesh {
+    let count = self.data.len() / self.layout.stride().max(1);
+    let indices = self.indices.unwrap_or_else(|| (0..count as u32).collect());
+    let index_type = IndexType::fitting(&indices);
+    let index_data = index_type.encode(&indices);
     unsafe {
       let mut vert_arr = 0;
       gl::GenVertexArrays(1, &mut vert_arr);
@@ -213,8 +1222,8 @@ impl Mesh {
       gl::BindBuffer(gl::ARRAY_BUFFER, vert_buf);
       gl::BufferData(
         gl::ARRAY_BUFFER,
-        (vertices.len() * 32) as _,
-        vertices.as_ptr() as _,
+        self.data.len() as _,
+        self.data.as_ptr() as _,
         gl::STATIC_DRAW,
       );
       let mut idx_buf = 0;
@@ -222,53 +1231,669 @@ impl Mesh {
       gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, idx_buf);
       gl::BufferData(
         gl::ELEMENT_ARRAY_BUFFER,
-        (indices.len() * 4) as _,
-        indices.as_ptr() as _,
+        index_data.len() as _,
+        index_data.as_ptr() as _,
         gl::STATIC_DRAW,
       );
-      gl::EnableVertexAttribArray(0);
-      gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 32, 0 as _);
-      gl::EnableVertexAttribArray(1);
-      gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, 32, 12 as _);
-      gl::EnableVertexAttribArray(2);
-      gl::VertexAttribPointer(2, 3, gl::FLOAT, gl::FALSE, 32, 20 as _);
-      Self {
+      self.layout.bind(0);
+      // instance attributes are only enabled around instanced draws, see `instance_attribs`
+      let mut inst_buf = 0;
+      gl::GenBuffers(1, &mut inst_buf);
+      vram::track(GpuKind::Buffer, vert_buf, self.data.len());
+      vram::track(GpuKind::Buffer, idx_buf, index_data.len());
+      vram::track(GpuKind::Buffer, inst_buf, 0);
+      let positions = self.layout.positions(&self.data);
+      Mesh {
         vert_arr,
         vert_buf,
         idx_buf,
-        vertices: vertices.to_vec(),
-        indices: indices.to_vec(),
+        inst_buf,
+        aabb: Aabb::from_points(positions.iter().copied()),
+        positions,
+        vert_bytes: self.data.len(),
+        idx_bytes: index_data.len(),
+        indices,
+        index_type,
+        layout: self.layout,
+        topology: self.topology,
+        line_width: 1.0,
+        point_size: 1.0,
+        bvh: OnceCell::new(),
+      }
+    }
+  }
+}
+
+#[derive(Copy, Clone)]
+pub struct Aabb {
+  pub min: Vec3,
+  pub max: Vec3,
+}
+
+impl Aabb {
+  pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Self {
+    points.into_iter().fold(
+      Self {
+        min: Vec3::splat(f32::MAX),
+        max: Vec3::splat(f32::MIN),
+      },
+      |a, p| Self {
+        min: a.min.min(p),
+        max: a.max.max(p),
+      },
+    )
+  }
+
+  pub fn corners(&self) -> [Vec3; 8] {
+    [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+      Vec3::select(
+        BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+        self.max,
+        self.min,
+      )
+    })
+  }
+
+  pub fn transform(&self, mat: &Mat4) -> Self {
+    Self::from_points(self.corners().map(|c| mat.transform_point3(c)))
+  }
+}
+
+#[asset(load_mesh, unload_mesh)]
+#[derive(Clone)]
+pub struct Mesh {
+  pub vert_arr: u32,
+  pub vert_buf: u32,
+  pub idx_buf: u32,
+  pub inst_buf: u32,
+  // kept for picking and colliders
+  pub positions: Vec<Vec3>,
+  pub indices: Vec<u32>,
+  // the gpu's copy is 16 bit when every index fits
+  pub index_type: IndexType,
+  pub aabb: Aabb,
+  pub layout: VertexLayout,
+  pub topology: Topology,
+  // in pixels. wide lines are clamped to what the driver supports, which is often just 1
+  pub line_width: f32,
+  pub point_size: f32,
+  // bytes allocated for each buffer, `update` grows them
+  vert_bytes: usize,
+  idx_bytes: usize,
+  // for `raycast`, built when first needed
+  bvh: OnceCell<Rc<Bvh>>,
+}
+
+fn load_mesh(_: &mut World, path: &str) -> Result<Mesh> {
+  let obj: Obj<TexturedVertex, u32> = obj::load_obj(BufReader::new(File::open(path)?))?;
+  let mut vertices = obj
+    .vertices
+    .iter()
+    .map(|v| Vertex {
+      pos: v.position,
+      uv: [v.texture[0], v.texture[1]],
+      normal: v.normal,
+      ..Default::default()
+    })
+    .collect::<Vec<_>>();
+  Mesh::compute_tangents(&mut vertices, &obj.indices);
+  let mesh = Mesh::new(&vertices, &obj.indices);
+  mesh.label(path);
+  Ok(mesh)
+}
+
+fn unload_mesh(_: &mut World, mesh: &Mesh) {
+  mesh.delete();
+}
+
+impl Mesh {
+  pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+    for tri in indices.chunks_exact(3) {
+      let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| &vertices[i as usize]);
+      let e1 = Vec3::from(b.pos) - Vec3::from(a.pos);
+      let e2 = Vec3::from(c.pos) - Vec3::from(a.pos);
+      let duv1 = Vec2::from(b.uv) - Vec2::from(a.uv);
+      let duv2 = Vec2::from(c.uv) - Vec2::from(a.uv);
+      let det = duv1.x * duv2.y - duv2.x * duv1.y;
+      if det.abs() < f32::EPSILON {
+        continue;
+      }
+      for i in tri {
+        tangents[*i as usize] += (e1 * duv2.y - e2 * duv1.y) / det;
+        bitangents[*i as usize] += (e2 * duv1.x - e1 * duv2.x) / det;
+      }
+    }
+    for (i, v) in vertices.iter_mut().enumerate() {
+      let n = Vec3::from(v.normal);
+      let t = (tangents[i] - n * n.dot(tangents[i])).normalize_or_zero();
+      let b = if n.cross(t).dot(bitangents[i]) < 0.0 {
+        -n.cross(t)
+      } else {
+        n.cross(t)
+      };
+      v.tangent = t.into();
+      v.bitangent = b.into();
+    }
+  }
+
+  #[track_caller]
+  pub fn new(vertices: &[Vertex], indices: &[u32]) -> Self {
+    MeshBuilder::new(VertexLayout::standard())
+      .vertices(vertices)
+      .indices(indices)
+      .build()
+  }
+
+  pub fn topology(mut self, topology: Topology) -> Self {
+    self.topology = topology;
+    self.bvh = OnceCell::new();
+    self
+  }
+
+  pub fn line_width(mut self, width: f32) -> Self {
+    self.line_width = width;
+    self
+  }
+
+  pub fn point_size(mut self, size: f32) -> Self {
+    self.point_size = size;
+    self
+  }
+
+  // the indices as triangles for picking and colliders, strips are unrolled with consistent
+  // winding. lines and points have none
+  pub fn triangles(&self) -> Vec<[u32; 3]> {
+    let i = &self.indices;
+    match self.topology {
+      Topology::Triangles => i.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+      Topology::TriangleStrip => (2..i.len())
+        .map(|n| match n % 2 {
+          0 => [i[n - 2], i[n - 1], i[n]],
+          _ => [i[n - 1], i[n - 2], i[n]],
+        })
+        .collect(),
+      _ => vec![],
+    }
+  }
+
+  // replaces the geometry in place, for meshes changed every frame like trails or deformed
+  // terrain. vertices are in the mesh's layout
+  pub fn update<T: Pod>(&mut self, vertices: &[T], indices: &[u32]) {
+    assert_eq!(
+      mem::size_of::<T>(),
+      self.layout.stride(),
+      "Vertices don't match the layout's stride."
+    );
+    let data = as_bytes(vertices);
+    unsafe {
+      // the index buffer binding belongs to the vertex array
+      gl::BindVertexArray(self.vert_arr);
+      upload(gl::ARRAY_BUFFER, self.vert_buf, &mut self.vert_bytes, data);
+      self.index_type = IndexType::fitting(indices);
+      upload(
+        gl::ELEMENT_ARRAY_BUFFER,
+        self.idx_buf,
+        &mut self.idx_bytes,
+        &self.index_type.encode(indices),
+      );
+    }
+    self.positions = self.layout.positions(data);
+    self.indices = indices.to_vec();
+    self.aabb = Aabb::from_points(self.positions.iter().copied());
+    self.bvh = OnceCell::new();
+  }
+
+  pub(crate) fn bvh(&self) -> &Bvh {
+    self.bvh.get_or_init(|| Rc::new(Bvh::new(self)))
+  }
+
+  pub fn delete(&self) {
+    unsafe {
+      gl::DeleteVertexArrays(1, &self.vert_arr);
+      gl::DeleteBuffers(3, [self.vert_buf, self.idx_buf, self.inst_buf].as_ptr());
+    }
+    for buf in [self.vert_buf, self.idx_buf, self.inst_buf] {
+      vram::untrack(GpuKind::Buffer, buf);
+    }
+  }
+
+  pub fn label(&self, label: &str) {
+    object_label(gl::VERTEX_ARRAY, self.vert_arr, label);
+    object_label(gl::BUFFER, self.vert_buf, &format!("{} vertices", label));
+    object_label(gl::BUFFER, self.idx_buf, &format!("{} indices", label));
+    object_label(gl::BUFFER, self.inst_buf, &format!("{} instances", label));
+  }
+
+  // covers clip space, for fullscreen passes
+  #[track_caller]
+  pub fn quad() -> Self {
+    let vertex = |x: f32, y: f32| Vertex {
+      pos: [x, y, 0.0],
+      uv: [x * 0.5 + 0.5, y * 0.5 + 0.5],
+      ..Default::default()
+    };
+    Self::new(
+      &[
+        vertex(1.0, 1.0),
+        vertex(1.0, -1.0),
+        vertex(-1.0, -1.0),
+        vertex(-1.0, 1.0),
+      ],
+      &[0, 1, 3, 1, 2, 3],
+    )
+  }
+
+  // -0.5 to 0.5 on each axis, positions only
+  #[track_caller]
+  pub fn cube() -> Self {
+    let vertices = (0..8)
+      .map(|i| [0, 1, 2].map(|axis| if i & (1 << axis) == 0 { -0.5 } else { 0.5 }))
+      .collect::<Vec<[f32; 3]>>();
+    // corners are indexed by which axes are at max
+    MeshBuilder::new(VertexLayout::new().attrib(0, 3, AttribType::F32))
+      .vertices(&vertices)
+      .indices(&[
+        0, 2, 3, 0, 3, 1, 4, 5, 7, 4, 7, 6, 0, 1, 5, 0, 5, 4, 2, 6, 7, 2, 7, 3, 0, 4, 6, 0, 6, 2,
+        1, 3, 7, 1, 7, 5,
+      ])
+      .build()
+  }
+
+  // `lines` each way across the xz plane, `spacing` apart and centred on the origin, positions only
+  #[track_caller]
+  pub fn grid(lines: u32, spacing: f32) -> Self {
+    let half = (lines.max(1) - 1) as f32 * spacing / 2.0;
+    let vertices = (0..lines)
+      .flat_map(|i| {
+        let d = i as f32 * spacing - half;
+        [
+          [d, 0.0, -half],
+          [d, 0.0, half],
+          [-half, 0.0, d],
+          [half, 0.0, d],
+        ]
+      })
+      .collect::<Vec<[f32; 3]>>();
+    let indices = (0..lines * 2)
+      .flat_map(|l| [l * 2, l * 2 + 1])
+      .collect::<Vec<_>>();
+    MeshBuilder::new(VertexLayout::new().attrib(0, 3, AttribType::F32))
+      .vertices(&vertices)
+      .indices(&indices)
+      .topology(Topology::Lines)
+      .build()
+  }
+
+  // binds the vertex array and sets the line width or point size, returning the gl primitive
+  unsafe fn bind(&self) -> u32 {
+    gl::BindVertexArray(self.vert_arr);
+    match self.topology {
+      Topology::Lines | Topology::LineStrip => {
+        let mut range = [1.0; 2];
+        gl::GetFloatv(gl::ALIASED_LINE_WIDTH_RANGE, range.as_mut_ptr());
+        gl::LineWidth(self.line_width.clamp(range[0], range[1]));
+      }
+      Topology::Points => gl::PointSize(self.point_size),
+      _ => {}
+    }
+    self.topology.gl()
+  }
+
+  // they're global state, so other draws don't pick them up
+  unsafe fn unbind(&self) {
+    match self.topology {
+      Topology::Lines | Topology::LineStrip => gl::LineWidth(1.0),
+      Topology::Points => gl::PointSize(1.0),
+      _ => {}
+    }
+  }
+
+  pub fn draw(&self) {
+    unsafe {
+      let mode = self.bind();
+      gl::DrawElements(
+        mode,
+        self.indices.len() as _,
+        self.index_type.gl(),
+        std::ptr::null(),
+      );
+      self.unbind();
+    }
+  }
+
+  // for tessellation shaders, every `vertices` indices make a patch
+  pub fn draw_patches(&self, vertices: u32) {
+    unsafe {
+      gl::PatchParameteri(gl::PATCH_VERTICES, vertices as _);
+      gl::BindVertexArray(self.vert_arr);
+      gl::DrawElements(
+        gl::PATCHES,
+        self.indices.len() as _,
+        self.index_type.gl(),
+        std::ptr::null(),
+      );
+    }
+  }
+
+  // draws `count` instances starting `offset` bytes into a buffer that is already on the gpu
+  pub fn draw_instanced_buffer(&self, instances: u32, offset: usize, count: usize) {
+    if count == 0 {
+      return;
+    }
+    unsafe {
+      let mode = self.bind();
+      instance_attribs(instances, offset);
+      gl::DrawElementsInstanced(
+        mode,
+        self.indices.len() as _,
+        self.index_type.gl(),
+        std::ptr::null(),
+        count as _,
+      );
+      disable_instance_attribs();
+      self.unbind();
+    }
+  }
+
+  // `commands` holds DrawElementsIndirectCommands written on the gpu, needs opengl 4.3
+  pub fn draw_indirect(&self, instances: u32, commands: u32, count: usize) {
+    unsafe {
+      let mode = self.bind();
+      instance_attribs(instances, 0);
+      gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, commands);
+      gl::MultiDrawElementsIndirect(mode, self.index_type.gl(), std::ptr::null(), count as _, 0);
+      gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+      disable_instance_attribs();
+      self.unbind();
+    }
+  }
+
+  pub fn draw_instanced(&self, instances: &[Mat4]) {
+    if instances.is_empty() {
+      return;
+    }
+    unsafe {
+      let mode = self.bind();
+      gl::BindBuffer(gl::ARRAY_BUFFER, self.inst_buf);
+      gl::BufferData(
+        gl::ARRAY_BUFFER,
+        (instances.len() * 64) as _,
+        instances.as_ptr() as _,
+        gl::STREAM_DRAW,
+      );
+      vram::resize(GpuKind::Buffer, self.inst_buf, instances.len() * 64);
+      instance_attribs(self.inst_buf, 0);
+      gl::DrawElementsInstanced(
+        mode,
+        self.indices.len() as _,
+        self.index_type.gl(),
+        std::ptr::null(),
+        instances.len() as _,
+      );
+      disable_instance_attribs();
+      self.unbind();
+    }
+  }
+}
+
+// orphans the buffer's storage so draws still using it don't stall, growing it when `data` doesn't
+// fit, then writes `data` at the start
+unsafe fn upload(target: u32, buf: u32, bytes: &mut usize, data: &[u8]) {
+  if data.len() > *bytes {
+    *bytes = data.len().next_power_of_two();
+    vram::resize(GpuKind::Buffer, buf, *bytes);
+  }
+  gl::BindBuffer(target, buf);
+  gl::BufferData(target, *bytes as _, ptr::null(), gl::DYNAMIC_DRAW);
+  gl::BufferSubData(target, 0, data.len() as _, data.as_ptr() as _);
+}
+
+// per instance model matrix, one column per attribute, on the bound vertex array. shaders declare
+// it whether they're instanced or not, so it's only enabled for instanced draws, other draws read
+// the constant attribute value rather than past the end of the buffer
+unsafe fn instance_attribs(buf: u32, offset: usize) {
+  gl::BindBuffer(gl::ARRAY_BUFFER, buf);
+  for i in 0..4 {
+    gl::EnableVertexAttribArray(5 + i);
+    gl::VertexAttribPointer(
+      5 + i,
+      4,
+      gl::FLOAT,
+      gl::FALSE,
+      64,
+      (offset + i as usize * 16) as _,
+    );
+    gl::VertexAttribDivisor(5 + i, 1);
+  }
+}
+
+unsafe fn disable_instance_attribs() {
+  for i in 0..4 {
+    gl::DisableVertexAttribArray(5 + i);
+  }
+}
+
+#[derive(Copy, Clone)]
+#[asset(load_tex, unload_tex)]
+pub struct Texture {
+  pub id: u32,
+  pub width: u32,
+  pub height: u32,
+  pub iformat: u32,
+  pub format: u32,
+  pub typ: u32,
+}
+
+// from EXT_texture_filter_anisotropic, the gl bindings stop before it became core in 4.6
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84fe;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84ff;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Filter {
+  Nearest,
+  Linear,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Wrap {
+  Clamp,
+  Repeat,
+  Mirror,
+}
+
+// how a texture is sampled, DEFAULT is what textures had before they were configurable
+#[derive(Copy, Clone)]
+pub struct TextureOptions {
+  pub min_filter: Filter,
+  pub mag_filter: Filter,
+  pub wrap: Wrap,
+  pub mipmaps: bool,
+  // between mip levels, linear with a linear min filter is trilinear
+  pub mip_filter: Filter,
+  // 1 is off, clamped to what the driver supports
+  pub anisotropy: f32,
+}
+
+impl TextureOptions {
+  pub const DEFAULT: Self = Self {
+    min_filter: Filter::Linear,
+    mag_filter: Filter::Linear,
+    wrap: Wrap::Clamp,
+    mipmaps: false,
+    mip_filter: Filter::Linear,
+    anisotropy: 1.0,
+  };
+
+  // what loaded textures get without a .tex file, distant surfaces shimmer without mips and
+  // blur at grazing angles without anisotropy
+  pub const MATERIAL: Self = Self {
+    mipmaps: true,
+    anisotropy: 8.0,
+    ..Self::DEFAULT
+  };
+
+  pub fn filter(mut self, filter: Filter) -> Self {
+    self.min_filter = filter;
+    self.mag_filter = filter;
+    self.mip_filter = filter;
+    self
+  }
+
+  pub fn min_filter(mut self, filter: Filter) -> Self {
+    self.min_filter = filter;
+    self
+  }
+
+  pub fn mag_filter(mut self, filter: Filter) -> Self {
+    self.mag_filter = filter;
+    self
+  }
+
+  pub fn wrap(mut self, wrap: Wrap) -> Self {
+    self.wrap = wrap;
+    self
+  }
+
+  pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+    self.mipmaps = mipmaps;
+    self
+  }
+
+  pub fn mip_filter(mut self, filter: Filter) -> Self {
+    self.mip_filter = filter;
+    self
+  }
+
+  pub fn anisotropy(mut self, anisotropy: f32) -> Self {
+    self.anisotropy = anisotropy;
+    self
+  }
+
+  // reads `key = value` lines, eg `filter = nearest`, `wrap = repeat`, `mipmaps = false` or
+  // `anisotropy = 8`, anything unset keeps `self`'s value
+  pub fn parse(mut self, src: &str) -> Result<Self> {
+    let filter = |v: &str| match v {
+      "nearest" => Some(Filter::Nearest),
+      "linear" => Some(Filter::Linear),
+      _ => None,
+    };
+    for line in src.lines().map(str::trim).filter(|l| !l.is_empty()) {
+      let err = || format!("Invalid texture option '{}'.", line);
+      let (key, value) = line.split_once('=').ok_or_else(err)?;
+      let value = value.trim();
+      match key.trim() {
+        "filter" => self = self.filter(filter(value).ok_or_else(err)?),
+        "min_filter" => self.min_filter = filter(value).ok_or_else(err)?,
+        "mag_filter" => self.mag_filter = filter(value).ok_or_else(err)?,
+        "mip_filter" => self.mip_filter = filter(value).ok_or_else(err)?,
+        "wrap" => {
+          self.wrap = match value {
+            "clamp" => Wrap::Clamp,
+            "repeat" => Wrap::Repeat,
+            "mirror" => Wrap::Mirror,
+            _ => return Err(err().into()),
+          }
+        }
+        "mipmaps" => self.mipmaps = value.parse().map_err(|_| err())?,
+        "anisotropy" => self.anisotropy = value.parse().map_err(|_| err())?,
+        _ => return Err(err().into()),
       }
     }
+    Ok(self)
   }
 
-  pub fn draw(&self) {
-    unsafe {
-      gl::BindVertexArray(self.vert_arr);
-      gl::DrawElements(
-        gl::TRIANGLES,
-        self.indices.len() as _,
-        gl::UNSIGNED_INT,
-        std::ptr::null(),
+  // sets the sampling state of the bound 2d texture
+  pub(crate) unsafe fn apply(&self) {
+    let min = match (self.min_filter, self.mipmaps, self.mip_filter) {
+      (Filter::Nearest, false, _) => gl::NEAREST,
+      (Filter::Linear, false, _) => gl::LINEAR,
+      (Filter::Nearest, true, Filter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+      (Filter::Nearest, true, Filter::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+      (Filter::Linear, true, Filter::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+      (Filter::Linear, true, Filter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+    };
+    let mag = match self.mag_filter {
+      Filter::Nearest => gl::NEAREST,
+      Filter::Linear => gl::LINEAR,
+    };
+    let wrap = match self.wrap {
+      Wrap::Clamp => gl::CLAMP_TO_EDGE,
+      Wrap::Repeat => gl::REPEAT,
+      Wrap::Mirror => gl::MIRRORED_REPEAT,
+    };
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as _);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as _);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as _);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as _);
+    // also set back to 1, since options can be applied to a texture again
+    let max = Texture::max_anisotropy();
+    if max > 1.0 {
+      gl::TexParameterf(
+        gl::TEXTURE_2D,
+        TEXTURE_MAX_ANISOTROPY,
+        self.anisotropy.clamp(1.0, max),
       );
     }
   }
 }
 
-#[derive(Copy, Clone)]
-#[asset(load_tex)]
-pub struct Texture {
-  pub id: u32,
-  pub width: u32,
-  pub height: u32,
-  pub iformat: u32,
-  pub format: u32,
-  pub typ: u32,
+// common internal formats with the pixel format and type they're uploaded from, the float ones
+// keep values past 1 for hdr, Rgb10a2 is still 0 to 1 but with more precision than Rgba8
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextureFormat {
+  Rgba8,
+  Srgba8,
+  Rgba16f,
+  Rgba32f,
+  Rgb10a2,
+}
+
+impl TextureFormat {
+  // internal format, format and type for Texture::new
+  pub fn gl(self) -> (u32, u32, u32) {
+    match self {
+      Self::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+      Self::Srgba8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+      Self::Rgba16f => (gl::RGBA16F, gl::RGBA, gl::FLOAT),
+      Self::Rgba32f => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+      Self::Rgb10a2 => (gl::RGB10_A2, gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV),
+    }
+  }
+}
+
+fn load_tex(world: &mut World, path: &str) -> Result<Texture> {
+  let tex = upload_tex(world, path)?;
+  tex.label(path);
+  Ok(tex)
 }
 
-fn load_tex(_: &mut World, path: &str) -> Result<Texture> {
+fn unload_tex(world: &mut World, tex: &Texture) {
+  if let Some(streamer) = world.get_resource::<TextureStreamer>() {
+    streamer.remove(tex);
+  }
+  tex.delete();
+}
+
+// `brick.png` is sampled with the options in `brick.png.tex` when there is one
+fn upload_tex(world: &mut World, path: &str) -> Result<Texture> {
+  let options_path = format!("{}.tex", path);
+  let options = match Path::new(&options_path).exists() {
+    true => Some(TextureOptions::MATERIAL.parse(&fs::read_to_string(options_path)?)?),
+    false => None,
+  };
+  if path.ends_with(".dds") || path.ends_with(".ktx2") {
+    return load_compressed(path, options.unwrap_or(TextureOptions::MATERIAL));
+  }
   let mut img = image::open(path)?.to_rgba8();
   imageops::flip_vertical_in_place(&mut img);
+  if let Some(streamer) = world.get_resource::<TextureStreamer>() {
+    // streaming needs the mip chain, textures without mips are uploaded whole
+    if options.is_none_or(|o| o.mipmaps) {
+      return Ok(streamer.load(img, options.unwrap_or(TextureOptions::MATERIAL)));
+    }
+  }
   Ok(Texture::new(
     img.as_ptr(),
     img.width(),
@@ -276,10 +1901,12 @@ fn load_tex(_: &mut World, path: &str) -> Result<Texture> {
     gl::SRGB_ALPHA,
     gl::RGBA,
     gl::UNSIGNED_BYTE,
+    options.unwrap_or(TextureOptions::MATERIAL),
   ))
 }
 
 impl Texture {
+  #[track_caller]
   pub fn new(
     data: *const u8,
     width: u32,
@@ -287,15 +1914,13 @@ impl Texture {
     iformat: u32,
     format: u32,
     typ: u32,
+    options: TextureOptions,
   ) -> Self {
     unsafe {
       let mut tex = 0;
       gl::GenTextures(1, &mut tex);
       gl::BindTexture(gl::TEXTURE_2D, tex);
-      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
-      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
-      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
-      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+      options.apply();
       gl::TexImage2D(
         gl::TEXTURE_2D,
         0,
@@ -307,6 +1932,14 @@ impl Texture {
         typ,
         data as _,
       );
+      if options.mipmaps && !data.is_null() {
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+      }
+      vram::track(
+        GpuKind::Texture,
+        tex,
+        vram::texture_bytes(width, height, iformat, options.mipmaps && !data.is_null()),
+      );
 
       Self {
         id: tex,
@@ -319,6 +1952,19 @@ impl Texture {
     }
   }
 
+  #[track_caller]
+  pub fn with_format(
+    data: *const u8,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    options: TextureOptions,
+  ) -> Self {
+    let (iformat, format, typ) = format.gl();
+    Self::new(data, width, height, iformat, format, typ, options)
+  }
+
+  #[track_caller]
   pub fn empty() -> Self {
     Self::new(
       ptr::null(),
@@ -327,9 +1973,50 @@ impl Texture {
       gl::SRGB_ALPHA,
       gl::RGBA,
       gl::UNSIGNED_BYTE,
+      TextureOptions::DEFAULT,
     )
   }
 
+  pub fn label(&self, label: &str) {
+    object_label(gl::TEXTURE, self.id, label);
+  }
+
+  // changes how it's sampled, mips still have to be generated if it didn't have them
+  pub fn set_options(&self, options: TextureOptions) {
+    unsafe {
+      self.bind(0);
+      options.apply();
+    }
+  }
+
+  // for textures made with mipmaps but no data, or drawn into since, it can't be sampled with a
+  // mipmapped filter until they're generated
+  pub fn generate_mipmaps(&self) {
+    unsafe {
+      self.bind(0);
+      gl::GenerateMipmap(gl::TEXTURE_2D);
+    }
+    vram::resize(
+      GpuKind::Texture,
+      self.id,
+      vram::texture_bytes(self.width, self.height, self.iformat, true),
+    );
+  }
+
+  // 1 without the anisotropic filtering extension, it's core from opengl 4.6
+  pub fn max_anisotropy() -> f32 {
+    let mut max = 1.0;
+    if gl_version() >= (4, 6)
+      || gl_extension("GL_ARB_texture_filter_anisotropic")
+      || gl_extension("GL_EXT_texture_filter_anisotropic")
+    {
+      unsafe {
+        gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+      }
+    }
+    max
+  }
+
   pub fn bind(&self, unit: u32) {
     unsafe {
       gl::ActiveTexture(gl::TEXTURE0 + unit);
@@ -354,6 +2041,130 @@ impl Texture {
       self.width = width;
       self.height = height;
     }
+    vram::resize(
+      GpuKind::Texture,
+      self.id,
+      vram::texture_bytes(width, height, self.iformat, false),
+    );
+  }
+
+  // copies of it are left with a dead id
+  pub fn delete(&self) {
+    unsafe {
+      gl::DeleteTextures(1, &self.id);
+    }
+    vram::untrack(GpuKind::Texture, self.id);
+  }
+}
+
+#[derive(Copy, Clone)]
+#[asset(load_cubemap, unload_cubemap)]
+pub struct Cubemap {
+  pub id: u32,
+  pub size: u32,
+}
+
+fn load_cubemap(_: &mut World, path: &str) -> Result<Cubemap> {
+  let mut faces = vec![];
+  for face in ["px", "nx", "py", "ny", "pz", "nz"] {
+    let file = fs::read_dir(path)?
+      .filter_map(|f| f.ok())
+      .find(|f| f.path().file_stem().is_some_and(|s| s == face))
+      .ok_or(format!("Missing face '{}' in cubemap '{}'.", face, path))?;
+    faces.push(image::open(file.path())?.to_rgba8());
+  }
+  let size = faces[0].width();
+  if faces
+    .iter()
+    .any(|f| f.width() != size || f.height() != size)
+  {
+    return Err(
+      format!(
+        "Cubemap faces in '{}' must be square and equally sized.",
+        path
+      )
+      .into(),
+    );
+  }
+  Ok(Cubemap::new(
+    [0, 1, 2, 3, 4, 5].map(|i| faces[i].as_ptr()),
+    size,
+    gl::SRGB_ALPHA,
+    gl::RGBA,
+    gl::UNSIGNED_BYTE,
+  ))
+}
+
+fn unload_cubemap(_: &mut World, cubemap: &Cubemap) {
+  cubemap.delete();
+}
+
+impl Cubemap {
+  #[track_caller]
+  pub fn new(faces: [*const u8; 6], size: u32, iformat: u32, format: u32, typ: u32) -> Self {
+    unsafe {
+      let mut tex = 0;
+      gl::GenTextures(1, &mut tex);
+      gl::BindTexture(gl::TEXTURE_CUBE_MAP, tex);
+      gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_MIN_FILTER,
+        gl::LINEAR as _,
+      );
+      gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_MAG_FILTER,
+        gl::LINEAR as _,
+      );
+      gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_WRAP_S,
+        gl::CLAMP_TO_EDGE as _,
+      );
+      gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_WRAP_T,
+        gl::CLAMP_TO_EDGE as _,
+      );
+      gl::TexParameteri(
+        gl::TEXTURE_CUBE_MAP,
+        gl::TEXTURE_WRAP_R,
+        gl::CLAMP_TO_EDGE as _,
+      );
+      for (i, data) in faces.iter().enumerate() {
+        gl::TexImage2D(
+          gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+          0,
+          iformat as _,
+          size as _,
+          size as _,
+          0,
+          format,
+          typ,
+          *data as _,
+        );
+      }
+      vram::track(
+        GpuKind::Texture,
+        tex,
+        vram::texture_bytes(size, size, iformat, false) * 6,
+      );
+      Self { id: tex, size }
+    }
+  }
+
+  pub fn delete(&self) {
+    unsafe {
+      gl::DeleteTextures(1, &self.id);
+    }
+    vram::untrack(GpuKind::Texture, self.id);
+  }
+
+  pub fn bind(&self, unit: u32) {
+    unsafe {
+      gl::ActiveTexture(gl::TEXTURE0 + unit);
+      gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+    }
   }
 }
 
@@ -366,10 +2177,12 @@ pub struct Framebuffer {
 impl Framebuffer {
   pub const DEFAULT: Framebuffer = Self { fb: 0, rb: 0 };
 
+  #[track_caller]
   pub fn new() -> Self {
     unsafe {
       let mut s = Self::new_no_depth();
       gl::GenRenderbuffers(1, &mut s.rb);
+      vram::track(GpuKind::Renderbuffer, s.rb, 0);
       gl::BindRenderbuffer(gl::RENDERBUFFER, s.rb);
       gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, 0, 0);
       gl::FramebufferRenderbuffer(
@@ -397,6 +2210,27 @@ impl Framebuffer {
     }
   }
 
+  // rgba8 copy of the color buffer. `rect` is x, y, width and height in pixels from the bottom
+  // left as gl has it, but rows come top first like an image
+  pub fn read_pixels(&self, rect: (u32, u32, u32, u32)) -> RgbaImage {
+    let (x, y, w, h) = rect;
+    let mut img = RgbaImage::new(w, h);
+    unsafe {
+      gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fb);
+      gl::ReadPixels(
+        x as _,
+        y as _,
+        w as _,
+        h as _,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        img.as_mut_ptr() as _,
+      );
+    }
+    imageops::flip_vertical_in_place(&mut img);
+    img
+  }
+
   pub fn bind_tex(&self, tex: &Texture, unit: u32) {
     unsafe {
       self.bind();
@@ -423,16 +2257,183 @@ impl Framebuffer {
     }
   }
 
+  // one face of a cubemap as the depth attachment, `face` in the order +x -x +y -y +z -z
+  pub fn bind_depth_face(&self, cubemap: &Cubemap, face: u32) {
+    unsafe {
+      self.bind();
+      gl::FramebufferTexture2D(
+        gl::FRAMEBUFFER,
+        gl::DEPTH_ATTACHMENT,
+        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+        cubemap.id,
+        0,
+      );
+    }
+  }
+
+  // enables the first `n` color attachments for drawing
+  pub fn draw_buffers(&self, n: u32) {
+    unsafe {
+      self.bind();
+      let buffers = (0..n)
+        .map(|i| gl::COLOR_ATTACHMENT0 + i)
+        .collect::<Vec<_>>();
+      gl::DrawBuffers(n as _, buffers.as_ptr());
+    }
+  }
+
+  pub fn bind_rb(&self, rb: &Renderbuffer, unit: u32) {
+    unsafe {
+      self.bind();
+      gl::FramebufferRenderbuffer(
+        gl::FRAMEBUFFER,
+        gl::COLOR_ATTACHMENT0 + unit,
+        gl::RENDERBUFFER,
+        rb.id,
+      );
+    }
+  }
+
   pub fn resize(&self, width: u32, height: u32) {
+    self.resize_multisample(width, height, 0);
+  }
+
+  pub fn resize_multisample(&self, width: u32, height: u32, samples: u32) {
     unsafe {
       gl::BindRenderbuffer(gl::RENDERBUFFER, self.rb);
-      gl::RenderbufferStorage(
+      gl::RenderbufferStorageMultisample(
         gl::RENDERBUFFER,
+        samples as _,
         gl::DEPTH24_STENCIL8,
         width as _,
         height as _,
       );
     }
+    let bytes = width as usize * height as usize * 4 * samples.max(1) as usize;
+    vram::resize(GpuKind::Renderbuffer, self.rb, bytes);
+  }
+
+  // copies a color attachment into the same attachment of `dst`, resolving multisampling
+  pub fn blit_color(&self, dst: Framebuffer, unit: u32, width: u32, height: u32) {
+    unsafe {
+      gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fb);
+      gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst.fb);
+      gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + unit);
+      gl::DrawBuffer(gl::COLOR_ATTACHMENT0 + unit);
+      blit(width, height, gl::COLOR_BUFFER_BIT);
+    }
+  }
+
+  pub fn blit_depth(&self, dst: Framebuffer, width: u32, height: u32) {
+    unsafe {
+      gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fb);
+      gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst.fb);
+      blit(width, height, gl::DEPTH_BUFFER_BIT);
+    }
+  }
+
+  // between framebuffers of different sizes, depth can't be filtered so it's nearest
+  pub fn blit_depth_scaled(&self, dst: Framebuffer, from: [u32; 2], to: [u32; 2]) {
+    unsafe {
+      gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fb);
+      gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst.fb);
+      gl::BlitFramebuffer(
+        0,
+        0,
+        from[0] as _,
+        from[1] as _,
+        0,
+        0,
+        to[0] as _,
+        to[1] as _,
+        gl::DEPTH_BUFFER_BIT,
+        gl::NEAREST,
+      );
+    }
+  }
+}
+
+// a framebuffer drawing into one color texture, sized with `resize`
+#[derive(Copy, Clone)]
+pub struct RenderTarget {
+  pub fb: Framebuffer,
+  pub tex: Texture,
+}
+
+impl RenderTarget {
+  // `depth` adds a depth and stencil renderbuffer
+  pub fn new(format: TextureFormat, depth: bool) -> Self {
+    let fb = match depth {
+      true => Framebuffer::new(),
+      false => Framebuffer::new_no_depth(),
+    };
+    let tex = Texture::with_format(ptr::null(), 0, 0, format, TextureOptions::DEFAULT);
+    fb.bind_tex(&tex, 0);
+    Self { fb, tex }
+  }
+
+  // rgba16f with depth, for scenes lit past 1 before tonemapping
+  pub fn hdr() -> Self {
+    Self::new(TextureFormat::Rgba16f, true)
+  }
+
+  pub fn resize(&mut self, width: u32, height: u32) {
+    if self.fb.rb != 0 {
+      self.fb.resize(width, height);
+    }
+    self.tex.resize(width, height);
+  }
+}
+
+unsafe fn blit(width: u32, height: u32, mask: u32) {
+  gl::BlitFramebuffer(
+    0,
+    0,
+    width as _,
+    height as _,
+    0,
+    0,
+    width as _,
+    height as _,
+    mask,
+    gl::NEAREST,
+  );
+}
+
+// multisampled attachment storage, read back by blitting into a texture
+pub struct Renderbuffer {
+  pub id: u32,
+  pub iformat: u32,
+}
+
+impl Renderbuffer {
+  #[track_caller]
+  pub fn new(iformat: u32) -> Self {
+    unsafe {
+      let mut id = 0;
+      gl::GenRenderbuffers(1, &mut id);
+      vram::track(GpuKind::Renderbuffer, id, 0);
+      Self { id, iformat }
+    }
+  }
+
+  pub fn resize(&self, width: u32, height: u32, samples: u32) {
+    unsafe {
+      gl::BindRenderbuffer(gl::RENDERBUFFER, self.id);
+      gl::RenderbufferStorageMultisample(
+        gl::RENDERBUFFER,
+        samples as _,
+        self.iformat,
+        width as _,
+        height as _,
+      );
+    }
+    let texels = width as usize * height as usize * samples.max(1) as usize;
+    vram::resize(
+      GpuKind::Renderbuffer,
+      self.id,
+      texels * vram::bytes_per_pixel(self.iformat),
+    );
   }
 }
 
@@ -455,6 +2456,15 @@ impl Query {
     }
   }
 
+  // counts the samples that pass the depth test while `f` draws
+  pub fn samples<F: FnMut()>(&self, mut f: F) {
+    unsafe {
+      gl::BeginQuery(gl::SAMPLES_PASSED, self.0);
+      f();
+      gl::EndQuery(gl::SAMPLES_PASSED);
+    }
+  }
+
   pub fn get_blocking(&mut self) -> u64 {
     unsafe {
       let mut v = 0;
@@ -463,8 +2473,12 @@ impl Query {
     }
   }
 
+  // none until the result is ready, or if the query has never run
   pub fn get(&mut self) -> Option<u64> {
     unsafe {
+      if gl::IsQuery(self.0) == gl::FALSE {
+        return None;
+      }
       let mut avail = 0;
       gl::GetQueryObjectiv(self.0, gl::QUERY_RESULT_AVAILABLE, &mut avail);
       if avail > 0 {
@@ -475,3 +2489,213 @@ impl Query {
     }
   }
 }
+
+impl Drop for Query {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteQueries(1, &self.0);
+    }
+  }
+}
+
+pub fn gl_version() -> (i32, i32) {
+  let (mut major, mut minor) = (0, 0);
+  unsafe {
+    gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+    gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+  }
+  (major, minor)
+}
+
+// whether the current context has the extension `name`
+pub fn gl_extension(name: &str) -> bool {
+  let mut count = 0;
+  unsafe {
+    gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+  }
+  (0..count as u32).any(|i| {
+    let ext = unsafe { gl::GetStringi(gl::EXTENSIONS, i) };
+    !ext.is_null() && unsafe { CStr::from_ptr(ext as _) }.to_bytes() == name.as_bytes()
+  })
+}
+
+// a buffer kept on a uniform block binding, shaders read it once Shader::bind_block points
+// their block at the same binding. `T` has to be laid out like the block is under std140
+pub struct UniformBuffer<T> {
+  pub id: u32,
+  pub binding: u32,
+  _data: PhantomData<T>,
+}
+
+impl<T: Copy> UniformBuffer<T> {
+  #[track_caller]
+  pub fn new(binding: u32) -> Self {
+    let mut id = 0;
+    unsafe {
+      gl::GenBuffers(1, &mut id);
+      gl::BindBuffer(gl::UNIFORM_BUFFER, id);
+      gl::BufferData(
+        gl::UNIFORM_BUFFER,
+        mem::size_of::<T>() as _,
+        ptr::null(),
+        gl::DYNAMIC_DRAW,
+      );
+      gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+    }
+    vram::track(GpuKind::Buffer, id, mem::size_of::<T>());
+    object_label(gl::BUFFER, id, std::any::type_name::<T>());
+    Self {
+      id,
+      binding,
+      _data: PhantomData,
+    }
+  }
+
+  pub fn set(&self, data: &T) {
+    unsafe {
+      gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+      gl::BufferSubData(
+        gl::UNIFORM_BUFFER,
+        0,
+        mem::size_of::<T>() as _,
+        data as *const T as _,
+      );
+    }
+  }
+}
+
+impl<T> Drop for UniformBuffer<T> {
+  fn drop(&mut self) {
+    unsafe {
+      gl::DeleteBuffers(1, &self.id);
+    }
+    vram::untrack(GpuKind::Buffer, self.id);
+  }
+}
+
+// a ring buffer for data rewritten every frame, on opengl 4.4 it stays mapped so writes are a
+// memcpy and fences keep the cpu off ranges the gpu hasn't read yet, older contexts orphan the
+// buffer each time it wraps instead
+pub struct StreamBuffer {
+  pub id: u32,
+  target: u32,
+  size: usize,
+  head: usize,
+  map: *mut u8,
+  // ranges written since the last fence
+  written: Vec<(usize, usize)>,
+  fences: VecDeque<(Vec<(usize, usize)>, gl::types::GLsync)>,
+}
+
+impl StreamBuffer {
+  #[track_caller]
+  pub fn new(target: u32, size: usize) -> Self {
+    let mut buf = Self {
+      id: 0,
+      target,
+      size: 0,
+      head: 0,
+      map: ptr::null_mut(),
+      written: vec![],
+      fences: VecDeque::new(),
+    };
+    buf.alloc(size);
+    buf
+  }
+
+  #[track_caller]
+  fn alloc(&mut self, size: usize) {
+    unsafe {
+      let old = self.id;
+      if old != 0 {
+        gl::DeleteBuffers(1, &old);
+      }
+      gl::GenBuffers(1, &mut self.id);
+      match old {
+        0 => vram::track(GpuKind::Buffer, self.id, size),
+        _ => vram::replace(GpuKind::Buffer, old, self.id, size),
+      }
+      gl::BindBuffer(self.target, self.id);
+      if gl_version() >= (4, 4) {
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        gl::BufferStorage(self.target, size as _, ptr::null(), flags);
+        self.map = gl::MapBufferRange(self.target, 0, size as _, flags) as _;
+      } else {
+        gl::BufferData(self.target, size as _, ptr::null(), gl::STREAM_DRAW);
+      }
+    }
+    self.size = size;
+    self.head = 0;
+  }
+
+  // copies `data` in at a multiple of `align` bytes and returns its offset, leaving the buffer
+  // bound. a write bigger than the buffer replaces it, so `id` can change
+  pub fn write<T: Copy>(&mut self, data: &[T], align: usize) -> usize {
+    let len = mem::size_of_val(data);
+    unsafe {
+      gl::BindBuffer(self.target, self.id);
+      if len > self.size {
+        self.wait(0, usize::MAX);
+        self.alloc((len * 2).next_power_of_two());
+      }
+      let mut offset = self.head.div_ceil(align) * align;
+      if offset + len > self.size {
+        offset = 0;
+        if self.map.is_null() {
+          gl::BufferData(self.target, self.size as _, ptr::null(), gl::STREAM_DRAW);
+        }
+      }
+      self.wait(offset, offset + len);
+      if self.map.is_null() {
+        gl::BufferSubData(self.target, offset as _, len as _, data.as_ptr() as _);
+      } else {
+        ptr::copy_nonoverlapping(data.as_ptr() as *const u8, self.map.add(offset), len);
+      }
+      self.head = offset + len;
+      self.written.push((offset, offset + len));
+      offset
+    }
+  }
+
+  // call once the draws reading everything written so far have been issued
+  pub fn fence(&mut self) {
+    if self.written.is_empty() {
+      return;
+    }
+    let written = mem::take(&mut self.written);
+    if !self.map.is_null() {
+      let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+      self.fences.push_back((written, sync));
+    }
+  }
+
+  fn wait(&mut self, start: usize, end: usize) {
+    let overlaps = |ranges: &[(usize, usize)]| ranges.iter().any(|r| r.0 < end && start < r.1);
+    // wrapped within a frame, whatever was written has been drawn already so fence it now
+    if overlaps(&self.written) {
+      self.fence();
+    }
+    self.fences.retain(|(ranges, sync)| {
+      if !overlaps(ranges) {
+        return true;
+      }
+      unsafe {
+        gl::ClientWaitSync(*sync, gl::SYNC_FLUSH_COMMANDS_BIT, gl::TIMEOUT_IGNORED);
+        gl::DeleteSync(*sync);
+      }
+      false
+    });
+  }
+}
+
+impl Drop for StreamBuffer {
+  fn drop(&mut self) {
+    unsafe {
+      for (_, sync) in self.fences.drain(..) {
+        gl::DeleteSync(sync);
+      }
+      gl::DeleteBuffers(1, &self.id);
+    }
+    vram::untrack(GpuKind::Buffer, self.id);
+  }
+}
@@ -0,0 +1,191 @@
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+use image::{Rgba, RgbaImage};
+use log::info;
+use crate::ecs::{World, stage};
+use crate::scene::Scene;
+use crate::{Engine, Result, WORLD};
+
+// makes Engine::run render into a hidden window and exit after `frames`, keeping the last one
+pub struct Headless {
+  pub size: [u32; 2],
+  pub frames: usize,
+  pub capture: Option<RgbaImage>,
+}
+
+impl Headless {
+  pub fn new(size: [u32; 2], frames: usize) -> Self {
+    Self {
+      size,
+      frames,
+      capture: None,
+    }
+  }
+}
+
+// renders a scene headlessly and compares the result against a reference png
+// glfw needs the main thread, so run these from a `harness = false` test or a binary
+pub struct GoldenTest {
+  scene: Option<PathBuf>,
+  size: [u32; 2],
+  frames: usize,
+  // per channel difference a pixel can have before it counts as mismatched
+  threshold: u8,
+  // fraction of pixels allowed to mismatch
+  tolerance: f32,
+}
+
+impl GoldenTest {
+  pub fn new() -> Self {
+    Self {
+      scene: None,
+      size: [640, 360],
+      frames: 3,
+      threshold: 8,
+      tolerance: 0.001,
+    }
+  }
+
+  pub fn scene<P: Into<PathBuf>>(mut self, path: P) -> Self {
+    self.scene = Some(path.into());
+    self
+  }
+
+  pub fn size(mut self, width: u32, height: u32) -> Self {
+    self.size = [width, height];
+    self
+  }
+
+  pub fn frames(mut self, frames: usize) -> Self {
+    self.frames = frames.max(1);
+    self
+  }
+
+  pub fn threshold(mut self, threshold: u8) -> Self {
+    self.threshold = threshold;
+    self
+  }
+
+  pub fn tolerance(mut self, tolerance: f32) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  // `engine` should already have its plugins added
+  pub fn render(&self, engine: Engine) -> Result<RgbaImage> {
+    let scene = self.scene.clone();
+    engine
+      .add_resource(Headless::new(self.size, self.frames))
+      .add_system(stage::START, move |world: &mut World| match &scene {
+        Some(path) => Scene::load(world, path.clone()),
+        None => Ok(()),
+      })
+      .run()?;
+    unsafe { WORLD.get_mut().unwrap() }
+      .take_resource::<Headless>()
+      .and_then(|h| h.capture)
+      .ok_or_else(|| "Nothing was rendered.".into())
+  }
+
+  // writes the reference instead when it is missing or PHOSPHOR_BLESS is set
+  pub fn check<P: AsRef<Path>>(&self, engine: Engine, reference: P) -> Result {
+    let reference = reference.as_ref();
+    let actual = self.render(engine)?;
+    let missing = !reference.exists();
+    if missing || env::var("PHOSPHOR_BLESS").is_ok() {
+      if let Some(dir) = reference.parent() {
+        fs::create_dir_all(dir)?;
+      }
+      actual.save(reference)?;
+      info!("Wrote reference image '{}'.", reference.display());
+      return match missing {
+        true => Err(format!("No reference image, wrote '{}'.", reference.display()).into()),
+        false => Ok(()),
+      };
+    }
+    let expected = image::open(reference)?.to_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+      return Err(
+        format!(
+          "'{}' is {:?}, rendered {:?}.",
+          reference.display(),
+          expected.dimensions(),
+          actual.dimensions()
+        )
+        .into(),
+      );
+    }
+    let (diff, mismatched) = compare(&actual, &expected, self.threshold);
+    if mismatched > self.tolerance {
+      actual.save(reference.with_extension("actual.png"))?;
+      diff.save(reference.with_extension("diff.png"))?;
+      return Err(
+        format!(
+          "'{}' differs in {:.3}% of pixels (tolerance {:.3}%).",
+          reference.display(),
+          mismatched * 100.0,
+          self.tolerance * 100.0
+        )
+        .into(),
+      );
+    }
+    Ok(())
+  }
+}
+
+// returns an image with mismatched pixels in red and the fraction of pixels that mismatched
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, threshold: u8) -> (RgbaImage, f32) {
+  let mut diff = RgbaImage::new(actual.width(), actual.height());
+  let mut mismatched = 0;
+  for ((a, e), d) in actual
+    .pixels()
+    .zip(expected.pixels())
+    .zip(diff.pixels_mut())
+  {
+    let delta = a
+      .0
+      .iter()
+      .zip(e.0)
+      .map(|(a, e)| a.abs_diff(e))
+      .max()
+      .unwrap();
+    *d = match delta > threshold {
+      true => {
+        mismatched += 1;
+        Rgba([255, 0, 0, 255])
+      }
+      // faded copy of the expected image for context
+      false => Rgba([e[0] / 4, e[1] / 4, e[2] / 4, 255]),
+    };
+  }
+  (
+    diff,
+    mismatched as f32 / (actual.width() * actual.height()).max(1) as f32,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn compare_threshold() {
+    let expected = RgbaImage::from_pixel(4, 1, Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    // at the threshold still matches, past it doesn't
+    actual.put_pixel(0, 0, Rgba([108, 100, 100, 255]));
+    actual.put_pixel(1, 0, Rgba([100, 91, 100, 255]));
+    let (diff, mismatched) = compare(&actual, &expected, 8);
+    assert_eq!(mismatched, 0.25);
+    assert_eq!(*diff.get_pixel(0, 0), Rgba([25, 25, 25, 255]));
+    assert_eq!(*diff.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+  }
+
+  #[test]
+  fn compare_alpha() {
+    let expected = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+    let actual = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 0]));
+    assert_eq!(compare(&expected, &expected, 0).1, 0.0);
+    assert_eq!(compare(&actual, &expected, 0).1, 1.0);
+  }
+}
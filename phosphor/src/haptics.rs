@@ -0,0 +1,173 @@
+use std::time::{Duration, Instant};
+use gilrs::{Gilrs, GamepadId};
+use gilrs::ff::{Effect, EffectBuilder, BaseEffect, BaseEffectType, Replay, Envelope, Ticks};
+use log::{debug, warn};
+use crate::Result;
+
+// a rumble, strengths are 0 to 1 and times are seconds. the envelope ramps from the attack
+// level up to full strength and back down to the fade level over the last `fade` seconds
+#[derive(Copy, Clone)]
+pub struct HapticEffect {
+  // the low frequency motor, felt more than heard
+  pub strong: f32,
+  // the high frequency motor
+  pub weak: f32,
+  pub duration: f32,
+  pub delay: f32,
+  pub attack: f32,
+  pub attack_level: f32,
+  pub fade: f32,
+  pub fade_level: f32,
+}
+
+impl HapticEffect {
+  pub const DEFAULT: Self = Self {
+    strong: 1.0,
+    weak: 1.0,
+    duration: 0.2,
+    delay: 0.0,
+    attack: 0.0,
+    attack_level: 0.0,
+    fade: 0.0,
+    fade_level: 0.0,
+  };
+
+  pub fn new(strength: f32, duration: f32) -> Self {
+    Self {
+      strong: strength,
+      weak: strength,
+      duration,
+      ..Self::DEFAULT
+    }
+  }
+
+  pub fn motors(mut self, strong: f32, weak: f32) -> Self {
+    self.strong = strong;
+    self.weak = weak;
+    self
+  }
+
+  pub fn delay(mut self, delay: f32) -> Self {
+    self.delay = delay;
+    self
+  }
+
+  pub fn attack(mut self, attack: f32, level: f32) -> Self {
+    self.attack = attack;
+    self.attack_level = level;
+    self
+  }
+
+  pub fn fade(mut self, fade: f32, level: f32) -> Self {
+    self.fade = fade;
+    self.fade_level = level;
+    self
+  }
+}
+
+fn ticks(secs: f32) -> Ticks {
+  Ticks::from_ms((secs.max(0.0) * 1000.0) as _)
+}
+
+fn magnitude(strength: f32) -> u16 {
+  (strength.clamp(0.0, 1.0) * u16::MAX as f32) as _
+}
+
+// gamepad rumble, does nothing when there's no gamepad backend. adaptive trigger effects aren't
+// exposed by any backend yet so only the rumble motors are driven
+pub struct Haptics {
+  gilrs: Option<Gilrs>,
+  // effects stop when they're dropped, so they're held until they've finished
+  playing: Vec<(Effect, Instant)>,
+}
+
+impl Haptics {
+  pub fn new() -> Self {
+    let gilrs = match Gilrs::new() {
+      Ok(g) => Some(g),
+      Err(e) => {
+        warn!("Couldn't initialize gamepads, haptics are disabled: {}", e);
+        None
+      }
+    };
+    Self {
+      gilrs,
+      playing: vec![],
+    }
+  }
+
+  // connected gamepads that can rumble
+  pub fn gamepads(&self) -> Vec<GamepadId> {
+    match &self.gilrs {
+      Some(g) => g
+        .gamepads()
+        .filter(|(_, g)| g.is_ff_supported())
+        .map(|(id, _)| id)
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  pub fn play(&mut self, gamepad: GamepadId, strength: f32, duration: f32) -> Result {
+    self.play_effect(gamepad, HapticEffect::new(strength, duration))
+  }
+
+  pub fn play_effect(&mut self, gamepad: GamepadId, effect: HapticEffect) -> Result {
+    let gilrs = match &mut self.gilrs {
+      Some(g) => g,
+      None => return Ok(()),
+    };
+    let scheduling = Replay {
+      after: ticks(effect.delay),
+      play_for: ticks(effect.duration),
+      with_delay: Ticks::from_ms(0),
+    };
+    let envelope = Envelope {
+      attack_length: ticks(effect.attack),
+      attack_level: effect.attack_level.clamp(0.0, 1.0),
+      fade_length: ticks(effect.fade),
+      fade_level: effect.fade_level.clamp(0.0, 1.0),
+    };
+    let mut builder = EffectBuilder::new();
+    for kind in [
+      BaseEffectType::Strong {
+        magnitude: magnitude(effect.strong),
+      },
+      BaseEffectType::Weak {
+        magnitude: magnitude(effect.weak),
+      },
+    ] {
+      builder.add_effect(BaseEffect {
+        kind,
+        scheduling,
+        envelope,
+      });
+    }
+    let ff = builder
+      .gamepads(&[gamepad])
+      .finish(gilrs)
+      .map_err(|e| format!("Couldn't create haptic effect: {}", e))?;
+    ff.play()
+      .map_err(|e| format!("Couldn't play haptic effect: {}", e))?;
+    let end = Duration::from_secs_f32((effect.delay + effect.duration).max(0.0));
+    self.playing.push((ff, Instant::now() + end));
+    Ok(())
+  }
+
+  pub fn stop(&mut self) {
+    self.playing.clear();
+  }
+
+  pub(crate) fn update(&mut self) {
+    if let Some(gilrs) = &mut self.gilrs {
+      // gilrs only notices gamepads connecting through its events
+      while let Some(e) = gilrs.next_event() {
+        if let gilrs::EventType::Connected = e.event {
+          debug!("Connected gamepad '{}'.", gilrs.gamepad(e.id).name());
+        }
+      }
+    }
+    let now = Instant::now();
+    self.playing.retain(|(_, end)| *end > now);
+  }
+}
@@ -0,0 +1,240 @@
+use std::collections::{HashSet, HashMap};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::ops::RangeInclusive;
+use glfw::{WindowEvent, Key, MouseButton, Action};
+use log::warn;
+use crate::Result;
+use crate::accessibility::hold_to_toggle;
+
+pub const INPUT_PATH: &str = "input.txt";
+
+// the values glfw's keys are defined over
+const KEYS: [RangeInclusive<i32>; 13] = [
+  32..=32,
+  39..=39,
+  44..=57,
+  59..=59,
+  61..=61,
+  65..=93,
+  96..=96,
+  161..=162,
+  256..=269,
+  280..=284,
+  290..=314,
+  320..=336,
+  340..=348,
+];
+
+// none for values glfw doesn't define a key for, besides its -1 for unknown keys
+pub(crate) fn key_from_i32(k: i32) -> Option<Key> {
+  (k == -1 || KEYS.iter().any(|r| r.contains(&k))).then(|| unsafe { mem::transmute::<i32, Key>(k) })
+}
+
+pub(crate) fn button_from_i32(b: i32) -> Option<MouseButton> {
+  (0..=7)
+    .contains(&b)
+    .then(|| unsafe { mem::transmute::<i32, MouseButton>(b) })
+}
+
+// what an action is bound to, written as glfw's key names or `Mouse1` to `Mouse8`
+#[derive(Copy, Clone, PartialEq)]
+pub enum Binding {
+  Key(Key),
+  Button(MouseButton),
+}
+
+impl Binding {
+  pub fn parse(s: &str) -> Option<Self> {
+    if let Some(n) = s.strip_prefix("Mouse") {
+      return match n.parse::<i32>() {
+        Ok(n @ 1..=8) => button_from_i32(n - 1).map(Self::Button),
+        _ => None,
+      };
+    }
+    KEYS
+      .into_iter()
+      .flatten()
+      .filter_map(key_from_i32)
+      .find(|k| format!("{:?}", k).eq_ignore_ascii_case(s))
+      .map(Self::Key)
+  }
+
+  pub fn name(&self) -> String {
+    match self {
+      Self::Key(k) => format!("{:?}", k),
+      Self::Button(b) => format!("Mouse{}", *b as i32 + 1),
+    }
+  }
+}
+
+// files dropped on the window this frame and where the cursor was, a resource until the next
+pub struct FileDrop {
+  pub paths: Vec<PathBuf>,
+  pub cursor: (f32, f32),
+}
+
+// input state built from window events, so it follows replayed input as well as real input
+pub struct Input {
+  keys: HashSet<Key>,
+  pressed: HashSet<Key>,
+  buttons: HashSet<MouseButton>,
+  pressed_buttons: HashSet<MouseButton>,
+  // flipped on every press, for `hold_to_toggle`
+  toggled_keys: HashSet<Key>,
+  toggled_buttons: HashSet<MouseButton>,
+  pub cursor: (f32, f32),
+  // cursor movement and scrolling since the last frame
+  pub delta: (f32, f32),
+  pub scroll: (f32, f32),
+  // named actions, the input file's bindings over the ones plugins set up
+  defaults: HashMap<String, Vec<Binding>>,
+  bindings: HashMap<String, Vec<Binding>>,
+}
+
+impl Input {
+  pub fn new(cursor: (f64, f64)) -> Self {
+    Self {
+      keys: HashSet::new(),
+      pressed: HashSet::new(),
+      buttons: HashSet::new(),
+      pressed_buttons: HashSet::new(),
+      toggled_keys: HashSet::new(),
+      toggled_buttons: HashSet::new(),
+      cursor: (cursor.0 as _, cursor.1 as _),
+      delta: (0.0, 0.0),
+      scroll: (0.0, 0.0),
+      defaults: HashMap::new(),
+      bindings: HashMap::new(),
+    }
+  }
+
+  pub fn key_down(&self, key: Key) -> bool {
+    self.keys.contains(&key)
+  }
+
+  // only true on the frame the key went down
+  pub fn key_pressed(&self, key: Key) -> bool {
+    self.pressed.contains(&key)
+  }
+
+  pub fn button_down(&self, button: MouseButton) -> bool {
+    self.buttons.contains(&button)
+  }
+
+  pub fn button_pressed(&self, button: MouseButton) -> bool {
+    self.pressed_buttons.contains(&button)
+  }
+
+  // for actions held down like aiming or sprinting, these toggle with each press instead while
+  // `hold_to_toggle` is on
+  pub fn held(&self, key: Key) -> bool {
+    match hold_to_toggle.get() {
+      true => self.toggled_keys.contains(&key),
+      false => self.key_down(key),
+    }
+  }
+
+  pub fn button_held(&self, button: MouseButton) -> bool {
+    match hold_to_toggle.get() {
+      true => self.toggled_buttons.contains(&button),
+      false => self.button_down(button),
+    }
+  }
+
+  // used until the input file binds the action
+  pub fn bind_default(&mut self, action: &str, bindings: &[Binding]) {
+    self.defaults.insert(action.to_string(), bindings.to_vec());
+    self
+      .bindings
+      .entry(action.to_string())
+      .or_insert(bindings.to_vec());
+  }
+
+  pub fn bindings(&self, action: &str) -> &[Binding] {
+    self.bindings.get(action).map_or(&[], |b| b.as_slice())
+  }
+
+  pub fn action_down(&self, action: &str) -> bool {
+    self.bindings(action).iter().any(|b| match *b {
+      Binding::Key(k) => self.key_down(k),
+      Binding::Button(b) => self.button_down(b),
+    })
+  }
+
+  pub fn action_pressed(&self, action: &str) -> bool {
+    self.bindings(action).iter().any(|b| match *b {
+      Binding::Key(k) => self.key_pressed(k),
+      Binding::Button(b) => self.button_pressed(b),
+    })
+  }
+
+  pub fn action_held(&self, action: &str) -> bool {
+    self.bindings(action).iter().any(|b| match *b {
+      Binding::Key(k) => self.held(k),
+      Binding::Button(b) => self.button_held(b),
+    })
+  }
+
+  // `action = Key, Key` lines in the project root, an empty list unbinds it. actions left out go
+  // back to their defaults
+  pub fn load_bindings<P: AsRef<Path>>(&mut self, path: P) -> Result {
+    self.bindings = self.defaults.clone();
+    for line in fs::read_to_string(path)?.lines() {
+      if let Some((k, v)) = line.split_once('=') {
+        let mut bindings = vec![];
+        for name in v.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()) {
+          match Binding::parse(name) {
+            Some(b) => bindings.push(b),
+            None => warn!("Unknown key '{}' for '{}'.", name, k.trim()),
+          }
+        }
+        self.bindings.insert(k.trim().to_string(), bindings);
+      }
+    }
+    Ok(())
+  }
+
+  pub(crate) fn new_frame(&mut self) {
+    self.pressed.clear();
+    self.pressed_buttons.clear();
+    self.delta = (0.0, 0.0);
+    self.scroll = (0.0, 0.0);
+  }
+
+  pub(crate) fn handle(&mut self, event: &WindowEvent) {
+    match *event {
+      WindowEvent::Key(key, _, Action::Press, _) => {
+        self.keys.insert(key);
+        self.pressed.insert(key);
+        if !self.toggled_keys.remove(&key) {
+          self.toggled_keys.insert(key);
+        }
+      }
+      WindowEvent::Key(key, _, Action::Release, _) => {
+        self.keys.remove(&key);
+      }
+      WindowEvent::MouseButton(button, Action::Press, _) => {
+        self.buttons.insert(button);
+        self.pressed_buttons.insert(button);
+        if !self.toggled_buttons.remove(&button) {
+          self.toggled_buttons.insert(button);
+        }
+      }
+      WindowEvent::MouseButton(button, Action::Release, _) => {
+        self.buttons.remove(&button);
+      }
+      WindowEvent::CursorPos(x, y) => {
+        self.delta.0 += x as f32 - self.cursor.0;
+        self.delta.1 += y as f32 - self.cursor.1;
+        self.cursor = (x as _, y as _);
+      }
+      WindowEvent::Scroll(x, y) => {
+        self.scroll.0 += x as f32;
+        self.scroll.1 += y as f32;
+      }
+      _ => {}
+    }
+  }
+}
@@ -6,22 +6,50 @@ pub mod gfx;
 pub mod ecs;
 pub mod assets;
 pub mod scene;
+pub mod cvars;
+pub mod config;
+pub mod coroutines;
+pub mod input;
+pub mod replay;
+pub mod golden;
+pub mod streaming;
+pub mod haptics;
+pub mod accessibility;
+pub mod profiling;
+pub mod vram;
+pub mod raycast;
+mod compressed;
 
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::any::{Any, TypeId, type_name};
 use std::cmp::Ordering;
 use std::mem;
-use glfw::Context;
+use std::path::{Path, PathBuf};
+use std::env;
+use glfw::{Context, WindowEvent};
 use once_cell::unsync::OnceCell;
-use crate::gfx::Renderer;
+use crate::gfx::{Renderer, WindowOptions, Framebuffer, swap_interval};
 use crate::ecs::{World, System, stage};
 use crate::assets::Assets;
+use crate::cvars::CVARS_PATH;
+use crate::config::ConfigFiles;
+use crate::coroutines::Coroutines;
+use crate::input::{Input, FileDrop, INPUT_PATH};
+use crate::replay::Replay;
+use crate::golden::Headless;
+use crate::streaming::TextureStreamer;
+use crate::haptics::Haptics;
+use crate::accessibility::Subtitles;
+use crate::profiling::GpuProfiler;
 
 pub use phosphor_derive::*;
 pub use glam as math;
 pub use log;
 pub use glfw;
+pub use raw_window_handle;
+pub use image;
+pub use gilrs;
 pub use bincode;
 pub use linkme;
 
@@ -32,6 +60,49 @@ static mut WORLD: OnceCell<World> = OnceCell::new();
 pub struct Engine;
 pub struct DeltaTime(pub f32);
 
+pub struct Time {
+  pub scale: f32,
+  pub paused: bool,
+  pub elapsed: f32,
+  pub unscaled_delta: f32,
+  // used instead of the real frame time when set, eg while recording or replaying input
+  pub fixed: Option<f32>,
+  step: bool,
+}
+
+impl Time {
+  pub fn new() -> Self {
+    Self {
+      scale: 1.0,
+      paused: false,
+      elapsed: 0.0,
+      unscaled_delta: 0.0,
+      fixed: None,
+      step: false,
+    }
+  }
+
+  pub fn step(&mut self) {
+    self.step = true;
+  }
+
+  fn advance(&mut self, dt: f32) -> f32 {
+    self.unscaled_delta = dt;
+    let dt = self.fixed.unwrap_or(dt);
+    let dt = if self.paused && !self.step {
+      0.0
+    } else if self.paused {
+      // step by a fixed 60hz frame so stepping is independent of editor framerate
+      self.scale / 60.0
+    } else {
+      dt * self.scale
+    };
+    self.step = false;
+    self.elapsed += dt;
+    dt
+  }
+}
+
 impl Engine {
   pub fn new() -> Self {
     unsafe {
@@ -56,27 +127,117 @@ impl Engine {
 
   pub fn run(self) -> Result<()> {
     let world = unsafe { WORLD.get_mut().unwrap() };
+    if Path::new(CVARS_PATH).exists() {
+      cvars::load(CVARS_PATH)?;
+    }
     world.add_resource(Assets::new());
-    world.add_resource(Renderer::new()?);
-    let renderer = world.get_resource::<Renderer>().unwrap();
+    let headless = world.get_resource::<Headless>().map(|h| h.size);
+    let options = world
+      .get_resource::<WindowOptions>()
+      .map_or(WindowOptions::DEFAULT, |o| *o);
+    world.add_resource(match headless {
+      Some([w, h]) => Renderer::new(&options.size(w, h).visible(false))?,
+      None => Renderer::new(&options)?,
+    });
+    // headless output is compared against goldens, so textures are uploaded whole there and
+    // gamepads are left alone
+    if headless.is_none() {
+      world.add_resource(TextureStreamer::new());
+      world.add_resource(Haptics::new());
+    }
+    world.add_resource(Subtitles::new());
+    world.add_resource(Coroutines::new());
+    world.add_resource(GpuProfiler::new());
+    if world.get_resource::<Time>().is_none() {
+      world.add_resource(Time::new());
+    }
+    if headless.is_some() {
+      // headless runs are for comparing output, so advance the same amount every frame
+      world.get_resource::<Time>().unwrap().fixed = Some(1.0 / 60.0);
+    }
+    let renderer = unsafe { WORLD.get().unwrap() }
+      .get_resource::<Renderer>()
+      .unwrap();
+    let mut input = Input::new(renderer.window.get_cursor_pos());
+    if Path::new(INPUT_PATH).exists() {
+      input.load_bindings(INPUT_PATH)?;
+    }
+    world.add_resource(input);
+    let mut config = ConfigFiles::new();
+    config.watch(CVARS_PATH, |_, path| cvars::load(path));
+    config.watch(INPUT_PATH, |world, path| {
+      world.get_resource::<Input>().unwrap().load_bindings(path)
+    });
+    world.add_resource(config);
     world.run_system(stage::INIT);
     world.run_system(stage::START);
+    // sessions recorded from startup replay exactly
+    if let Ok(path) = env::var("PHOSPHOR_RECORD") {
+      Replay::record(world, PathBuf::from(path))?;
+    }
+    if let Ok(path) = env::var("PHOSPHOR_REPLAY") {
+      Replay::play(world, PathBuf::from(path))?;
+    }
     let mut t = renderer.glfw.get_time();
     while !renderer.window.should_close() {
       puffin::GlobalProfiler::lock().new_frame();
+      world.new_frame();
       let n = renderer.glfw.get_time();
-      world.add_resource(DeltaTime((n - t) as _));
+      let dt = world.get_resource::<Time>().unwrap().advance((n - t) as _);
+      world.add_resource(DeltaTime(dt));
       t = n;
       renderer.glfw.poll_events();
-      for (_, event) in renderer.events.try_iter() {
+      let events = renderer
+        .events
+        .try_iter()
+        .map(|(_, e)| e)
+        .chain(renderer.queued.drain(..))
+        .collect();
+      world.get_resource::<Input>().unwrap().new_frame();
+      world.take_resource::<FileDrop>();
+      for event in replay::frame(world, events)? {
+        let input = world.get_resource::<Input>().unwrap();
+        input.handle(&event);
+        if let WindowEvent::FileDrop(paths) = &event {
+          match world.get_resource::<FileDrop>() {
+            Some(drop) => drop.paths.extend(paths.iter().cloned()),
+            None => world.add_resource(FileDrop {
+              paths: paths.clone(),
+              cursor: input.cursor,
+            }),
+          }
+        }
         world.add_resource(event);
         world.run_system(stage::EVENT);
       }
       world.run_system(stage::PRE_DRAW);
+      Coroutines::update(world);
       world.run_system(stage::DRAW);
       world.run_system(stage::POST_DRAW);
+      ConfigFiles::update(world);
+      world.get_resource::<Assets>().unwrap().update();
+      if let Some(streamer) = world.get_resource::<TextureStreamer>() {
+        streamer.update();
+      }
+      if let Some(haptics) = world.get_resource::<Haptics>() {
+        haptics.update();
+      }
+      if let Some(profiler) = world.get_resource::<GpuProfiler>() {
+        profiler.update();
+      }
+      if let Some(headless) = world.get_resource::<Headless>() {
+        headless.frames = headless.frames.saturating_sub(1);
+        if headless.frames == 0 {
+          let (w, h) = renderer.window.get_framebuffer_size();
+          headless.capture = Some(Framebuffer::DEFAULT.read_pixels((0, 0, w as _, h as _)));
+          renderer.window.set_should_close(true);
+        }
+      }
+      renderer.set_swap_interval(swap_interval.get());
+      renderer.apply_cursor();
       renderer.window.swap_buffers();
     }
+    Replay::stop(world)?;
     Ok(())
   }
 }
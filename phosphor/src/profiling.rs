@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use puffin::{GlobalFrameView, NanoSecond, Reader, Stream};
+use log::info;
+use crate::Result;
+use crate::gfx::Query;
+
+// gpu samples kept, a few per pass for every recent frame
+const MAX_GPU_SAMPLES: usize = 4096;
+// queries each gpu scope cycles through, so results can take this many frames to come back
+const GPU_LATENCY: usize = 4;
+// resolved timings kept per gpu scope
+const GPU_HISTORY: usize = 300;
+
+struct GpuSample {
+  name: &'static str,
+  start_ns: NanoSecond,
+  duration_ns: NanoSecond,
+}
+
+// keeps the frames puffin records, along with gpu pass times, so they can be exported for
+// chrome://tracing or perfetto. adding it turns profiling on
+pub struct Profiler {
+  view: GlobalFrameView,
+  gpu: VecDeque<GpuSample>,
+}
+
+impl Profiler {
+  pub fn new() -> Self {
+    puffin::set_scopes_on(true);
+    Self {
+      view: GlobalFrameView::default(),
+      gpu: VecDeque::new(),
+    }
+  }
+
+  pub fn frames(&self) -> usize {
+    self.view.lock().recent_frames().count()
+  }
+
+  // the gpu only reports durations, so passes are laid out one after another from when they're
+  // recorded. they're usually read back a frame late and show up there
+  pub fn gpu_scope(&mut self, name: &'static str, duration_ns: u64) {
+    let start_ns = match self.gpu.back() {
+      Some(s) => puffin::now_ns().max(s.start_ns + s.duration_ns),
+      None => puffin::now_ns(),
+    };
+    self.gpu.push_back(GpuSample {
+      name,
+      start_ns,
+      duration_ns: duration_ns as _,
+    });
+    if self.gpu.len() > MAX_GPU_SAMPLES {
+      self.gpu.pop_front();
+    }
+  }
+
+  // every recent frame as a chrome trace, each thread gets a track and gpu passes go on their own
+  pub fn export_chrome_trace<P: AsRef<Path>>(&self, path: P) -> Result {
+    let mut events = vec![];
+    let mut threads = vec![];
+    let mut range: Option<(NanoSecond, NanoSecond)> = None;
+    for frame in self.view.lock().recent_frames() {
+      let frame = frame
+        .unpacked()
+        .map_err(|e| format!("Couldn't unpack profiler frame. {}", e))?;
+      let (start, end) = frame.meta.range_ns;
+      range = Some(range.map_or((start, end), |(s, e)| (s.min(start), e.max(end))));
+      for (thread, info) in &frame.thread_streams {
+        let tid = match threads.iter().position(|t| *t == thread.name) {
+          Some(i) => i,
+          None => {
+            threads.push(thread.name.clone());
+            threads.len() - 1
+          }
+        };
+        scopes(&info.stream, None, tid, &mut events)?;
+      }
+    }
+    let (start, end) = range.ok_or("No profiler frames have been recorded.")?;
+    let gpu = threads.len();
+    for s in self
+      .gpu
+      .iter()
+      .filter(|s| s.start_ns >= start && s.start_ns <= end)
+    {
+      events.push(event(s.name, "gpu", s.start_ns, s.duration_ns, gpu, ""));
+    }
+    threads.push("GPU".to_string());
+    for (tid, name) in threads.iter().enumerate() {
+      events.push(format!(
+        r#"{{"name":"thread_name","ph":"M","pid":0,"tid":{},"args":{{"name":"{}"}}}}"#,
+        tid,
+        escape(name)
+      ));
+    }
+    let path = path.as_ref();
+    fs::write(
+      path,
+      format!(
+        r#"{{"displayTimeUnit":"ms","traceEvents":[{}]}}"#,
+        events.join(",\n")
+      ),
+    )?;
+    info!("Exported profile to '{}'.", path.display());
+    Ok(())
+  }
+}
+
+// walks the scope tree depth first, viewers nest complete events by their times
+fn scopes(stream: &Stream, offset: Option<u64>, tid: usize, events: &mut Vec<String>) -> Result {
+  let reader = match offset {
+    Some(o) => Reader::with_offset(stream, o)
+      .map_err(|e| format!("Couldn't read profiler scope. {:?}", e))?,
+    None => Reader::from_start(stream),
+  };
+  for scope in reader {
+    let scope = scope.map_err(|e| format!("Couldn't read profiler scope. {:?}", e))?;
+    let r = scope.record;
+    events.push(event(
+      r.id,
+      r.location,
+      r.start_ns,
+      r.duration_ns,
+      tid,
+      r.data,
+    ));
+    scopes(stream, Some(scope.child_begin_position), tid, events)?;
+  }
+  Ok(())
+}
+
+// a complete event, chrome traces are in microseconds
+fn event(
+  name: &str,
+  cat: &str,
+  start: NanoSecond,
+  duration: NanoSecond,
+  tid: usize,
+  data: &str,
+) -> String {
+  let mut e = format!(
+    r#"{{"name":"{}","cat":"{}","ph":"X","ts":{:.3},"dur":{:.3},"pid":0,"tid":{}"#,
+    escape(name),
+    escape(cat),
+    start as f64 / 1000.0,
+    duration as f64 / 1000.0,
+    tid
+  );
+  if !data.is_empty() {
+    write!(e, r#","args":{{"data":"{}"}}"#, escape(data)).unwrap();
+  }
+  e.push('}');
+  e
+}
+
+fn escape(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+      c => out.push(c),
+    }
+  }
+  out
+}
+
+pub struct GpuScope {
+  pub name: &'static str,
+  // milliseconds, oldest first
+  pub history: VecDeque<f32>,
+  // each query and whether it's waiting on a result
+  queries: Vec<(Query, bool)>,
+  next: usize,
+}
+
+impl GpuScope {
+  fn new(name: &'static str) -> Self {
+    Self {
+      name,
+      history: VecDeque::new(),
+      queries: (0..GPU_LATENCY).map(|_| (Query::new(), false)).collect(),
+      next: 0,
+    }
+  }
+
+  fn push(&mut self, ms: f32) {
+    self.history.push_back(ms);
+    if self.history.len() > GPU_HISTORY {
+      self.history.pop_front();
+    }
+  }
+
+  pub fn last(&self) -> Option<f32> {
+    self.history.back().copied()
+  }
+
+  pub fn average(&self) -> Option<f32> {
+    (!self.history.is_empty()).then(|| self.history.iter().sum::<f32>() / self.history.len() as f32)
+  }
+
+  pub fn max(&self) -> Option<f32> {
+    self.history.iter().copied().reduce(f32::max)
+  }
+}
+
+// times named parts of a frame on the gpu. each scope cycles through a few timer queries and reads
+// them back once they're ready, so it never waits on the gpu. a scope run more than once a frame,
+// like for each view, gets a sample each time
+pub struct GpuProfiler {
+  scopes: Vec<GpuScope>,
+  timing: bool,
+}
+
+impl GpuProfiler {
+  pub fn new() -> Self {
+    Self {
+      scopes: vec![],
+      timing: false,
+    }
+  }
+
+  // gl only runs one timer at a time, so nested scopes and ones whose queries are all still in
+  // flight just run `f` untimed
+  pub fn scope<F: FnMut()>(&mut self, name: &'static str, mut f: F) {
+    if self.timing {
+      f();
+      return;
+    }
+    let scope = get_or_add(&mut self.scopes, name);
+    let (query, pending) = &mut scope.queries[scope.next];
+    if *pending {
+      f();
+      return;
+    }
+    self.timing = true;
+    query.time(f);
+    *pending = true;
+    scope.next = (scope.next + 1) % GPU_LATENCY;
+    self.timing = false;
+  }
+
+  // for times measured with other queries, like the scene renderer's passes
+  pub fn record(&mut self, name: &'static str, ns: u64) {
+    get_or_add(&mut self.scopes, name).push(ns as f32 / 1000000.0);
+  }
+
+  // reads back whatever finished, in the order it was run. called by the engine every frame
+  pub fn update(&mut self) {
+    for scope in &mut self.scopes {
+      for i in 0..GPU_LATENCY {
+        let (query, pending) = &mut scope.queries[(scope.next + i) % GPU_LATENCY];
+        if !*pending {
+          continue;
+        }
+        match query.get() {
+          Some(ns) => {
+            *pending = false;
+            scope.push(ns as f32 / 1000000.0);
+          }
+          // later ones can't be done either
+          None => break,
+        }
+      }
+    }
+  }
+
+  pub fn scopes(&self) -> &[GpuScope] {
+    &self.scopes
+  }
+
+  pub fn get(&self, name: &str) -> Option<&GpuScope> {
+    self.scopes.iter().find(|s| s.name == name)
+  }
+}
+
+fn get_or_add<'a>(scopes: &'a mut Vec<GpuScope>, name: &'static str) -> &'a mut GpuScope {
+  let i = match scopes.iter().position(|s| s.name == name) {
+    Some(i) => i,
+    None => {
+      scopes.push(GpuScope::new(name));
+      scopes.len() - 1
+    }
+  };
+  &mut scopes[i]
+}
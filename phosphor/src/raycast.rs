@@ -0,0 +1,171 @@
+use glam::{Vec3, Mat3, Mat4};
+use crate::gfx::{Aabb, Mesh};
+
+// triangles per leaf
+const LEAF: usize = 4;
+
+#[derive(Copy, Clone)]
+pub struct Hit {
+  // along the ray's direction as given, so with a unit direction it's the world distance
+  pub distance: f32,
+  pub point: Vec3,
+  // faces the ray's side of the triangle
+  pub normal: Vec3,
+  // the hit triangle's vertices, index into the mesh's positions
+  pub vertices: [u32; 3],
+}
+
+struct Node {
+  aabb: Aabb,
+  // leaves own `count` triangles from `first`, others have their children at `first` and
+  // `first + 1`
+  first: usize,
+  count: usize,
+}
+
+impl Node {
+  const EMPTY: Self = Self {
+    aabb: Aabb {
+      min: Vec3::ZERO,
+      max: Vec3::ZERO,
+    },
+    first: 0,
+    count: 0,
+  };
+}
+
+// bounding volume hierarchy over a mesh's triangles in its own space, split at the median along
+// the widest axis until leaves are small
+pub(crate) struct Bvh {
+  nodes: Vec<Node>,
+  triangles: Vec<[u32; 3]>,
+}
+
+impl Bvh {
+  pub(crate) fn new(mesh: &Mesh) -> Self {
+    let mut bvh = Self {
+      nodes: vec![],
+      triangles: mesh.triangles(),
+    };
+    if !bvh.triangles.is_empty() {
+      bvh.nodes.push(Node::EMPTY);
+      bvh.split(&mesh.positions, 0, 0, bvh.triangles.len());
+    }
+    bvh
+  }
+
+  fn split(&mut self, positions: &[Vec3], node: usize, start: usize, end: usize) {
+    let corners = |t: &[u32; 3]| t.map(|i| positions[i as usize]);
+    let centroid = |t: &[u32; 3]| corners(t).iter().sum::<Vec3>() / 3.0;
+    let tris = &mut self.triangles[start..end];
+    self.nodes[node] = Node {
+      aabb: Aabb::from_points(tris.iter().flat_map(corners)),
+      first: start,
+      count: tris.len(),
+    };
+    if tris.len() <= LEAF {
+      return;
+    }
+    let bounds = Aabb::from_points(tris.iter().map(centroid));
+    let size = bounds.max - bounds.min;
+    let axis = match size.x >= size.y && size.x >= size.z {
+      true => 0,
+      false if size.y >= size.z => 1,
+      false => 2,
+    };
+    tris.sort_unstable_by(|a, b| centroid(a)[axis].total_cmp(&centroid(b)[axis]));
+    let (mid, left) = ((start + end) / 2, self.nodes.len());
+    self.nodes.extend([Node::EMPTY, Node::EMPTY]);
+    self.nodes[node].first = left;
+    self.nodes[node].count = 0;
+    self.split(positions, left, start, mid);
+    self.split(positions, left + 1, mid, end);
+  }
+
+  // closest hit in the mesh's own space
+  pub(crate) fn raycast(
+    &self,
+    positions: &[Vec3],
+    (o, d): (Vec3, Vec3),
+  ) -> Option<(f32, [u32; 3])> {
+    let inv_d = d.recip();
+    let mut closest: Option<(f32, [u32; 3])> = None;
+    let mut stack = match self.nodes.is_empty() {
+      true => vec![],
+      false => vec![0],
+    };
+    while let Some(i) = stack.pop() {
+      let node = &self.nodes[i];
+      let max = closest.map_or(f32::MAX, |c| c.0);
+      if !ray_aabb(o, inv_d, &node.aabb).is_some_and(|t| t < max) {
+        continue;
+      }
+      if node.count == 0 {
+        stack.extend([node.first, node.first + 1]);
+        continue;
+      }
+      for tri in &self.triangles[node.first..node.first + node.count] {
+        if let Some(t) = ray_triangle((o, d), tri.map(|i| positions[i as usize])) {
+          if closest.is_none_or(|c| t < c.0) {
+            closest = Some((t, *tri));
+          }
+        }
+      }
+    }
+    closest
+  }
+}
+
+impl Mesh {
+  // closest hit against the mesh drawn with `transform`, for precise picking and hitscan against
+  // render geometry. lines and points are never hit. the bvh is built on the first call and kept
+  // until `update`, so changing `positions` or `indices` directly needs an `update` too
+  pub fn raycast(&self, ray: (Vec3, Vec3), transform: &Mat4) -> Option<Hit> {
+    let inv = transform.inverse();
+    // the direction isn't normalized so distances stay in world units
+    let local = (inv.transform_point3(ray.0), inv.transform_vector3(ray.1));
+    let (t, vertices) = self.bvh().raycast(&self.positions, local)?;
+    let [a, b, c] = vertices.map(|i| self.positions[i as usize]);
+    let mut normal = (Mat3::from_mat4(inv).transpose() * (b - a).cross(c - a)).normalize();
+    if normal.dot(ray.1) > 0.0 {
+      normal = -normal;
+    }
+    Some(Hit {
+      distance: t,
+      point: ray.0 + ray.1 * t,
+      normal,
+      vertices,
+    })
+  }
+}
+
+// entry distance, or none if it's missed or behind
+fn ray_aabb(o: Vec3, inv_d: Vec3, aabb: &Aabb) -> Option<f32> {
+  let t1 = (aabb.min - o) * inv_d;
+  let t2 = (aabb.max - o) * inv_d;
+  let near = t1.min(t2).max_element().max(0.0);
+  let far = t1.max(t2).min_element();
+  (near <= far).then_some(near)
+}
+
+// möller-trumbore, both sides
+pub fn ray_triangle((o, d): (Vec3, Vec3), [a, b, c]: [Vec3; 3]) -> Option<f32> {
+  let (e1, e2) = (b - a, c - a);
+  let p = d.cross(e2);
+  let det = e1.dot(p);
+  if det.abs() < 1e-7 {
+    return None;
+  }
+  let s = o - a;
+  let u = s.dot(p) / det;
+  if !(0.0..=1.0).contains(&u) {
+    return None;
+  }
+  let q = s.cross(e1);
+  let v = d.dot(q) / det;
+  if v < 0.0 || u + v > 1.0 {
+    return None;
+  }
+  let t = e2.dot(q) / det;
+  (t > 0.0).then_some(t)
+}
@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::time::Instant;
+use glfw::{WindowEvent, Action, Modifiers};
+use serde::{Serialize, Deserialize};
+use log::info;
+use crate::ecs::World;
+use crate::input::{Input, key_from_i32, button_from_i32};
+use crate::{Result, Time};
+
+const DEFAULT_STEP: f32 = 1.0 / 60.0;
+
+// the input events worth replaying, glfw enums are stored as their discriminants
+#[derive(Serialize, Deserialize)]
+enum RecordedEvent {
+  Key(i32, i32, i32, i32),
+  MouseButton(i32, i32, i32),
+  CursorPos(f64, f64),
+  Scroll(f64, f64),
+  Char(char),
+}
+
+impl RecordedEvent {
+  fn from_event(event: &WindowEvent) -> Option<Self> {
+    Some(match *event {
+      WindowEvent::Key(key, scancode, action, mods) => {
+        Self::Key(key as _, scancode, action as _, mods.bits())
+      }
+      WindowEvent::MouseButton(button, action, mods) => {
+        Self::MouseButton(button as _, action as _, mods.bits())
+      }
+      WindowEvent::CursorPos(x, y) => Self::CursorPos(x, y),
+      WindowEvent::Scroll(x, y) => Self::Scroll(x, y),
+      WindowEvent::Char(c) => Self::Char(c),
+      _ => return None,
+    })
+  }
+
+  // recordings come from disk, so anything glfw wouldn't have sent is an error rather than
+  // transmuted
+  fn to_event(&self) -> Result<WindowEvent> {
+    let key = |k| key_from_i32(k).ok_or_else(|| format!("Invalid key {} in recording.", k));
+    let button =
+      |b| button_from_i32(b).ok_or_else(|| format!("Invalid mouse button {} in recording.", b));
+    let action = |a| match a {
+      0 => Ok(Action::Release),
+      1 => Ok(Action::Press),
+      2 => Ok(Action::Repeat),
+      _ => Err(format!("Invalid action {} in recording.", a)),
+    };
+    Ok(match *self {
+      Self::Key(k, scancode, a, mods) => WindowEvent::Key(
+        key(k)?,
+        scancode,
+        action(a)?,
+        Modifiers::from_bits_truncate(mods),
+      ),
+      Self::MouseButton(b, a, mods) => {
+        WindowEvent::MouseButton(button(b)?, action(a)?, Modifiers::from_bits_truncate(mods))
+      }
+      Self::CursorPos(x, y) => WindowEvent::CursorPos(x, y),
+      Self::Scroll(x, y) => WindowEvent::Scroll(x, y),
+      Self::Char(c) => WindowEvent::Char(c),
+    })
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+  step: f32,
+  cursor: (f32, f32),
+  frames: Vec<Vec<RecordedEvent>>,
+}
+
+pub enum Replay {
+  Recording {
+    path: PathBuf,
+    recording: Recording,
+    fixed: Option<f32>,
+  },
+  Playing {
+    recording: Recording,
+    frame: usize,
+    start: Instant,
+    fixed: Option<f32>,
+  },
+}
+
+impl Replay {
+  // records from the next frame on, with a fixed timestep so playback advances identically
+  pub fn record(world: &mut World, path: PathBuf) -> Result {
+    Self::stop(world)?;
+    let time = world.get_resource::<Time>().unwrap();
+    let fixed = time.fixed;
+    let step = *time.fixed.get_or_insert(DEFAULT_STEP);
+    let cursor = world.get_resource::<Input>().unwrap().cursor;
+    info!("Recording input to '{}'.", path.display());
+    world.add_resource(Replay::Recording {
+      path,
+      recording: Recording {
+        step,
+        cursor,
+        frames: vec![],
+      },
+      fixed,
+    });
+    Ok(())
+  }
+
+  pub fn play(world: &mut World, path: PathBuf) -> Result {
+    Self::stop(world)?;
+    let recording: Recording = bincode::deserialize_from(BufReader::new(File::open(&path)?))?;
+    for event in recording.frames.iter().flatten() {
+      event.to_event()?;
+    }
+    let time = world.get_resource::<Time>().unwrap();
+    let fixed = time.fixed;
+    time.fixed = Some(recording.step);
+    world.get_resource::<Input>().unwrap().cursor = recording.cursor;
+    info!(
+      "Playing {} frames from '{}'.",
+      recording.frames.len(),
+      path.display()
+    );
+    world.add_resource(Replay::Playing {
+      recording,
+      frame: 0,
+      start: Instant::now(),
+      fixed,
+    });
+    Ok(())
+  }
+
+  // saves a recording or ends playback, restoring the previous timestep
+  pub fn stop(world: &mut World) -> Result {
+    let replay = match world.take_resource::<Replay>() {
+      Some(r) => r,
+      None => return Ok(()),
+    };
+    let time = world.get_resource::<Time>().unwrap();
+    match replay {
+      Replay::Recording {
+        path,
+        recording,
+        fixed,
+      } => {
+        time.fixed = fixed;
+        bincode::serialize_into(File::create(&path)?, &recording)?;
+        info!(
+          "Saved {} frames of input to '{}'.",
+          recording.frames.len(),
+          path.display()
+        );
+      }
+      Replay::Playing {
+        frame,
+        start,
+        fixed,
+        ..
+      } => {
+        time.fixed = fixed;
+        let secs = start.elapsed().as_secs_f32();
+        info!(
+          "Replayed {} frames in {:.2}s ({:.3}ms/frame).",
+          frame,
+          secs,
+          secs * 1000.0 / frame.max(1) as f32
+        );
+      }
+    }
+    Ok(())
+  }
+
+  pub fn is_playing(&self) -> bool {
+    matches!(self, Replay::Playing { .. })
+  }
+}
+
+// records this frame's input, or swaps it for the recorded input during playback
+pub(crate) fn frame(world: &mut World, events: Vec<WindowEvent>) -> Result<Vec<WindowEvent>> {
+  let next = match world.get_resource::<Replay>() {
+    Some(Replay::Recording { recording, .. }) => {
+      recording.frames.push(
+        events
+          .iter()
+          .filter_map(RecordedEvent::from_event)
+          .collect(),
+      );
+      return Ok(events);
+    }
+    Some(Replay::Playing {
+      recording, frame, ..
+    }) => {
+      let next = recording
+        .frames
+        .get(*frame)
+        .map(|f| {
+          f.iter()
+            .map(RecordedEvent::to_event)
+            .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?;
+      if next.is_some() {
+        *frame += 1;
+      }
+      next
+    }
+    None => return Ok(events),
+  };
+  match next {
+    Some(recorded) => {
+      // real input is dropped, other window events like resizes still go through
+      let mut events = events
+        .into_iter()
+        .filter(|e| RecordedEvent::from_event(e).is_none())
+        .collect::<Vec<_>>();
+      events.extend(recorded);
+      Ok(events)
+    }
+    None => {
+      Replay::stop(world)?;
+      Ok(events)
+    }
+  }
+}
@@ -1,11 +1,11 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::any::Any;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 use linkme::distributed_slice;
 use log::{info, warn, trace};
-use crate::ecs::World;
+use crate::ecs::{World, Entity, Parent, Children};
 use crate::assets::Assets;
 use crate::{TypeIdNamed, Result, HashMapExt};
 
@@ -14,17 +14,42 @@ pub struct Scene {
   entities: HashMap<usize, Vec<(usize, Vec<u8>)>>,
 }
 
+pub type Load = fn(Vec<u8>, &mut Assets) -> Result<Box<dyn Any>>;
+// a component loaded from a scene but not yet spawned, by its saved entity id
+type Loaded = (usize, TypeIdNamed, Box<dyn Any>);
+
 pub struct Loader {
   pub id: TypeIdNamed,
   pub save: fn(&Box<dyn Any>) -> Vec<u8>,
-  pub load: fn(Vec<u8>, &mut Assets) -> Box<dyn Any>,
+  pub load: Load,
 }
 
 #[distributed_slice]
 pub static COMPONENT_LOADERS: [Loader] = [..];
 
+// what differs between two captures of a world, see `Scene::diff`
+#[derive(Copy, Clone)]
+pub enum Change {
+  Spawned(usize),
+  Despawned(usize),
+  // components of a type on an entity in both
+  Added(usize, TypeIdNamed),
+  Removed(usize, TypeIdNamed),
+  Changed(usize, TypeIdNamed),
+}
+
+impl Change {
+  pub fn entity(&self) -> usize {
+    match *self {
+      Self::Spawned(id) | Self::Despawned(id) => id,
+      Self::Added(id, _) | Self::Removed(id, _) | Self::Changed(id, _) => id,
+    }
+  }
+}
+
 impl Scene {
-  pub fn save(world: &World, path: PathBuf) -> Result {
+  // every serializable component in the world, kept in memory
+  pub fn capture(world: &World) -> Self {
     let mut scene = Scene {
       entities: HashMap::new(),
     };
@@ -40,30 +65,183 @@ impl Scene {
         warn!("{} cannot be serialized.", t.name);
       }
     }
+    scene
+  }
+
+  pub fn save(world: &World, path: PathBuf) -> Result {
+    let scene = Self::capture(world);
     bincode::serialize_into(File::create(path.clone())?, &scene)?;
     info!("Saved scene to '{}'.", path.display());
     Ok(())
   }
 
   pub fn load(world: &mut World, path: PathBuf) -> Result {
-    let scene: Scene = bincode::deserialize_from(File::open(path.clone())?)?;
+    let scene = Self::read(&path)?;
+    // loaded before the old scene is cleared, so a bad or outdated component leaves it as it was
+    let components = scene.load_components(world, scene.entities.keys().copied(), None)?;
     world.components.clear();
-    for (_, v) in scene.entities.iter() {
-      let id = world.spawn_empty().id;
-      for (t, d) in v {
+    world.ticks.clear();
+    // whatever only the old scene used is unloaded at the end of the frame
+    Self::insert(world, components, |id| id);
+    info!("Loaded scene from '{}'.", path.display());
+    Ok(())
+  }
+
+  // only decodes the file, so it can run off the main thread
+  pub fn read(path: &Path) -> Result<Self> {
+    Ok(bincode::deserialize_from(File::open(path)?)?)
+  }
+
+  // adds the entities alongside whatever is already loaded and returns their ids
+  pub fn spawn(&self, world: &mut World) -> Result<Vec<usize>> {
+    let components = self.load_components(world, self.entities.keys().copied(), None)?;
+    // keep the saved ids so Parent/Children references stay valid
+    Self::insert(world, components, |id| id);
+    Ok(self.entities.keys().copied().collect())
+  }
+
+  // like `spawn` but under new ids, so the same scene can be spawned more than once. parents and
+  // children within the scene are remapped, ids held by other components are left as saved
+  pub fn spawn_copy(&self, world: &mut World) -> Result<Vec<usize>> {
+    let components = self.load_components(world, self.entities.keys().copied(), None)?;
+    let ids = self
+      .entities
+      .keys()
+      .map(|id| (*id, world.spawn_empty().id))
+      .collect::<HashMap<_, _>>();
+    Self::insert(world, components, |id| ids[&id]);
+    let remap = |id: &mut usize| *id = ids.get(id).copied().unwrap_or(*id);
+    for to in ids.values() {
+      let e = Entity { id: *to };
+      if let Some(p) = e.get_one::<Parent>() {
+        remap(&mut p.0);
+      }
+      if let Some(c) = e.get_one::<Children>() {
+        c.0.iter_mut().for_each(remap);
+      }
+    }
+    Ok(ids.into_values().collect())
+  }
+
+  // the components of each of `ids`, or only those of type `only`, all loaded before any are
+  // spawned so a bad one spawns nothing
+  fn load_components<I: Iterator<Item = usize>>(
+    &self,
+    world: &World,
+    ids: I,
+    only: Option<TypeIdNamed>,
+  ) -> Result<Vec<Loaded>> {
+    let mut components = vec![];
+    for id in ids {
+      for (t, d) in self.entities.get(&id).into_iter().flatten() {
+        if only.is_some_and(|o| o.id() != *t) {
+          continue;
+        }
         if let Some(loader) = COMPONENT_LOADERS.iter().find(|l| l.id.id() == *t) {
           trace!("Loading '{}' on {}.", loader.id.name, id);
-          world.components.push_or_insert(
-            loader.id,
-            (
-              id,
-              (loader.load)(d.clone(), world.get_resource::<Assets>().unwrap()),
-            ),
-          )
+          let c = (loader.load)(d.clone(), world.get_resource::<Assets>().unwrap())
+            .map_err(|e| format!("Couldn't load '{}' on {}. {}", loader.id.name, id, e))?;
+          components.push((id, loader.id, c));
         }
       }
     }
-    info!("Loaded scene from '{}'.", path.display());
+    Ok(components)
+  }
+
+  // onto the entity `to` gives for each saved id
+  fn insert<F: Fn(usize) -> usize>(world: &World, components: Vec<Loaded>, to: F) {
+    for (id, t, c) in components {
+      world.insert_id(t, to(id), c);
+    }
+  }
+
+  // puts the world back how it was captured. components that can't be serialized are left as
+  // they are, since the capture doesn't have them
+  pub fn restore(&self, world: &mut World) -> Result {
+    let components = self.load_components(world, self.entities.keys().copied(), None)?;
+    for (t, v) in world.components.iter_mut() {
+      if COMPONENT_LOADERS.iter().any(|l| l.id == *t) {
+        v.clear();
+      }
+    }
+    Self::insert(world, components, |id| id);
     Ok(())
   }
+
+  // how `after` differs from this capture, by entity and then component type
+  pub fn diff(&self, after: &Scene) -> Vec<Change> {
+    let mut changes = vec![];
+    for id in self
+      .entities
+      .keys()
+      .filter(|id| !after.entities.contains_key(id))
+    {
+      changes.push(Change::Despawned(*id));
+    }
+    for id in after.entities.keys() {
+      let (old, new) = match self.entities.contains_key(id) {
+        true => (self.by_type(*id), after.by_type(*id)),
+        false => {
+          changes.push(Change::Spawned(*id));
+          continue;
+        }
+      };
+      for loader in COMPONENT_LOADERS.iter() {
+        let t = loader.id.id();
+        match (old.get(&t), new.get(&t)) {
+          (None, Some(_)) => changes.push(Change::Added(*id, loader.id)),
+          (Some(_), None) => changes.push(Change::Removed(*id, loader.id)),
+          (Some(a), Some(b)) if a != b => changes.push(Change::Changed(*id, loader.id)),
+          _ => {}
+        }
+      }
+    }
+    changes.sort_by_key(|c| c.entity());
+    changes
+  }
+
+  // makes `change`, one of those from `diff`ing against this capture, in `world`
+  pub fn apply(&self, world: &mut World, change: Change) -> Result {
+    match change {
+      Change::Spawned(id) => {
+        let components = self.load_components(world, [id].into_iter(), None)?;
+        Self::insert(world, components, |id| id);
+      }
+      Change::Despawned(id) => Self::despawn(world, &[id]),
+      Change::Added(id, t) | Change::Changed(id, t) => {
+        let components = self.load_components(world, [id].into_iter(), Some(t))?;
+        world.remove_id(t, id);
+        Self::insert(world, components, |id| id);
+      }
+      Change::Removed(id, t) => world.remove_id(t, id),
+    }
+    Ok(())
+  }
+
+  // loads a copy of a component from the capture, eg to name an entity that isn't in the world.
+  // none if it doesn't have one or it can't be loaded
+  pub fn component<T: Any>(&self, world: &World, id: usize) -> Option<T> {
+    let t = TypeIdNamed::of::<T>();
+    let loader = COMPONENT_LOADERS.iter().find(|l| l.id == t)?;
+    let (_, d) = self.entities.get(&id)?.iter().find(|c| c.0 == t.id())?;
+    let c = (loader.load)(d.clone(), world.get_resource::<Assets>().unwrap()).ok()?;
+    c.downcast().ok().map(|c| *c)
+  }
+
+  fn by_type(&self, id: usize) -> HashMap<usize, Vec<&[u8]>> {
+    let mut types = HashMap::new();
+    for (t, d) in self.entities.get(&id).into_iter().flatten() {
+      types.push_or_insert(*t, d.as_slice());
+    }
+    types
+  }
+
+  pub fn despawn(world: &mut World, ids: &[usize]) {
+    for v in world.components.values_mut() {
+      v.retain(|(id, _)| !ids.contains(id));
+    }
+    for ticks in world.ticks.values_mut() {
+      ticks.retain(|id, _| !ids.contains(id));
+    }
+  }
 }
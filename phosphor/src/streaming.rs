@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::ptr;
+use image::{imageops, RgbaImage};
+use crate::gfx::{Texture, TextureOptions, StreamBuffer, gl};
+use crate::cvar;
+use crate::vram::{self, GpuKind};
+
+// mips at or below this size are always resident, so anything can be drawn straight away
+const TAIL_SIZE: u32 = 64;
+
+cvar!(pub texture_budget_mb: i32 = 512);
+cvar!(pub texture_upload_kb: i32 = 8192);
+
+struct Streamed {
+  // level 0 first, kept on the cpu so levels can come and go
+  mips: Vec<RgbaImage>,
+  // lowest level on the gpu, every level after it is resident too
+  base: usize,
+  tail: usize,
+  // textures the scene never asks for, eg ui images, are wanted at full size
+  requested: bool,
+  // largest size on screen asked for since the last update
+  px: f32,
+}
+
+impl Streamed {
+  fn bytes(&self, from: usize) -> usize {
+    self.mips[from..].iter().map(|m| m.as_raw().len()).sum()
+  }
+
+  fn size(&self, level: usize) -> f32 {
+    self.mips[level].width().max(self.mips[level].height()) as f32
+  }
+
+  // smallest level that still covers `px` pixels
+  fn wanted(&self) -> usize {
+    if !self.requested {
+      return 0;
+    }
+    let level = (self.size(0) / self.px.max(1.0)).log2().floor().max(0.0) as usize;
+    level.min(self.tail)
+  }
+
+  // how many screen pixels each texel of `level` covers, the lowest loses detail first
+  fn density(&self, level: usize) -> f32 {
+    match self.requested {
+      true => self.px / self.size(level),
+      false => f32::MAX,
+    }
+  }
+}
+
+// keeps only the mip levels textures need on screen resident within `texture_budget_mb`,
+// missing levels are uploaded through a pixel buffer a few at a time each frame
+pub struct TextureStreamer {
+  textures: HashMap<u32, Streamed>,
+  pbo: StreamBuffer,
+}
+
+impl TextureStreamer {
+  pub fn new() -> Self {
+    let pbo = StreamBuffer::new(gl::PIXEL_UNPACK_BUFFER, 16 << 20);
+    // while bound, every other upload would read from it
+    unsafe {
+      gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+    }
+    Self {
+      textures: HashMap::new(),
+      pbo,
+    }
+  }
+
+  // builds the mip chain and uploads only the small levels, mips are always on
+  #[track_caller]
+  pub fn load(&mut self, img: RgbaImage, options: TextureOptions) -> Texture {
+    let (width, height) = img.dimensions();
+    let mut mips = vec![img];
+    loop {
+      let last = mips.last().unwrap();
+      if last.width() == 1 && last.height() == 1 {
+        break;
+      }
+      let (w, h) = ((last.width() / 2).max(1), (last.height() / 2).max(1));
+      mips.push(imageops::resize(last, w, h, imageops::FilterType::Triangle));
+    }
+    let tail = mips
+      .iter()
+      .position(|m| m.width().max(m.height()) <= TAIL_SIZE)
+      .unwrap();
+    let mut id = 0;
+    unsafe {
+      gl::GenTextures(1, &mut id);
+      gl::BindTexture(gl::TEXTURE_2D, id);
+      options.mipmaps(true).apply();
+      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, (mips.len() - 1) as _);
+      for (level, mip) in mips.iter().enumerate().skip(tail) {
+        tex_image(level, mip, mip.as_ptr() as _);
+      }
+      gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, tail as _);
+    }
+    let streamed = Streamed {
+      mips,
+      base: tail,
+      tail,
+      requested: false,
+      px: 0.0,
+    };
+    vram::track(GpuKind::Texture, id, streamed.bytes(tail));
+    self.textures.insert(id, streamed);
+    Texture {
+      id,
+      width,
+      height,
+      iformat: gl::SRGB_ALPHA,
+      format: gl::RGBA,
+      typ: gl::UNSIGNED_BYTE,
+    }
+  }
+
+  // `px` is roughly how many pixels across the texture covers on screen this frame
+  pub fn request(&mut self, tex: &Texture, px: f32) {
+    if let Some(t) = self.textures.get_mut(&tex.id) {
+      t.requested = true;
+      t.px = t.px.max(px);
+    }
+  }
+
+  // stops streaming it, the texture itself isn't deleted
+  pub fn remove(&mut self, tex: &Texture) {
+    self.textures.remove(&tex.id);
+  }
+
+  // bytes currently on the gpu
+  pub fn resident(&self) -> usize {
+    self.textures.values().map(|t| t.bytes(t.base)).sum()
+  }
+
+  pub fn update(&mut self) {
+    let budget = (texture_budget_mb.get().max(0) as usize) << 20;
+    // resident levels stay until something needs the space
+    let mut targets = self
+      .textures
+      .iter()
+      .map(|(id, t)| (*id, t.wanted().min(t.base)))
+      .collect::<Vec<_>>();
+    let mut total = targets
+      .iter()
+      .map(|(id, level)| self.textures[id].bytes(*level))
+      .sum::<usize>();
+    while total > budget {
+      let drop = targets
+        .iter_mut()
+        .filter(|(id, level)| *level < self.textures[id].tail)
+        .min_by(|a, b| {
+          let da = self.textures[&a.0].density(a.1);
+          let db = self.textures[&b.0].density(b.1);
+          da.total_cmp(&db)
+        });
+      match drop {
+        Some((id, level)) => {
+          total -= self.textures[&*id].mips[*level].as_raw().len();
+          *level += 1;
+        }
+        None => break,
+      }
+    }
+
+    // evict straight away, then upload the most visible textures first
+    for (id, target) in &targets {
+      let t = self.textures.get_mut(id).unwrap();
+      if *target > t.base {
+        unsafe {
+          gl::BindTexture(gl::TEXTURE_2D, *id);
+          gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, *target as _);
+          for level in t.base..*target {
+            gl::TexImage2D(
+              gl::TEXTURE_2D,
+              level as _,
+              gl::SRGB_ALPHA as _,
+              0,
+              0,
+              0,
+              gl::RGBA,
+              gl::UNSIGNED_BYTE,
+              ptr::null(),
+            );
+          }
+        }
+        t.base = *target;
+        vram::resize(GpuKind::Texture, *id, t.bytes(t.base));
+      }
+    }
+    targets.retain(|(id, target)| *target < self.textures[id].base);
+    targets.sort_by(|a, b| self.textures[&b.0].px.total_cmp(&self.textures[&a.0].px));
+    let mut upload = (texture_upload_kb.get().max(0) as usize) << 10;
+    let mut uploaded = false;
+    'textures: for (id, target) in targets {
+      let t = self.textures.get_mut(&id).unwrap();
+      while t.base > target {
+        let mip = &t.mips[t.base - 1];
+        let len = mip.as_raw().len();
+        // always upload at least one level a frame, however big
+        if uploaded && len > upload {
+          break 'textures;
+        }
+        let offset = self.pbo.write(mip.as_raw(), 4);
+        unsafe {
+          gl::BindTexture(gl::TEXTURE_2D, id);
+          tex_image(t.base - 1, mip, offset as _);
+          gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, (t.base - 1) as _);
+        }
+        t.base -= 1;
+        vram::resize(GpuKind::Texture, id, t.bytes(t.base));
+        upload = upload.saturating_sub(len);
+        uploaded = true;
+      }
+    }
+    unsafe {
+      gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+    }
+    self.pbo.fence();
+    for t in self.textures.values_mut() {
+      t.px = 0.0;
+    }
+  }
+}
+
+// `data` is an offset into the bound pixel buffer when there is one
+unsafe fn tex_image(level: usize, mip: &RgbaImage, data: *const u8) {
+  gl::TexImage2D(
+    gl::TEXTURE_2D,
+    level as _,
+    gl::SRGB_ALPHA as _,
+    mip.width() as _,
+    mip.height() as _,
+    0,
+    gl::RGBA,
+    gl::UNSIGNED_BYTE,
+    data as _,
+  );
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::panic::Location;
+use std::sync::Mutex;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GpuKind {
+  Texture,
+  Buffer,
+  Program,
+  Renderbuffer,
+}
+
+impl GpuKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Texture => "Texture",
+      Self::Buffer => "Buffer",
+      Self::Program => "Program",
+      Self::Renderbuffer => "Renderbuffer",
+    }
+  }
+
+  fn from_gl(identifier: u32) -> Option<Self> {
+    match identifier {
+      gl::TEXTURE => Some(Self::Texture),
+      gl::BUFFER => Some(Self::Buffer),
+      gl::PROGRAM => Some(Self::Program),
+      gl::RENDERBUFFER => Some(Self::Renderbuffer),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct GpuObject {
+  pub kind: GpuKind,
+  pub id: u32,
+  pub label: Option<String>,
+  // worked out from the format and size, drivers can pad or compress it
+  pub bytes: usize,
+  pub creator: &'static Location<'static>,
+}
+
+// gl only hands a name out again once it's deleted, so kind and id are unique while it's alive
+static OBJECTS: Mutex<BTreeMap<(GpuKind, u32), GpuObject>> = Mutex::new(BTreeMap::new());
+
+// gfx tracks what it makes itself, this is for objects made with raw gl calls. the creator is the
+// first caller that isn't `#[track_caller]`
+#[track_caller]
+pub fn track(kind: GpuKind, id: u32, bytes: usize) {
+  OBJECTS.lock().unwrap().insert(
+    (kind, id),
+    GpuObject {
+      kind,
+      id,
+      label: None,
+      bytes,
+      creator: Location::caller(),
+    },
+  );
+}
+
+// storage was reallocated, eg a render target resized
+pub fn resize(kind: GpuKind, id: u32, bytes: usize) {
+  if let Some(o) = OBJECTS.lock().unwrap().get_mut(&(kind, id)) {
+    o.bytes = bytes;
+  }
+}
+
+// made again under a new name, keeping the old one's label and creator
+pub fn replace(kind: GpuKind, old: u32, id: u32, bytes: usize) {
+  let mut objects = OBJECTS.lock().unwrap();
+  if let Some(mut o) = objects.remove(&(kind, old)) {
+    o.id = id;
+    o.bytes = bytes;
+    objects.insert((kind, id), o);
+  }
+}
+
+pub fn untrack(kind: GpuKind, id: u32) {
+  OBJECTS.lock().unwrap().remove(&(kind, id));
+}
+
+pub(crate) fn label(identifier: u32, id: u32, label: &str) {
+  if let Some(kind) = GpuKind::from_gl(identifier) {
+    if let Some(o) = OBJECTS.lock().unwrap().get_mut(&(kind, id)) {
+      o.label = Some(label.to_string());
+    }
+  }
+}
+
+pub fn objects() -> Vec<GpuObject> {
+  OBJECTS.lock().unwrap().values().cloned().collect()
+}
+
+pub fn total_bytes() -> usize {
+  OBJECTS.lock().unwrap().values().map(|o| o.bytes).sum()
+}
+
+// uncompressed internal formats
+pub fn bytes_per_pixel(iformat: u32) -> usize {
+  match iformat {
+    gl::R8 => 1,
+    gl::RG8 | gl::R16F | gl::DEPTH_COMPONENT16 => 2,
+    gl::RGB | gl::RGB8 | gl::SRGB | gl::SRGB8 => 3,
+    gl::RGB16F => 6,
+    gl::RGBA16F => 8,
+    gl::RGB32F => 12,
+    gl::RGBA32F => 16,
+    _ => 4,
+  }
+}
+
+pub fn texture_bytes(width: u32, height: u32, iformat: u32, mipmaps: bool) -> usize {
+  let bytes = width as usize * height as usize * bytes_per_pixel(iformat);
+  match mipmaps {
+    // the whole chain adds about a third
+    true => bytes * 4 / 3,
+    false => bytes,
+  }
+}
@@ -0,0 +1,229 @@
+use std::f32::consts::PI;
+use std::fmt;
+use phosphor::{Result, Time};
+use phosphor::ecs::{World, stage};
+use phosphor::assets::Assets;
+use phosphor::gfx::{Mesh, Renderer};
+use phosphor::math::{Vec3, Quat, Mat4};
+use phosphor::log::{info, warn};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::{Transform, Camera, Model, Material, Light, Foliage, ScenePerf};
+
+// the light array in lights.glsl
+const MAX_LIGHTS: usize = 100;
+
+// procedural stress scene, the same config and seed always give the same layout
+pub struct BenchScene {
+  pub meshes: usize,
+  pub lights: usize,
+  // there is no particle system yet, so particles are small instanced spheres
+  pub particles: usize,
+  pub extent: f32,
+  pub seed: u64,
+}
+
+impl BenchScene {
+  pub const DEFAULT: Self = Self {
+    meshes: 1000,
+    lights: 16,
+    particles: 10000,
+    extent: 50.0,
+    seed: 0,
+  };
+
+  // replaces the current scene
+  pub fn generate(&self, world: &mut World) -> Result {
+    let assets = world.get_resource::<Assets>().unwrap();
+    let meshes = [
+      assets.load::<Mesh>("cube.obj")?,
+      assets.load::<Mesh>("sphere.obj")?,
+      assets.load::<Mesh>("cone.obj")?,
+    ];
+    let mut rng = StdRng::seed_from_u64(self.seed);
+    let pos = |rng: &mut StdRng, height: f32| {
+      Vec3::new(
+        rng.gen_range(-self.extent..self.extent),
+        rng.gen_range(0.0..height),
+        rng.gen_range(-self.extent..self.extent),
+      )
+    };
+    world.components.clear();
+
+    world
+      .spawn("bench camera")
+      .insert(Transform::new())
+      .insert(Camera::new(80.0, [0.1, self.extent * 4.0]));
+    world
+      .spawn("ground")
+      .insert(Transform::new().scale(Vec3::new(self.extent, 0.1, self.extent)))
+      .insert(Model::new(meshes[0].clone()));
+    for i in 0..self.meshes {
+      let mesh = meshes[i % meshes.len()].clone();
+      world
+        .spawn(&format!("mesh {}", i))
+        .insert(
+          Transform::new()
+            .pos(pos(&mut rng, 4.0))
+            .rot(Quat::from_rotation_y(rng.gen_range(0.0..PI * 2.0)))
+            .scale(Vec3::splat(rng.gen_range(0.5..1.5))),
+        )
+        .insert(Model::new(mesh))
+        .insert(Material {
+          albedo: Vec3::new(rng.gen(), rng.gen(), rng.gen()),
+          metallic: rng.gen(),
+          roughness: rng.gen(),
+          ..Material::DEFAULT
+        });
+    }
+    if self.lights > MAX_LIGHTS {
+      warn!("Only {} lights are supported.", MAX_LIGHTS);
+    }
+    for i in 0..self.lights.min(MAX_LIGHTS) {
+      world
+        .spawn(&format!("light {}", i))
+        .insert(Transform::new().pos(pos(&mut rng, 6.0) + Vec3::Y))
+        .insert(Light::new(Vec3::new(rng.gen(), rng.gen(), rng.gen())).strength(4.0));
+    }
+    let mut particles = Foliage::new(meshes[1].clone());
+    particles.cast_shadows = false;
+    particles.instances = (0..self.particles)
+      .map(|_| {
+        Mat4::from_scale_rotation_translation(
+          Vec3::splat(0.05),
+          Quat::IDENTITY,
+          pos(&mut rng, 10.0),
+        )
+      })
+      .collect();
+    world.spawn("particles").insert(particles).insert(Material {
+      albedo: Vec3::ONE,
+      ..Material::DEFAULT
+    });
+    info!(
+      "Generated bench scene with {} meshes, {} lights and {} particles.",
+      self.meshes,
+      self.lights.min(MAX_LIGHTS),
+      self.particles
+    );
+    Ok(())
+  }
+
+  // generates the scene and measures the next `frames` frames while orbiting the camera
+  pub fn run(&self, world: &mut World, frames: usize) -> Result {
+    self.generate(world)?;
+    // vsync would cap every frame at the refresh rate
+    let renderer = world.get_resource::<Renderer>().unwrap();
+    let swap_interval = renderer.swap_interval();
+    renderer.set_swap_interval(0);
+    world.add_resource(BenchRun {
+      frame: 0,
+      frames: frames.max(1),
+      radius: self.extent,
+      times: vec![],
+      drawn: 0,
+      culled: 0,
+      swap_interval,
+    });
+    Ok(())
+  }
+}
+
+struct BenchRun {
+  frame: usize,
+  frames: usize,
+  radius: f32,
+  times: Vec<f32>,
+  drawn: usize,
+  culled: usize,
+  swap_interval: i32,
+}
+
+pub struct BenchReport {
+  pub frames: usize,
+  pub avg_ms: f32,
+  pub min_ms: f32,
+  pub max_ms: f32,
+  pub p95_ms: f32,
+  pub p99_ms: f32,
+  pub avg_drawn: usize,
+  pub avg_culled: usize,
+}
+
+impl BenchReport {
+  fn new(run: &BenchRun) -> Self {
+    let mut times = run.times.clone();
+    times.sort_by(f32::total_cmp);
+    let percentile = |p: f32| times[((times.len() - 1) as f32 * p) as usize];
+    Self {
+      frames: times.len(),
+      avg_ms: times.iter().sum::<f32>() / times.len() as f32,
+      min_ms: times[0],
+      max_ms: times[times.len() - 1],
+      p95_ms: percentile(0.95),
+      p99_ms: percentile(0.99),
+      avg_drawn: run.drawn / times.len(),
+      avg_culled: run.culled / times.len(),
+    }
+  }
+}
+
+impl fmt::Display for BenchReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{} frames: avg {:.3}ms, min {:.3}ms, max {:.3}ms, p95 {:.3}ms, p99 {:.3}ms, {} drawn, {} culled",
+      self.frames,
+      self.avg_ms,
+      self.min_ms,
+      self.max_ms,
+      self.p95_ms,
+      self.p99_ms,
+      self.avg_drawn,
+      self.avg_culled
+    )
+  }
+}
+
+fn bench_update(world: &mut World) -> Result {
+  let run = match world.get_resource::<BenchRun>() {
+    Some(r) => r,
+    None => return Ok(()),
+  };
+  // the first frame includes generating the scene
+  if run.frame > 0 {
+    run
+      .times
+      .push(world.get_resource::<Time>().unwrap().unscaled_delta * 1000.0);
+    let perf = world.get_resource::<ScenePerf>().unwrap();
+    run.drawn += perf.drawn;
+    run.culled += perf.culled;
+  }
+  if run.frame == run.frames {
+    let report = BenchReport::new(run);
+    info!("Bench: {}", report);
+    let interval = run.swap_interval;
+    world
+      .get_resource::<Renderer>()
+      .unwrap()
+      .set_swap_interval(interval);
+    world.take_resource::<BenchRun>();
+    world.add_resource(report);
+    return Ok(());
+  }
+  // orbit by frame rather than time so every run sees the same views
+  let a = run.frame as f32 / run.frames as f32 * PI * 2.0;
+  let eye = Vec3::new(a.cos() * run.radius, run.radius * 0.3, a.sin() * run.radius);
+  run.frame += 1;
+  if let Some(cam) = world.get_name("bench camera") {
+    if let Some(t) = cam.modify::<Transform>() {
+      *t = Transform::from_mat4(Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y).inverse());
+    }
+  }
+  Ok(())
+}
+
+pub fn bench_plugin(world: &mut World) -> Result {
+  world.add_system(stage::PRE_DRAW, bench_update);
+  Ok(())
+}
@@ -0,0 +1,464 @@
+use std::f32::consts::PI;
+use std::fmt::{self, Display};
+use phosphor::{Result, Time, DeltaTime, component};
+use phosphor::ecs::{World, Entity};
+use phosphor::cvars::{self, CVarValue};
+use phosphor::math::{Vec3, Quat, EulerRot};
+use serde::{Serialize, Deserialize};
+use crate::{Transform, Camera, Light, Material};
+
+// a number on an entity that a binding can drive, and that expressions can read as
+// `name.property`
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum Property {
+  X,
+  Y,
+  Z,
+  // euler degrees, like the inspector shows
+  Yaw,
+  Pitch,
+  Roll,
+  // sets every axis, reads x
+  Scale,
+  Strength,
+  Intensity,
+  Fov,
+  Emissive,
+  Alpha,
+  Roughness,
+  Metallic,
+}
+
+impl Property {
+  pub const ALL: [Self; 14] = [
+    Self::X,
+    Self::Y,
+    Self::Z,
+    Self::Yaw,
+    Self::Pitch,
+    Self::Roll,
+    Self::Scale,
+    Self::Strength,
+    Self::Intensity,
+    Self::Fov,
+    Self::Emissive,
+    Self::Alpha,
+    Self::Roughness,
+    Self::Metallic,
+  ];
+
+  // as written in expressions
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::X => "x",
+      Self::Y => "y",
+      Self::Z => "z",
+      Self::Yaw => "yaw",
+      Self::Pitch => "pitch",
+      Self::Roll => "roll",
+      Self::Scale => "scale",
+      Self::Strength => "strength",
+      Self::Intensity => "intensity",
+      Self::Fov => "fov",
+      Self::Emissive => "emissive",
+      Self::Alpha => "alpha",
+      Self::Roughness => "roughness",
+      Self::Metallic => "metallic",
+    }
+  }
+
+  // none if the entity doesn't have the component it's on
+  pub fn get(&self, e: Entity) -> Option<f32> {
+    let euler = |t: &Transform| {
+      let (y, x, z) = t.rotation.to_euler(EulerRot::YXZ);
+      [y, x, z].map(f32::to_degrees)
+    };
+    match self {
+      Self::X => e.get_one::<Transform>().map(|t| t.position.x),
+      Self::Y => e.get_one::<Transform>().map(|t| t.position.y),
+      Self::Z => e.get_one::<Transform>().map(|t| t.position.z),
+      Self::Yaw => e.get_one::<Transform>().map(|t| euler(t)[0]),
+      Self::Pitch => e.get_one::<Transform>().map(|t| euler(t)[1]),
+      Self::Roll => e.get_one::<Transform>().map(|t| euler(t)[2]),
+      Self::Scale => e.get_one::<Transform>().map(|t| t.scale.x),
+      Self::Strength => e.get_one::<Light>().map(|l| l.strength),
+      Self::Intensity => e.get_one::<Light>().map(|l| l.intensity),
+      Self::Fov => e.get_one::<Camera>().map(|c| c.fov),
+      Self::Emissive => e.get_one::<Material>().map(|m| m.emissive),
+      Self::Alpha => e.get_one::<Material>().map(|m| m.alpha),
+      Self::Roughness => e.get_one::<Material>().map(|m| m.roughness),
+      Self::Metallic => e.get_one::<Material>().map(|m| m.metallic),
+    }
+  }
+
+  pub fn set(&self, e: Entity, v: f32) {
+    // unchanged values aren't written so transforms don't propagate every frame
+    if self.get(e).is_none_or(|old| old == v) {
+      return;
+    }
+    let rotate = |i: usize| {
+      if let Some(t) = e.modify::<Transform>() {
+        let (y, x, z) = t.rotation.to_euler(EulerRot::YXZ);
+        let mut euler = [y, x, z];
+        euler[i] = v.to_radians();
+        t.rotation = Quat::from_euler(EulerRot::YXZ, euler[0], euler[1], euler[2]);
+      }
+    };
+    match self {
+      Self::X => e.modify::<Transform>().unwrap().position.x = v,
+      Self::Y => e.modify::<Transform>().unwrap().position.y = v,
+      Self::Z => e.modify::<Transform>().unwrap().position.z = v,
+      Self::Yaw => rotate(0),
+      Self::Pitch => rotate(1),
+      Self::Roll => rotate(2),
+      Self::Scale => e.modify::<Transform>().unwrap().scale = Vec3::splat(v),
+      Self::Strength => e.get_one::<Light>().unwrap().strength = v,
+      Self::Intensity => e.get_one::<Light>().unwrap().intensity = v,
+      Self::Fov => e.get_one::<Camera>().unwrap().fov = v,
+      Self::Emissive => e.get_one::<Material>().unwrap().emissive = v,
+      Self::Alpha => e.get_one::<Material>().unwrap().alpha = v,
+      Self::Roughness => e.get_one::<Material>().unwrap().roughness = v,
+      Self::Metallic => e.get_one::<Material>().unwrap().metallic = v,
+    }
+  }
+}
+
+#[derive(Clone)]
+enum Token {
+  Num(f32),
+  Ident(String),
+  // a quoted entity name, for names with spaces
+  Str(String),
+  Op(char),
+}
+
+impl Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Num(n) => write!(f, "'{}'", n),
+      Self::Ident(s) => write!(f, "'{}'", s),
+      Self::Str(s) => write!(f, "'\"{}\"'", s),
+      Self::Op(c) => write!(f, "'{}'", c),
+    }
+  }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+  let mut tokens = vec![];
+  let mut chars = s.chars().peekable();
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+    } else if c.is_ascii_digit()
+      || c == '.' && tokens.last().is_none_or(|t| matches!(t, Token::Op(_)))
+    {
+      let mut n = String::new();
+      while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+        n.push(c);
+        chars.next();
+      }
+      tokens.push(Token::Num(
+        n.parse().map_err(|_| format!("Invalid number '{}'.", n))?,
+      ));
+    } else if c.is_alphabetic() || c == '_' {
+      let mut ident = String::new();
+      while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+        ident.push(c);
+        chars.next();
+      }
+      tokens.push(Token::Ident(ident));
+    } else if c == '"' {
+      chars.next();
+      let name = chars.by_ref().take_while(|c| *c != '"').collect();
+      tokens.push(Token::Str(name));
+    } else if "+-*/%^(),.".contains(c) {
+      tokens.push(Token::Op(c));
+      chars.next();
+    } else {
+      return Err(format!("Unexpected '{}'.", c).into());
+    }
+  }
+  Ok(tokens)
+}
+
+const FUNCTIONS: [(&str, usize); 12] = [
+  ("sin", 1),
+  ("cos", 1),
+  ("tan", 1),
+  ("abs", 1),
+  ("sqrt", 1),
+  ("floor", 1),
+  ("ceil", 1),
+  ("fract", 1),
+  ("min", 2),
+  ("max", 2),
+  ("clamp", 3),
+  ("lerp", 3),
+];
+
+enum Expr {
+  Num(f32),
+  // `time`, `dt`, `pi` or a cvar
+  Var(String),
+  // another entity's by name, or the bound one's
+  Prop(Option<String>, Property),
+  Neg(Box<Expr>),
+  Op(char, Box<Expr>, Box<Expr>),
+  Call(&'static str, Vec<Expr>),
+}
+
+// precedence climbing over `+ -`, `* / %`, unary minus, then `^` which is right associative
+struct Parser {
+  tokens: Vec<Token>,
+  i: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.i)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    self.i += 1;
+    self.tokens.get(self.i - 1).cloned()
+  }
+
+  fn eat(&mut self, op: char) -> bool {
+    let found = matches!(self.peek(), Some(Token::Op(c)) if *c == op);
+    if found {
+      self.i += 1;
+    }
+    found
+  }
+
+  fn expect(&mut self, op: char) -> Result {
+    match self.eat(op) {
+      true => Ok(()),
+      false => Err(format!("Expected '{}'.", op).into()),
+    }
+  }
+
+  fn expr(&mut self) -> Result<Expr> {
+    let mut lhs = self.term()?;
+    while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+      self.i += 1;
+      lhs = Expr::Op(op, Box::new(lhs), Box::new(self.term()?));
+    }
+    Ok(lhs)
+  }
+
+  fn term(&mut self) -> Result<Expr> {
+    let mut lhs = self.unary()?;
+    while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+      self.i += 1;
+      lhs = Expr::Op(op, Box::new(lhs), Box::new(self.unary()?));
+    }
+    Ok(lhs)
+  }
+
+  fn unary(&mut self) -> Result<Expr> {
+    match self.eat('-') {
+      true => Ok(Expr::Neg(Box::new(self.unary()?))),
+      false => self.power(),
+    }
+  }
+
+  fn power(&mut self) -> Result<Expr> {
+    let base = self.atom()?;
+    match self.eat('^') {
+      true => Ok(Expr::Op('^', Box::new(base), Box::new(self.unary()?))),
+      false => Ok(base),
+    }
+  }
+
+  fn property(&mut self) -> Result<Property> {
+    match self.next() {
+      Some(Token::Ident(name)) => Property::ALL
+        .into_iter()
+        .find(|p| p.name() == name)
+        .ok_or_else(|| format!("Unknown property '{}'.", name).into()),
+      _ => Err("Expected a property after '.'.".into()),
+    }
+  }
+
+  fn atom(&mut self) -> Result<Expr> {
+    match self.next() {
+      Some(Token::Num(n)) => Ok(Expr::Num(n)),
+      Some(Token::Op('(')) => {
+        let e = self.expr()?;
+        self.expect(')')?;
+        Ok(e)
+      }
+      Some(Token::Str(name)) => {
+        self.expect('.')?;
+        Ok(Expr::Prop(Some(name), self.property()?))
+      }
+      Some(Token::Ident(name)) => {
+        if self.eat('.') {
+          let entity = (name != "self").then_some(name);
+          return Ok(Expr::Prop(entity, self.property()?));
+        }
+        if self.eat('(') {
+          let (f, arity) = FUNCTIONS
+            .into_iter()
+            .find(|f| f.0 == name)
+            .ok_or_else(|| format!("Unknown function '{}'.", name))?;
+          let mut args = vec![];
+          if !self.eat(')') {
+            args.push(self.expr()?);
+            while self.eat(',') {
+              args.push(self.expr()?);
+            }
+            self.expect(')')?;
+          }
+          if args.len() != arity {
+            return Err(format!("'{}' takes {} arguments.", f, arity).into());
+          }
+          return Ok(Expr::Call(f, args));
+        }
+        match ["time", "dt", "pi"].contains(&name.as_str()) || cvars::find(&name).is_some() {
+          true => Ok(Expr::Var(name)),
+          false => Err(format!("Unknown variable '{}'.", name).into()),
+        }
+      }
+      Some(t) => Err(format!("Unexpected {}.", t).into()),
+      None => Err("Unexpected end.".into()),
+    }
+  }
+}
+
+impl Expr {
+  fn parse(s: &str) -> Result<Self> {
+    let mut parser = Parser {
+      tokens: tokenize(s)?,
+      i: 0,
+    };
+    let e = parser.expr()?;
+    match parser.peek() {
+      Some(t) => Err(format!("Unexpected {}.", t).into()),
+      None => Ok(e),
+    }
+  }
+
+  // none when it reads a property that isn't there, like a missing entity's
+  fn eval(&self, world: &World, e: Entity) -> Option<f32> {
+    Some(match self {
+      Self::Num(n) => *n,
+      Self::Var(name) => match name.as_str() {
+        "time" => world.get_resource::<Time>().unwrap().elapsed,
+        "dt" => world.get_resource::<DeltaTime>().unwrap().0,
+        "pi" => PI,
+        _ => match cvars::find(name)?.value() {
+          CVarValue::Bool(b) => b as u8 as f32,
+          CVarValue::I32(i) => i as f32,
+          CVarValue::F32(f) => f,
+        },
+      },
+      Self::Prop(None, p) => p.get(e)?,
+      Self::Prop(Some(name), p) => p.get(world.get_name(name)?)?,
+      Self::Neg(a) => -a.eval(world, e)?,
+      Self::Op(op, a, b) => {
+        let (a, b) = (a.eval(world, e)?, b.eval(world, e)?);
+        match op {
+          '+' => a + b,
+          '-' => a - b,
+          '*' => a * b,
+          '/' => a / b,
+          '%' => a.rem_euclid(b),
+          _ => a.powf(b),
+        }
+      }
+      Self::Call(f, args) => {
+        let args = args
+          .iter()
+          .map(|a| a.eval(world, e))
+          .collect::<Option<Vec<_>>>()?;
+        match (*f, args.as_slice()) {
+          ("sin", [x]) => x.sin(),
+          ("cos", [x]) => x.cos(),
+          ("tan", [x]) => x.tan(),
+          ("abs", [x]) => x.abs(),
+          ("sqrt", [x]) => x.sqrt(),
+          ("floor", [x]) => x.floor(),
+          ("ceil", [x]) => x.ceil(),
+          ("fract", [x]) => x - x.floor(),
+          ("min", [a, b]) => a.min(*b),
+          ("max", [a, b]) => a.max(*b),
+          ("clamp", [x, a, b]) => x.max(*a).min(*b),
+          ("lerp", [a, b, t]) => a + (b - a) * t,
+          _ => unreachable!(),
+        }
+      }
+    })
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Binding {
+  pub property: Property,
+  pub expr: String,
+  // what `expr` last parsed to, redone when it's edited
+  #[serde(skip)]
+  parsed: Option<(String, std::result::Result<Expr, String>)>,
+}
+
+impl Binding {
+  pub fn new(property: Property, expr: &str) -> Self {
+    Self {
+      property,
+      expr: expr.to_string(),
+      parsed: None,
+    }
+  }
+
+  fn parsed(&mut self) -> &std::result::Result<Expr, String> {
+    if self.parsed.as_ref().is_none_or(|p| p.0 != self.expr) {
+      let e = Expr::parse(&self.expr).map_err(|e| e.to_string());
+      self.parsed = Some((self.expr.clone(), e));
+    }
+    &self.parsed.as_ref().unwrap().1
+  }
+
+  // why `expr` doesn't parse, bindings that don't are skipped
+  pub fn error(&mut self) -> Option<&str> {
+    self.parsed().as_ref().err().map(|e| e.as_str())
+  }
+
+  // what it evaluates to for `e`, none if it doesn't parse or reads something missing
+  pub fn value(&mut self, world: &World, e: Entity) -> Option<f32> {
+    self.parsed().as_ref().ok()?.eval(world, e)
+  }
+}
+
+// drives the entity's properties from expressions every frame, for prototyping without writing a
+// system. expressions have numbers, `+ - * / % ^`, the functions in `FUNCTIONS`, `time`, `dt`,
+// `pi`, cvars by name, and properties as `self.x`, `door.y` or `"main light".strength`:
+//
+//   Bindings::new()
+//     .bind(Property::Strength, "2 + sin(time * 3)")
+//     .bind(Property::Y, "door.y + 1")
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Bindings(pub Vec<Binding>);
+
+impl Bindings {
+  pub fn new() -> Self {
+    Self(vec![])
+  }
+
+  pub fn bind(mut self, property: Property, expr: &str) -> Self {
+    self.0.push(Binding::new(property, expr));
+    self
+  }
+}
+
+pub(crate) fn bindings_update(world: &mut World) -> Result {
+  for (e, bindings) in world.query::<Bindings>() {
+    for b in &mut bindings.0 {
+      if let Some(v) = b.value(world, e).filter(|v| v.is_finite()) {
+        b.property.set(e, v);
+      }
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,163 @@
+use phosphor::{Result, DeltaTime};
+use phosphor::ecs::{World, stage};
+use phosphor::input::{Input, Binding};
+use phosphor::gfx::Renderer;
+use phosphor::glfw::{Key, MouseButton, CursorMode};
+use phosphor::math::{Vec3, Quat, EulerRot};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::Transform;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum CameraMode {
+  // wasd to move, mouse to look while the right button is held or the cursor is captured. the
+  // keys are the `move_*`, `sprint` and `look` actions
+  Fly,
+  // middle drag to orbit around the target, shift + middle drag to pan, scroll to zoom. middle is
+  // the `orbit` action
+  Orbit,
+}
+
+impl CameraMode {
+  pub const ALL: [Self; 2] = [Self::Fly, Self::Orbit];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Fly => "Fly",
+      Self::Orbit => "Orbit",
+    }
+  }
+}
+
+// drives the entity's transform from the window input
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct CameraController {
+  pub mode: CameraMode,
+  // units per second, sprinting moves 4x faster
+  pub speed: f32,
+  // degrees per pixel
+  pub sensitivity: f32,
+  pub yaw: f32,
+  pub pitch: f32,
+  pub target: Vec3,
+  pub distance: f32,
+}
+
+impl CameraController {
+  pub fn fly() -> Self {
+    Self {
+      mode: CameraMode::Fly,
+      speed: 5.0,
+      sensitivity: 0.2,
+      yaw: 0.0,
+      pitch: 0.0,
+      target: Vec3::ZERO,
+      distance: 10.0,
+    }
+  }
+
+  pub fn orbit(target: Vec3, distance: f32) -> Self {
+    Self {
+      mode: CameraMode::Orbit,
+      target,
+      distance,
+      ..Self::fly()
+    }
+  }
+
+  pub fn yaw_pitch(mut self, yaw: f32, pitch: f32) -> Self {
+    self.yaw = yaw;
+    self.pitch = pitch;
+    self
+  }
+
+  pub fn speed(mut self, speed: f32) -> Self {
+    self.speed = speed;
+    self
+  }
+
+  fn rotation(&self) -> Quat {
+    Quat::from_euler(
+      EulerRot::YXZ,
+      self.yaw.to_radians(),
+      self.pitch.to_radians(),
+      0.0,
+    )
+  }
+
+  fn look(&mut self, delta: (f32, f32)) {
+    self.yaw -= delta.0 * self.sensitivity;
+    self.pitch = (self.pitch - delta.1 * self.sensitivity).clamp(-89.0, 89.0);
+  }
+
+  fn update(&mut self, t: &mut Transform, input: &Input, captured: bool, dt: f32) {
+    let fast = input.action_down("sprint");
+    match self.mode {
+      CameraMode::Fly => {
+        if captured || input.action_down("look") {
+          self.look(input.delta);
+        }
+        t.rotation = self.rotation();
+        let axis = |pos: &str, neg: &str| {
+          input.action_down(pos) as i32 as f32 - input.action_down(neg) as i32 as f32
+        };
+        let local = Vec3::new(
+          axis("move_right", "move_left"),
+          0.0,
+          axis("move_back", "move_forward"),
+        );
+        let up = Vec3::Y * axis("move_up", "move_down");
+        let speed = self.speed * if fast { 4.0 } else { 1.0 } * dt;
+        t.position += (t.rotation * local + up).normalize_or_zero() * speed;
+      }
+      CameraMode::Orbit => {
+        if input.action_down("orbit") {
+          if fast {
+            // pan speed scales with distance so the target follows the cursor
+            let pan = self.distance * self.sensitivity * 0.01;
+            let rot = self.rotation();
+            self.target += (rot * Vec3::X * -input.delta.0 + rot * Vec3::Y * input.delta.1) * pan;
+          } else {
+            self.look(input.delta);
+          }
+        }
+        self.distance = (self.distance * (1.0 - input.scroll.1 * 0.1)).max(0.1);
+        t.rotation = self.rotation();
+        t.position = self.target + t.rotation * Vec3::Z * self.distance;
+      }
+    }
+  }
+}
+
+fn camera_controller_update(world: &mut World) -> Result {
+  let input = world.get_resource::<Input>().unwrap();
+  let renderer = world.get_resource::<Renderer>().unwrap();
+  let captured = renderer.window.get_cursor_mode() == CursorMode::Disabled;
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
+  for (e, controller) in world.query::<CameraController>() {
+    if let Some(t) = e.modify::<Transform>() {
+      controller.update(t, input, captured, dt);
+    }
+  }
+  Ok(())
+}
+
+pub fn camera_controller_plugin(world: &mut World) -> Result {
+  let input = world.get_resource::<Input>().unwrap();
+  for (action, binding) in [
+    ("move_forward", Binding::Key(Key::W)),
+    ("move_back", Binding::Key(Key::S)),
+    ("move_left", Binding::Key(Key::A)),
+    ("move_right", Binding::Key(Key::D)),
+    ("move_up", Binding::Key(Key::E)),
+    ("move_down", Binding::Key(Key::Q)),
+    ("sprint", Binding::Key(Key::LeftShift)),
+    ("look", Binding::Button(MouseButton::Button2)),
+    ("orbit", Binding::Button(MouseButton::Button3)),
+  ] {
+    input.bind_default(action, &[binding]);
+  }
+  world.add_system(stage::PRE_DRAW, camera_controller_update);
+  Ok(())
+}
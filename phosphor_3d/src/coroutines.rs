@@ -0,0 +1,37 @@
+use phosphor::DeltaTime;
+use phosphor::ecs::{World, Entity};
+use phosphor::coroutines::next_frame;
+use phosphor::math::Vec3;
+use crate::{Transform, GameEvents, GameEvent};
+
+// the first event with the name sent from this frame on
+pub async fn wait_for_event(world: &World, name: &str) -> GameEvent {
+  loop {
+    let events = world.get_resource::<GameEvents>().unwrap();
+    if let Some(e) = events.events().iter().find(|e| e.name == name) {
+      return e.clone();
+    }
+    next_frame().await;
+  }
+}
+
+// eases the entity's position to `target` over `seconds` of game time, ends early if it's despawned
+pub async fn move_to(world: &World, e: Entity, target: Vec3, seconds: f32) {
+  let start = match e.get_one::<Transform>() {
+    Some(t) => t.position,
+    None => return,
+  };
+  let mut elapsed = 0.0;
+  while elapsed < seconds {
+    next_frame().await;
+    elapsed += world.get_resource::<DeltaTime>().unwrap().0;
+    let x = (elapsed / seconds).min(1.0);
+    match e.modify::<Transform>() {
+      Some(t) => t.position = start.lerp(target, x * x * (3.0 - 2.0 * x)),
+      None => return,
+    }
+  }
+  if let Some(t) = e.modify::<Transform>() {
+    t.position = target;
+  }
+}
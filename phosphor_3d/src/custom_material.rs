@@ -0,0 +1,175 @@
+use std::mem;
+use std::collections::{HashMap, BTreeMap};
+use phosphor::Result;
+use phosphor::assets::Handle;
+use phosphor::gfx::{Shader, Texture, Uniform, UniformType};
+use phosphor::math::{Vec2, Vec3, Vec4};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::bind_blocks;
+
+// Material uses the units below this
+const FIRST_UNIT: u32 = 5;
+
+// set by the renderer or Material, so they aren't properties
+const BUILTIN_UNIFORMS: &[&str] = &[
+  "model",
+  "view",
+  "projection",
+  "instanced",
+  "uv_offset",
+  "use_lod_fade",
+  "lod_fade",
+  "albedo",
+  "metallic",
+  "roughness",
+  "ao",
+  "alpha",
+  "emissive",
+];
+const BUILTIN_TEXTURES: &[&str] = &["albedo", "metallic", "roughness", "normal", "ao"];
+
+fn builtin(name: &str) -> bool {
+  BUILTIN_UNIFORMS.contains(&name)
+    || BUILTIN_TEXTURES
+      .iter()
+      .any(|t| name == format!("{}_tex", t) || name == format!("use_{}_tex", t))
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum UniformValue {
+  Float(f32),
+  Vec2(Vec2),
+  Vec3(Vec3),
+  Vec4(Vec4),
+  Int(i32),
+  Bool(bool),
+  Texture(Option<Handle<Texture>>),
+}
+
+impl UniformValue {
+  // none for types that can't be edited
+  pub fn default_for(typ: UniformType) -> Option<Self> {
+    Some(match typ {
+      UniformType::Float => Self::Float(0.0),
+      UniformType::Vec2 => Self::Vec2(Vec2::ZERO),
+      UniformType::Vec3 => Self::Vec3(Vec3::ONE),
+      UniformType::Vec4 => Self::Vec4(Vec4::ONE),
+      UniformType::Int => Self::Int(0),
+      UniformType::Bool => Self::Bool(false),
+      UniformType::Sampler2D => Self::Texture(None),
+      UniformType::Mat4 => return None,
+    })
+  }
+
+  fn matches(&self, typ: UniformType) -> bool {
+    Self::default_for(typ).is_some_and(|d| mem::discriminant(&d) == mem::discriminant(self))
+  }
+}
+
+pub struct MaterialShader {
+  pub shader: Shader,
+  // uniforms the shader declares beyond the builtin ones, in declaration order
+  pub properties: Vec<Uniform>,
+}
+
+// custom shader pairs drawn in the geometry pass in place of the pbr shader, the vertex shader
+// gets the same inputs as base.vert and the fragment shader writes the gbuffer, usually by
+// including gbuffer.glsl. camera.glsl and lights.glsl can be included as well
+pub struct MaterialShaders {
+  shaders: HashMap<String, MaterialShader>,
+}
+
+impl MaterialShaders {
+  pub(crate) fn new() -> Self {
+    Self {
+      shaders: HashMap::new(),
+    }
+  }
+
+  pub fn register(&mut self, name: &str, vert: &str, frag: &str) -> Result {
+    let shader = bind_blocks(Shader::new(vert, frag)?);
+    let properties = shader
+      .uniforms()
+      .into_iter()
+      .filter(|u| !builtin(&u.name) && UniformValue::default_for(u.typ).is_some())
+      .collect();
+    self
+      .shaders
+      .insert(name.to_string(), MaterialShader { shader, properties });
+    Ok(())
+  }
+
+  pub fn get(&self, name: &str) -> Option<&MaterialShader> {
+    self.shaders.get(name)
+  }
+
+  pub fn names(&self) -> Vec<&str> {
+    let mut names = self.shaders.keys().map(|n| n.as_str()).collect::<Vec<_>>();
+    names.sort();
+    names
+  }
+}
+
+// draws the entity with a registered shader, Material still drives the builtin pbr inputs
+// when it's there, transparent materials keep the forward shader
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct CustomMaterial {
+  pub shader: String,
+  pub values: BTreeMap<String, UniformValue>,
+}
+
+impl CustomMaterial {
+  pub fn new(shader: &str) -> Self {
+    Self {
+      shader: shader.to_string(),
+      values: BTreeMap::new(),
+    }
+  }
+
+  pub fn set(mut self, name: &str, value: UniformValue) -> Self {
+    self.values.insert(name.to_string(), value);
+    self
+  }
+
+  // fills in properties that are missing or changed type, values the shader doesn't have are
+  // kept so they survive switching shaders
+  pub fn sync(&mut self, shader: &MaterialShader) {
+    for p in &shader.properties {
+      if !self.values.get(&p.name).is_some_and(|v| v.matches(p.typ)) {
+        self
+          .values
+          .insert(p.name.clone(), UniformValue::default_for(p.typ).unwrap());
+      }
+    }
+  }
+
+  pub(crate) fn bind(&self, shader: &MaterialShader) {
+    let s = &shader.shader;
+    let mut unit = FIRST_UNIT;
+    for p in &shader.properties {
+      let default = UniformValue::default_for(p.typ).unwrap();
+      let value = self
+        .values
+        .get(&p.name)
+        .filter(|v| v.matches(p.typ))
+        .unwrap_or(&default);
+      match value {
+        UniformValue::Float(v) => s.set_f32(&p.name, v),
+        UniformValue::Vec2(v) => s.set_vec2(&p.name, v),
+        UniformValue::Vec3(v) => s.set_vec3(&p.name, v),
+        UniformValue::Vec4(v) => s.set_vec4(&p.name, v),
+        UniformValue::Int(v) => s.set_i32(&p.name, v),
+        UniformValue::Bool(v) => s.set_i32(&p.name, &(*v as _)),
+        UniformValue::Texture(tex) => {
+          if let Some(tex) = tex {
+            tex.bind(unit);
+          }
+          s.set_i32(&p.name, &(unit as _));
+          unit += 1;
+        }
+      }
+    }
+  }
+}
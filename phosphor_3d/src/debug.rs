@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use phosphor::Result;
+use phosphor::gfx::{Renderer, RenderState, Shader, Aabb, StreamBuffer, VertexLayout, AttribType, gl};
+use phosphor::math::{Vec3, Mat4};
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugVertex {
+  pos: Vec3,
+  color: Vec3,
+}
+
+// immediate mode lines in world space, drawn over the scene and cleared every frame,
+// anything added after the scene renderer has drawn shows up on the next frame
+pub struct DebugDraw {
+  // hide lines behind geometry
+  pub depth_test: bool,
+  vertices: Vec<DebugVertex>,
+  vert_arr: u32,
+  vert_buf: StreamBuffer,
+  layout: VertexLayout,
+  shader: Shader,
+}
+
+impl DebugDraw {
+  pub fn new() -> Result<Self> {
+    unsafe {
+      let mut vert_arr = 0;
+      gl::GenVertexArrays(1, &mut vert_arr);
+      Ok(Self {
+        depth_test: true,
+        vertices: vec![],
+        vert_arr,
+        vert_buf: StreamBuffer::new(gl::ARRAY_BUFFER, 1 << 20),
+        layout: VertexLayout::new()
+          .attrib(0, 3, AttribType::F32)
+          .attrib(1, 3, AttribType::F32),
+        shader: Shader::new("debug.vert", "debug.frag")?,
+      })
+    }
+  }
+
+  pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+    self.vertices.push(DebugVertex { pos: a, color });
+    self.vertices.push(DebugVertex { pos: b, color });
+  }
+
+  pub fn aabb(&mut self, aabb: &Aabb, color: Vec3) {
+    let c = aabb.corners();
+    // corners are indexed by which axes are at max, so edges differ by one bit
+    for i in 0..8 {
+      for bit in [1, 2, 4] {
+        if i & bit == 0 {
+          self.line(c[i], c[i | bit], color);
+        }
+      }
+    }
+  }
+
+  pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec3) {
+    let (u, v) = normal.normalize().any_orthonormal_pair();
+    let point = |i: usize| {
+      let a = i as f32 / CIRCLE_SEGMENTS as f32 * PI * 2.0;
+      center + (u * a.cos() + v * a.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+      self.line(point(i), point(i + 1), color);
+    }
+  }
+
+  pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+    for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+      self.circle(center, normal, radius, color);
+    }
+  }
+
+  // red, green and blue lines along the transform's x, y and z axes
+  pub fn axes(&mut self, mat: Mat4, size: f32) {
+    let origin = mat.w_axis.truncate();
+    for (axis, color) in [
+      (mat.x_axis, Vec3::X),
+      (mat.y_axis, Vec3::Y),
+      (mat.z_axis, Vec3::Z),
+    ] {
+      self.line(
+        origin,
+        origin + axis.truncate().normalize_or_zero() * size,
+        color,
+      );
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.vertices.clear();
+  }
+
+  pub(crate) fn flush(&mut self, renderer: &Renderer, view: &Mat4, projection: &Mat4) {
+    if self.vertices.is_empty() {
+      return;
+    }
+    self.shader.bind();
+    self.shader.set_mat4("view", view);
+    self.shader.set_mat4("projection", projection);
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_test: self.depth_test,
+      ..prev
+    });
+    unsafe {
+      gl::BindVertexArray(self.vert_arr);
+      let offset = self.vert_buf.write(&self.vertices, 4);
+      self.layout.bind(offset);
+      gl::DrawArrays(gl::LINES, 0, self.vertices.len() as _);
+    }
+    renderer.apply_state(prev);
+    self.vert_buf.fence();
+    self.clear();
+  }
+}
@@ -0,0 +1,96 @@
+use phosphor::{Result, component};
+use phosphor::ecs::World;
+use phosphor::assets::Handle;
+use phosphor::gfx::Cubemap;
+use phosphor::math::{Vec2, Vec3};
+use serde::{Serialize, Deserialize};
+use crate::{SkySettings, Skybox, PostProcess, PostEffect};
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct Fog {
+  // lit by the sun like the sky
+  pub color: Vec3,
+  // falloff per unit, 0 disables it
+  pub density: f32,
+  // distance from the camera it starts at
+  pub start: f32,
+}
+
+impl Fog {
+  pub const NONE: Self = Self {
+    color: Vec3::new(0.6, 0.7, 0.8),
+    density: 0.0,
+    start: 0.0,
+  };
+}
+
+// a scene's look, saved with it. while an entity has one it's applied over the sky, skybox and
+// post process resources every frame, so they only need setting in code for scenes without one.
+// the first one found is used
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct WorldEnvironment {
+  // sun yaw and pitch in degrees
+  pub sun_dir: Vec2,
+  pub sun_lux: Option<f32>,
+  pub ambient: Vec3,
+  // the procedural sky when none
+  pub skybox: Option<Handle<Cubemap>>,
+  pub skybox_intensity: f32,
+  pub fog: Fog,
+  pub post: Vec<PostEffect>,
+}
+
+impl WorldEnvironment {
+  // the renderer's own defaults
+  pub fn new() -> Self {
+    Self {
+      sun_dir: Vec2::new(30.0, 320.0),
+      sun_lux: None,
+      ambient: Vec3::splat(0.1),
+      skybox: None,
+      skybox_intensity: 1.0,
+      fog: Fog::NONE,
+      post: PostEffect::ALL.to_vec(),
+    }
+  }
+
+  // what's currently set in the resources, for moving a look set up in code into a scene
+  pub fn from_world(world: &World) -> Self {
+    let sky = world.get_resource::<SkySettings>().unwrap();
+    let skybox = world.get_resource::<Skybox>();
+    Self {
+      sun_dir: sky.dir,
+      sun_lux: sky.sun_lux,
+      ambient: sky.ambient,
+      skybox: skybox.as_ref().map(|s| s.cubemap.clone()),
+      skybox_intensity: skybox.map_or(1.0, |s| s.intensity),
+      fog: sky.fog,
+      post: world.get_resource::<PostProcess>().unwrap().effects.clone(),
+    }
+  }
+}
+
+pub(crate) fn world_environment_apply(world: &mut World) -> Result {
+  let env = match world.query::<WorldEnvironment>().into_iter().next() {
+    Some((_, env)) => env,
+    None => return Ok(()),
+  };
+  let sky = world.get_resource::<SkySettings>().unwrap();
+  sky.dir = env.sun_dir;
+  sky.sun_lux = env.sun_lux;
+  sky.ambient = env.ambient;
+  sky.fog = env.fog;
+  world.get_resource::<PostProcess>().unwrap().effects = env.post.clone();
+  let skybox = env.skybox.clone().map(|cubemap| Skybox {
+    cubemap,
+    intensity: env.skybox_intensity,
+  });
+  match skybox {
+    Some(skybox) => world.add_resource(skybox),
+    None => {
+      world.take_resource::<Skybox>();
+    }
+  }
+  Ok(())
+}
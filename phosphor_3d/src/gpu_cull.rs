@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::{mem, ptr};
+use phosphor::Result;
+use phosphor::ecs::{World, Entity, Changed, Filter};
+use phosphor::gfx::{Shader, gl, gl_version};
+use phosphor::gfx::gl::types::GLsync;
+use phosphor::math::Mat4;
+use phosphor::vram::{self, GpuKind};
+use crate::{Foliage, Frustum};
+
+const GROUP_SIZE: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DrawCommand {
+  count: u32,
+  instance_count: u32,
+  first_index: u32,
+  base_vertex: i32,
+  base_instance: u32,
+}
+
+impl DrawCommand {
+  fn new(count: u32) -> Self {
+    Self {
+      count,
+      instance_count: 0,
+      first_index: 0,
+      base_vertex: 0,
+      base_instance: 0,
+    }
+  }
+}
+
+struct CullBuffers {
+  // every instance, only uploaded when they change
+  instances: u32,
+  // survivors of the cull, read as the instance attribute buffer
+  visible: u32,
+  command: u32,
+  // the command's instance count copied out after a dispatch, read once `fence` has passed so
+  // reading it never waits on the gpu
+  readback: u32,
+  fence: Option<GLsync>,
+  capacity: usize,
+  last_visible: usize,
+}
+
+impl CullBuffers {
+  fn new() -> Self {
+    let mut bufs = [0; 4];
+    unsafe {
+      gl::GenBuffers(4, bufs.as_mut_ptr());
+      gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, bufs[2]);
+      gl::BufferData(
+        gl::DRAW_INDIRECT_BUFFER,
+        mem::size_of::<DrawCommand>() as _,
+        &DrawCommand::new(0) as *const _ as _,
+        gl::DYNAMIC_DRAW,
+      );
+      gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+      gl::BindBuffer(gl::COPY_WRITE_BUFFER, bufs[3]);
+      gl::BufferData(gl::COPY_WRITE_BUFFER, 4, ptr::null(), gl::STREAM_READ);
+      gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+    }
+    vram::track(GpuKind::Buffer, bufs[0], 0);
+    vram::track(GpuKind::Buffer, bufs[1], 0);
+    vram::track(GpuKind::Buffer, bufs[2], mem::size_of::<DrawCommand>());
+    vram::track(GpuKind::Buffer, bufs[3], 4);
+    Self {
+      instances: bufs[0],
+      visible: bufs[1],
+      command: bufs[2],
+      readback: bufs[3],
+      fence: None,
+      capacity: 0,
+      last_visible: 0,
+    }
+  }
+
+  fn upload(&mut self, instances: &[Mat4]) {
+    unsafe {
+      if instances.len() > self.capacity {
+        self.capacity = instances.len().next_power_of_two();
+        for buf in [self.instances, self.visible] {
+          gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buf);
+          gl::BufferData(
+            gl::SHADER_STORAGE_BUFFER,
+            (self.capacity * 64) as _,
+            ptr::null(),
+            gl::DYNAMIC_DRAW,
+          );
+          vram::resize(GpuKind::Buffer, buf, self.capacity * 64);
+        }
+      }
+      gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.instances);
+      gl::BufferSubData(
+        gl::SHADER_STORAGE_BUFFER,
+        0,
+        (instances.len() * 64) as _,
+        instances.as_ptr() as _,
+      );
+      gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+    }
+  }
+}
+
+impl Drop for CullBuffers {
+  fn drop(&mut self) {
+    let bufs = [self.instances, self.visible, self.command, self.readback];
+    unsafe {
+      if let Some(fence) = self.fence {
+        gl::DeleteSync(fence);
+      }
+      gl::DeleteBuffers(4, bufs.as_ptr());
+    }
+    for buf in bufs {
+      vram::untrack(GpuKind::Buffer, buf);
+    }
+  }
+}
+
+// frustum culls instanced meshes in a compute shader and draws the survivors indirectly,
+// so large instance counts never go through the cpu each frame
+pub(crate) struct GpuCuller {
+  shader: Shader,
+  buffers: HashMap<usize, CullBuffers>,
+}
+
+impl GpuCuller {
+  // none when the context is older than 4.3
+  pub fn new() -> Result<Option<Self>> {
+    if gl_version() < (4, 3) {
+      return Ok(None);
+    }
+    Ok(Some(Self {
+      shader: Shader::new_compute("cull.comp")?,
+      buffers: HashMap::new(),
+    }))
+  }
+
+  // syncs the instance buffers with the world, dropping ones whose entity is gone. instances are
+  // only uploaded for new entities and those changed through `modify` or `set_changed`
+  pub fn upload(&mut self, world: &World) {
+    let instanced = world.query::<Foliage>();
+    self
+      .buffers
+      .retain(|id, _| instanced.iter().any(|(e, _)| e.id == *id));
+    for (e, instanced) in instanced {
+      let new = !self.buffers.contains_key(&e.id);
+      let b = self.buffers.entry(e.id).or_insert_with(CullBuffers::new);
+      if new || Changed::<Foliage>::matches(world, e.id) {
+        b.upload(&instanced.instances);
+      }
+    }
+  }
+
+  pub fn draw_all(&self, e: Entity, instanced: &Foliage) {
+    if let Some(b) = self.buffers.get(&e.id) {
+      instanced
+        .mesh
+        .draw_instanced_buffer(b.instances, 0, instanced.instances.len());
+    }
+  }
+
+  // dispatches the cull, this binds the compute shader so do it before binding the material,
+  // returns how many instances were visible as of the last dispatch the gpu has finished
+  pub fn cull(&mut self, e: Entity, instanced: &Foliage, frustum: &Frustum) -> usize {
+    let b = match self.buffers.get_mut(&e.id) {
+      Some(b) => b,
+      None => return 0,
+    };
+    let n = instanced.instances.len();
+    if n == 0 {
+      return 0;
+    }
+    let s = &self.shader;
+    unsafe {
+      if let Some(fence) = b.fence {
+        let mut status = 0;
+        gl::GetSynciv(fence, gl::SYNC_STATUS, 1, ptr::null_mut(), &mut status);
+        if status == gl::SIGNALED as i32 {
+          let mut count = 0u32;
+          gl::BindBuffer(gl::COPY_READ_BUFFER, b.readback);
+          gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, 4, &mut count as *mut _ as _);
+          gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+          gl::DeleteSync(fence);
+          b.fence = None;
+          b.last_visible = (count as usize).min(n);
+        }
+      }
+      let command = DrawCommand::new(instanced.mesh.indices.len() as _);
+      gl::BufferSubData(
+        gl::DRAW_INDIRECT_BUFFER,
+        0,
+        mem::size_of::<DrawCommand>() as _,
+        &command as *const _ as _,
+      );
+      gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+
+      s.bind();
+      for (i, p) in frustum.planes.iter().enumerate() {
+        s.set_vec4(&format!("planes[{}]", i), p);
+      }
+      s.set_vec3("aabb_min", &instanced.mesh.aabb.min);
+      s.set_vec3("aabb_max", &instanced.mesh.aabb.max);
+      s.set_i32("num_instances", &(n as _));
+      gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, b.instances);
+      gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, b.visible);
+      gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 2, b.command);
+      gl::DispatchCompute(n.div_ceil(GROUP_SIZE) as _, 1, 1);
+      gl::MemoryBarrier(
+        gl::COMMAND_BARRIER_BIT
+          | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT
+          | gl::BUFFER_UPDATE_BARRIER_BIT,
+      );
+      // a new readback once the previous one has been read
+      if b.fence.is_none() {
+        gl::BindBuffer(gl::COPY_READ_BUFFER, b.command);
+        gl::BindBuffer(gl::COPY_WRITE_BUFFER, b.readback);
+        gl::CopyBufferSubData(gl::COPY_READ_BUFFER, gl::COPY_WRITE_BUFFER, 4, 0, 4);
+        gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+        gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+        b.fence = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+      }
+    }
+    b.last_visible
+  }
+
+  pub fn draw_visible(&self, e: Entity, instanced: &Foliage) {
+    if instanced.instances.is_empty() {
+      return;
+    }
+    if let Some(b) = self.buffers.get(&e.id) {
+      instanced.mesh.draw_indirect(b.visible, b.command, 1);
+    }
+  }
+}
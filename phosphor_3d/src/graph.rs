@@ -0,0 +1,69 @@
+use std::mem;
+
+// a pass and the textures it samples and renders into, textures are matched by name
+#[derive(Clone)]
+pub struct GraphPass {
+  pub name: &'static str,
+  pub reads: Vec<&'static str>,
+  pub writes: Vec<&'static str>,
+  pub size: [u32; 2],
+  // gpu milliseconds
+  pub time: Option<f32>,
+}
+
+impl GraphPass {
+  pub fn new(name: &'static str, size: [u32; 2]) -> Self {
+    Self {
+      name,
+      reads: vec![],
+      writes: vec![],
+      size,
+      time: None,
+    }
+  }
+
+  pub fn reads(mut self, textures: &[&'static str]) -> Self {
+    self.reads.extend_from_slice(textures);
+    self
+  }
+
+  pub fn writes(mut self, textures: &[&'static str]) -> Self {
+    self.writes.extend_from_slice(textures);
+    self
+  }
+
+  // from a query's nanoseconds
+  pub fn time(mut self, ns: Option<u64>) -> Self {
+    self.time = ns.map(|ns| ns as f32 / 1000000.0);
+    self
+  }
+}
+
+// the passes run for the main view, in order. custom passes should `add` themselves after drawing
+// so they show up in the editor's frame graph
+pub struct FrameGraph {
+  passes: Vec<GraphPass>,
+  recording: Vec<GraphPass>,
+}
+
+impl FrameGraph {
+  pub fn new() -> Self {
+    Self {
+      passes: vec![],
+      recording: vec![],
+    }
+  }
+
+  pub fn add(&mut self, pass: GraphPass) {
+    self.recording.push(pass);
+  }
+
+  // the last whole frame
+  pub fn passes(&self) -> &[GraphPass] {
+    &self.passes
+  }
+
+  pub(crate) fn begin(&mut self) {
+    self.passes = mem::take(&mut self.recording);
+  }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use phosphor::Result;
+use phosphor::ecs::{World, Entity, stage};
+use phosphor::scene::Scene;
+use phosphor::gfx::Aabb;
+use phosphor::math::Vec3;
+use phosphor::log::{info, error};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::{Transform, Camera, DebugDraw};
+
+// keeps a sub-scene loaded while the camera is inside the box around the entity
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct StreamingVolume {
+  pub scene: String,
+  pub half_extents: Vec3,
+  // how far past the box the camera goes before unloading, so walking along the edge doesn't
+  // load and unload every frame
+  pub margin: f32,
+  // higher loads first when several volumes are entered at once
+  pub priority: i32,
+}
+
+impl StreamingVolume {
+  pub fn new(scene: &str, half_extents: Vec3) -> Self {
+    Self {
+      scene: scene.to_string(),
+      half_extents,
+      margin: 2.0,
+      priority: 0,
+    }
+  }
+
+  pub fn priority(mut self, priority: i32) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  fn bounds(&self, t: &Transform, e: Entity) -> Aabb {
+    Aabb {
+      min: -self.half_extents,
+      max: self.half_extents,
+    }
+    .transform(&t.global_mat4(e))
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum VolumeState {
+  Unloaded,
+  // waiting for or being read on the reader thread
+  Loading,
+  // read and waiting its turn to be spawned
+  Queued,
+  Resident,
+  Failed,
+}
+
+impl VolumeState {
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Unloaded => "Unloaded",
+      Self::Loading => "Loading",
+      Self::Queued => "Queued",
+      Self::Resident => "Resident",
+      Self::Failed => "Failed",
+    }
+  }
+
+  fn color(&self) -> Vec3 {
+    match self {
+      Self::Unloaded => Vec3::new(0.5, 0.5, 0.5),
+      Self::Failed => Vec3::new(0.9, 0.2, 0.2),
+      Self::Loading | Self::Queued => Vec3::new(0.9, 0.7, 0.2),
+      Self::Resident => Vec3::new(0.3, 0.8, 0.3),
+    }
+  }
+}
+
+struct Volume {
+  state: VolumeState,
+  scene: String,
+  // entities spawned from the scene, removed again on unload
+  entities: Vec<usize>,
+}
+
+// files are read and decoded in order on a reader thread, then spawned on the main thread one
+// scene a frame in priority order, since component loading touches the world and assets
+pub struct LevelStreamer {
+  // draw every volume's bounds colored by its state
+  pub debug: bool,
+  volumes: HashMap<usize, Volume>,
+  queued: Vec<(usize, i32, Scene)>,
+  reads: Sender<(usize, PathBuf)>,
+  rx: Receiver<(usize, Result<Scene>)>,
+}
+
+impl LevelStreamer {
+  fn new() -> Self {
+    let (reads, pending) = mpsc::channel::<(usize, PathBuf)>();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+      for (id, path) in pending {
+        if tx.send((id, Scene::read(&path))).is_err() {
+          break;
+        }
+      }
+    });
+    Self {
+      debug: false,
+      volumes: HashMap::new(),
+      queued: vec![],
+      reads,
+      rx,
+    }
+  }
+
+  // scene, state and spawned entity count of every volume seen so far
+  pub fn volumes(&self) -> Vec<(&str, VolumeState, usize)> {
+    let mut v = self
+      .volumes
+      .values()
+      .map(|v| (v.scene.as_str(), v.state, v.entities.len()))
+      .collect::<Vec<_>>();
+    v.sort_by_key(|v| v.0);
+    v
+  }
+
+  fn unload(&mut self, world: &mut World, id: usize) {
+    if let Some(v) = self.volumes.get_mut(&id) {
+      if v.state == VolumeState::Resident {
+        info!("Unloading '{}'.", v.scene);
+        // assets only it used are unloaded at the end of the frame
+        Scene::despawn(world, &v.entities);
+      }
+      v.entities.clear();
+      v.state = VolumeState::Unloaded;
+    }
+    self.queued.retain(|q| q.0 != id);
+  }
+}
+
+fn level_streaming_update(world: &mut World) -> Result {
+  // taken out so spawning and despawning can borrow the world
+  let mut streamer = world.take_resource::<LevelStreamer>().unwrap();
+  let viewer = world.query::<Camera>().first().and_then(|(e, _)| {
+    e.get_one::<Transform>()
+      .map(|t| t.global_mat4(*e).w_axis.truncate())
+  });
+  let volumes = world
+    .query::<StreamingVolume>()
+    .into_iter()
+    .filter_map(|(e, v)| {
+      let t = e.get_one::<Transform>()?;
+      Some((e.id, v.bounds(t, e), v.scene.clone(), v.margin, v.priority))
+    })
+    .collect::<Vec<_>>();
+
+  // volumes whose entity is gone take their scene with them
+  let gone = streamer
+    .volumes
+    .keys()
+    .filter(|id| volumes.iter().all(|v| v.0 != **id))
+    .copied()
+    .collect::<Vec<_>>();
+  for id in gone {
+    streamer.unload(world, id);
+    streamer.volumes.remove(&id);
+  }
+
+  let mut entered = vec![];
+  for (id, bounds, scene, margin, priority) in &volumes {
+    let state = streamer
+      .volumes
+      .entry(*id)
+      .or_insert_with(|| Volume {
+        state: VolumeState::Unloaded,
+        scene: scene.clone(),
+        entities: vec![],
+      })
+      .state;
+    if streamer.debug {
+      if let Some(debug) = world.get_resource::<DebugDraw>() {
+        debug.aabb(bounds, state.color());
+      }
+    }
+    let dist = match viewer {
+      Some(p) => p.clamp(bounds.min, bounds.max).distance(p),
+      None => f32::MAX,
+    };
+    match state {
+      VolumeState::Unloaded if dist == 0.0 => entered.push((*id, scene, *priority)),
+      VolumeState::Unloaded => {}
+      _ if dist > *margin => streamer.unload(world, *id),
+      _ => {}
+    }
+  }
+
+  entered.sort_by_key(|v| -v.2);
+  for (id, scene, _) in entered {
+    info!("Loading '{}'.", scene);
+    streamer.volumes.get_mut(&id).unwrap().state = VolumeState::Loading;
+    let _ = streamer.reads.send((id, PathBuf::from(scene)));
+  }
+
+  let loaded = streamer.rx.try_iter().collect::<Vec<_>>();
+  for (id, scene) in loaded {
+    // left the volume while it was loading
+    let v = match streamer.volumes.get_mut(&id) {
+      Some(v) if v.state == VolumeState::Loading => v,
+      _ => continue,
+    };
+    match scene {
+      Ok(scene) => {
+        v.state = VolumeState::Queued;
+        let priority = volumes.iter().find(|v| v.0 == id).map_or(0, |v| v.4);
+        streamer.queued.push((id, priority, scene));
+      }
+      Err(e) => {
+        // not retried until the camera leaves and comes back
+        error!("Couldn't load '{}': {}", v.scene, e);
+        v.state = VolumeState::Failed;
+      }
+    }
+  }
+
+  // spawning loads assets on this thread, so one scene a frame
+  streamer.queued.sort_by_key(|q| -q.1);
+  if !streamer.queued.is_empty() {
+    let (id, _, scene) = streamer.queued.remove(0);
+    let v = streamer.volumes.get_mut(&id).unwrap();
+    // fresh ids, so volumes sharing a scene don't despawn each other's entities
+    match scene.spawn_copy(world) {
+      Ok(entities) => {
+        v.entities = entities;
+        v.state = VolumeState::Resident;
+      }
+      Err(e) => {
+        error!("Couldn't spawn '{}': {}", v.scene, e);
+        v.state = VolumeState::Failed;
+      }
+    }
+  }
+  world.add_resource(streamer);
+  Ok(())
+}
+
+pub fn level_streaming_plugin(world: &mut World) -> Result {
+  world.add_resource(LevelStreamer::new());
+  world.add_system(stage::PRE_DRAW, level_streaming_update);
+  Ok(())
+}
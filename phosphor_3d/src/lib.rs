@@ -1,16 +1,72 @@
 #![allow(clippy::new_without_default)]
 use std::ptr;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
 use phosphor::Result;
-use phosphor::gfx::{Renderer, Shader, Texture, Mesh, Framebuffer, Vertex, Query, gl};
-use phosphor::ecs::{World, Name, stage};
-use phosphor::math::{Vec3, Quat, Mat4, Vec2, EulerRot};
+use phosphor::gfx::{
+  Renderer, RenderState, BlendFunc, Shader, ShaderVariants, UniformBuffer, Texture, TextureOptions,
+  TextureFormat, Filter, Wrap, Cubemap, Mesh, Framebuffer, Renderbuffer, Query, Aabb, StreamBuffer,
+  gl,
+};
+use phosphor::ecs::{World, Name, Entity, Changed, Filter as _, stage};
+use phosphor::math::{Vec3, Vec4, Quat, Mat4, Vec2, EulerRot};
 use phosphor::assets::Handle;
+use phosphor::streaming::TextureStreamer;
+use phosphor::profiling::{Profiler, GpuProfiler};
+use phosphor::config::ConfigFiles;
+use phosphor::log::warn;
 use phosphor::component;
 use log_once::warn_once;
 use rand::Rng;
 use serde::{Serialize, Deserialize};
 
-const SHADOW_RES: u32 = 4096;
+mod post;
+mod bench;
+mod controller;
+mod particles;
+mod gpu_cull;
+mod debug;
+mod level_streaming;
+mod lod;
+mod shadows;
+mod point_shadows;
+mod occlusion;
+mod material_animation;
+mod custom_material;
+mod material_file;
+mod graph;
+mod sequencer;
+mod triggers;
+mod spline;
+mod coroutines;
+mod environment;
+mod quality;
+mod bindings;
+pub use post::{PostProcess, PostEffect, DepthOfField, MotionBlur, FilmLook};
+pub use bench::{BenchScene, BenchReport, bench_plugin};
+pub use controller::{CameraController, CameraMode, camera_controller_plugin};
+pub use particles::{ParticleEmitter, ParticleBlend, Curve, Lerp};
+pub use debug::DebugDraw;
+pub use lod::Lod;
+pub use material_animation::MaterialAnimation;
+pub use custom_material::{CustomMaterial, MaterialShaders, MaterialShader, UniformValue};
+pub use material_file::{MaterialAsset, SharedMaterial};
+pub use graph::{FrameGraph, GraphPass};
+pub use sequencer::{Sequence, Track, TrackKind, SequencePlayer, SequenceEvent};
+pub use triggers::{TriggerVolume, TriggerShape, GameEvents, GameEvent, EventHandler};
+pub use spline::{Spline, SplineKind, SplineFollower};
+pub use coroutines::{wait_for_event, move_to};
+pub use environment::{WorldEnvironment, Fog};
+pub use quality::QualityScaling;
+pub use bindings::{Bindings, Binding, Property};
+pub use level_streaming::{StreamingVolume, VolumeState, LevelStreamer, level_streaming_plugin};
+use gpu_cull::GpuCuller;
+use shadows::Cascade;
+use point_shadows::PointShadows;
+use post::LensView;
+use occlusion::OcclusionCuller;
 
 #[derive(Serialize, Deserialize)]
 #[component]
@@ -57,6 +113,166 @@ impl Transform {
   pub fn as_mat4(&self) -> Mat4 {
     Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
   }
+
+  pub fn from_mat4(mat: Mat4) -> Self {
+    let (scale, rotation, position) = mat.to_scale_rotation_translation();
+    Self {
+      position,
+      rotation,
+      scale,
+    }
+  }
+
+  // world space matrix, falls back to the local one until it has been propagated
+  pub fn global_mat4(&self, e: Entity) -> Mat4 {
+    match e.get_one::<GlobalTransform>() {
+      Some(g) => g.0,
+      None => self.as_mat4(),
+    }
+  }
+
+  // in world space by its parent's current global matrix, without any shear a non uniformly
+  // scaled parent adds
+  pub fn to_global(&self, e: Entity) -> Self {
+    let parent = parent_mat4(e);
+    let (scale, rotation, _) = parent.to_scale_rotation_translation();
+    Self {
+      position: parent.transform_point3(self.position),
+      rotation: rotation * self.rotation,
+      scale: scale * self.scale,
+    }
+  }
+
+  // back from `to_global`, so it ends up at `global` under its current parent
+  pub fn set_global(&mut self, e: Entity, global: &Self) {
+    let parent = parent_mat4(e);
+    let (scale, rotation, _) = parent.to_scale_rotation_translation();
+    self.position = parent.inverse().transform_point3(global.position);
+    self.rotation = rotation.inverse() * global.rotation;
+    self.scale = global.scale / scale;
+  }
+}
+
+// global matrix of the closest ancestor with a transform, what an entity's own is relative to
+pub fn parent_mat4(e: Entity) -> Mat4 {
+  let mut p = e.parent();
+  while let Some(a) = p {
+    if let Some(t) = a.get_one::<Transform>() {
+      return t.global_mat4(a);
+    }
+    p = a.parent();
+  }
+  Mat4::IDENTITY
+}
+
+// the local matrix it was last propagated from is kept to notice moves made through `get_one`
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct GlobalTransform(pub Mat4, #[serde(skip)] Mat4);
+
+// groups entities in the outline without a transform of its own, so children keep theirs. a
+// hidden folder stops everything in it being drawn, and a locked one stops it being selected in
+// the editor
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Folder {
+  pub visible: bool,
+  pub locked: bool,
+}
+
+impl Folder {
+  pub const DEFAULT: Self = Self {
+    visible: true,
+    locked: false,
+  };
+
+  // whether a folder it's in, or itself, is hidden
+  pub fn hides(e: Entity) -> bool {
+    any_ancestor(e, |e| e.get_one::<Folder>().is_some_and(|f| !f.visible))
+  }
+
+  // whether it or anything it's in is locked, either a folder or with `Locked`
+  pub fn locks(e: Entity) -> bool {
+    any_ancestor(e, |e| {
+      e.has::<Locked>() || e.get_one::<Folder>().is_some_and(|f| f.locked)
+    })
+  }
+}
+
+// keeps an entity and its children from being selected or edited in the editor, so finished set
+// dressing doesn't get nudged by accident
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Locked;
+
+fn any_ancestor(e: Entity, f: fn(Entity) -> bool) -> bool {
+  let mut next = Some(e);
+  while let Some(e) = next {
+    if f(e) {
+      return true;
+    }
+    next = e.parent();
+  }
+  false
+}
+
+fn propagate(e: Entity, parent: Mat4) {
+  let global = match e.get_one::<Transform>() {
+    Some(t) => {
+      let local = t.as_mat4();
+      let global = parent * local;
+      match e.get_one::<GlobalTransform>() {
+        Some(g) => *g = GlobalTransform(global, local),
+        None => {
+          e.insert(GlobalTransform(global, local));
+        }
+      }
+      global
+    }
+    None => parent,
+  };
+  for child in e.children() {
+    propagate(child, global);
+  }
+}
+
+fn transform_propagate(world: &mut World) -> Result {
+  // only from what moved, was added or reparented since last frame, however it was written to.
+  // folders pass their parent's transform straight through
+  let moved = world
+    .query::<Transform>()
+    .into_iter()
+    .filter(|(e, t)| {
+      Changed::<Transform>::matches(world, e.id)
+        || e
+          .get_one::<GlobalTransform>()
+          .is_none_or(|g| g.1 != t.as_mat4())
+    })
+    .map(|(e, _)| e.id)
+    .chain(
+      world
+        .query_filtered::<Folder, Changed<Folder>>()
+        .into_iter()
+        .map(|(e, _)| e.id),
+    )
+    .collect::<HashSet<_>>();
+  'moved: for id in &moved {
+    let e = Entity { id: *id };
+    let mut parent = None;
+    let mut p = e.parent();
+    while let Some(a) = p {
+      // redone along with it
+      if moved.contains(&a.id) {
+        continue 'moved;
+      }
+      if parent.is_none() && a.has::<Transform>() {
+        parent = a.get_one::<GlobalTransform>().map(|g| g.0);
+      }
+      p = a.parent();
+    }
+    propagate(e, parent.unwrap_or(Mat4::IDENTITY));
+  }
+  Ok(())
 }
 
 fn dir(yaw: f32, pitch: f32) -> Vec3 {
@@ -72,11 +288,42 @@ fn dir(yaw: f32, pitch: f32) -> Vec3 {
 pub struct Camera {
   pub fov: f32,
   pub clip: [f32; 2],
+  pub exposure: Exposure,
+  pub dof: Option<DepthOfField>,
+  pub motion_blur: Option<MotionBlur>,
+  pub film: Option<FilmLook>,
 }
 
 impl Camera {
   pub fn new(fov: f32, clip: [f32; 2]) -> Self {
-    Self { fov, clip }
+    Self {
+      fov,
+      clip,
+      exposure: Exposure::Manual(1.0),
+      dof: None,
+      motion_blur: None,
+      film: None,
+    }
+  }
+
+  pub fn exposure(mut self, exposure: Exposure) -> Self {
+    self.exposure = exposure;
+    self
+  }
+
+  pub fn dof(mut self, dof: DepthOfField) -> Self {
+    self.dof = Some(dof);
+    self
+  }
+
+  pub fn motion_blur(mut self, blur: MotionBlur) -> Self {
+    self.motion_blur = Some(blur);
+    self
+  }
+
+  pub fn film(mut self, film: FilmLook) -> Self {
+    self.film = Some(film);
+    self
   }
 
   pub fn matrices(&self, transform: &Transform, aspect: f32) -> (Mat4, Mat4) {
@@ -91,6 +338,64 @@ impl Camera {
   }
 }
 
+// everything drawn into the hdr target is scaled by the exposure, so emissive materials and
+// particles need brighter values too once lights are in physical units
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum Exposure {
+  // a plain multiplier, 1 keeps relative light values as they are
+  Manual(f32),
+  Ev100(f32),
+  // f-number, seconds and iso, like a real camera
+  Physical {
+    aperture: f32,
+    shutter: f32,
+    iso: f32,
+  },
+}
+
+impl Exposure {
+  pub const ALL: [Self; 3] = [
+    Self::Manual(1.0),
+    // a bright indoor scene
+    Self::Ev100(8.0),
+    // sunny 16
+    Self::Physical {
+      aperture: 16.0,
+      shutter: 0.01,
+      iso: 100.0,
+    },
+  ];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Manual(_) => "Manual",
+      Self::Ev100(_) => "EV100",
+      Self::Physical { .. } => "Physical",
+    }
+  }
+
+  // the exposure value at iso 100, manual exposure has none
+  pub fn ev100(&self) -> Option<f32> {
+    match *self {
+      Self::Manual(_) => None,
+      Self::Ev100(ev) => Some(ev),
+      Self::Physical {
+        aperture,
+        shutter,
+        iso,
+      } => Some((aperture * aperture / shutter * 100.0 / iso).log2()),
+    }
+  }
+
+  // luminance is scaled so the brightest a sensor could take without clipping ends up at 1
+  pub fn multiplier(&self) -> f32 {
+    match *self {
+      Self::Manual(m) => m,
+      _ => 1.0 / (1.2 * 2f32.powf(self.ev100().unwrap())),
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize)]
 #[component]
 pub struct Model {
@@ -109,29 +414,240 @@ impl Model {
   }
 }
 
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum BillboardMode {
+  // faces the view plane
+  Spherical,
+  // only turns around the y axis, for things that should stay upright
+  Cylindrical,
+}
+
+impl BillboardMode {
+  pub const ALL: [Self; 2] = [Self::Spherical, Self::Cylindrical];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Spherical => "Spherical",
+      Self::Cylindrical => "Cylindrical",
+    }
+  }
+}
+
+// replaces the model's rotation so its +z side faces the camera, use with quad.obj
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Billboard(pub BillboardMode);
+
+impl Billboard {
+  fn apply(&self, mat: Mat4, cam_t: &Transform) -> Mat4 {
+    let (scale, _, position) = mat.to_scale_rotation_translation();
+    let rotation = match self.0 {
+      BillboardMode::Spherical => cam_t.rotation,
+      BillboardMode::Cylindrical => {
+        let d = cam_t.position - position;
+        Quat::from_rotation_y(d.x.atan2(d.z))
+      }
+    };
+    Mat4::from_scale_rotation_translation(scale, rotation, position)
+  }
+}
+
+// roughly how many pixels tall the bounds appear
+fn screen_size(aabb: &Aabb, cam_pos: Vec3, projection: &Mat4, height: f32) -> f32 {
+  let radius = (aabb.max - aabb.min).length() * 0.5;
+  let dist = ((aabb.min + aabb.max) * 0.5).distance(cam_pos).max(0.01);
+  radius * projection.y_axis.y / dist * height
+}
+
+// the model's mesh, or its level of detail at this distance from the camera
+fn model_mesh(
+  e: Entity,
+  model: &Model,
+  model_mat: &Mat4,
+  cam_t: &Transform,
+) -> (Handle<Mesh>, Option<(Handle<Mesh>, f32)>) {
+  match e.get_one::<Lod>() {
+    Some(lod) => lod.select(
+      &model.mesh,
+      model_mat.w_axis.truncate().distance(cam_t.position),
+    ),
+    None => (model.mesh.clone(), None),
+  }
+}
+
+// the entity's own material, then its shared one
+fn entity_material(e: &Entity) -> &Material {
+  match e.get_one::<Material>() {
+    Some(m) => m,
+    None => match e.get_one::<SharedMaterial>() {
+      Some(shared) => &shared.0.material,
+      None => &Material::DEFAULT,
+    },
+  }
+}
+
+fn entity_custom_material(e: &Entity) -> Option<&CustomMaterial> {
+  match e.get_one::<CustomMaterial>() {
+    Some(c) => Some(c),
+    None => e.get_one::<SharedMaterial>()?.0.custom.as_ref(),
+  }
+}
+
+fn model_mat4(e: Entity, t: &Transform, cam_t: &Transform) -> Mat4 {
+  let mat = t.global_mat4(e);
+  match e.get_one::<Billboard>() {
+    Some(b) => b.apply(mat, cam_t),
+    None => mat,
+  }
+}
+
+// world space instances of a single mesh, drawn in one call with the entity's material
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Foliage {
+  pub mesh: Handle<Mesh>,
+  pub instances: Vec<Mat4>,
+  pub cast_shadows: bool,
+}
+
+impl Foliage {
+  pub fn new(mesh: Handle<Mesh>) -> Self {
+    Self {
+      mesh,
+      instances: vec![],
+      cast_shadows: true,
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize)]
 #[component]
 pub struct Material {
-  pub color: Vec3,
-  pub tex: Option<Handle<Texture>>,
-  pub spec: f32,
+  pub albedo: Vec3,
+  pub albedo_tex: Option<Handle<Texture>>,
   pub metallic: f32,
+  pub metallic_tex: Option<Handle<Texture>>,
+  pub roughness: f32,
+  pub roughness_tex: Option<Handle<Texture>>,
+  pub normal_tex: Option<Handle<Texture>>,
+  pub ao: f32,
+  pub ao_tex: Option<Handle<Texture>>,
+  pub alpha: f32,
+  pub transparent: bool,
+  // glow added on top of lighting, tinted by the albedo
+  pub emissive: f32,
+  pub uv_offset: Vec2,
 }
 
 impl Material {
   pub const DEFAULT: Self = Self {
-    color: Vec3::splat(0.8),
-    tex: None,
-    spec: 0.5,
+    albedo: Vec3::splat(0.8),
+    albedo_tex: None,
     metallic: 0.0,
+    metallic_tex: None,
+    roughness: 0.5,
+    roughness_tex: None,
+    normal_tex: None,
+    ao: 1.0,
+    ao_tex: None,
+    alpha: 1.0,
+    transparent: false,
+    emissive: 0.0,
+    uv_offset: Vec2::ZERO,
   };
+
+  // defines for the pbr shader variant that draws this material
+  fn defines(&self) -> Vec<&'static str> {
+    let mut defines = vec![];
+    if self.normal_tex.is_some() {
+      defines.push("HAS_NORMAL_MAP");
+    }
+    defines
+  }
+
+  // asks for the mips needed to cover `px` pixels on screen
+  fn stream(&self, streamer: &mut TextureStreamer, px: f32) {
+    for tex in [
+      &self.albedo_tex,
+      &self.metallic_tex,
+      &self.roughness_tex,
+      &self.normal_tex,
+      &self.ao_tex,
+    ]
+    .into_iter()
+    .flatten()
+    {
+      streamer.request(tex, px);
+    }
+  }
+
+  pub fn bind(&self, shader: &Shader) {
+    for (i, (name, tex)) in [
+      ("albedo", &self.albedo_tex),
+      ("metallic", &self.metallic_tex),
+      ("roughness", &self.roughness_tex),
+      ("normal", &self.normal_tex),
+      ("ao", &self.ao_tex),
+    ]
+    .iter()
+    .enumerate()
+    {
+      match tex {
+        Some(tex) => {
+          tex.bind(i as _);
+          shader.set_i32(&format!("{}_tex", name), &(i as _));
+          shader.set_i32(&format!("use_{}_tex", name), &1);
+        }
+        None => shader.set_i32(&format!("use_{}_tex", name), &0),
+      }
+    }
+    shader.set_vec3("albedo", &self.albedo);
+    shader.set_f32("metallic", &self.metallic);
+    shader.set_f32("roughness", &self.roughness);
+    shader.set_f32("ao", &self.ao);
+    shader.set_f32("alpha", &self.alpha);
+    shader.set_f32("emissive", &self.emissive);
+    shader.set_vec2("uv_offset", &self.uv_offset);
+  }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum LightUnit {
+  // the color sets the brightness and `strength` how far it reaches
+  Relative,
+  // total output, like on a bulb's box
+  Lumens,
+  // lumens per steradian
+  Candela,
 }
 
+impl LightUnit {
+  pub const ALL: [Self; 3] = [Self::Relative, Self::Lumens, Self::Candela];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Relative => "Relative",
+      Self::Lumens => "Lumens",
+      Self::Candela => "Candela",
+    }
+  }
+}
+
+// physical lights are cut off where they fall below this many lux
+const LIGHT_CUTOFF_LUX: f32 = 0.1;
+
 #[derive(Serialize, Deserialize)]
 #[component]
 pub struct Light {
   pub color: Vec3,
   pub strength: f32,
+  pub unit: LightUnit,
+  // in `unit`, unused when relative
+  pub intensity: f32,
+  // only the first few shadowed lights get a shadow map
+  pub shadows: bool,
+  // size of each cubemap face
+  pub shadow_res: u32,
 }
 
 impl Light {
@@ -139,6 +655,10 @@ impl Light {
     Self {
       color,
       strength: 2.5,
+      unit: LightUnit::Relative,
+      intensity: 800.0,
+      shadows: false,
+      shadow_res: 512,
     }
   }
 
@@ -146,10 +666,256 @@ impl Light {
     self.strength = strength;
     self
   }
+
+  pub fn lumens(mut self, lumens: f32) -> Self {
+    self.unit = LightUnit::Lumens;
+    self.intensity = lumens;
+    self
+  }
+
+  pub fn candela(mut self, candela: f32) -> Self {
+    self.unit = LightUnit::Candela;
+    self.intensity = candela;
+    self
+  }
+
+  pub fn shadows(mut self, res: u32) -> Self {
+    self.shadows = true;
+    self.shadow_res = res;
+    self
+  }
+
+  // lights are points, so this is the same in every direction
+  pub fn luminous_intensity(&self) -> Option<f32> {
+    match self.unit {
+      LightUnit::Relative => None,
+      LightUnit::Lumens => Some(self.intensity / (4.0 * PI)),
+      LightUnit::Candela => Some(self.intensity),
+    }
+  }
+
+  // where the falloff is down to about 1%, or to the cutoff for physical lights. nothing past it
+  // casts a shadow
+  pub fn range(&self) -> f32 {
+    match self.luminous_intensity() {
+      Some(cd) => (cd.max(0.0) / LIGHT_CUTOFF_LUX).sqrt().max(0.01),
+      None => self.strength.max(0.01) * 10.0,
+    }
+  }
+
+  // the color and falloff the shader takes, it scales the color by pi and divides it by
+  // (distance / strength)^2 + 1. physical lights fall off with the inverse square from 1m
+  fn gpu_color(&self) -> (Vec3, f32) {
+    match self.luminous_intensity() {
+      Some(cd) => (self.color * cd / PI, 1.0),
+      None => (self.color, self.strength),
+    }
+  }
 }
 
 pub struct SkySettings {
   pub dir: Vec2,
+  // the sun's illuminance in lux, the sky and ambient light are scaled along with it
+  pub sun_lux: Option<f32>,
+  // light every surface gets regardless of shadows, as a fraction of the sun's
+  pub ambient: Vec3,
+  pub fog: Fog,
+}
+
+impl SkySettings {
+  // a relative sun lights a white surface facing it to 1
+  fn sun_intensity(&self) -> f32 {
+    self.sun_lux.unwrap_or(PI)
+  }
+}
+
+pub struct Skybox {
+  pub cubemap: Handle<Cubemap>,
+  pub intensity: f32,
+}
+
+impl Skybox {
+  pub fn new(cubemap: Handle<Cubemap>) -> Self {
+    Self {
+      cubemap,
+      intensity: 1.0,
+    }
+  }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum AntiAliasing {
+  None,
+  Msaa(u32),
+  Fxaa,
+}
+
+impl AntiAliasing {
+  pub const ALL: [Self; 5] = [
+    Self::None,
+    Self::Msaa(2),
+    Self::Msaa(4),
+    Self::Msaa(8),
+    Self::Fxaa,
+  ];
+
+  pub fn name(&self) -> String {
+    match self {
+      Self::None => "None".to_string(),
+      Self::Msaa(n) => format!("MSAA {}x", n),
+      Self::Fxaa => "FXAA".to_string(),
+    }
+  }
+}
+
+pub const GRAPHICS_PATH: &str = "graphics.txt";
+
+#[derive(Copy, Clone)]
+pub struct SceneRendererOptions {
+  pub aa: AntiAliasing,
+  // fraction of the output resolution the scene is drawn at, upscaled after post processing
+  pub render_scale: f32,
+  // cull instanced meshes in a compute shader, ignored without opengl 4.3
+  pub gpu_culling: bool,
+  // skips meshes whose bounds were hidden by the depth buffer last frame, they can show up a
+  // frame late when uncovered
+  pub occlusion_culling: bool,
+  // the sun's shadow is split into this many maps along the view, up to 4
+  pub shadow_cascades: usize,
+  // how far from the camera shadows are drawn
+  pub shadow_distance: f32,
+  // 0 splits the cascades evenly, 1 logarithmically for more detail up close
+  pub shadow_split: f32,
+  // size of the sun's shadow atlas, point lights' maps are scaled by its fraction of the default
+  pub shadow_res: u32,
+  pub ssao: bool,
+  // off skips any bloom in the post process effects
+  pub bloom: bool,
+  // tints the scene by which cascade it reads its shadow from
+  pub debug_cascades: bool,
+}
+
+impl SceneRendererOptions {
+  pub const DEFAULT: Self = Self {
+    aa: AntiAliasing::Fxaa,
+    render_scale: 1.0,
+    gpu_culling: true,
+    occlusion_culling: false,
+    shadow_cascades: 4,
+    shadow_distance: 60.0,
+    shadow_split: 0.75,
+    shadow_res: 4096,
+    ssao: true,
+    bloom: true,
+    debug_cascades: false,
+  };
+
+  // `name = value` lines in the project root, eg `aa = MSAA 4x` or `shadow_cascades = 2`. options
+  // left out keep their value
+  pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result {
+    for line in fs::read_to_string(path)?.lines() {
+      if let Some((k, v)) = line.split_once('=') {
+        let (k, v) = (k.trim(), v.trim());
+        let valid = match k {
+          "aa" => AntiAliasing::ALL
+            .into_iter()
+            .find(|aa| aa.name().eq_ignore_ascii_case(v))
+            .map(|aa| self.aa = aa),
+          "render_scale" => v
+            .parse()
+            .ok()
+            .map(|s: f32| self.render_scale = s.clamp(0.25, 2.0)),
+          "gpu_culling" => v.parse().ok().map(|b| self.gpu_culling = b),
+          "occlusion_culling" => v.parse().ok().map(|b| self.occlusion_culling = b),
+          "shadow_cascades" => v
+            .parse()
+            .ok()
+            .map(|n: usize| self.shadow_cascades = n.clamp(1, 4)),
+          "shadow_distance" => v
+            .parse()
+            .ok()
+            .map(|d: f32| self.shadow_distance = d.max(1.0)),
+          "shadow_split" => v
+            .parse()
+            .ok()
+            .map(|s: f32| self.shadow_split = s.clamp(0.0, 1.0)),
+          "shadow_res" => v
+            .parse()
+            .ok()
+            .map(|r: u32| self.shadow_res = r.clamp(256, 8192)),
+          "ssao" => v.parse().ok().map(|b| self.ssao = b),
+          "bloom" => v.parse().ok().map(|b| self.bloom = b),
+          "debug_cascades" => v.parse().ok().map(|b| self.debug_cascades = b),
+          _ => {
+            warn!("Unknown graphics option '{}'.", k);
+            continue;
+          }
+        };
+        if valid.is_none() {
+          warn!("Invalid value '{}' for '{}'.", v, k);
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+// uniform block bindings, see camera.glsl and lights.glsl
+const CAMERA_BLOCK: u32 = 0;
+const LIGHTS_BLOCK: u32 = 1;
+const MAX_LIGHTS: usize = 100;
+// in the order they run, matching ScenePerf
+const PASSES: [&str; 7] = [
+  "shadow",
+  "geometry",
+  "ssao",
+  "lighting",
+  "transparent",
+  "particles",
+  "post",
+];
+
+// the std140 layouts of the camera and light_data blocks
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CameraUniforms {
+  view: Mat4,
+  projection: Mat4,
+  cam_pos: Vec3,
+  exposure: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuLight {
+  pos: Vec3,
+  _pad: f32,
+  color: Vec3,
+  strength: f32,
+  // index into the point shadows plus one, 0 when unshadowed
+  shadow: i32,
+  shadow_far: f32,
+  _pad2: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LightUniforms {
+  lights: [GpuLight; MAX_LIGHTS],
+  num_lights: i32,
+  sun_intensity: f32,
+  _pad: [i32; 2],
+  ambient: Vec3,
+  fog_density: f32,
+  fog_color: Vec3,
+  fog_start: f32,
+}
+
+// points a shader's camera and light_data blocks at the buffers the renderer writes each view
+pub(crate) fn bind_blocks(shader: Shader) -> Shader {
+  shader.bind_block("camera", CAMERA_BLOCK);
+  shader.bind_block("light_data", LIGHTS_BLOCK);
+  shader
 }
 
 struct SceneRenderer {
@@ -158,6 +924,12 @@ struct SceneRenderer {
   gposition: Texture,
   gnormal: Texture,
   gmaterial: Texture,
+  // multisampled gbuffer, resolved into the one above after the geometry pass
+  msaa_fb: Framebuffer,
+  msaa_rbs: [Renderbuffer; 4],
+  msaa_size: (u32, u32, u32),
+  culler: Option<GpuCuller>,
+  occlusion: OcclusionCuller,
   quad: Mesh,
   light_shader: Shader,
   ssao_samples: Vec<Vec3>,
@@ -167,10 +939,19 @@ struct SceneRenderer {
   ssao_shader: Shader,
   sky_mesh: Mesh,
   sky_shader: Shader,
+  skybox_shader: Shader,
+  transparent_shader: Shader,
+  particle_shader: Shader,
+  particle_buf: StreamBuffer,
   shadow_fb: Framebuffer,
   shadow_tex: Texture,
   shadow_shader: Shader,
-  default_shader: Shader,
+  point_shadows: PointShadows,
+  pbr: ShaderVariants,
+  camera_ubo: UniformBuffer<CameraUniforms>,
+  light_ubo: UniformBuffer<LightUniforms>,
+  // each view's last view projection for motion blur, views are told apart by their framebuffer
+  prev_view_proj: HashMap<u32, Mat4>,
 }
 
 pub struct ScenePerf {
@@ -178,9 +959,46 @@ pub struct ScenePerf {
   pub geometry_pass: Query,
   pub ssao_pass: Query,
   pub lighting_pass: Query,
+  pub transparent_pass: Query,
+  pub particle_pass: Query,
+  pub post_pass: Query,
+  pub drawn: usize,
+  pub culled: usize,
+  pub particles: usize,
+  // the main view's passes in milliseconds, from the last frame whose timings all came back
+  pub gpu_time: f32,
 }
 
-#[derive(Copy, Clone)]
+pub struct Frustum {
+  pub(crate) planes: [Vec4; 6],
+}
+
+impl Frustum {
+  pub fn from_mat4(view_proj: Mat4) -> Self {
+    let m = view_proj.transpose();
+    let planes = [
+      m.w_axis + m.x_axis,
+      m.w_axis - m.x_axis,
+      m.w_axis + m.y_axis,
+      m.w_axis - m.y_axis,
+      m.w_axis + m.z_axis,
+      m.w_axis - m.z_axis,
+    ];
+    Self {
+      planes: planes.map(|p| p / p.truncate().length()),
+    }
+  }
+
+  pub fn intersects(&self, aabb: &Aabb) -> bool {
+    self.planes.iter().all(|p| {
+      // corner furthest along the plane normal
+      let v = Vec3::select(p.truncate().cmpge(Vec3::ZERO), aabb.max, aabb.min);
+      p.truncate().dot(v) + p.w >= 0.0
+    })
+  }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone)]
 pub enum Tonemap {
   Aces,
   Filmic,
@@ -201,8 +1019,16 @@ impl Tonemap {
   }
 }
 
-fn gbuf() -> Texture {
-  Texture::new(ptr::null(), 0, 0, gl::RGBA16F, gl::RGBA, gl::FLOAT)
+fn gbuf(label: &str) -> Texture {
+  let tex = Texture::with_format(
+    ptr::null(),
+    0,
+    0,
+    TextureFormat::Rgba16f,
+    TextureOptions::DEFAULT,
+  );
+  tex.label(label);
+  tex
 }
 
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
@@ -212,28 +1038,26 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 pub fn scenerenderer_plugin(world: &mut World) -> Result {
   world.add_resource(SkySettings {
     dir: Vec2::new(30.0, 320.0),
+    sun_lux: None,
+    ambient: Vec3::splat(0.1),
+    fog: Fog::NONE,
   });
   let gbuffer = Framebuffer::new();
-  let galbedo = gbuf();
+  let galbedo = gbuf("gbuffer albedo");
   gbuffer.bind_tex(&galbedo, 0);
-  let gposition = gbuf();
+  let gposition = gbuf("gbuffer position");
   gbuffer.bind_tex(&gposition, 1);
-  let gnormal = gbuf();
+  let gnormal = gbuf("gbuffer normal");
   gbuffer.bind_tex(&gnormal, 2);
-  let gmaterial = gbuf();
+  let gmaterial = gbuf("gbuffer material");
   gbuffer.bind_tex(&gmaterial, 3);
-  unsafe {
-    gl::DrawBuffers(
-      4,
-      [
-        gl::COLOR_ATTACHMENT0,
-        gl::COLOR_ATTACHMENT1,
-        gl::COLOR_ATTACHMENT2,
-        gl::COLOR_ATTACHMENT3,
-      ]
-      .as_ptr(),
-    );
+  gbuffer.draw_buffers(4);
+  let msaa_fb = Framebuffer::new();
+  let msaa_rbs = [0; 4].map(|_| Renderbuffer::new(gl::RGBA16F));
+  for (i, rb) in msaa_rbs.iter().enumerate() {
+    msaa_fb.bind_rb(rb, i as _);
   }
+  msaa_fb.draw_buffers(4);
 
   let mut rng = rand::thread_rng();
   let mut ssao_samples = vec![];
@@ -264,96 +1088,118 @@ pub fn scenerenderer_plugin(world: &mut World) -> Result {
     gl::RGBA16F,
     gl::RGB,
     gl::FLOAT,
+    // tiled over the screen
+    TextureOptions::DEFAULT
+      .filter(Filter::Nearest)
+      .wrap(Wrap::Repeat),
   );
   let ssao_fb = Framebuffer::new_no_depth();
-  let ssao_tex = Texture::new(ptr::null(), 0, 0, gl::RED, gl::RED, gl::FLOAT);
+  let ssao_tex = Texture::new(
+    ptr::null(),
+    0,
+    0,
+    gl::RED,
+    gl::RED,
+    gl::FLOAT,
+    TextureOptions::DEFAULT,
+  );
   ssao_fb.bind_tex(&ssao_tex, 0);
 
   let shadow_fb = Framebuffer::new_no_depth();
   let shadow_tex = Texture::new(
     ptr::null(),
-    SHADOW_RES,
-    SHADOW_RES,
+    SceneRendererOptions::DEFAULT.shadow_res,
+    SceneRendererOptions::DEFAULT.shadow_res,
     gl::DEPTH_COMPONENT,
     gl::DEPTH_COMPONENT,
     gl::FLOAT,
+    TextureOptions::DEFAULT,
   );
   shadow_fb.bind_depth(&shadow_tex);
+  let mut pbr = ShaderVariants::new("pbr.vert", "pbr.frag").block("camera", CAMERA_BLOCK);
+  pbr.get(&[])?;
   world.add_resource(SceneRenderer {
     gbuffer,
     galbedo,
     gposition,
     gnormal,
     gmaterial,
-    quad: Mesh::new(
-      &[
-        Vertex {
-          pos: [1.0, 1.0, 0.0],
-          uv: [1.0, 1.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [1.0, -1.0, 0.0],
-          uv: [1.0, 0.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [-1.0, -1.0, 0.0],
-          uv: [0.0, 0.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [-1.0, 1.0, 0.0],
-          uv: [0.0, 1.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-      ],
-      &[0, 1, 3, 1, 2, 3],
-    ),
-    sky_mesh: Mesh::new(
-      &[
-        Vertex {
-          pos: [1.0, 1.0, 0.0],
-          uv: [1.0, 1.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [1.0, -1.0, 0.0],
-          uv: [1.0, 0.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [-1.0, 1.0, 0.0],
-          uv: [0.0, 1.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-        Vertex {
-          pos: [-1.0, -1.0, 0.0],
-          uv: [0.0, 0.0],
-          normal: [0.0, 0.0, 0.0],
-        },
-      ],
-      &[0, 1, 2, 1, 3, 2],
-    ),
-    light_shader: Shader::new("light.vert", "light.frag")?,
+    msaa_fb,
+    msaa_rbs,
+    msaa_size: (0, 0, 0),
+    culler: GpuCuller::new()?,
+    occlusion: OcclusionCuller::new()?,
+    quad: Mesh::quad(),
+    sky_mesh: Mesh::quad(),
+    light_shader: bind_blocks(Shader::new("light.vert", "light.frag")?),
     ssao_samples,
     ssao_noise,
     ssao_fb,
     ssao_tex,
-    ssao_shader: Shader::new("light.vert", "ssao.frag")?,
-    sky_shader: Shader::new("sky.vert", "sky.frag")?,
+    ssao_shader: bind_blocks(Shader::new("light.vert", "ssao.frag")?),
+    sky_shader: bind_blocks(Shader::new("sky.vert", "sky.frag")?),
+    skybox_shader: bind_blocks(Shader::new("sky.vert", "skybox.frag")?),
+    transparent_shader: bind_blocks(Shader::new("pbr.vert", "transparent.frag")?),
+    particle_shader: bind_blocks(Shader::new("particle.vert", "particle.frag")?),
+    particle_buf: StreamBuffer::new(gl::ARRAY_BUFFER, 1 << 20),
     shadow_fb,
     shadow_tex,
     shadow_shader: Shader::new("shadow.vert", "shadow.frag")?,
-    default_shader: Shader::new("base.vert", "default.frag")?,
+    point_shadows: PointShadows::new()?,
+    pbr,
+    camera_ubo: UniformBuffer::new(CAMERA_BLOCK),
+    light_ubo: UniformBuffer::new(LIGHTS_BLOCK),
+    prev_view_proj: HashMap::new(),
   });
   world.add_resource(ScenePerf {
     shadow_pass: Query::new(),
     geometry_pass: Query::new(),
     ssao_pass: Query::new(),
     lighting_pass: Query::new(),
+    transparent_pass: Query::new(),
+    particle_pass: Query::new(),
+    post_pass: Query::new(),
+    drawn: 0,
+    culled: 0,
+    particles: 0,
+    gpu_time: 0.0,
   });
-  world.add_resource(Tonemap::Reinhard);
+  world.add_resource(PostProcess::new()?);
+  world.add_resource(DebugDraw::new()?);
+  world.add_resource(FrameGraph::new());
+  if world.get_resource::<SceneRendererOptions>().is_none() {
+    world.add_resource(SceneRendererOptions::DEFAULT);
+  }
+  if Path::new(GRAPHICS_PATH).exists() {
+    let options = world.get_resource::<SceneRendererOptions>().unwrap();
+    if let Err(e) = options.load(GRAPHICS_PATH) {
+      warn!("Couldn't load '{}'. {}", GRAPHICS_PATH, e);
+    }
+  }
+  world
+    .get_resource::<ConfigFiles>()
+    .unwrap()
+    .watch(GRAPHICS_PATH, |world, path| {
+      world
+        .get_resource::<SceneRendererOptions>()
+        .unwrap()
+        .load(path)
+    });
+  world.add_resource(MaterialShaders::new());
+  if world.get_resource::<GameEvents>().is_none() {
+    world.add_resource(GameEvents::new());
+  }
+  world.add_system(stage::PRE_DRAW, triggers::game_events_dispatch);
+  world.add_system(stage::PRE_DRAW, environment::world_environment_apply);
+  // before transforms are propagated, so cameras and children follow this frame
+  world.add_system(stage::DRAW, sequencer::sequencer_update);
+  world.add_system(stage::DRAW, spline::spline_follow);
+  world.add_system(stage::DRAW, bindings::bindings_update);
+  world.add_system(stage::DRAW, transform_propagate);
+  world.add_system(stage::DRAW, triggers::trigger_update);
+  world.add_system(stage::DRAW, particles::particles_update);
+  world.add_system(stage::DRAW, material_animation::material_animation_update);
+  world.add_system(stage::DRAW, quality::quality_update);
   world.add_system(stage::DRAW, scenerenderer_draw);
   Ok(())
 }
@@ -361,186 +1207,609 @@ pub fn scenerenderer_plugin(world: &mut World) -> Result {
 pub struct SceneDrawOptions {
   pub fb: Framebuffer,
   pub size: [f32; 2],
+  // drawn from here instead of the first camera, for cameras that aren't entities
+  pub camera: Option<(Camera, Transform)>,
 }
 
+// drawn before the main view each frame, like a game preview next to an editor's view
+pub struct SceneViews(pub Vec<SceneDrawOptions>);
+
 fn scenerenderer_draw(world: &mut World) -> Result {
   let renderer = world.get_resource::<Renderer>().unwrap();
   let (w, h) = renderer.window.get_framebuffer_size();
-  match world.query::<Camera>().get(0) {
-    Some((e, cam)) => match e.get_one::<Transform>() {
-      Some(cam_t) => {
-        let r = world.get_resource::<SceneRenderer>().unwrap();
-        let perf = world.get_resource::<ScenePerf>().unwrap();
-        let sky = world.get_resource::<SkySettings>().unwrap();
-        let sun_dir = dir(sky.dir.x, sky.dir.y);
-        let sun_view = Mat4::look_at_rh(sun_dir, Vec3::ZERO, Vec3::Y);
-        // todo calculate this from cam frustum
-        let sun_projection = Mat4::orthographic_rh(-15.0, 15.0, -15.0, 15.0, 0.1, 15.0);
-
-        // shadow pass
-        perf.shadow_pass.time(|| {
-          r.shadow_fb.bind();
-          renderer.resize(SHADOW_RES, SHADOW_RES);
-          renderer.clear(0.0, 0.0, 0.0, 1.0);
-          r.shadow_shader.bind();
-          r.shadow_shader.set_mat4("view", &sun_view);
-          r.shadow_shader.set_mat4("projection", &sun_projection);
-          for (e, model) in world.query::<Model>() {
-            if model.cast_shadows {
-              if let Some(model_t) = e.get_one::<Transform>() {
-                r.shadow_shader.set_mat4("model", &model_t.as_mat4());
-                model.mesh.draw();
-              }
+  let main = match world.get_resource::<SceneDrawOptions>() {
+    Some(o) => (o.fb, o.size, o.camera.as_ref(), true),
+    None => (Framebuffer::DEFAULT, [w as _, h as _], None, true),
+  };
+  // the main view goes last so the perf stats are its own
+  let views = world
+    .get_resource::<SceneViews>()
+    .map_or(&[][..], |v| &v.0)
+    .iter()
+    .map(|o| (o.fb, o.size, o.camera.as_ref(), false))
+    .chain([main]);
+  for (fb, size, camera, main) in views {
+    match camera {
+      Some((cam, cam_t)) => draw_view(world, cam, cam_t, fb, size, main),
+      None => match scene_camera(world) {
+        Some((cam, cam_t)) => draw_view(world, cam, &cam_t, fb, size, main),
+        None => warn_once!("Scene will not be rendered (Missing camera)."),
+      },
+    }
+  }
+  // lines never pile up while there is nothing to draw them with
+  world.get_resource::<DebugDraw>().unwrap().clear();
+  unsafe {
+    gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+  }
+  Framebuffer::DEFAULT.bind();
+  renderer.resize(w as _, h as _);
+  Ok(())
+}
+
+// the first camera with its transform in world space
+fn scene_camera(world: &World) -> Option<(&Camera, Transform)> {
+  let (e, cam) = world.query::<Camera>().into_iter().next()?;
+  let cam_t = e.get_one::<Transform>()?;
+  Some((cam, Transform::from_mat4(cam_t.global_mat4(e))))
+}
+
+// occlusion results are kept per entity, so only the main view uses them
+fn draw_view(
+  world: &World,
+  cam: &Camera,
+  cam_t: &Transform,
+  fb: Framebuffer,
+  out: [f32; 2],
+  main: bool,
+) {
+  let renderer = world.get_resource::<Renderer>().unwrap();
+  let r = world.get_resource::<SceneRenderer>().unwrap();
+  let perf = world.get_resource::<ScenePerf>().unwrap();
+  let sky = world.get_resource::<SkySettings>().unwrap();
+  let sun_dir = dir(sky.dir.x, sky.dir.y);
+  let options = world.get_resource::<SceneRendererOptions>().unwrap();
+  let options = match world.get_resource::<QualityScaling>() {
+    Some(q) => q.apply(options),
+    None => *options,
+  };
+  // everything up to post processing is drawn at the scaled size
+  let [w, h] = out.map(|s| (s * options.render_scale).round().max(1.0));
+  let cascades = shadows::cascades(cam, cam_t, w / h, sun_dir, &options);
+  let aa = options.aa;
+  let occlusion_culling = options.occlusion_culling && main;
+  let mut culler = r.culler.as_mut().filter(|_| options.gpu_culling);
+  if let Some(c) = &mut culler {
+    c.upload(world);
+  }
+  // last frame's pass times, read before the queries are reused
+  let mut times = [None; 7];
+  if main {
+    for (i, query) in [
+      &mut perf.shadow_pass,
+      &mut perf.geometry_pass,
+      &mut perf.ssao_pass,
+      &mut perf.lighting_pass,
+      &mut perf.transparent_pass,
+      &mut perf.particle_pass,
+      &mut perf.post_pass,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+      times[i] = query.get();
+    }
+    if let Some(ns) = times.iter().copied().sum::<Option<u64>>() {
+      perf.gpu_time = ns as f32 / 1000000.0;
+    }
+  }
+  if let Some(profiler) = world.get_resource::<Profiler>().filter(|_| main) {
+    for (name, time) in PASSES.iter().zip(times) {
+      if let Some(ns) = time {
+        profiler.gpu_scope(name, ns);
+      }
+    }
+  }
+  if let Some(profiler) = world.get_resource::<GpuProfiler>().filter(|_| main) {
+    for (name, time) in PASSES.iter().zip(times) {
+      if let Some(ns) = time {
+        profiler.record(name, ns);
+      }
+    }
+  }
+
+  // shadow pass
+  let mut point_shadows = vec![];
+  let (shadow_res, cascade_res) = (options.shadow_res, Cascade::res(&options));
+  if r.shadow_tex.width != shadow_res {
+    r.shadow_tex.resize(shadow_res, shadow_res);
+  }
+  perf.shadow_pass.time(|| {
+    r.shadow_fb.bind();
+    renderer.resize(shadow_res, shadow_res);
+    renderer.clear(0.0, 0.0, 0.0, 1.0);
+    r.shadow_shader.bind();
+    r.shadow_shader.set_mat4("view", &Mat4::IDENTITY);
+    for (i, cascade) in cascades.iter().enumerate() {
+      let (x, y) = Cascade::viewport(i, cascade_res);
+      unsafe {
+        gl::Viewport(x, y, cascade_res as _, cascade_res as _);
+      }
+      r.shadow_shader.set_mat4("projection", &cascade.matrix);
+      let cascade_frustum = Frustum::from_mat4(cascade.matrix);
+      for (e, model) in world.query::<Model>() {
+        if model.cast_shadows && !Folder::hides(e) {
+          if let Some(model_t) = e.get_one::<Transform>() {
+            let model_mat = model_mat4(e, model_t, cam_t);
+            let (mesh, _) = model_mesh(e, model, &model_mat, cam_t);
+            if cascade_frustum.intersects(&mesh.aabb.transform(&model_mat)) {
+              r.shadow_shader.set_mat4("model", &model_mat);
+              mesh.draw();
             }
           }
-        });
-
-        let (fb, w, h) = match world.get_resource::<SceneDrawOptions>() {
-          Some(o) => (o.fb, o.size[0], o.size[1]),
-          None => (Framebuffer::DEFAULT, w as _, h as _),
-        };
-        let (view, projection) = cam.matrices(cam_t, w / h);
-        // geometry pass
-        perf.geometry_pass.time(|| {
-          r.gbuffer.bind();
-          renderer.resize(w as _, h as _);
-          r.gbuffer.resize(w as _, h as _);
-          r.galbedo.resize(w as _, h as _);
-          r.gposition.resize(w as _, h as _);
-          r.gnormal.resize(w as _, h as _);
-          r.gmaterial.resize(w as _, h as _);
-          r.ssao_fb.resize(w as _, h as _);
-          r.ssao_tex.resize(w as _, h as _);
-          renderer.clear(0.0, 0.0, 0.0, 1.0);
-
-          r.sky_shader.bind();
-          r.sky_shader.set_mat4("view", &view);
-          r.sky_shader.set_mat4("projection", &projection);
-          r.sky_shader.set_vec3("sun_dir", &sun_dir);
-          unsafe {
-            gl::DepthMask(gl::FALSE);
-            r.sky_mesh.draw();
-            gl::DepthMask(gl::TRUE);
+        }
+      }
+      r.shadow_shader.set_i32("instanced", &1);
+      for (e, instanced) in world.query::<Foliage>() {
+        if instanced.cast_shadows && !Folder::hides(e) {
+          match &culler {
+            Some(c) => c.draw_all(e, instanced),
+            None => instanced.mesh.draw_instanced(&instanced.instances),
           }
+        }
+      }
+      r.shadow_shader.set_i32("instanced", &0);
+    }
+    let scale = shadow_res as f32 / SceneRendererOptions::DEFAULT.shadow_res as f32;
+    point_shadows = r
+      .point_shadows
+      .draw(world, renderer, culler.as_deref(), cam_t, scale);
+  });
+
+  let (view, projection) = cam.matrices(cam_t, w / h);
+  r.camera_ubo.set(&CameraUniforms {
+    view,
+    projection,
+    cam_pos: cam_t.position,
+    exposure: cam.exposure.multiplier(),
+  });
+  let view_proj = projection * view;
+  let prev_view_proj = r
+    .prev_view_proj
+    .insert(fb.fb, view_proj)
+    .unwrap_or(view_proj);
+  let frustum = Frustum::from_mat4(view_proj);
+  perf.drawn = 0;
+  perf.culled = 0;
+  let mut transparent = vec![];
+  let mut occlusion_tests = vec![];
+  let mut streamer = world.get_resource::<TextureStreamer>();
+  let material_shaders = world.get_resource::<MaterialShaders>().unwrap();
+  // geometry pass
+  perf.geometry_pass.time(|| {
+    r.gbuffer.bind();
+    renderer.resize(w as _, h as _);
+    r.gbuffer.resize(w as _, h as _);
+    r.galbedo.resize(w as _, h as _);
+    r.gposition.resize(w as _, h as _);
+    r.gnormal.resize(w as _, h as _);
+    r.gmaterial.resize(w as _, h as _);
+    r.ssao_fb.resize(w as _, h as _);
+    r.ssao_tex.resize(w as _, h as _);
+    if let AntiAliasing::Msaa(samples) = aa {
+      if r.msaa_size != (w as _, h as _, samples) {
+        r.msaa_size = (w as _, h as _, samples);
+        r.msaa_fb.resize_multisample(w as _, h as _, samples);
+        for rb in &r.msaa_rbs {
+          rb.resize(w as _, h as _, samples);
+        }
+      }
+      r.msaa_fb.bind();
+    }
+    renderer.clear(0.0, 0.0, 0.0, 1.0);
 
-          r.default_shader.bind();
-          r.default_shader.set_mat4("view", &view);
-          r.default_shader.set_mat4("projection", &projection);
-          for (e, model) in world.query::<Model>() {
-            match e.get_one::<Transform>() {
-              Some(model_t) => {
-                let mat = match e.get_one::<Material>() {
-                  Some(m) => m,
-                  None => &Material::DEFAULT,
-                };
-                match &mat.tex {
-                  Some(tex) => {
-                    tex.bind(0);
-                    r.default_shader.set_i32("use_tex", &1);
-                  }
-                  None => r.default_shader.set_i32("use_tex", &0),
-                };
-                r.default_shader.set_vec3("color", &mat.color);
-                r.default_shader.set_f32("spec", &mat.spec);
-                r.default_shader.set_f32("metallic", &mat.metallic);
-                r.default_shader.set_mat4("model", &model_t.as_mat4());
-                unsafe {
-                  gl::PolygonMode(
-                    gl::FRONT_AND_BACK,
-                    if model.wireframe { gl::LINE } else { gl::FILL },
-                  );
-                }
-                model.mesh.draw();
-              }
-              None => warn_once!(
-                "Mesh on entity '{}'({}) won't be rendered (Missing Transform).",
-                e.get_one::<Name>().map_or("?", |n| &n.0),
-                e.id
-              ),
+    let skybox = world.get_resource::<Skybox>();
+    let sky_shader = match skybox {
+      Some(_) => &r.skybox_shader,
+      None => &r.sky_shader,
+    };
+    sky_shader.bind();
+    match skybox {
+      Some(skybox) => {
+        skybox.cubemap.bind(0);
+        sky_shader.set_i32("skybox", &0);
+        sky_shader.set_f32("intensity", &skybox.intensity);
+      }
+      None => sky_shader.set_vec3("sun_dir", &sun_dir),
+    }
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_write: false,
+      ..prev
+    });
+    r.sky_mesh.draw();
+    renderer.apply_state(prev);
+
+    for (e, model) in world.query::<Model>() {
+      if Folder::hides(e) {
+        continue;
+      }
+      match e.get_one::<Transform>() {
+        Some(model_t) => {
+          let model_mat = model_mat4(e, model_t, cam_t);
+          let (mesh, fade) = model_mesh(e, model, &model_mat, cam_t);
+          let bounds = mesh.aabb.transform(&model_mat);
+          if !frustum.intersects(&bounds) {
+            perf.culled += 1;
+            continue;
+          }
+          let mat = entity_material(&e);
+          if occlusion_culling && !mat.transparent {
+            occlusion_tests.push((e.id, bounds));
+            if r.occlusion.occluded(e.id) {
+              perf.culled += 1;
+              continue;
             }
           }
-        });
-
-        // ssao pass
-        perf.ssao_pass.time(|| {
-          r.ssao_fb.bind();
-          renderer.clear(0.0, 0.0, 0.0, 1.0);
-          r.ssao_shader.bind();
-          r.galbedo.bind(0);
-          r.ssao_shader.set_i32("galbedo", &0);
-          r.gposition.bind(1);
-          r.ssao_shader.set_i32("gposition", &1);
-          r.gnormal.bind(2);
-          r.ssao_shader.set_i32("gnormal", &2);
-          r.ssao_noise.bind(3);
-          r.ssao_shader.set_i32("noise", &3);
-          for (i, s) in r.ssao_samples.iter().enumerate() {
-            r.ssao_shader.set_vec3(&format!("samples[{}]", i), s);
+          perf.drawn += 1;
+          if let Some(s) = &mut streamer {
+            mat.stream(s, screen_size(&bounds, cam_t.position, &projection, h));
+          }
+          if mat.transparent {
+            transparent.push((model_mat, e, &*model, mesh));
+            continue;
           }
-          r.ssao_shader.set_mat4("view", &view);
-          r.ssao_shader.set_mat4("projection", &projection);
-          r.quad.draw();
-        });
-
-        // lighting pass
-        perf.lighting_pass.time(|| {
-          fb.bind();
-          renderer.clear(0.0, 0.0, 0.0, 1.0);
-          r.light_shader.bind();
-          r.galbedo.bind(0);
-          r.light_shader.set_i32("galbedo", &0);
-          r.gposition.bind(1);
-          r.light_shader.set_i32("gposition", &1);
-          r.gnormal.bind(2);
-          r.light_shader.set_i32("gnormal", &2);
-          r.gmaterial.bind(3);
-          r.light_shader.set_i32("gmaterial", &3);
-          r.ssao_tex.bind(4);
-          r.light_shader.set_i32("ssao_tex", &4);
-          r.shadow_tex.bind(5);
-          r.light_shader.set_mat4("view", &view);
-          r.light_shader.set_mat4("projection", &projection);
-          r.light_shader.set_i32("shadow_map", &5);
-          r.light_shader.set_vec3("cam_pos", &cam_t.position);
-          r.light_shader.set_vec3("sun_dir", &sun_dir);
-          r.light_shader.set_mat4("sun_view", &sun_view);
-          r.light_shader.set_mat4("sun_projection", &sun_projection);
-          r.light_shader.set_i32(
-            "tonemap",
-            &(*world
-              .get_resource::<Tonemap>()
-              .unwrap_or(&mut Tonemap::Aces) as i32),
-          );
-          let lights = world.query::<Light>();
-          for (i, (e, light)) in lights.iter().enumerate() {
-            match e.get_one::<Transform>() {
-              Some(light_t) => {
-                r.light_shader
-                  .set_vec3(&format!("lights[{}].pos", i), &light_t.position);
-                r.light_shader
-                  .set_vec3(&format!("lights[{}].color", i), &light.color);
-                r.light_shader
-                  .set_f32(&format!("lights[{}].strength", i), &light.strength);
-              }
-              None => warn_once!(
-                "Light on entity '{}'({}) will not be rendered (Missing transform).",
-                e.get_one::<Name>().map_or("?", |n| &n.0),
-                e.id
-              ),
+          let custom = entity_custom_material(&e).and_then(|c| {
+            let shader = material_shaders.get(&c.shader);
+            if shader.is_none() {
+              warn_once!("Material shader '{}' isn't registered.", c.shader);
             }
+            Some((c, shader?))
+          });
+          let shader = match &custom {
+            Some((_, custom)) => custom.shader,
+            // the variant files were checked when the renderer was created
+            None => r.pbr.get(&mat.defines()).unwrap(),
+          };
+          shader.bind();
+          mat.bind(&shader);
+          // custom shaders may still declare the camera as plain uniforms
+          if let Some((c, custom)) = &custom {
+            shader.set_mat4("view", &view);
+            shader.set_mat4("projection", &projection);
+            c.bind(custom);
           }
-          r.light_shader.set_i32("num_lights", &(lights.len() as _));
-          r.quad.draw();
-        });
+          shader.set_mat4("model", &model_mat);
+          unsafe {
+            gl::PolygonMode(
+              gl::FRONT_AND_BACK,
+              if model.wireframe { gl::LINE } else { gl::FILL },
+            );
+          }
+          match fade {
+            // each level keeps its share of a dither pattern, so together they cover
+            // every pixel
+            Some((next, t)) => {
+              shader.set_i32("use_lod_fade", &1);
+              shader.set_vec2("lod_fade", &Vec2::new(0.0, 1.0 - t));
+              mesh.draw();
+              shader.set_vec2("lod_fade", &Vec2::new(1.0 - t, 1.0));
+              next.draw();
+              shader.set_i32("use_lod_fade", &0);
+            }
+            None => mesh.draw(),
+          }
+        }
+        None => warn_once!(
+          "Mesh on entity '{}'({}) won't be rendered (Missing Transform).",
+          e.get_one::<Name>().map_or("?", |n| &n.0),
+          e.id
+        ),
       }
-      None => warn_once!("Scene will not be rendered (Missing camera transform)."),
-    },
-    None => warn_once!("Scene will not be rendered (Missing camera)."),
+    }
+    unsafe {
+      gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+    }
+    for (e, instanced) in world.query::<Foliage>() {
+      if Folder::hides(e) {
+        continue;
+      }
+      let mat = entity_material(&e);
+      let mut defines = mat.defines();
+      defines.push("INSTANCED");
+      let shader = r.pbr.get(&defines).unwrap();
+      // instances are usually scattered around the camera, so keep their textures sharp
+      if let Some(s) = &mut streamer {
+        mat.stream(s, f32::MAX);
+      }
+      let total = instanced.instances.len();
+      match &mut culler {
+        Some(c) => {
+          let visible = c.cull(e, instanced, &frustum);
+          perf.drawn += visible;
+          perf.culled += total - visible;
+          shader.bind();
+          mat.bind(&shader);
+          c.draw_visible(e, instanced);
+        }
+        None => {
+          let visible = instanced
+            .instances
+            .iter()
+            .filter(|m| frustum.intersects(&instanced.mesh.aabb.transform(m)))
+            .copied()
+            .collect::<Vec<_>>();
+          perf.drawn += visible.len();
+          perf.culled += total - visible.len();
+          shader.bind();
+          mat.bind(&shader);
+          instanced.mesh.draw_instanced(&visible);
+        }
+      }
+    }
+    if occlusion_culling {
+      r.occlusion.test(
+        renderer,
+        &occlusion_tests,
+        &view,
+        &projection,
+        cam_t.position,
+      );
+    }
+
+    if let AntiAliasing::Msaa(_) = aa {
+      // resolving averages the gbuffer, so edge pixels are lit from blended attributes
+      for i in 0..4 {
+        r.msaa_fb.blit_color(r.gbuffer, i, w as _, h as _);
+      }
+      r.msaa_fb.blit_depth(r.gbuffer, w as _, h as _);
+      r.gbuffer.draw_buffers(4);
+    }
+  });
+
+  // ssao pass
+  perf.ssao_pass.time(|| {
+    r.ssao_fb.bind();
+    // left unoccluded when it's off
+    if !options.ssao {
+      renderer.clear(1.0, 1.0, 1.0, 1.0);
+      return;
+    }
+    renderer.clear(0.0, 0.0, 0.0, 1.0);
+    r.ssao_shader.bind();
+    r.galbedo.bind(0);
+    r.ssao_shader.set_i32("galbedo", &0);
+    r.gposition.bind(1);
+    r.ssao_shader.set_i32("gposition", &1);
+    r.gnormal.bind(2);
+    r.ssao_shader.set_i32("gnormal", &2);
+    r.ssao_noise.bind(3);
+    r.ssao_shader.set_i32("noise", &3);
+    for (i, s) in r.ssao_samples.iter().enumerate() {
+      r.ssao_shader.set_vec3(&format!("samples[{}]", i), s);
+    }
+    r.quad.draw();
+  });
+
+  // lights are shared by the deferred and forward passes
+  let mut lights = LightUniforms {
+    lights: [GpuLight {
+      pos: Vec3::ZERO,
+      _pad: 0.0,
+      color: Vec3::ZERO,
+      strength: 0.0,
+      shadow: 0,
+      shadow_far: 0.0,
+      _pad2: [0.0; 2],
+    }; MAX_LIGHTS],
+    num_lights: 0,
+    sun_intensity: sky.sun_intensity(),
+    _pad: [0; 2],
+    ambient: sky.ambient,
+    fog_density: sky.fog.density,
+    fog_color: sky.fog.color,
+    fog_start: sky.fog.start,
   };
-  unsafe {
-    gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+  for (e, light) in world.query::<Light>() {
+    if Folder::hides(e) {
+      continue;
+    }
+    let light_t = match e.get_one::<Transform>() {
+      Some(t) => t,
+      None => {
+        warn_once!(
+          "Light on entity '{}'({}) will not be rendered (Missing transform).",
+          e.get_one::<Name>().map_or("?", |n| &n.0),
+          e.id
+        );
+        continue;
+      }
+    };
+    if lights.num_lights as usize == MAX_LIGHTS {
+      warn_once!("Only {} lights are rendered.", MAX_LIGHTS);
+      break;
+    }
+    let shadow = point_shadows.iter().position(|s| s.id == e.id);
+    let (color, strength) = light.gpu_color();
+    lights.lights[lights.num_lights as usize] = GpuLight {
+      pos: light_t.global_mat4(e).w_axis.truncate(),
+      _pad: 0.0,
+      color,
+      strength,
+      shadow: shadow.map_or(0, |s| s as i32 + 1),
+      shadow_far: shadow.map_or(0.0, |s| point_shadows[s].far),
+      _pad2: [0.0; 2],
+    };
+    lights.num_lights += 1;
   }
-  Framebuffer::DEFAULT.bind();
-  renderer.resize(w as _, h as _);
-  Ok(())
+  r.light_ubo.set(&lights);
+
+  // lighting pass
+  let post = world.get_resource::<PostProcess>().unwrap();
+  post.resize(w as _, h as _);
+  perf.lighting_pass.time(|| {
+    post.hdr.fb.bind();
+    renderer.clear(0.0, 0.0, 0.0, 1.0);
+    r.light_shader.bind();
+    r.galbedo.bind(0);
+    r.light_shader.set_i32("galbedo", &0);
+    r.gposition.bind(1);
+    r.light_shader.set_i32("gposition", &1);
+    r.gnormal.bind(2);
+    r.light_shader.set_i32("gnormal", &2);
+    r.gmaterial.bind(3);
+    r.light_shader.set_i32("gmaterial", &3);
+    r.ssao_tex.bind(4);
+    r.light_shader.set_i32("ssao_tex", &4);
+    r.shadow_tex.bind(5);
+    r.light_shader.set_i32("shadow_map", &5);
+    r.light_shader.set_vec3("sun_dir", &sun_dir);
+    for (i, cascade) in cascades.iter().enumerate() {
+      r.light_shader
+        .set_mat4(&format!("cascades[{}]", i), &cascade.matrix);
+      r.light_shader
+        .set_f32(&format!("cascade_far[{}]", i), &cascade.far);
+      r.light_shader
+        .set_f32(&format!("cascade_bias[{}]", i), &cascade.bias);
+    }
+    r.light_shader
+      .set_i32("num_cascades", &(cascades.len() as _));
+    r.light_shader
+      .set_i32("debug_cascades", &(options.debug_cascades as _));
+    r.point_shadows.bind(&r.light_shader, &point_shadows);
+    r.quad.draw();
+  });
+
+  // transparent pass, forward shaded back to front over the lit scene
+  perf.transparent_pass.time(|| {
+    if transparent.is_empty() {
+      return;
+    }
+    transparent.sort_by(|a, b| {
+      let da = a.0.w_axis.truncate().distance_squared(cam_t.position);
+      let db = b.0.w_axis.truncate().distance_squared(cam_t.position);
+      db.total_cmp(&da)
+    });
+    let s = &r.transparent_shader;
+    s.bind();
+    s.set_vec3("sun_dir", &sun_dir);
+    // depth from the gbuffer so transparent meshes are occluded by opaque ones
+    r.gbuffer.blit_depth(post.hdr.fb, w as _, h as _);
+    post.hdr.fb.bind();
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_write: false,
+      blend: Some(BlendFunc::ALPHA),
+      ..prev
+    });
+    for (model_mat, e, model, mesh) in &transparent {
+      entity_material(e).bind(s);
+      s.set_mat4("model", model_mat);
+      unsafe {
+        gl::PolygonMode(
+          gl::FRONT_AND_BACK,
+          if model.wireframe { gl::LINE } else { gl::FILL },
+        );
+      }
+      mesh.draw();
+    }
+    renderer.apply_state(prev);
+  });
+
+  perf.particle_pass.time(|| {
+    post.hdr.fb.bind();
+    perf.particles = particles::draw_particles(
+      world,
+      particles::ParticleDraw {
+        shader: &r.particle_shader,
+        quad: &r.quad,
+        instances: &mut r.particle_buf,
+        galbedo: &r.galbedo,
+        gposition: &r.gposition,
+        cam_pos: cam_t.position,
+      },
+    );
+  });
+
+  let lens = LensView {
+    cam,
+    cam_pos: cam_t.position,
+    view_proj,
+    prev_view_proj,
+    galbedo: &r.galbedo,
+    gposition: &r.gposition,
+  };
+  perf.post_pass.time(|| {
+    let scaled = [w, h] != out;
+    post.run(
+      renderer,
+      if scaled { post.scaled.fb } else { fb },
+      w as _,
+      h as _,
+      aa == AntiAliasing::Fxaa,
+      options.bloom,
+      &lens,
+    );
+    if scaled {
+      post.upscale(renderer, fb, [w as _, h as _], out.map(|s| s as _));
+    }
+  });
+  fb.bind();
+  if main {
+    let graph = world.get_resource::<FrameGraph>().unwrap();
+    graph.begin();
+    let size = [w as u32, h as u32];
+    let gbuffer = ["albedo", "position", "normal", "material"];
+    // bloom is blurred in its own half size targets, every other effect ping pongs
+    let mut post_writes = vec!["ping", "output"];
+    if post
+      .effects
+      .iter()
+      .any(|e| matches!(e, PostEffect::Bloom { .. }))
+    {
+      post_writes.insert(0, "bloom");
+    }
+    // camera effects read depth from the gbuffer and run first
+    let mut post_reads = vec!["hdr"];
+    if cam.dof.is_some() || cam.motion_blur.is_some() {
+      post_reads.extend_from_slice(&gbuffer[..2]);
+      post_writes.insert(0, "lens");
+    }
+    if cam.motion_blur.is_some() {
+      post_writes.insert(0, "velocity");
+    }
+    for (pass, time) in [
+      GraphPass::new(PASSES[0], [shadow_res, shadow_res]).writes(&["shadow map", "point shadows"]),
+      GraphPass::new(PASSES[1], size)
+        .writes(&gbuffer)
+        .writes(&["depth"]),
+      GraphPass::new(PASSES[2], size)
+        .reads(&gbuffer[..3])
+        .reads(&["ssao noise"])
+        .writes(&["ssao"]),
+      GraphPass::new(PASSES[3], size)
+        .reads(&gbuffer)
+        .reads(&["ssao", "shadow map", "point shadows"])
+        .writes(&["hdr"]),
+      GraphPass::new(PASSES[4], size)
+        .reads(&["depth", "hdr"])
+        .writes(&["hdr"]),
+      GraphPass::new(PASSES[5], size)
+        .reads(&gbuffer[..2])
+        .reads(&["hdr"])
+        .writes(&["hdr"]),
+      GraphPass::new(PASSES[6], size)
+        .reads(&post_reads)
+        .writes(&post_writes),
+    ]
+    .into_iter()
+    .zip(times)
+    {
+      graph.add(pass.time(time));
+    }
+  }
+  world
+    .get_resource::<DebugDraw>()
+    .unwrap()
+    .flush(renderer, &view, &projection);
 }
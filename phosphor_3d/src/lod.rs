@@ -0,0 +1,56 @@
+use phosphor::assets::Handle;
+use phosphor::gfx::Mesh;
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+
+// swaps the entity's Model mesh for simpler ones further from the camera
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Lod {
+  // meshes used past each distance, nearest first, the Model's own mesh is used before the first
+  pub levels: Vec<(f32, Handle<Mesh>)>,
+  // distance before each threshold over which the next level dithers in, 0 switches instantly
+  pub fade: f32,
+}
+
+impl Lod {
+  pub fn new() -> Self {
+    Self {
+      levels: vec![],
+      fade: 0.0,
+    }
+  }
+
+  pub fn level(mut self, distance: f32, mesh: Handle<Mesh>) -> Self {
+    let i = self.levels.partition_point(|l| l.0 <= distance);
+    self.levels.insert(i, (distance, mesh));
+    self
+  }
+
+  pub fn fade(mut self, fade: f32) -> Self {
+    self.fade = fade;
+    self
+  }
+
+  // the mesh to draw at `dist`, and the next one with how far it has faded in
+  pub(crate) fn select(
+    &self,
+    base: &Handle<Mesh>,
+    dist: f32,
+  ) -> (Handle<Mesh>, Option<(Handle<Mesh>, f32)>) {
+    let i = self.levels.partition_point(|l| l.0 <= dist);
+    let mesh = match i {
+      0 => base.clone(),
+      _ => self.levels[i - 1].1.clone(),
+    };
+    if self.fade > 0.0 {
+      if let Some((threshold, next)) = self.levels.get(i) {
+        let t = 1.0 - (threshold - dist) / self.fade;
+        if t > 0.0 {
+          return (mesh, Some((next.clone(), t)));
+        }
+      }
+    }
+    (mesh, None)
+  }
+}
@@ -0,0 +1,102 @@
+use phosphor::{Result, DeltaTime};
+use phosphor::ecs::World;
+use phosphor::math::{Vec2, Vec3};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::{Material, Curve};
+
+// drives the entity's material over time, curves are keyed from 0 to 1 over `duration` like
+// particle curves are over a particle's life, unset curves leave the material alone
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct MaterialAnimation {
+  pub playing: bool,
+  pub looping: bool,
+  // seconds
+  pub duration: f32,
+  pub albedo: Option<Curve<Vec3>>,
+  pub emissive: Option<Curve<f32>>,
+  pub alpha: Option<Curve<f32>>,
+  // uv units per second, keeps scrolling after the curves end
+  pub uv_scroll: Vec2,
+  #[serde(skip)]
+  time: f32,
+}
+
+impl MaterialAnimation {
+  pub fn new(duration: f32) -> Self {
+    Self {
+      playing: true,
+      looping: true,
+      duration,
+      albedo: None,
+      emissive: None,
+      alpha: None,
+      uv_scroll: Vec2::ZERO,
+      time: 0.0,
+    }
+  }
+
+  pub fn looping(mut self, looping: bool) -> Self {
+    self.looping = looping;
+    self
+  }
+
+  pub fn albedo(mut self, albedo: Curve<Vec3>) -> Self {
+    self.albedo = Some(albedo);
+    self
+  }
+
+  pub fn emissive(mut self, emissive: Curve<f32>) -> Self {
+    self.emissive = Some(emissive);
+    self
+  }
+
+  pub fn alpha(mut self, alpha: Curve<f32>) -> Self {
+    self.alpha = Some(alpha);
+    self
+  }
+
+  pub fn uv_scroll(mut self, uv_scroll: Vec2) -> Self {
+    self.uv_scroll = uv_scroll;
+    self
+  }
+
+  pub fn restart(&mut self) {
+    self.time = 0.0;
+  }
+
+  fn apply(&self, mat: &mut Material) {
+    let t = self.time / self.duration.max(0.001);
+    let t = match self.looping {
+      true => t.fract(),
+      false => t.min(1.0),
+    };
+    if let Some(albedo) = &self.albedo {
+      mat.albedo = albedo.sample(t);
+    }
+    if let Some(emissive) = &self.emissive {
+      mat.emissive = emissive.sample(t);
+    }
+    if let Some(alpha) = &self.alpha {
+      mat.alpha = alpha.sample(t);
+    }
+    if self.uv_scroll != Vec2::ZERO {
+      let offset = self.uv_scroll * self.time;
+      mat.uv_offset = offset - offset.floor();
+    }
+  }
+}
+
+pub(crate) fn material_animation_update(world: &mut World) -> Result {
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
+  for (e, anim) in world.query::<MaterialAnimation>() {
+    if anim.playing {
+      anim.time += dt;
+    }
+    if let Some(mat) = e.get_one::<Material>() {
+      anim.apply(mat);
+    }
+  }
+  Ok(())
+}
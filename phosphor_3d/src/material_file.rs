@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use phosphor::{Result, asset, component};
+use phosphor::ecs::World;
+use phosphor::assets::{Assets, Handle};
+use phosphor::gfx::{Texture, UniformType};
+use phosphor::math::{Vec2, Vec3, Vec4};
+use serde::{Serialize, Deserialize};
+use crate::{Material, CustomMaterial, MaterialShaders, UniformValue};
+
+enum Value {
+  Str(String),
+  Bool(bool),
+  Int(i32),
+  Float(f32),
+  Array(Vec<f32>),
+}
+
+impl Value {
+  fn parse(s: &str) -> Option<Self> {
+    if let Some(s) = s.strip_prefix('"') {
+      return Some(Self::Str(s.strip_suffix('"')?.to_string()));
+    }
+    if let Some(s) = s.strip_prefix('[') {
+      let items = s
+        .strip_suffix(']')?
+        .split(',')
+        .filter(|i| !i.trim().is_empty());
+      return items
+        .map(|i| i.trim().parse().ok())
+        .collect::<Option<_>>()
+        .map(Self::Array);
+    }
+    match s {
+      "true" => Some(Self::Bool(true)),
+      "false" => Some(Self::Bool(false)),
+      _ => match s.parse() {
+        Ok(i) => Some(Self::Int(i)),
+        Err(_) => s.parse().ok().map(Self::Float),
+      },
+    }
+  }
+
+  fn float(&self) -> Option<f32> {
+    match self {
+      Self::Int(i) => Some(*i as _),
+      Self::Float(f) => Some(*f),
+      _ => None,
+    }
+  }
+
+  fn vec3(&self) -> Option<Vec3> {
+    match self {
+      Self::Array(a) if a.len() == 3 => Some(Vec3::from_slice(a)),
+      _ => None,
+    }
+  }
+
+  fn uniform(&self, assets: &mut Assets) -> Result<UniformValue> {
+    Ok(match self {
+      Self::Str(path) => UniformValue::Texture(Some(assets.load(path)?)),
+      Self::Bool(b) => UniformValue::Bool(*b),
+      Self::Int(i) => UniformValue::Int(*i),
+      Self::Float(f) => UniformValue::Float(*f),
+      Self::Array(a) => match a.len() {
+        2 => UniformValue::Vec2(Vec2::from_slice(a)),
+        3 => UniformValue::Vec3(Vec3::from_slice(a)),
+        4 => UniformValue::Vec4(Vec4::from_slice(a)),
+        n => return Err(format!("Uniforms can't have {} components.", n).into()),
+      },
+    })
+  }
+}
+
+// a material shared by every entity that refers to it, loaded from a small toml style file:
+//
+//   albedo = [0.8, 0.8, 0.8]
+//   albedo_tex = "brick.png"
+//   roughness = 0.7
+//   shader = "water"
+//
+//   [uniforms]
+//   speed = 1.5
+//   noise = "noise.png"
+//
+// `shader` names a registered MaterialShaders entry, `[uniforms]` sets its properties
+#[asset(load_material)]
+pub struct MaterialAsset {
+  pub material: Material,
+  pub custom: Option<CustomMaterial>,
+}
+
+fn load_material(world: &mut World, path: &str) -> Result<MaterialAsset> {
+  let assets = world.get_resource::<Assets>().unwrap();
+  let mut mat = Material::DEFAULT;
+  let mut shader = None;
+  let mut uniforms = BTreeMap::new();
+  let mut in_uniforms = false;
+  for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+    let line = line.split('#').next().unwrap().trim();
+    if line.is_empty() {
+      continue;
+    }
+    if line.starts_with('[') && !line.contains('=') {
+      in_uniforms = line == "[uniforms]";
+      continue;
+    }
+    let err = || format!("Invalid line {} in '{}'.", i + 1, path);
+    let (key, value) = line.split_once('=').ok_or_else(err)?;
+    let (key, value) = (key.trim(), Value::parse(value.trim()).ok_or_else(err)?);
+    if in_uniforms {
+      uniforms.insert(key.to_string(), value.uniform(assets)?);
+      continue;
+    }
+    let texture = |assets: &mut Assets| -> Result<Option<Handle<Texture>>> {
+      match &value {
+        Value::Str(path) => Ok(Some(assets.load(path)?)),
+        _ => Err(err().into()),
+      }
+    };
+    match key {
+      "albedo" => mat.albedo = value.vec3().ok_or_else(err)?,
+      "metallic" => mat.metallic = value.float().ok_or_else(err)?,
+      "roughness" => mat.roughness = value.float().ok_or_else(err)?,
+      "ao" => mat.ao = value.float().ok_or_else(err)?,
+      "alpha" => mat.alpha = value.float().ok_or_else(err)?,
+      "emissive" => mat.emissive = value.float().ok_or_else(err)?,
+      "albedo_tex" => mat.albedo_tex = texture(assets)?,
+      "metallic_tex" => mat.metallic_tex = texture(assets)?,
+      "roughness_tex" => mat.roughness_tex = texture(assets)?,
+      "normal_tex" => mat.normal_tex = texture(assets)?,
+      "ao_tex" => mat.ao_tex = texture(assets)?,
+      "transparent" => match &value {
+        Value::Bool(b) => mat.transparent = *b,
+        _ => return Err(err().into()),
+      },
+      "shader" => match &value {
+        Value::Str(s) => shader = Some(s.clone()),
+        _ => return Err(err().into()),
+      },
+      _ => return Err(format!("Unknown material key '{}' in '{}'.", key, path).into()),
+    }
+  }
+
+  let custom = shader.map(|shader| {
+    let mut custom = CustomMaterial::new(&shader);
+    custom.values = uniforms;
+    // whole numbers are read as ints, so match them to float uniforms
+    if let Some(s) = world
+      .get_resource::<MaterialShaders>()
+      .and_then(|s| s.get(&shader))
+    {
+      for p in &s.properties {
+        if let (Some(UniformValue::Int(i)), UniformType::Float) =
+          (custom.values.get(&p.name), p.typ)
+        {
+          custom
+            .values
+            .insert(p.name.clone(), UniformValue::Float(*i as _));
+        }
+      }
+    }
+    custom
+  });
+  Ok(MaterialAsset {
+    material: mat,
+    custom,
+  })
+}
+
+impl MaterialAsset {
+  // writes the file `load_material` reads, texture paths are the asset names
+  pub fn save<P: AsRef<Path>>(mat: &Material, custom: Option<&CustomMaterial>, path: P) -> Result {
+    let mut s = String::new();
+    let v = mat.albedo;
+    writeln!(s, "albedo = [{}, {}, {}]", v.x, v.y, v.z)?;
+    writeln!(s, "metallic = {:?}", mat.metallic)?;
+    writeln!(s, "roughness = {:?}", mat.roughness)?;
+    writeln!(s, "ao = {:?}", mat.ao)?;
+    writeln!(s, "alpha = {:?}", mat.alpha)?;
+    writeln!(s, "emissive = {:?}", mat.emissive)?;
+    writeln!(s, "transparent = {}", mat.transparent)?;
+    for (key, tex) in [
+      ("albedo_tex", &mat.albedo_tex),
+      ("metallic_tex", &mat.metallic_tex),
+      ("roughness_tex", &mat.roughness_tex),
+      ("normal_tex", &mat.normal_tex),
+      ("ao_tex", &mat.ao_tex),
+    ] {
+      if let Some(tex) = tex {
+        writeln!(s, "{} = \"{}\"", key, tex.name)?;
+      }
+    }
+    if let Some(custom) = custom {
+      writeln!(s, "shader = \"{}\"", custom.shader)?;
+      writeln!(s, "\n[uniforms]")?;
+      for (name, value) in &custom.values {
+        let value = match value {
+          UniformValue::Float(f) => format!("{:?}", f),
+          UniformValue::Int(i) => i.to_string(),
+          UniformValue::Bool(b) => b.to_string(),
+          UniformValue::Vec2(v) => format!("[{}, {}]", v.x, v.y),
+          UniformValue::Vec3(v) => format!("[{}, {}, {}]", v.x, v.y, v.z),
+          UniformValue::Vec4(v) => format!("[{}, {}, {}, {}]", v.x, v.y, v.z, v.w),
+          UniformValue::Texture(Some(tex)) => format!("\"{}\"", tex.name),
+          UniformValue::Texture(None) => continue,
+        };
+        writeln!(s, "{} = {}", name, value)?;
+      }
+    }
+    fs::write(path, s)?;
+    Ok(())
+  }
+}
+
+// uses a material asset, an entity's own Material and CustomMaterial take priority over it
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct SharedMaterial(pub Handle<MaterialAsset>);
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use phosphor::Result;
+use phosphor::gfx::{Renderer, RenderState, Shader, Mesh, Query, Aabb, gl};
+use phosphor::math::{Vec3, Quat, Mat4};
+
+// boxes this close to the camera are always visible, their faces may be clipped away
+const NEAR_MARGIN: f32 = 0.5;
+
+struct Occludee {
+  query: Query,
+  // a query was issued and its result hasn't been read yet
+  pending: bool,
+  visible: bool,
+}
+
+// each frame the bounds of every opaque mesh are drawn against the finished depth buffer, a mesh
+// is skipped once its box passes no samples, results are read a frame or more later so they
+// never stall
+pub(crate) struct OcclusionCuller {
+  cube: Mesh,
+  shader: Shader,
+  occludees: HashMap<usize, Occludee>,
+}
+
+impl OcclusionCuller {
+  pub fn new() -> Result<Self> {
+    Ok(Self {
+      cube: Mesh::cube(),
+      shader: Shader::new("shadow.vert", "shadow.frag")?,
+      occludees: HashMap::new(),
+    })
+  }
+
+  // entities are visible until they've been tested
+  pub fn occluded(&mut self, id: usize) -> bool {
+    match self.occludees.get_mut(&id) {
+      Some(o) => {
+        if o.pending {
+          if let Some(samples) = o.query.get() {
+            o.visible = samples > 0;
+            o.pending = false;
+          }
+        }
+        !o.visible
+      }
+      None => false,
+    }
+  }
+
+  // tests `bounds` against the depth buffer with writes off, anything not tested is forgotten
+  pub fn test(
+    &mut self,
+    renderer: &Renderer,
+    bounds: &[(usize, Aabb)],
+    view: &Mat4,
+    projection: &Mat4,
+    cam_pos: Vec3,
+  ) {
+    self
+      .occludees
+      .retain(|id, _| bounds.iter().any(|b| b.0 == *id));
+    let s = &self.shader;
+    s.bind();
+    s.set_mat4("view", view);
+    s.set_mat4("projection", projection);
+    s.set_i32("instanced", &0);
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_test: true,
+      depth_write: false,
+      ..prev
+    });
+    unsafe {
+      gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+    }
+    for (id, aabb) in bounds {
+      let o = self.occludees.entry(*id).or_insert_with(|| Occludee {
+        query: Query::new(),
+        pending: false,
+        visible: true,
+      });
+      // the last result isn't back yet
+      if o.pending {
+        continue;
+      }
+      let margin = Vec3::splat(NEAR_MARGIN);
+      if cam_pos.cmpge(aabb.min - margin).all() && cam_pos.cmple(aabb.max + margin).all() {
+        o.visible = true;
+        continue;
+      }
+      s.set_mat4(
+        "model",
+        &Mat4::from_scale_rotation_translation(
+          aabb.max - aabb.min,
+          Quat::IDENTITY,
+          (aabb.min + aabb.max) / 2.0,
+        ),
+      );
+      o.query.samples(|| self.cube.draw());
+      o.pending = true;
+    }
+    unsafe {
+      gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+    }
+    renderer.apply_state(prev);
+  }
+}
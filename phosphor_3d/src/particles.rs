@@ -0,0 +1,299 @@
+use std::f32::consts::PI;
+use phosphor::{Result, DeltaTime};
+use phosphor::ecs::World;
+use phosphor::assets::Handle;
+use phosphor::gfx::{Renderer, RenderState, BlendFunc, Shader, Texture, Mesh, StreamBuffer};
+use phosphor::math::{Vec3, Vec4, Quat, Mat4};
+use phosphor::component;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use crate::{Transform, Folder};
+
+pub trait Lerp: Copy {
+  fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+  fn lerp(self, other: Self, t: f32) -> Self {
+    self + (other - self) * t
+  }
+}
+
+impl Lerp for Vec3 {
+  fn lerp(self, other: Self, t: f32) -> Self {
+    Vec3::lerp(self, other, t)
+  }
+}
+
+impl Lerp for Vec4 {
+  fn lerp(self, other: Self, t: f32) -> Self {
+    Vec4::lerp(self, other, t)
+  }
+}
+
+impl Lerp for Quat {
+  fn lerp(self, other: Self, t: f32) -> Self {
+    self.slerp(other, t)
+  }
+}
+
+// keys over a particle's life from 0 to 1, linearly interpolated
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Curve<T> {
+  pub keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+  pub fn constant(v: T) -> Self {
+    Self {
+      keys: vec![(0.0, v)],
+    }
+  }
+
+  pub fn linear(from: T, to: T) -> Self {
+    Self {
+      keys: vec![(0.0, from), (1.0, to)],
+    }
+  }
+
+  pub fn key(mut self, t: f32, v: T) -> Self {
+    let i = self.keys.partition_point(|k| k.0 <= t);
+    self.keys.insert(i, (t, v));
+    self
+  }
+
+  pub fn sample(&self, t: f32) -> T {
+    let i = self.keys.partition_point(|k| k.0 <= t);
+    match (i.checked_sub(1).map(|i| self.keys[i]), self.keys.get(i)) {
+      (Some(a), Some(b)) => a.1.lerp(b.1, (t - a.0) / (b.0 - a.0)),
+      (Some(a), None) => a.1,
+      (None, Some(b)) => b.1,
+      (None, None) => panic!("Curve has no keys."),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum ParticleBlend {
+  Alpha,
+  Additive,
+}
+
+impl ParticleBlend {
+  pub const ALL: [Self; 2] = [Self::Alpha, Self::Additive];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Alpha => "Alpha",
+      Self::Additive => "Additive",
+    }
+  }
+}
+
+#[derive(Copy, Clone)]
+struct Particle {
+  pos: Vec3,
+  vel: Vec3,
+  age: f32,
+  lifetime: f32,
+  rotation: f32,
+}
+
+// particles are simulated in world space from the entity's position and drawn as unlit,
+// camera facing quads, fading out where they meet the scene
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct ParticleEmitter {
+  pub playing: bool,
+  // particles per second
+  pub rate: f32,
+  pub max_particles: usize,
+  // seconds, picked between the two
+  pub lifetime: [f32; 2],
+  // spawn sphere around the entity
+  pub radius: f32,
+  pub velocity: Vec3,
+  // random velocity added in any direction
+  pub spread: f32,
+  pub gravity: Vec3,
+  // multiplies velocity over life
+  pub speed: Curve<f32>,
+  pub size: Curve<f32>,
+  pub color: Curve<Vec4>,
+  pub blend: ParticleBlend,
+  // a soft circle when unset
+  pub texture: Option<Handle<Texture>>,
+  // distance over which particles fade into geometry behind them
+  pub softness: f32,
+  #[serde(skip)]
+  particles: Vec<Particle>,
+  #[serde(skip)]
+  spawn: f32,
+}
+
+impl ParticleEmitter {
+  pub fn new() -> Self {
+    Self {
+      playing: true,
+      rate: 20.0,
+      max_particles: 1000,
+      lifetime: [1.0, 2.0],
+      radius: 0.1,
+      velocity: Vec3::Y,
+      spread: 0.5,
+      gravity: Vec3::ZERO,
+      speed: Curve::constant(1.0),
+      size: Curve::linear(0.2, 0.0),
+      color: Curve::linear(Vec4::ONE, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+      blend: ParticleBlend::Alpha,
+      texture: None,
+      softness: 0.5,
+      particles: vec![],
+      spawn: 0.0,
+    }
+  }
+
+  pub fn count(&self) -> usize {
+    self.particles.len()
+  }
+
+  pub fn clear(&mut self) {
+    self.particles.clear();
+  }
+
+  // spawns `n` particles at once, ignoring the rate
+  pub fn burst(&mut self, origin: Vec3, n: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..n.min(self.max_particles.saturating_sub(self.particles.len())) {
+      self.particles.push(Particle {
+        pos: origin + random_in_sphere(&mut rng) * self.radius,
+        vel: self.velocity + random_in_sphere(&mut rng) * self.spread,
+        age: 0.0,
+        lifetime: rng.gen_range(self.lifetime[0]..=self.lifetime[1].max(self.lifetime[0])),
+        rotation: rng.gen_range(0.0..PI * 2.0),
+      });
+    }
+  }
+
+  fn update(&mut self, origin: Vec3, dt: f32) {
+    for p in &mut self.particles {
+      p.age += dt;
+      p.vel += self.gravity * dt;
+      p.pos += p.vel * self.speed.sample(p.age / p.lifetime) * dt;
+    }
+    self.particles.retain(|p| p.age < p.lifetime);
+    if self.playing {
+      self.spawn += self.rate * dt;
+      let n = self.spawn as usize;
+      self.spawn -= n as f32;
+      self.burst(origin, n);
+    }
+  }
+}
+
+fn random_in_sphere<R: Rng>(rng: &mut R) -> Vec3 {
+  loop {
+    let v = Vec3::new(
+      rng.gen_range(-1.0..1.0),
+      rng.gen_range(-1.0..1.0),
+      rng.gen_range(-1.0..1.0),
+    );
+    if v.length_squared() <= 1.0 {
+      return v;
+    }
+  }
+}
+
+pub(crate) fn particles_update(world: &mut World) -> Result {
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
+  for (e, emitter) in world.query::<ParticleEmitter>() {
+    let origin = match e.get_one::<Transform>() {
+      Some(t) => t.global_mat4(e).w_axis.truncate(),
+      None => Vec3::ZERO,
+    };
+    emitter.update(origin, dt);
+  }
+  Ok(())
+}
+
+// the instanced path only carries a matrix, so each particle packs its position and size,
+// color and rotation into the columns, see particle.vert
+fn instances(emitter: &ParticleEmitter, cam_pos: Vec3) -> Vec<Mat4> {
+  let mut particles = emitter.particles.clone();
+  if emitter.blend == ParticleBlend::Alpha {
+    particles.sort_by(|a, b| {
+      let da = a.pos.distance_squared(cam_pos);
+      let db = b.pos.distance_squared(cam_pos);
+      db.total_cmp(&da)
+    });
+  }
+  particles
+    .iter()
+    .map(|p| {
+      let t = p.age / p.lifetime;
+      Mat4::from_cols(
+        p.pos.extend(emitter.size.sample(t)),
+        emitter.color.sample(t),
+        Vec4::new(p.rotation, 0.0, 0.0, 0.0),
+        Vec4::ZERO,
+      )
+    })
+    .collect()
+}
+
+pub(crate) struct ParticleDraw<'a> {
+  pub shader: &'a Shader,
+  pub quad: &'a Mesh,
+  pub instances: &'a mut StreamBuffer,
+  pub galbedo: &'a Texture,
+  pub gposition: &'a Texture,
+  pub cam_pos: Vec3,
+}
+
+// drawn over the lit scene, particles fade against the gbuffer instead of being depth tested
+pub(crate) fn draw_particles(world: &World, d: ParticleDraw) -> usize {
+  let emitters = world.query::<ParticleEmitter>();
+  let emitters = emitters
+    .iter()
+    .filter(|(e, emitter)| !emitter.particles.is_empty() && !Folder::hides(*e))
+    .collect::<Vec<_>>();
+  if emitters.is_empty() {
+    return 0;
+  }
+  let s = d.shader;
+  s.bind();
+  d.galbedo.bind(1);
+  s.set_i32("galbedo", &1);
+  d.gposition.bind(2);
+  s.set_i32("gposition", &2);
+  let renderer = world.get_resource::<Renderer>().unwrap();
+  let prev = renderer.state();
+  let mut drawn = 0;
+  for (_, emitter) in emitters {
+    renderer.apply_state(RenderState {
+      depth_test: false,
+      blend: Some(match emitter.blend {
+        ParticleBlend::Alpha => BlendFunc::ALPHA,
+        ParticleBlend::Additive => BlendFunc::ADDITIVE,
+      }),
+      ..prev
+    });
+    match &emitter.texture {
+      Some(tex) => {
+        tex.bind(0);
+        s.set_i32("tex", &0);
+        s.set_i32("use_tex", &1);
+      }
+      None => s.set_i32("use_tex", &0),
+    }
+    s.set_f32("softness", &emitter.softness.max(0.001));
+    let instances = instances(emitter, d.cam_pos);
+    let offset = d.instances.write(&instances, 64);
+    d.quad
+      .draw_instanced_buffer(d.instances.id, offset, instances.len());
+    drawn += instances.len();
+  }
+  d.instances.fence();
+  renderer.apply_state(prev);
+  drawn
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::ptr;
+use phosphor::Result;
+use phosphor::ecs::World;
+use phosphor::gfx::{Renderer, Shader, Cubemap, Framebuffer, gl};
+use phosphor::math::{Vec3, Mat4};
+use crate::{Transform, Model, Foliage, Light, Folder, Frustum, GpuCuller, model_mat4, model_mesh};
+
+// the lighting pass has a sampler for each, so only this many lights get shadows at once
+pub(crate) const MAX_POINT_SHADOWS: usize = 4;
+// cubemaps are bound from here up in the lighting pass
+pub(crate) const POINT_SHADOW_UNIT: u32 = 6;
+const NEAR: f32 = 0.05;
+
+// direction and up of each cubemap face, +x -x +y -y +z -z
+const FACES: [(Vec3, Vec3); 6] = [
+  (Vec3::X, Vec3::NEG_Y),
+  (Vec3::NEG_X, Vec3::NEG_Y),
+  (Vec3::Y, Vec3::Z),
+  (Vec3::NEG_Y, Vec3::NEG_Z),
+  (Vec3::Z, Vec3::NEG_Y),
+  (Vec3::NEG_Z, Vec3::NEG_Y),
+];
+
+// a shadowed light this frame, in the order its cubemap is bound
+pub(crate) struct PointShadow {
+  pub id: usize,
+  pub far: f32,
+}
+
+// distance to the light is stored in each cubemap face, so one lookup along the direction
+// from the light is enough to compare against
+pub(crate) struct PointShadows {
+  fb: Framebuffer,
+  shader: Shader,
+  maps: HashMap<usize, Cubemap>,
+}
+
+impl PointShadows {
+  pub fn new() -> Result<Self> {
+    Ok(Self {
+      fb: Framebuffer::new_no_depth(),
+      shader: Shader::new("point_shadow.vert", "point_shadow.frag")?,
+      maps: HashMap::new(),
+    })
+  }
+
+  pub fn draw(
+    &mut self,
+    world: &World,
+    renderer: &Renderer,
+    culler: Option<&GpuCuller>,
+    cam_t: &Transform,
+    // of each light's own resolution
+    scale: f32,
+  ) -> Vec<PointShadow> {
+    let lights = world
+      .query::<Light>()
+      .into_iter()
+      .filter(|(_, l)| l.shadows)
+      .filter_map(|(e, l)| {
+        let pos = e.get_one::<Transform>()?.global_mat4(e).w_axis.truncate();
+        let res = (l.shadow_res as f32 * scale) as u32;
+        Some((e.id, pos, l.range(), res.max(16)))
+      })
+      .take(MAX_POINT_SHADOWS)
+      .collect::<Vec<_>>();
+    self.maps.retain(|id, map| {
+      let keep = lights.iter().any(|l| l.0 == *id && l.3 == map.size);
+      if !keep {
+        map.delete();
+      }
+      keep
+    });
+
+    let s = &self.shader;
+    s.bind();
+    let mut shadows = vec![];
+    for (id, pos, far, res) in lights {
+      let map = *self.maps.entry(id).or_insert_with(|| {
+        Cubemap::new(
+          [ptr::null(); 6],
+          res,
+          gl::DEPTH_COMPONENT,
+          gl::DEPTH_COMPONENT,
+          gl::FLOAT,
+        )
+      });
+      let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, NEAR, far);
+      s.set_mat4("projection", &projection);
+      s.set_vec3("light_pos", &pos);
+      s.set_f32("far", &far);
+      for (face, (dir, up)) in FACES.iter().enumerate() {
+        self.fb.bind_depth_face(&map, face as _);
+        renderer.resize(res, res);
+        renderer.clear(0.0, 0.0, 0.0, 1.0);
+        let view = Mat4::look_to_rh(pos, *dir, *up);
+        s.set_mat4("view", &view);
+        let frustum = Frustum::from_mat4(projection * view);
+        for (e, model) in world.query::<Model>() {
+          if model.cast_shadows && !Folder::hides(e) {
+            if let Some(model_t) = e.get_one::<Transform>() {
+              let model_mat = model_mat4(e, model_t, cam_t);
+              let (mesh, _) = model_mesh(e, model, &model_mat, cam_t);
+              if frustum.intersects(&mesh.aabb.transform(&model_mat)) {
+                s.set_mat4("model", &model_mat);
+                mesh.draw();
+              }
+            }
+          }
+        }
+        s.set_i32("instanced", &1);
+        for (e, instanced) in world.query::<Foliage>() {
+          if instanced.cast_shadows && !Folder::hides(e) {
+            match culler {
+              Some(c) => c.draw_all(e, instanced),
+              None => instanced.mesh.draw_instanced(&instanced.instances),
+            }
+          }
+        }
+        s.set_i32("instanced", &0);
+      }
+      shadows.push(PointShadow { id, far });
+    }
+    shadows
+  }
+
+  // every sampler is set, even unused ones can't share a unit with the 2d textures
+  pub fn bind(&self, shader: &Shader, shadows: &[PointShadow]) {
+    for i in 0..MAX_POINT_SHADOWS {
+      let unit = POINT_SHADOW_UNIT + i as u32;
+      if let Some(map) = shadows.get(i).and_then(|s| self.maps.get(&s.id)) {
+        map.bind(unit);
+      }
+      shader.set_i32(&format!("point_shadows[{}]", i), &(unit as _));
+    }
+  }
+}
@@ -0,0 +1,368 @@
+use phosphor::Result;
+use phosphor::accessibility::{colorblind_filter, colorblind_strength};
+use std::mem;
+use phosphor::gfx::{
+  Renderer, RenderState, Shader, Texture, TextureFormat, RenderTarget, Mesh, Framebuffer,
+};
+use phosphor::math::{Vec3, Mat4};
+use serde::{Serialize, Deserialize};
+use crate::{Tonemap, Camera};
+
+const BLOOM_PASSES: usize = 4;
+// 35mm film's height, the camera's fov is taken across it to get a focal length
+const SENSOR_HEIGHT: f32 = 0.024;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct DepthOfField {
+  // meters to where it's sharpest
+  pub focus: f32,
+  // f-number, lower blurs more
+  pub aperture: f32,
+  // pixels
+  pub max_radius: f32,
+}
+
+impl DepthOfField {
+  pub const DEFAULT: Self = Self {
+    focus: 5.0,
+    aperture: 2.0,
+    max_radius: 12.0,
+  };
+
+  // pixels of blur at infinity, from the thin lens equation
+  fn coc_scale(&self, fov: f32, height: f32) -> f32 {
+    let focal = SENSOR_HEIGHT / 2.0 / (fov.to_radians() / 2.0).tan();
+    let diameter = focal / self.aperture.max(0.1);
+    let focus = self.focus.max(focal * 1.01);
+    diameter * focal / (focus - focal) / SENSOR_HEIGHT * height
+  }
+}
+
+// blurs along how the camera moved since the last frame
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct MotionBlur {
+  // the fraction of a frame the shutter is open, 0.5 is a 180° shutter
+  pub shutter: f32,
+  pub samples: u32,
+}
+
+impl MotionBlur {
+  pub const DEFAULT: Self = Self {
+    shutter: 0.5,
+    samples: 12,
+  };
+}
+
+// finishing touches on the final image, zero turns each one off
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct FilmLook {
+  pub vignette: f32,
+  // how far in from the corners the vignette reaches, 0 to 1
+  pub vignette_radius: f32,
+  pub grain: f32,
+  // pixels the red and blue channels are split by at the edges
+  pub chromatic_aberration: f32,
+}
+
+impl FilmLook {
+  pub const DEFAULT: Self = Self {
+    vignette: 0.3,
+    vignette_radius: 0.6,
+    grain: 0.04,
+    chromatic_aberration: 1.5,
+  };
+}
+
+#[derive(Copy, Clone)]
+enum Finish {
+  Fxaa,
+  Film(FilmLook),
+  Colorblind(i32),
+}
+
+// what the camera effects need from the view being drawn
+pub(crate) struct LensView<'a> {
+  pub cam: &'a Camera,
+  pub cam_pos: Vec3,
+  pub view_proj: Mat4,
+  pub prev_view_proj: Mat4,
+  pub galbedo: &'a Texture,
+  pub gposition: &'a Texture,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub enum PostEffect {
+  Bloom { threshold: f32, intensity: f32 },
+  Tonemap(Tonemap),
+  Vignette { strength: f32 },
+}
+
+impl PostEffect {
+  pub const ALL: [Self; 3] = [
+    Self::Bloom {
+      threshold: 1.0,
+      intensity: 0.6,
+    },
+    Self::Tonemap(Tonemap::Aces),
+    Self::Vignette { strength: 0.3 },
+  ];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Bloom { .. } => "Bloom",
+      Self::Tonemap(_) => "Tonemap",
+      Self::Vignette { .. } => "Vignette",
+    }
+  }
+}
+
+// the scene is lit into an hdr target, then each effect runs as a fullscreen pass in order
+pub struct PostProcess {
+  pub effects: Vec<PostEffect>,
+  pub(crate) hdr: RenderTarget,
+  // camera effects alternate between this and hdr
+  lens: RenderTarget,
+  velocity: RenderTarget,
+  ping: [RenderTarget; 2],
+  bloom: [RenderTarget; 2],
+  // the finished image when the scene is drawn below the output resolution, with depth so it's
+  // upscaled along with the colour
+  pub(crate) scaled: RenderTarget,
+  quad: Mesh,
+  copy_shader: Shader,
+  bright_shader: Shader,
+  blur_shader: Shader,
+  bloom_shader: Shader,
+  tonemap_shader: Shader,
+  vignette_shader: Shader,
+  fxaa_shader: Shader,
+  colorblind_shader: Shader,
+  film_shader: Shader,
+  velocity_shader: Shader,
+  motion_blur_shader: Shader,
+  dof_shader: Shader,
+}
+
+impl PostProcess {
+  pub fn new() -> Result<Self> {
+    let target = || RenderTarget::new(TextureFormat::Rgba16f, false);
+    Ok(Self {
+      effects: PostEffect::ALL.to_vec(),
+      hdr: RenderTarget::hdr(),
+      lens: target(),
+      velocity: target(),
+      ping: [target(), target()],
+      bloom: [target(), target()],
+      scaled: RenderTarget::hdr(),
+      quad: Mesh::quad(),
+      copy_shader: Shader::new("light.vert", "copy.frag")?,
+      bright_shader: Shader::new("light.vert", "bloom_bright.frag")?,
+      blur_shader: Shader::new("light.vert", "blur.frag")?,
+      bloom_shader: Shader::new("light.vert", "bloom.frag")?,
+      tonemap_shader: Shader::new("light.vert", "tonemap.frag")?,
+      vignette_shader: Shader::new("light.vert", "vignette.frag")?,
+      fxaa_shader: Shader::new("light.vert", "fxaa.frag")?,
+      colorblind_shader: Shader::new("light.vert", "colorblind.frag")?,
+      film_shader: Shader::new("light.vert", "film.frag")?,
+      velocity_shader: Shader::new("light.vert", "velocity.frag")?,
+      motion_blur_shader: Shader::new("light.vert", "motion_blur.frag")?,
+      dof_shader: Shader::new("light.vert", "dof.frag")?,
+    })
+  }
+
+  pub(crate) fn resize(&mut self, w: u32, h: u32) {
+    if self.hdr.tex.width == w && self.hdr.tex.height == h {
+      return;
+    }
+    self.hdr.resize(w, h);
+    self.lens.resize(w, h);
+    self.velocity.resize(w, h);
+    for t in &mut self.ping {
+      t.resize(w, h);
+    }
+    self.scaled.resize(w, h);
+    // bloom is blurred at half resolution
+    for t in &mut self.bloom {
+      t.resize((w / 2).max(1), (h / 2).max(1));
+    }
+  }
+
+  fn pass(&self, shader: &Shader, input: &Texture) {
+    shader.bind();
+    input.bind(0);
+    shader.set_i32("tex", &0);
+    self.quad.draw();
+  }
+
+  fn gbuffer(&self, shader: &Shader, view: &LensView) {
+    view.galbedo.bind(1);
+    shader.set_i32("galbedo", &1);
+    view.gposition.bind(2);
+    shader.set_i32("gposition", &2);
+    shader.set_vec3("cam_pos", &view.cam_pos);
+  }
+
+  // the camera's motion blur then depth of field, on the hdr image before the other effects.
+  // returns whichever target ended up with the result
+  fn lens(&self, view: &LensView, h: u32) -> &Texture {
+    let (mut input, mut output) = (&self.hdr, &self.lens);
+    if let Some(blur) = view.cam.motion_blur {
+      self.velocity.fb.bind();
+      let shader = &self.velocity_shader;
+      self.gbuffer(shader, view);
+      shader.set_mat4("inv_view_proj", &view.view_proj.inverse());
+      shader.set_mat4("prev_view_proj", &view.prev_view_proj);
+      shader.bind();
+      self.quad.draw();
+      output.fb.bind();
+      let shader = &self.motion_blur_shader;
+      self.velocity.tex.bind(1);
+      shader.set_i32("velocity", &1);
+      shader.set_f32("strength", &blur.shutter);
+      shader.set_i32("samples", &(blur.samples.max(1) as _));
+      self.pass(shader, &input.tex);
+      mem::swap(&mut input, &mut output);
+    }
+    if let Some(dof) = view.cam.dof {
+      output.fb.bind();
+      let shader = &self.dof_shader;
+      self.gbuffer(shader, view);
+      shader.set_f32("focus", &dof.focus);
+      shader.set_f32("coc_scale", &dof.coc_scale(view.cam.fov, h as _));
+      shader.set_f32("max_radius", &dof.max_radius);
+      self.pass(shader, &input.tex);
+      mem::swap(&mut input, &mut output);
+    }
+    &input.tex
+  }
+
+  // fxaa runs after every effect, on the tonemapped image, then the camera's film look so grain
+  // isn't smoothed away, then the colorblind filter so it sees what the player would
+  pub(crate) fn run(
+    &self,
+    renderer: &Renderer,
+    fb: Framebuffer,
+    w: u32,
+    h: u32,
+    fxaa: bool,
+    bloom: bool,
+    view: &LensView,
+  ) {
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_test: false,
+      blend: None,
+      ..prev
+    });
+    let mut input = self.lens(view, h);
+    let colorblind = colorblind_filter.get();
+    let finish = [
+      fxaa.then_some(Finish::Fxaa),
+      view.cam.film.map(Finish::Film),
+      (1..=3)
+        .contains(&colorblind)
+        .then_some(Finish::Colorblind(colorblind)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    let effects = self
+      .effects
+      .iter()
+      .filter(|e| bloom || !matches!(e, PostEffect::Bloom { .. }))
+      .collect::<Vec<_>>();
+    let passes = effects.len() + finish.len();
+    if passes == 0 {
+      fb.bind();
+      self.pass(&self.copy_shader, input);
+    }
+    for i in 0..passes {
+      let out = match i + 1 == passes {
+        true => fb,
+        false => self.ping[i % 2].fb,
+      };
+      let effect = match effects.get(i) {
+        Some(effect) => effect,
+        None => {
+          out.bind();
+          match finish[i - effects.len()] {
+            Finish::Fxaa => self.pass(&self.fxaa_shader, input),
+            Finish::Film(film) => {
+              let shader = &self.film_shader;
+              shader.set_f32("vignette", &film.vignette);
+              shader.set_f32("vignette_radius", &film.vignette_radius);
+              shader.set_f32("grain", &film.grain);
+              shader.set_f32("aberration", &film.chromatic_aberration);
+              // new grain every frame
+              shader.set_f32("seed", &((renderer.glfw.get_time() % 1000.0) as f32));
+              self.pass(shader, input);
+            }
+            Finish::Colorblind(mode) => {
+              let shader = &self.colorblind_shader;
+              shader.set_i32("mode", &mode);
+              shader.set_f32("strength", &colorblind_strength.get());
+              self.pass(shader, input);
+            }
+          }
+          input = &self.ping[i % 2].tex;
+          continue;
+        }
+      };
+      match effect {
+        PostEffect::Bloom {
+          threshold,
+          intensity,
+        } => {
+          let bloom = &self.bloom;
+          renderer.resize(bloom[0].tex.width, bloom[0].tex.height);
+          bloom[0].fb.bind();
+          self.bright_shader.set_f32("threshold", threshold);
+          self.pass(&self.bright_shader, input);
+          for _ in 0..BLOOM_PASSES {
+            for (horizontal, src, dst) in [(1, 0, 1), (0, 1, 0)] {
+              bloom[dst].fb.bind();
+              self.blur_shader.set_i32("horizontal", &horizontal);
+              self.pass(&self.blur_shader, &bloom[src].tex);
+            }
+          }
+          renderer.resize(w, h);
+          out.bind();
+          bloom[0].tex.bind(1);
+          self.bloom_shader.set_i32("bloom", &1);
+          self.bloom_shader.set_f32("intensity", intensity);
+          self.pass(&self.bloom_shader, input);
+        }
+        PostEffect::Tonemap(tonemap) => {
+          out.bind();
+          self.tonemap_shader.set_i32("tonemap", &(*tonemap as i32));
+          self.pass(&self.tonemap_shader, input);
+        }
+        PostEffect::Vignette { strength } => {
+          out.bind();
+          self.vignette_shader.set_f32("strength", strength);
+          self.pass(&self.vignette_shader, input);
+        }
+      }
+      input = &self.ping[i % 2].tex;
+    }
+    // keep the scene depth around for anything drawn over it
+    self.hdr.fb.blit_depth(fb, w, h);
+    renderer.apply_state(prev);
+  }
+
+  // stretches the image `run` left in `scaled` over `fb`, filtered, and its depth too so things
+  // drawn after the scene are still hidden behind it
+  pub(crate) fn upscale(&self, renderer: &Renderer, fb: Framebuffer, from: [u32; 2], to: [u32; 2]) {
+    let prev = renderer.state();
+    renderer.apply_state(RenderState {
+      depth_test: false,
+      blend: None,
+      ..prev
+    });
+    fb.bind();
+    renderer.resize(to[0], to[1]);
+    self.pass(&self.copy_shader, &self.scaled.tex);
+    self.scaled.fb.blit_depth_scaled(fb, from, to);
+    renderer.apply_state(prev);
+  }
+}
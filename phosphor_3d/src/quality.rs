@@ -0,0 +1,125 @@
+use phosphor::Result;
+use phosphor::ecs::World;
+use phosphor::log::debug;
+use crate::{ScenePerf, SceneRendererOptions};
+
+// frames between changes, so the last one shows up in the timings first
+const INTERVAL: u32 = 30;
+// how far under the target the gpu has to be before quality goes back up, so it doesn't bounce
+const HEADROOM: f32 = 0.8;
+const SCALE_STEP: f32 = 0.1;
+// effects given up once the resolution can't drop further, see `apply`
+const LEVELS: usize = 4;
+const MIN_SHADOW_RES: u32 = 512;
+
+// holds a framerate on weaker gpus by lowering quality while the scene's passes take longer than
+// `target_ms`, and raising it again once there's room. the resolution drops first since it's
+// gradual, down to `min_scale`, then ssao, half the shadow resolution, bloom, and a quarter of it
+// with at most 2 cascades are given up in turn. the options are left as they were set, this is
+// applied over them while drawing
+pub struct QualityScaling {
+  pub enabled: bool,
+  // gpu time in milliseconds, scene passes only
+  pub target_ms: f32,
+  pub min_scale: f32,
+  scale: f32,
+  level: usize,
+  average: Option<f32>,
+  frames: u32,
+}
+
+impl QualityScaling {
+  pub fn new(target_fps: f32) -> Self {
+    Self {
+      enabled: true,
+      target_ms: 1000.0 / target_fps,
+      min_scale: 0.5,
+      scale: 1.0,
+      level: 0,
+      average: None,
+      frames: 0,
+    }
+  }
+
+  pub fn min_scale(mut self, scale: f32) -> Self {
+    self.min_scale = scale.clamp(0.1, 1.0);
+    self
+  }
+
+  // the current fraction of `render_scale`
+  pub fn scale(&self) -> f32 {
+    self.scale
+  }
+
+  // how many effects have been given up
+  pub fn level(&self) -> usize {
+    self.level
+  }
+
+  // back to full quality, eg after changing the options
+  pub fn reset(&mut self) {
+    self.scale = 1.0;
+    self.level = 0;
+    self.average = None;
+    self.frames = 0;
+  }
+
+  pub(crate) fn apply(&self, options: &SceneRendererOptions) -> SceneRendererOptions {
+    let mut o = *options;
+    if !self.enabled {
+      return o;
+    }
+    o.render_scale *= self.scale;
+    if self.level >= 1 {
+      o.ssao = false;
+    }
+    if self.level >= 2 {
+      o.shadow_res = (o.shadow_res / 2).max(MIN_SHADOW_RES);
+    }
+    if self.level >= 3 {
+      o.bloom = false;
+    }
+    if self.level >= 4 {
+      o.shadow_res = (o.shadow_res / 2).max(MIN_SHADOW_RES);
+      o.shadow_cascades = o.shadow_cascades.min(2);
+    }
+    o
+  }
+}
+
+pub(crate) fn quality_update(world: &mut World) -> Result {
+  let q = match world.get_resource::<QualityScaling>() {
+    Some(q) if q.enabled => q,
+    _ => return Ok(()),
+  };
+  let ms = world.get_resource::<ScenePerf>().unwrap().gpu_time;
+  let average = q.average.map_or(ms, |a| a + (ms - a) * 0.1);
+  q.average = Some(average);
+  q.frames += 1;
+  if q.frames < INTERVAL {
+    return Ok(());
+  }
+  q.frames = 0;
+  let (scale, level) = (q.scale, q.level);
+  // given up in the opposite order they're restored
+  if average > q.target_ms {
+    match q.scale > q.min_scale {
+      true => q.scale = (q.scale - SCALE_STEP).max(q.min_scale),
+      false => q.level = (q.level + 1).min(LEVELS),
+    }
+  } else if average < q.target_ms * HEADROOM {
+    match q.level > 0 {
+      true => q.level -= 1,
+      false => q.scale = (q.scale + SCALE_STEP).min(1.0),
+    }
+  }
+  if (scale, level) != (q.scale, q.level) {
+    debug!(
+      "Quality scaled to {:.0}% resolution with {} effects off, gpu took {:.2}ms.",
+      q.scale * 100.0,
+      q.level,
+      average
+    );
+  }
+  Ok(())
+}
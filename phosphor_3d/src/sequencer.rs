@@ -0,0 +1,388 @@
+use std::fs::File;
+use std::path::Path;
+use phosphor::{Result, DeltaTime, asset, component, bincode};
+use phosphor::ecs::World;
+use phosphor::assets::Handle;
+use phosphor::math::{Vec3, Quat};
+use serde::{Serialize, Deserialize};
+use crate::{Transform, Camera, Curve, GameEvents, GameEvent};
+
+// one property of an entity over the timeline, curves are keyed in seconds
+#[derive(Serialize, Deserialize, Clone)]
+pub enum TrackKind {
+  Position(Curve<Vec3>),
+  Rotation(Curve<Quat>),
+  Scale(Curve<Vec3>),
+  Fov(Curve<f32>),
+  // the camera's depth of field focus distance
+  Focus(Curve<f32>),
+  // plays the target's audio source
+  Audio(Vec<f32>),
+  // named events for game code, also sent through GameEvents. the target is ignored
+  Event(Vec<(f32, String)>),
+  // one-shots by sound asset path, played where the target is, eg footsteps. after the others so
+  // saved sequences still load
+  Sound(Vec<(f32, String)>),
+}
+
+impl TrackKind {
+  pub const ALL: [Self; 8] = [
+    Self::Position(Curve { keys: Vec::new() }),
+    Self::Rotation(Curve { keys: Vec::new() }),
+    Self::Scale(Curve { keys: Vec::new() }),
+    Self::Fov(Curve { keys: Vec::new() }),
+    Self::Focus(Curve { keys: Vec::new() }),
+    Self::Audio(Vec::new()),
+    Self::Sound(Vec::new()),
+    Self::Event(Vec::new()),
+  ];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Position(_) => "Position",
+      Self::Rotation(_) => "Rotation",
+      Self::Scale(_) => "Scale",
+      Self::Fov(_) => "Fov",
+      Self::Focus(_) => "Focus",
+      Self::Audio(_) => "Audio",
+      Self::Event(_) => "Event",
+      Self::Sound(_) => "Sound",
+    }
+  }
+
+  pub fn times(&self) -> Vec<f32> {
+    match self {
+      Self::Position(c) | Self::Scale(c) => c.keys.iter().map(|k| k.0).collect(),
+      Self::Rotation(c) => c.keys.iter().map(|k| k.0).collect(),
+      Self::Fov(c) | Self::Focus(c) => c.keys.iter().map(|k| k.0).collect(),
+      Self::Audio(k) => k.clone(),
+      Self::Event(k) | Self::Sound(k) => k.iter().map(|k| k.0).collect(),
+    }
+  }
+
+  // keeps keys in order, so indices can change
+  pub fn move_key(&mut self, i: usize, time: f32) {
+    match self {
+      Self::Position(c) | Self::Scale(c) => move_key(&mut c.keys, i, time),
+      Self::Rotation(c) => move_key(&mut c.keys, i, time),
+      Self::Fov(c) | Self::Focus(c) => move_key(&mut c.keys, i, time),
+      Self::Audio(k) => {
+        k[i] = time;
+        k.sort_by(|a, b| a.total_cmp(b));
+      }
+      Self::Event(k) | Self::Sound(k) => move_key(k, i, time),
+    }
+  }
+
+  pub fn remove_key(&mut self, i: usize) {
+    match self {
+      Self::Position(c) | Self::Scale(c) => {
+        c.keys.remove(i);
+      }
+      Self::Rotation(c) => {
+        c.keys.remove(i);
+      }
+      Self::Fov(c) | Self::Focus(c) => {
+        c.keys.remove(i);
+      }
+      Self::Audio(k) => {
+        k.remove(i);
+      }
+      Self::Event(k) | Self::Sound(k) => {
+        k.remove(i);
+      }
+    }
+  }
+
+  // keys the target's current value at `time`, events get a placeholder name
+  pub fn record(&mut self, world: &World, target: &str, time: f32) {
+    let e = world.get_name(target);
+    let t = e.as_ref().and_then(|e| e.get_one::<Transform>());
+    let cam = e.as_ref().and_then(|e| e.get_one::<Camera>());
+    match self {
+      Self::Position(c) => {
+        if let Some(t) = t {
+          set_key(&mut c.keys, time, t.position);
+        }
+      }
+      Self::Rotation(c) => {
+        if let Some(t) = t {
+          set_key(&mut c.keys, time, t.rotation);
+        }
+      }
+      Self::Scale(c) => {
+        if let Some(t) = t {
+          set_key(&mut c.keys, time, t.scale);
+        }
+      }
+      Self::Fov(c) => {
+        if let Some(cam) = cam {
+          set_key(&mut c.keys, time, cam.fov);
+        }
+      }
+      Self::Focus(c) => {
+        if let Some(dof) = cam.and_then(|cam| cam.dof) {
+          set_key(&mut c.keys, time, dof.focus);
+        }
+      }
+      Self::Audio(k) => {
+        let i = k.partition_point(|k| *k <= time);
+        k.insert(i, time);
+      }
+      Self::Event(k) => set_key(k, time, "event".to_string()),
+      // the same sound as the last key, so repeated steps are quick to key
+      Self::Sound(k) => {
+        let sound = k.last().map_or(String::new(), |k| k.1.clone());
+        set_key(k, time, sound);
+      }
+    }
+  }
+
+  fn apply(&self, world: &World, target: &str, time: f32) {
+    let e = match world.get_name(target) {
+      Some(e) => e,
+      None => return,
+    };
+    let t = e.modify::<Transform>();
+    let cam = e.get_one::<Camera>();
+    match self {
+      Self::Position(c) if !c.keys.is_empty() => {
+        if let Some(t) = t {
+          t.position = c.sample(time);
+        }
+      }
+      Self::Rotation(c) if !c.keys.is_empty() => {
+        if let Some(t) = t {
+          t.rotation = c.sample(time);
+        }
+      }
+      Self::Scale(c) if !c.keys.is_empty() => {
+        if let Some(t) = t {
+          t.scale = c.sample(time);
+        }
+      }
+      Self::Fov(c) if !c.keys.is_empty() => {
+        if let Some(cam) = cam {
+          cam.fov = c.sample(time);
+        }
+      }
+      Self::Focus(c) if !c.keys.is_empty() => {
+        if let Some(dof) = cam.and_then(|cam| cam.dof.as_mut()) {
+          dof.focus = c.sample(time);
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+fn move_key<T>(keys: &mut Vec<(f32, T)>, i: usize, time: f32) {
+  keys[i].0 = time;
+  keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+}
+
+// replaces a key already at `time`
+fn set_key<T>(keys: &mut Vec<(f32, T)>, time: f32, v: T) {
+  match keys.iter_mut().find(|k| k.0 == time) {
+    Some(k) => k.1 = v,
+    None => {
+      let i = keys.partition_point(|k| k.0 <= time);
+      keys.insert(i, (time, v));
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Track {
+  // entities are found by name, so a sequence can be used across scenes
+  pub target: String,
+  pub kind: TrackKind,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum SequenceEvent {
+  // the named entity's audio source should play, phosphor_fmod does this
+  Audio(String),
+  // a sound asset to play once at the named entity, also phosphor_fmod
+  Sound(String, String),
+  Trigger(String),
+}
+
+// a cutscene, tracks animating entities on a shared timeline in seconds. saved with bincode
+#[asset(load_sequence)]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Sequence {
+  pub duration: f32,
+  pub tracks: Vec<Track>,
+}
+
+fn load_sequence(_: &mut World, path: &str) -> Result<Sequence> {
+  Ok(bincode::deserialize_from(File::open(path)?)?)
+}
+
+impl Sequence {
+  pub fn new(duration: f32) -> Self {
+    Self {
+      duration,
+      tracks: vec![],
+    }
+  }
+
+  pub fn track(mut self, target: &str, kind: TrackKind) -> Self {
+    self.tracks.push(Track {
+      target: target.to_string(),
+      kind,
+    });
+    self
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result {
+    bincode::serialize_into(File::create(path)?, self)?;
+    Ok(())
+  }
+
+  // poses every target at `time`
+  pub fn apply(&self, world: &World, time: f32) {
+    for track in &self.tracks {
+      track.kind.apply(world, &track.target, time);
+    }
+  }
+
+  // events keyed after `from` up to and including `to`
+  fn fire(&self, from: f32, to: f32, events: &mut Vec<SequenceEvent>) {
+    for track in &self.tracks {
+      match &track.kind {
+        TrackKind::Audio(keys) => events.extend(
+          keys
+            .iter()
+            .filter(|t| **t > from && **t <= to)
+            .map(|_| SequenceEvent::Audio(track.target.clone())),
+        ),
+        TrackKind::Event(keys) => events.extend(
+          keys
+            .iter()
+            .filter(|k| k.0 > from && k.0 <= to)
+            .map(|k| SequenceEvent::Trigger(k.1.clone())),
+        ),
+        TrackKind::Sound(keys) => events.extend(
+          keys
+            .iter()
+            .filter(|k| k.0 > from && k.0 <= to && !k.1.is_empty())
+            .map(|k| SequenceEvent::Sound(k.1.clone(), track.target.clone())),
+        ),
+        _ => {}
+      }
+    }
+  }
+}
+
+// plays a sequence with the game's time. targets are only posed while it's advancing or after a
+// seek, so they can still be moved around in the editor while it's paused
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct SequencePlayer {
+  pub sequence: Handle<Sequence>,
+  pub playing: bool,
+  pub looping: bool,
+  pub speed: f32,
+  #[serde(skip)]
+  time: f32,
+  // keys at the current time haven't fired yet
+  #[serde(skip)]
+  fresh: bool,
+  #[serde(skip)]
+  seeked: bool,
+  #[serde(skip)]
+  events: Vec<SequenceEvent>,
+}
+
+impl SequencePlayer {
+  pub fn new(sequence: Handle<Sequence>) -> Self {
+    Self {
+      sequence,
+      playing: true,
+      looping: false,
+      speed: 1.0,
+      time: 0.0,
+      fresh: true,
+      seeked: false,
+      events: vec![],
+    }
+  }
+
+  pub fn looping(mut self, looping: bool) -> Self {
+    self.looping = looping;
+    self
+  }
+
+  pub fn speed(mut self, speed: f32) -> Self {
+    self.speed = speed;
+    self
+  }
+
+  pub fn restart(&mut self) {
+    self.time = 0.0;
+    self.fresh = true;
+    self.seeked = true;
+  }
+
+  // jumps without firing anything in between
+  pub fn seek(&mut self, time: f32) {
+    self.time = time.clamp(0.0, self.sequence.duration);
+    self.fresh = false;
+    self.seeked = true;
+  }
+
+  pub fn time(&self) -> f32 {
+    self.time
+  }
+
+  pub fn finished(&self) -> bool {
+    !self.looping && self.time >= self.sequence.duration
+  }
+
+  // what the playhead passed over in its last update, kept until the next one
+  pub fn events(&self) -> &[SequenceEvent] {
+    &self.events
+  }
+}
+
+pub(crate) fn sequencer_update(world: &mut World) -> Result {
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
+  let game_events = world.get_resource::<GameEvents>().unwrap();
+  for (e, p) in world.query::<SequencePlayer>() {
+    p.events.clear();
+    let advancing = p.playing && dt > 0.0 && !p.finished();
+    if !advancing && !p.seeked {
+      continue;
+    }
+    let seq = p.sequence.clone();
+    if advancing {
+      let from = if p.fresh { -1.0 } else { p.time };
+      let duration = seq.duration.max(0.001);
+      let mut to = p.time + dt * p.speed.max(0.0);
+      if to >= duration && p.looping {
+        seq.fire(from, duration, &mut p.events);
+        to %= duration;
+        seq.fire(-1.0, to, &mut p.events);
+      } else {
+        to = to.min(duration);
+        seq.fire(from, to, &mut p.events);
+      }
+      p.time = to;
+      p.fresh = false;
+      for event in &p.events {
+        if let SequenceEvent::Trigger(name) = event {
+          game_events.send(GameEvent {
+            name: name.clone(),
+            source: e.id,
+            entity: None,
+          });
+        }
+      }
+    }
+    seq.apply(world, p.time);
+    p.seeked = false;
+  }
+  Ok(())
+}
@@ -0,0 +1,106 @@
+use phosphor::math::{Vec3, Mat4};
+use crate::{Camera, Transform, SceneRendererOptions};
+
+pub(crate) const MAX_CASCADES: usize = 4;
+// how far behind each cascade casters are still drawn
+const CASTER_DISTANCE: f32 = 50.0;
+
+pub(crate) struct Cascade {
+  pub matrix: Mat4,
+  // view distance this cascade covers up to
+  pub far: f32,
+  // depth bias of about a texel and a half in this cascade
+  pub bias: f32,
+}
+
+impl Cascade {
+  // the shadow map is split into a 2x2 atlas, one tile per cascade
+  pub fn res(options: &SceneRendererOptions) -> u32 {
+    options.shadow_res / 2
+  }
+
+  // bottom left of the cascade's tile in the atlas
+  pub fn viewport(i: usize, res: u32) -> (i32, i32) {
+    ((i % 2) as i32 * res as i32, (i / 2) as i32 * res as i32)
+  }
+}
+
+// blends logarithmic and even splits, log keeps detail close to the camera
+fn splits(near: f32, far: f32, n: usize, lambda: f32) -> Vec<f32> {
+  (1..=n)
+    .map(|i| {
+      let p = i as f32 / n as f32;
+      let log = near * (far / near).powf(p);
+      let even = near + (far - near) * p;
+      lambda * log + (1.0 - lambda) * even
+    })
+    .collect()
+}
+
+// each slice of the view is fit with a sphere so the cascade keeps its size as the camera turns,
+// and moved in whole texels so shadow edges don't shimmer as it moves
+pub(crate) fn cascades(
+  cam: &Camera,
+  cam_t: &Transform,
+  aspect: f32,
+  sun_dir: Vec3,
+  options: &SceneRendererOptions,
+) -> Vec<Cascade> {
+  let n = options.shadow_cascades.clamp(1, MAX_CASCADES);
+  let near = cam.clip[0].max(0.01);
+  let far = options.shadow_distance.clamp(near + 0.01, cam.clip[1]);
+  let forward = cam_t.rotation * Vec3::NEG_Z;
+  let right = forward.cross(Vec3::Y).normalize_or_zero();
+  let up = right.cross(forward);
+  let tan = (cam.fov.to_radians() / 2.0).tan();
+  let corners = |d: f32| {
+    let (h, w) = (d * tan, d * tan * aspect);
+    let center = cam_t.position + forward * d;
+    [
+      center + right * w + up * h,
+      center - right * w + up * h,
+      center + right * w - up * h,
+      center - right * w - up * h,
+    ]
+  };
+  let light_up = match sun_dir.normalize().dot(Vec3::Y).abs() > 0.99 {
+    true => Vec3::Z,
+    false => Vec3::Y,
+  };
+  let light_view = Mat4::look_to_rh(Vec3::ZERO, -sun_dir, light_up);
+
+  let mut last = near;
+  splits(near, far, n, options.shadow_split)
+    .into_iter()
+    .map(|split| {
+      let points = [corners(last), corners(split)].concat();
+      last = split;
+      let center = points.iter().sum::<Vec3>() / points.len() as f32;
+      let radius = points
+        .iter()
+        .map(|p| p.distance(center))
+        .fold(0.0, f32::max);
+      // rounded so float error doesn't change the texel size frame to frame
+      let radius = (radius * 16.0).ceil() / 16.0;
+      let texel = radius * 2.0 / Cascade::res(options) as f32;
+      let mut c = light_view.transform_point3(center);
+      c.x = (c.x / texel).floor() * texel;
+      c.y = (c.y / texel).floor() * texel;
+      let depth = radius * 2.0 + CASTER_DISTANCE;
+      let projection = Mat4::orthographic_rh(
+        c.x - radius,
+        c.x + radius,
+        c.y - radius,
+        c.y + radius,
+        -c.z - radius - CASTER_DISTANCE,
+        -c.z + radius,
+      );
+      Cascade {
+        matrix: projection * light_view,
+        far: split,
+        // depth is remapped to 0.5..1 in the shader
+        bias: texel * 1.5 * 0.5 / depth,
+      }
+    })
+    .collect()
+}
@@ -0,0 +1,322 @@
+use phosphor::{Result, DeltaTime};
+use phosphor::ecs::World;
+use phosphor::gfx::{Vertex, Mesh};
+use phosphor::math::{Vec3, Quat, Mat3, Mat4};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::Transform;
+
+// per segment, for measuring and drawing
+const SAMPLES: usize = 16;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum SplineKind {
+  CatmullRom,
+  Bezier,
+}
+
+impl SplineKind {
+  pub const ALL: [Self; 2] = [Self::CatmullRom, Self::Bezier];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::CatmullRom => "Catmull-Rom",
+      Self::Bezier => "Bezier",
+    }
+  }
+}
+
+// a curve through control points in the entity's space. catmull-rom passes through all of them,
+// bezier through every third with the two in between as handles. positions along it are given
+// as `t` from 0 to `segments()`
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Spline {
+  pub kind: SplineKind,
+  pub points: Vec<Vec3>,
+  // a closed bezier needs a multiple of 3 points, the last two are handles back to the first
+  pub closed: bool,
+}
+
+impl Spline {
+  pub fn new(kind: SplineKind) -> Self {
+    Self {
+      kind,
+      points: vec![],
+      closed: false,
+    }
+  }
+
+  pub fn point(mut self, point: Vec3) -> Self {
+    self.points.push(point);
+    self
+  }
+
+  pub fn closed(mut self, closed: bool) -> Self {
+    self.closed = closed;
+    self
+  }
+
+  pub fn segments(&self) -> usize {
+    let n = self.points.len();
+    match (self.kind, self.closed) {
+      _ if n < 2 => 0,
+      (SplineKind::CatmullRom, false) => n - 1,
+      (SplineKind::CatmullRom, true) => n,
+      (SplineKind::Bezier, false) => (n - 1) / 3,
+      (SplineKind::Bezier, true) => n / 3,
+    }
+  }
+
+  // appends an anchor, beziers get handles a third of the way from the last one
+  pub fn add_point(&mut self, point: Vec3) {
+    match (self.kind, self.points.last()) {
+      (SplineKind::Bezier, Some(&last)) => {
+        let step = (point - last) / 3.0;
+        self.points.extend([last + step, point - step, point]);
+      }
+      _ => self.points.push(point),
+    }
+  }
+
+  // bezier anchors take their handles with them, handles on their own can't be removed
+  pub fn remove_point(&mut self, i: usize) {
+    match self.kind {
+      SplineKind::CatmullRom => {
+        self.points.remove(i);
+      }
+      SplineKind::Bezier if i % 3 == 0 => {
+        let range = match i {
+          0 => 0..3.min(self.points.len()),
+          _ => i - 2..i + 1,
+        };
+        self.points.drain(range);
+      }
+      SplineKind::Bezier => {}
+    }
+  }
+
+  // whether the curve goes through the point rather than it being a handle
+  pub fn is_anchor(&self, i: usize) -> bool {
+    self.kind == SplineKind::CatmullRom || i % 3 == 0
+  }
+
+  // both kinds are evaluated as cubic beziers
+  fn segment(&self, i: usize) -> [Vec3; 4] {
+    let n = self.points.len();
+    match self.kind {
+      SplineKind::CatmullRom => {
+        let get = |j: isize| match self.closed {
+          true => self.points[j.rem_euclid(n as isize) as usize],
+          false => self.points[j.clamp(0, n as isize - 1) as usize],
+        };
+        let i = i as isize;
+        let [p0, p1, p2, p3] = [get(i - 1), get(i), get(i + 1), get(i + 2)];
+        [p1, p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0, p2]
+      }
+      SplineKind::Bezier => [0, 1, 2, 3].map(|j| self.points[(i * 3 + j) % n]),
+    }
+  }
+
+  fn locate(&self, t: f32) -> ([Vec3; 4], f32) {
+    let last = self
+      .segments()
+      .checked_sub(1)
+      .expect("Spline has no segments.");
+    let i = (t.max(0.0) as usize).min(last);
+    (self.segment(i), (t - i as f32).clamp(0.0, 1.0))
+  }
+
+  pub fn point_at(&self, t: f32) -> Vec3 {
+    let ([a, b, c, d], u) = self.locate(t);
+    let v = 1.0 - u;
+    a * v * v * v + b * 3.0 * v * v * u + c * 3.0 * v * u * u + d * u * u * u
+  }
+
+  // not normalized, it's longer where the curve moves faster
+  pub fn tangent_at(&self, t: f32) -> Vec3 {
+    let ([a, b, c, d], u) = self.locate(t);
+    let v = 1.0 - u;
+    (b - a) * 3.0 * v * v + (c - b) * 6.0 * v * u + (d - c) * 3.0 * u * u
+  }
+
+  // facing along the curve with -z forward like cameras, and y kept up where it can be
+  pub fn rotation_at(&self, t: f32) -> Quat {
+    let back = -self.tangent_at(t).normalize_or_zero();
+    let right = Vec3::Y.cross(back);
+    let right = match right.length_squared() > 1e-6 {
+      true => right.normalize(),
+      false => Vec3::X,
+    };
+    Quat::from_mat3(&Mat3::from_cols(right, back.cross(right), back))
+  }
+
+  // distance covered at each sample
+  fn lengths(&self) -> Vec<f32> {
+    let mut lengths = vec![0.0];
+    let mut prev = self.points[0];
+    for i in 1..=self.segments() * SAMPLES {
+      let p = self.point_at(i as f32 / SAMPLES as f32);
+      lengths.push(lengths[i - 1] + p.distance(prev));
+      prev = p;
+    }
+    lengths
+  }
+
+  pub fn length(&self) -> f32 {
+    match self.segments() {
+      0 => 0.0,
+      _ => *self.lengths().last().unwrap(),
+    }
+  }
+
+  // `t` the given distance along, so things can move along it at a constant speed
+  pub fn at_distance(&self, distance: f32) -> f32 {
+    distance_to_t(&self.lengths(), distance)
+  }
+
+  // SAMPLES points per segment
+  pub fn polyline(&self) -> Vec<Vec3> {
+    (0..=self.segments() * SAMPLES)
+      .map(|i| self.point_at(i as f32 / SAMPLES as f32))
+      .collect()
+  }
+
+  // transforms every `spacing` along it facing along the curve, for instances or props
+  pub fn scatter(&self, spacing: f32) -> Vec<Mat4> {
+    if self.segments() == 0 {
+      return vec![];
+    }
+    let lengths = self.lengths();
+    let length = *lengths.last().unwrap();
+    let count = (length / spacing.max(0.01)) as usize + 1;
+    (0..count)
+      .map(|i| {
+        let t = distance_to_t(&lengths, i as f32 * spacing.max(0.01));
+        Mat4::from_rotation_translation(self.rotation_at(t), self.point_at(t))
+      })
+      .collect()
+  }
+
+  // a flat strip `width` across facing up from the curve, like a road, with a pair of vertices
+  // every `spacing`. v runs along it in multiples of the width so textures tile
+  pub fn extrude(&self, width: f32, spacing: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    if self.segments() == 0 {
+      return (vertices, indices);
+    }
+    let lengths = self.lengths();
+    let length = *lengths.last().unwrap();
+    let steps = (length / spacing.max(0.01)).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+      let d = length * i as f32 / steps as f32;
+      let t = distance_to_t(&lengths, d);
+      let (p, rot) = (self.point_at(t), self.rotation_at(t));
+      let right = rot * Vec3::X * width / 2.0;
+      for (side, u) in [(-1.0, 0.0), (1.0, 1.0)] {
+        vertices.push(Vertex {
+          pos: (p + right * side).into(),
+          uv: [u, d / width.max(0.01)],
+          normal: (rot * Vec3::Y).into(),
+          ..Default::default()
+        });
+      }
+      if i > 0 {
+        let j = i as u32 * 2;
+        indices.extend([j - 2, j - 1, j, j - 1, j + 1, j]);
+      }
+    }
+    Mesh::compute_tangents(&mut vertices, &indices);
+    (vertices, indices)
+  }
+}
+
+fn distance_to_t(lengths: &[f32], distance: f32) -> f32 {
+  let last = lengths.len() - 1;
+  if last == 0 {
+    return 0.0;
+  }
+  let d = distance.clamp(0.0, lengths[last]);
+  let i = lengths.partition_point(|l| *l < d).clamp(1, last);
+  let span = lengths[i] - lengths[i - 1];
+  let frac = match span > 0.0 {
+    true => (d - lengths[i - 1]) / span,
+    false => 0.0,
+  };
+  (i as f32 - 1.0 + frac) / SAMPLES as f32
+}
+
+// moves the entity along a spline entity's curve, found by name, at a constant speed. its
+// transform is set in world space so it shouldn't have a parent
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct SplineFollower {
+  pub spline: String,
+  // units per second, negative goes backwards
+  pub speed: f32,
+  pub distance: f32,
+  // wraps around at the ends, otherwise it stops
+  pub looping: bool,
+  // faces along the curve
+  pub align: bool,
+}
+
+impl SplineFollower {
+  pub fn new(spline: &str) -> Self {
+    Self {
+      spline: spline.to_string(),
+      speed: 1.0,
+      distance: 0.0,
+      looping: false,
+      align: true,
+    }
+  }
+
+  pub fn speed(mut self, speed: f32) -> Self {
+    self.speed = speed;
+    self
+  }
+
+  pub fn looping(mut self, looping: bool) -> Self {
+    self.looping = looping;
+    self
+  }
+
+  pub fn align(mut self, align: bool) -> Self {
+    self.align = align;
+    self
+  }
+}
+
+pub(crate) fn spline_follow(world: &mut World) -> Result {
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
+  for (e, f) in world.query::<SplineFollower>() {
+    let path = match world.get_name(&f.spline) {
+      Some(p) => p,
+      None => continue,
+    };
+    let (spline, mat) = match (path.get_one::<Spline>(), path.get_one::<Transform>()) {
+      (Some(s), Some(t)) if s.segments() > 0 => (s, t.global_mat4(path)),
+      _ => continue,
+    };
+    let t = match e.modify::<Transform>() {
+      Some(t) => t,
+      None => continue,
+    };
+    let lengths = spline.lengths();
+    let length = *lengths.last().unwrap();
+    f.distance += f.speed * dt;
+    f.distance = match f.looping && length > 0.0 {
+      true => f.distance.rem_euclid(length),
+      false => f.distance.clamp(0.0, length),
+    };
+    let s = distance_to_t(&lengths, f.distance);
+    t.position = mat.transform_point3(spline.point_at(s));
+    if f.align {
+      let (_, rotation, _) = mat.to_scale_rotation_translation();
+      t.rotation = rotation * spline.rotation_at(s);
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,202 @@
+use std::mem;
+use phosphor::Result;
+use phosphor::ecs::World;
+use phosphor::math::{Vec3, Mat4};
+use phosphor::component;
+use serde::{Serialize, Deserialize};
+use crate::Transform;
+
+#[derive(Clone, PartialEq)]
+pub struct GameEvent {
+  pub name: String,
+  // the entity that sent it, eg a trigger volume or sequence player
+  pub source: usize,
+  // what set it off, eg the entity entering a trigger
+  pub entity: Option<usize>,
+}
+
+pub type EventHandler = fn(&mut World, &GameEvent) -> Result;
+
+// named gameplay events. events sent during a frame are handed out together at the start of the
+// next, to the handlers bound to their name and in `events` for systems to read
+pub struct GameEvents {
+  events: Vec<GameEvent>,
+  pending: Vec<GameEvent>,
+  handlers: Vec<(String, EventHandler)>,
+}
+
+impl GameEvents {
+  pub fn new() -> Self {
+    Self {
+      events: vec![],
+      pending: vec![],
+      handlers: vec![],
+    }
+  }
+
+  pub fn bind(&mut self, name: &str, handler: EventHandler) {
+    self.handlers.push((name.to_string(), handler));
+  }
+
+  pub fn send(&mut self, event: GameEvent) {
+    self.pending.push(event);
+  }
+
+  // this frame's
+  pub fn events(&self) -> &[GameEvent] {
+    &self.events
+  }
+
+  pub fn received(&self, name: &str) -> bool {
+    self.events.iter().any(|e| e.name == name)
+  }
+}
+
+pub(crate) fn game_events_dispatch(world: &mut World) -> Result {
+  let events = world.get_resource::<GameEvents>().unwrap();
+  events.events = mem::take(&mut events.pending);
+  let handlers = events.handlers.clone();
+  for event in events.events.clone() {
+    for (_, handler) in handlers.iter().filter(|h| h.0 == event.name) {
+      handler(world, &event)?;
+    }
+  }
+  Ok(())
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum TriggerShape {
+  // half extents
+  Box(Vec3),
+  Sphere(f32),
+}
+
+impl TriggerShape {
+  pub const ALL: [Self; 2] = [Self::Box(Vec3::splat(0.5)), Self::Sphere(0.5)];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Box(_) => "Box",
+      Self::Sphere(_) => "Sphere",
+    }
+  }
+}
+
+// sends events when the named entities' origins enter or leave it, in the entity's space so it
+// moves, turns and scales with it
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct TriggerVolume {
+  pub shape: TriggerShape,
+  pub targets: Vec<String>,
+  // event names, empty ones aren't sent
+  pub on_enter: String,
+  pub on_exit: String,
+  // stops after the first enter
+  pub once: bool,
+  #[serde(skip)]
+  inside: Vec<usize>,
+  #[serde(skip)]
+  done: bool,
+}
+
+impl TriggerVolume {
+  pub fn new(shape: TriggerShape) -> Self {
+    Self {
+      shape,
+      targets: vec![],
+      on_enter: String::new(),
+      on_exit: String::new(),
+      once: false,
+      inside: vec![],
+      done: false,
+    }
+  }
+
+  pub fn target(mut self, name: &str) -> Self {
+    self.targets.push(name.to_string());
+    self
+  }
+
+  pub fn on_enter(mut self, event: &str) -> Self {
+    self.on_enter = event.to_string();
+    self
+  }
+
+  pub fn on_exit(mut self, event: &str) -> Self {
+    self.on_exit = event.to_string();
+    self
+  }
+
+  pub fn once(mut self, once: bool) -> Self {
+    self.once = once;
+    self
+  }
+
+  // `volume` is the entity's world matrix
+  pub fn contains(&self, volume: Mat4, point: Vec3) -> bool {
+    let local = volume.inverse().transform_point3(point);
+    match self.shape {
+      TriggerShape::Box(half) => local.abs().cmple(half).all(),
+      TriggerShape::Sphere(radius) => local.length() <= radius,
+    }
+  }
+
+  // ids of the targets in it now
+  pub fn inside(&self) -> &[usize] {
+    &self.inside
+  }
+
+  pub fn reset(&mut self) {
+    self.inside.clear();
+    self.done = false;
+  }
+}
+
+pub(crate) fn trigger_update(world: &mut World) -> Result {
+  let events = world.get_resource::<GameEvents>().unwrap();
+  for (e, v) in world.query::<TriggerVolume>() {
+    if v.done {
+      continue;
+    }
+    let volume = match e.get_one::<Transform>() {
+      Some(t) => t.global_mat4(e),
+      None => continue,
+    };
+    for name in &v.targets {
+      let target = match world.get_name(name) {
+        Some(t) => t,
+        None => continue,
+      };
+      let pos = match target.get_one::<Transform>() {
+        Some(t) => t.global_mat4(target).w_axis.truncate(),
+        None => continue,
+      };
+      let send = |name: &str| GameEvent {
+        name: name.to_string(),
+        source: e.id,
+        entity: Some(target.id),
+      };
+      match (v.contains(volume, pos), v.inside.contains(&target.id)) {
+        (true, false) => {
+          v.inside.push(target.id);
+          if !v.on_enter.is_empty() {
+            events.send(send(&v.on_enter));
+          }
+          v.done = v.once;
+        }
+        (false, true) => {
+          v.inside.retain(|i| *i != target.id);
+          if !v.on_exit.is_empty() {
+            events.send(send(&v.on_exit));
+          }
+        }
+        _ => {}
+      }
+      if v.done {
+        break;
+      }
+    }
+  }
+  Ok(())
+}
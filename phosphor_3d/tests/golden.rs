@@ -0,0 +1,72 @@
+use std::env;
+use std::path::Path;
+use phosphor::{Engine, Result};
+use phosphor::ecs::{World, stage};
+use phosphor::assets::Assets;
+use phosphor::golden::GoldenTest;
+use phosphor::math::Vec3;
+use phosphor_3d::{Transform, Camera, Model, Material, Light, scenerenderer_plugin};
+
+// a lit cube and sphere on a floor, bless the reference again with PHOSPHOR_BLESS=1 after a change
+// that's meant to look different
+fn scene(world: &mut World) -> Result {
+  let assets = world.get_resource::<Assets>().unwrap();
+  world
+    .spawn("cam")
+    .insert(
+      Transform::new()
+        .pos(Vec3::new(0.0, 2.0, 6.0))
+        .rot_euler(0.0, -15.0, 0.0),
+    )
+    .insert(Camera::new(60.0, [0.1, 100.0]));
+  world
+    .spawn("floor")
+    .insert(Transform::new().scale(Vec3::new(10.0, 0.01, 10.0)))
+    .insert(Model::new(assets.load("cube.obj")?))
+    .insert(Material {
+      albedo: Vec3::splat(0.75),
+      ..Material::DEFAULT
+    });
+  world
+    .spawn("cube")
+    .insert(
+      Transform::new()
+        .pos(Vec3::new(-1.0, 0.5, 0.0))
+        .rot_euler(30.0, 0.0, 0.0),
+    )
+    .insert(Model::new(assets.load("cube.obj")?))
+    .insert(Material {
+      albedo: Vec3::new(0.8, 0.2, 0.2),
+      ..Material::DEFAULT
+    });
+  world
+    .spawn("sphere")
+    .insert(
+      Transform::new()
+        .pos(Vec3::new(1.0, 0.5, 0.0))
+        .scale(Vec3::splat(0.5)),
+    )
+    .insert(Model::new(assets.load("sphere.obj")?))
+    .insert(Material {
+      albedo: Vec3::new(0.2, 0.4, 0.8),
+      metallic: 0.5,
+      ..Material::DEFAULT
+    });
+  world
+    .spawn("light")
+    .insert(Transform::new().pos(Vec3::new(2.0, 3.0, 2.0)))
+    .insert(Light::new(Vec3::ONE).strength(10.0));
+  Ok(())
+}
+
+// glfw needs the main thread, so this runs without the test harness
+fn main() -> Result {
+  // assets are found relative to the workspace
+  env::set_current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(".."))?;
+  GoldenTest::new().check(
+    Engine::new()
+      .add_system(stage::INIT, scenerenderer_plugin)
+      .add_system(stage::INIT, scene),
+    "phosphor_3d/tests/golden/basic.png",
+  )
+}
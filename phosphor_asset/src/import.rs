@@ -0,0 +1,111 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use image::DynamicImage;
+use image::imageops::FilterType;
+use crate::{Result, model};
+
+pub struct Options {
+  // the game's assets folder, written files are referred to by their path in it
+  pub assets: PathBuf,
+  // textures bigger than this on either side are scaled down to fit
+  pub max_size: Option<u32>,
+}
+
+const TEXTURES: [&str; 7] = ["png", "jpg", "jpeg", "tga", "bmp", "gif", "webp"];
+const AUDIO: [&str; 5] = ["bank", "wav", "ogg", "flac", "mp3"];
+
+pub fn extension(path: &Path) -> String {
+  path
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default()
+}
+
+pub fn importable(path: &Path) -> bool {
+  let ext = extension(path);
+  ext == "gltf" || ext == "glb" || TEXTURES.contains(&ext.as_str()) || AUDIO.contains(&ext.as_str())
+}
+
+// runs the importer for the file's type, returning what it wrote to `out`
+pub fn import(path: &Path, out: &Path, options: &Options) -> Result<Vec<PathBuf>> {
+  fs::create_dir_all(out)?;
+  let ext = extension(path);
+  match ext.as_str() {
+    "gltf" | "glb" => model::import(path, out, options),
+    e if TEXTURES.contains(&e) => texture(path, out, options),
+    e if AUDIO.contains(&e) => audio(path, out),
+    _ => Err(format!("No importer for '.{}' files.", ext).into()),
+  }
+}
+
+// decoded to catch broken files and scaled down if needed, keeping the name and format so
+// references to it still work. its `.tex` options go with it
+fn texture(path: &Path, out: &Path, options: &Options) -> Result<Vec<PathBuf>> {
+  let img = image::open(path)?;
+  let dst = out.join(path.file_name().unwrap());
+  match options.max_size {
+    Some(max) if img.width() > max || img.height() > max => fit(img, max).save(&dst)?,
+    _ => copy(path, &dst)?,
+  }
+  let mut written = vec![dst];
+  let tex = PathBuf::from(format!("{}.tex", path.display()));
+  if tex.exists() {
+    let dst = out.join(tex.file_name().unwrap());
+    copy(&tex, &dst)?;
+    written.push(dst);
+  }
+  Ok(written)
+}
+
+// keeps the aspect ratio
+pub fn fit(img: DynamicImage, max: u32) -> DynamicImage {
+  match img.width() > max || img.height() > max {
+    true => img.resize(max, max, FilterType::Triangle),
+    false => img,
+  }
+}
+
+// checked against the format's magic number so a corrupt or misnamed file fails the build rather
+// than fmod at runtime, banks are riff containers
+fn audio(path: &Path, out: &Path) -> Result<Vec<PathBuf>> {
+  let mut header = [0; 4];
+  File::open(path)?.read_exact(&mut header)?;
+  let valid = match extension(path).as_str() {
+    "bank" | "wav" => &header == b"RIFF",
+    "ogg" => &header == b"OggS",
+    "flac" => &header == b"fLaC",
+    // an id3 tag or straight into a frame
+    _ => header.starts_with(b"ID3") || header[0] == 0xff,
+  };
+  if !valid {
+    return Err(format!("'{}' isn't a valid audio file.", path.display()).into());
+  }
+  let dst = out.join(path.file_name().unwrap());
+  copy(path, &dst)?;
+  Ok(vec![dst])
+}
+
+// importing in place would otherwise truncate the file
+pub fn copy(src: &Path, dst: &Path) -> Result {
+  if dst.exists() && fs::canonicalize(src)? == fs::canonicalize(dst)? {
+    return Ok(());
+  }
+  fs::copy(src, dst)?;
+  Ok(())
+}
+
+// what `Assets::load` takes to find the written file
+pub fn asset_name(path: &Path, options: &Options) -> String {
+  let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+  let assets = fs::canonicalize(&options.assets).unwrap_or(options.assets.clone());
+  match path.strip_prefix(&assets) {
+    Ok(name) => name
+      .components()
+      .map(|c| c.as_os_str().to_string_lossy())
+      .collect::<Vec<_>>()
+      .join("/"),
+    // outside the assets folder, it's assumed to be moved to the top of it
+    Err(_) => path.file_name().unwrap().to_string_lossy().to_string(),
+  }
+}
@@ -0,0 +1,79 @@
+// processes assets without the engine or a window, for build servers:
+//
+//   phosphor-asset import <files..> [--out dir]
+//   phosphor-asset pack <src> <dst>
+//
+// both take `--assets dir`, the folder asset names are relative to, and `--max-size px` to scale
+// textures down. imported files are written next to the originals unless given `--out`
+mod import;
+mod model;
+mod pack;
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use log::{LevelFilter, error, info};
+use crate::import::Options;
+
+pub type Result<T = ()> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+const USAGE: &str = "usage:
+  phosphor-asset import <files..> [--out dir] [--assets dir] [--max-size px]
+  phosphor-asset pack <src> <dst> [--assets dir] [--max-size px]";
+
+fn main() {
+  if let Err(e) = run() {
+    error!("{}", e);
+    process::exit(1);
+  }
+}
+
+fn run() -> Result {
+  ezlogger::init(LevelFilter::Info)?;
+  let mut args = env::args().skip(1);
+  let command = args.next().unwrap_or_default();
+  let mut paths = vec![];
+  let mut out = None;
+  let mut options = Options {
+    assets: PathBuf::from("assets"),
+    max_size: None,
+  };
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or(format!("Missing value for '{}'.", arg));
+    match arg.as_str() {
+      "-o" | "--out" => out = Some(PathBuf::from(value()?)),
+      "--assets" => options.assets = PathBuf::from(value()?),
+      "--max-size" => options.max_size = Some(value()?.parse()?),
+      _ => paths.push(PathBuf::from(arg)),
+    }
+  }
+
+  match (command.as_str(), paths.as_slice()) {
+    ("import", [_, ..]) => {
+      let mut failed = 0;
+      for path in &paths {
+        let dir = match &out {
+          Some(out) => out.clone(),
+          None => path.parent().unwrap_or(&PathBuf::new()).to_path_buf(),
+        };
+        match import::import(path, &dir, &options) {
+          Ok(written) => {
+            for file in written {
+              info!("Wrote '{}'.", file.display());
+            }
+          }
+          Err(e) => {
+            error!("Couldn't import '{}'. {}", path.display(), e);
+            failed += 1;
+          }
+        }
+      }
+      match failed {
+        0 => Ok(()),
+        n => Err(format!("{} of {} files failed to import.", n, paths.len()).into()),
+      }
+    }
+    ("pack", [src, dst]) => pack::pack(src, dst, &options),
+    _ => Err(USAGE.into()),
+  }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use gltf::image::{Data, Format};
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use image::{DynamicImage, GrayImage, GrayAlphaImage, RgbImage, RgbaImage, Luma};
+use log::warn;
+use crate::Result;
+use crate::import::{Options, asset_name, fit};
+
+// writes each triangle primitive as an obj, each material as a .mat and the images they use as
+// pngs, all prefixed with the file's name. meshes are left in their own space, node transforms
+// and the scene hierarchy aren't imported
+pub fn import(path: &Path, out: &Path, options: &Options) -> Result<Vec<PathBuf>> {
+  let (doc, buffers, images) = gltf::import(path)?;
+  let stem = path.file_stem().unwrap().to_string_lossy();
+  let mut written = vec![];
+
+  for mesh in doc.meshes() {
+    let name = format!("{}_{}", stem, clean(mesh.name(), mesh.index()));
+    let single = mesh.primitives().len() == 1;
+    for prim in mesh.primitives() {
+      if prim.mode() != Mode::Triangles {
+        warn!(
+          "Skipping {:?} in '{}', only triangles are imported.",
+          prim.mode(),
+          name
+        );
+        continue;
+      }
+      let reader = prim.reader(|b| Some(&buffers[b.index()].0[..]));
+      let positions = match reader.read_positions() {
+        Some(p) => p.collect::<Vec<_>>(),
+        None => continue,
+      };
+      let indices = match reader.read_indices() {
+        Some(i) => i.into_u32().collect(),
+        None => (0..positions.len() as u32).collect::<Vec<_>>(),
+      };
+      let uvs = match reader.read_tex_coords(0) {
+        Some(uv) => uv.into_f32().collect(),
+        None => vec![[0.0; 2]; positions.len()],
+      };
+      let normals = match reader.read_normals() {
+        Some(n) => n.collect(),
+        None => smooth_normals(&positions, &indices),
+      };
+      let file = match single {
+        true => out.join(format!("{}.obj", name)),
+        false => out.join(format!("{}_{}.obj", name, prim.index())),
+      };
+      write_obj(&file, &positions, &uvs, &normals, &indices)?;
+      written.push(file);
+    }
+  }
+
+  // images by index and the channel taken from them, converted when a material first uses them
+  let mut textures = HashMap::new();
+  for mat in doc.materials() {
+    let pbr = mat.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    let [er, eg, eb] = mat.emissive_factor();
+    let mut s = String::new();
+    writeln!(s, "albedo = [{}, {}, {}]", r, g, b)?;
+    writeln!(s, "metallic = {:?}", pbr.metallic_factor())?;
+    writeln!(s, "roughness = {:?}", pbr.roughness_factor())?;
+    writeln!(s, "alpha = {:?}", a)?;
+    writeln!(s, "emissive = {:?}", er.max(eg).max(eb))?;
+    writeln!(s, "transparent = {}", mat.alpha_mode() == AlphaMode::Blend)?;
+
+    // the engine samples single channel maps from red, glTF packs roughness in green and
+    // metallic in blue
+    let mut maps = vec![];
+    if let Some(t) = pbr.base_color_texture() {
+      maps.push(("albedo_tex", t.texture().source().index(), None));
+    }
+    if let Some(t) = pbr.metallic_roughness_texture() {
+      let i = t.texture().source().index();
+      maps.extend([("metallic_tex", i, Some(2)), ("roughness_tex", i, Some(1))]);
+    }
+    if let Some(t) = mat.normal_texture() {
+      maps.push(("normal_tex", t.texture().source().index(), None));
+    }
+    if let Some(t) = mat.occlusion_texture() {
+      maps.push(("ao_tex", t.texture().source().index(), None));
+    }
+    for (key, i, channel) in maps {
+      if !textures.contains_key(&(i, channel)) {
+        let name = doc.images().nth(i).and_then(|img| img.name());
+        let file = match channel {
+          Some(c) => format!("{}_{}_{}.png", stem, clean(name, i), ["r", "g", "b"][c]),
+          None => format!("{}_{}.png", stem, clean(name, i)),
+        };
+        let file = out.join(file);
+        let mut img = to_image(&images[i])?;
+        if let Some(c) = channel {
+          let rgb = img.to_rgb8();
+          img = DynamicImage::ImageLuma8(GrayImage::from_fn(img.width(), img.height(), |x, y| {
+            Luma([rgb.get_pixel(x, y)[c]])
+          }));
+        }
+        if let Some(max) = options.max_size {
+          img = fit(img, max);
+        }
+        img.save(&file)?;
+        textures.insert((i, channel), asset_name(&file, options));
+        written.push(file);
+      }
+      writeln!(s, "{} = \"{}\"", key, textures[&(i, channel)])?;
+    }
+
+    let file = out.join(format!(
+      "{}_{}.mat",
+      stem,
+      clean(mat.name(), mat.index().unwrap_or(0))
+    ));
+    fs::write(&file, s)?;
+    written.push(file);
+  }
+  Ok(written)
+}
+
+// names can be anything, falls back to the index
+fn clean(name: Option<&str>, index: usize) -> String {
+  match name {
+    Some(n) if !n.is_empty() => n
+      .chars()
+      .map(|c| match c.is_alphanumeric() || c == '-' {
+        true => c,
+        false => '_',
+      })
+      .collect(),
+    _ => index.to_string(),
+  }
+}
+
+fn to_image(data: &Data) -> Result<DynamicImage> {
+  let (w, h, pixels) = (data.width, data.height, data.pixels.clone());
+  let img = match data.format {
+    Format::R8 => GrayImage::from_raw(w, h, pixels).map(DynamicImage::ImageLuma8),
+    Format::R8G8 => GrayAlphaImage::from_raw(w, h, pixels).map(DynamicImage::ImageLumaA8),
+    Format::R8G8B8 => RgbImage::from_raw(w, h, pixels).map(DynamicImage::ImageRgb8),
+    Format::R8G8B8A8 => RgbaImage::from_raw(w, h, pixels).map(DynamicImage::ImageRgba8),
+    f => return Err(format!("{:?} images aren't supported.", f).into()),
+  };
+  img.ok_or_else(|| "Invalid image data.".into())
+}
+
+// area weighted, for meshes exported without normals
+fn smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+  let mut normals = vec![[0.0; 3]; positions.len()];
+  for tri in indices.chunks_exact(3) {
+    let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| positions[i as usize]);
+    let (u, v) = (sub(b, a), sub(c, a));
+    let n = [
+      u[1] * v[2] - u[2] * v[1],
+      u[2] * v[0] - u[0] * v[2],
+      u[0] * v[1] - u[1] * v[0],
+    ];
+    for i in tri {
+      for k in 0..3 {
+        normals[*i as usize][k] += n[k];
+      }
+    }
+  }
+  for n in &mut normals {
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt().max(1e-6);
+    *n = n.map(|x| x / len);
+  }
+  normals
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+  [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// glTF's uvs start at the top, objs' at the bottom
+fn write_obj(
+  path: &Path,
+  positions: &[[f32; 3]],
+  uvs: &[[f32; 2]],
+  normals: &[[f32; 3]],
+  indices: &[u32],
+) -> Result {
+  let mut obj = String::new();
+  for ((p, uv), n) in positions.iter().zip(uvs).zip(normals) {
+    writeln!(obj, "v {} {} {}", p[0], p[1], p[2])?;
+    writeln!(obj, "vt {} {}", uv[0], 1.0 - uv[1])?;
+    writeln!(obj, "vn {} {} {}", n[0], n[1], n[2])?;
+  }
+  for tri in indices.chunks_exact(3) {
+    let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| i + 1);
+    writeln!(obj, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+  }
+  fs::write(path, obj)?;
+  Ok(())
+}
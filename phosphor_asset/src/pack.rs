@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::Path;
+use log::{error, info};
+use crate::Result;
+use crate::import::{self, Options};
+
+// editor and authoring files that the game never loads
+const SOURCE_ONLY: [&str; 6] = ["blend", "blend1", "psd", "xcf", "kra", "fspro"];
+
+// builds a shippable copy of an assets folder, running importers over what they handle and
+// copying the rest. files whose output is newer than them are skipped, so repeat packs are quick
+pub fn pack(src: &Path, dst: &Path, options: &Options) -> Result {
+  let options = Options {
+    assets: dst.to_path_buf(),
+    max_size: options.max_size,
+  };
+  let (mut done, mut failed) = (0, 0);
+  walk(src, dst, &options, &mut done, &mut failed)?;
+  info!("Packed {} files into '{}'.", done, dst.display());
+  match failed {
+    0 => Ok(()),
+    n => Err(format!("{} files failed to pack.", n).into()),
+  }
+}
+
+fn walk(src: &Path, dst: &Path, options: &Options, done: &mut usize, failed: &mut usize) -> Result {
+  fs::create_dir_all(dst)?;
+  for entry in fs::read_dir(src)? {
+    let path = entry?.path();
+    let out = dst.join(path.file_name().unwrap());
+    if path.is_dir() {
+      walk(&path, &out, options, done, failed)?;
+      continue;
+    }
+    let ext = import::extension(&path);
+    // a .gltf's buffers are read when it's imported
+    let buffers = ext == "bin" && path.with_extension("gltf").exists();
+    if SOURCE_ONLY.contains(&ext.as_str()) || buffers || !stale(&path, &out) {
+      continue;
+    }
+    let result = match import::importable(&path) {
+      true => import::import(&path, dst, options).map(|_| ()),
+      false => import::copy(&path, &out),
+    };
+    match result {
+      Ok(()) => *done += 1,
+      Err(e) => {
+        error!("Couldn't pack '{}'. {}", path.display(), e);
+        *failed += 1;
+      }
+    }
+  }
+  Ok(())
+}
+
+// models are split into other files, so they're always imported again
+fn stale(src: &Path, out: &Path) -> bool {
+  let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified());
+  match (modified(src), modified(out)) {
+    (Ok(src), Ok(out)) => src > out,
+    _ => true,
+  }
+}
@@ -36,8 +36,8 @@ pub fn component(_: TokenStream, input: TokenStream) -> TokenStream {
         #phosphor::bincode::serialize(&data.downcast_ref::<#ident>().unwrap()).unwrap()
       }
       #[allow(non_snake_case)]
-      fn #load(data: Vec<u8>, _: &mut #phosphor::assets::Assets) -> Box<dyn std::any::Any> {
-        Box::new(#phosphor::bincode::deserialize::<#ident>(&data).unwrap())
+      fn #load(data: Vec<u8>, _: &mut #phosphor::assets::Assets) -> #phosphor::Result<Box<dyn std::any::Any>> {
+        Ok(Box::new(#phosphor::bincode::deserialize::<#ident>(&data)?))
       }
       #[allow(non_upper_case_globals)]
       #[#phosphor::linkme::distributed_slice(#phosphor::scene::COMPONENT_LOADERS)]
@@ -51,25 +51,45 @@ pub fn component(_: TokenStream, input: TokenStream) -> TokenStream {
   })
 }
 
+// #[asset(load)] or #[asset(load, unload)], unload frees what the asset holds outside of rust
+// when nothing uses it anymore, eg gpu objects
 #[proc_macro_attribute]
 pub fn asset(args: TokenStream, input: TokenStream) -> TokenStream {
   let args = parse_macro_input!(args as AttributeArgs);
-  enum_struct(input, |input, ident| match args.first() {
-    Some(NestedMeta::Meta(Meta::Path(p))) => {
+  enum_struct(input, |input, ident| match (args.first(), args.get(1)) {
+    (Some(NestedMeta::Meta(Meta::Path(p))), unload) => {
       let phosphor = get_crate();
       let func = p.get_ident().unwrap();
       let new_func = format_ident!("_{}", func);
       let var = format_ident!("{}_LOADER", ident);
+      let (unloader, unload_func) = match unload {
+        Some(NestedMeta::Meta(Meta::Path(p))) => {
+          let func = p.get_ident().unwrap();
+          let new_func = format_ident!("_{}", func);
+          (
+            quote! {Some(#new_func)},
+            quote! {
+              fn #new_func(world: &mut World, data: &dyn std::any::Any) {
+                #func(world, data.downcast_ref::<#ident>().unwrap())
+              }
+            },
+          )
+        }
+        None => (quote! {None}, quote! {}),
+        _ => return quote! {compile_error!("invalid syntax.")},
+      };
       quote! {
         fn #new_func(world: &mut World, path: &str) -> #phosphor::Result<std::rc::Rc<dyn std::any::Any>> {
           Ok(std::rc::Rc::new(#func(world, path)?))
         }
+        #unload_func
 
         #[allow(non_upper_case_globals)]
         #[#phosphor::linkme::distributed_slice(#phosphor::assets::ASSET_LOADERS)]
         static #var: #phosphor::assets::AssetLoader = #phosphor::assets::AssetLoader {
           id: #phosphor::TypeIdNamed::of::<#ident>(),
           loader: #new_func,
+          unloader: #unloader,
         };
         #input
       }
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::{env, fs};
+use phosphor::Result;
+use phosphor_imgui::glyphs;
+
+pub struct LocaleInfo {
+  pub code: &'static str,
+  pub name: &'static str,
+  pub fonts: &'static [(&'static str, &'static [u32])],
+}
+
+pub const LOCALES: &[LocaleInfo] = &[
+  LocaleInfo {
+    code: "en",
+    name: "English",
+    fonts: &[],
+  },
+  LocaleInfo {
+    code: "ru",
+    name: "Русский",
+    // cyrillic is always merged from roboto
+    fonts: &[],
+  },
+  LocaleInfo {
+    code: "ja",
+    name: "日本語",
+    // the large title font, which also covers kana and kanji
+    fonts: &[("assets/fonts/shingo.otf", glyphs::JAPANESE)],
+  },
+];
+
+pub struct Locale {
+  pub info: &'static LocaleInfo,
+  strings: HashMap<String, String>,
+}
+
+impl Locale {
+  // PHOSPHOR_LOCALE, then LANG (eg ru_RU.UTF-8), then english
+  pub fn detect() -> &'static LocaleInfo {
+    let var = env::var("PHOSPHOR_LOCALE")
+      .or_else(|_| env::var("LANG"))
+      .unwrap_or_default();
+    LOCALES
+      .iter()
+      .find(|l| var.starts_with(l.code))
+      .unwrap_or(&LOCALES[0])
+  }
+
+  // assets/locale/<code>.txt holds `english = translated` lines
+  pub fn load(info: &'static LocaleInfo) -> Result<Self> {
+    let mut strings = HashMap::new();
+    if info.code != "en" {
+      for line in fs::read_to_string(format!("assets/locale/{}.txt", info.code))?.lines() {
+        if let Some((k, v)) = line.split_once(" = ") {
+          strings.insert(k.trim().to_string(), v.trim().to_string());
+        }
+      }
+    }
+    Ok(Self { info, strings })
+  }
+
+  pub fn tr(&self, s: &str) -> String {
+    // keep icons in front of the translated text
+    let (icon, text) = match s.split_once(' ') {
+      Some((i, t)) if !i.chars().any(char::is_alphanumeric) => (i, t),
+      _ => ("", s),
+    };
+    let text = self.strings.get(text).map_or(text, |t| t.as_str());
+    match icon {
+      "" => text.to_string(),
+      _ => format!("{} {}", icon, text),
+    }
+  }
+}
@@ -1,22 +1,36 @@
 #![feature(is_some_and)]
 #![allow(clippy::redundant_pattern_matching)]
 mod panels;
+mod locale;
+mod project;
 
 use std::fs;
-use phosphor::{Engine, Result};
+use std::path::{Path, PathBuf};
+use phosphor::{Engine, Result, Time};
 use phosphor::ecs::{World, Entity, stage};
 use phosphor::scene::Scene;
-use phosphor::log::{LevelFilter, error};
-use phosphor::glfw::{WindowEvent, Key, Modifiers};
-use phosphor_imgui::{imgui_plugin, UiRendererOptions};
-use phosphor_imgui::imgui::{Ui, StyleStackToken, Context};
-use phosphor_fmod::{FmodOptions, fmod_plugin};
+use phosphor::log::{LevelFilter, error, warn, info};
+use phosphor::gfx::{Renderer, Mesh, Texture};
+use phosphor::assets::Assets;
+use phosphor::input::FileDrop;
+use phosphor::glfw::{WindowEvent, Key, Action, Modifiers};
+use phosphor_imgui::{imgui_plugin, console_plugin, hover_tooltip, glyphs, UiRendererOptions};
+use phosphor_imgui::access::{Role, access_plugin, label};
+use phosphor_imgui::imgui::{Ui, StyleStackToken, Context, WindowHoveredFlags, WindowFocusedFlags};
+use phosphor_fmod::FmodOptions;
 use rfd::FileDialog;
-use crate::panels::{Panel, setup_panels};
+use crate::panels::{Panel, PanelFocus, setup_panels, play};
+use crate::locale::{Locale, LOCALES};
+use crate::project::{Project, RecentProjects, LoadedPlugins, launcher, open_path};
 
 pub struct SelectedEntity(Option<Entity>);
 pub struct SceneName(String);
 struct Layout(String);
+// asked for from the menu, taken a frame later so the menu isn't in it
+struct Screenshot {
+  path: PathBuf,
+  waited: bool,
+}
 
 const VER: &str = concat!(
   "\u{f5d3} ",
@@ -27,6 +41,16 @@ const VER: &str = concat!(
 
 fn main() -> Result {
   ezlogger::init(LevelFilter::Debug)?;
+  let locale = Locale::load(Locale::detect()).unwrap_or_else(|e| {
+    warn!("Couldn't load locale, falling back to English. {}", e);
+    Locale::load(&LOCALES[0]).unwrap()
+  });
+  // roboto covers cyrillic and greek, so names in those always render
+  let mut fallback = vec![
+    ("assets/fonts/roboto.ttf", glyphs::CYRILLIC),
+    ("assets/fonts/roboto.ttf", glyphs::GREEK),
+  ];
+  fallback.extend_from_slice(locale.info.fonts);
   Engine::new()
     .add_resource(UiRendererOptions {
       docking: true,
@@ -48,19 +72,26 @@ fn main() -> Result {
           ),
         ],
       ],
+      fallback: Box::leak(fallback.into_boxed_slice()),
     })
+    .add_resource(locale)
     .add_resource(FmodOptions {
       play_on_start: false,
     })
     .add_resource(SelectedEntity(None))
     .add_resource(SceneName("".to_string()))
     .add_resource(Layout("Default.ini".to_string()))
+    .add_resource(RecentProjects::load())
+    .add_resource(LoadedPlugins(vec![]))
     .add_system(stage::INIT, imgui_plugin)
-    .add_system(stage::INIT, fmod_plugin)
+    .add_system(stage::INIT, access_plugin)
+    .add_system(stage::INIT, console_plugin)
     .add_system(stage::INIT, setup_panels)
     .add_system(stage::DRAW, draw_ui)
     .add_system(stage::POST_DRAW, layout_change)
+    .add_system(stage::POST_DRAW, take_screenshot)
     .add_system(stage::EVENT, shortcut_handler)
+    .add_system(stage::PRE_DRAW, file_drop)
     .run()
 }
 
@@ -78,27 +109,58 @@ fn layout_change(world: &mut World) -> Result {
 
 fn draw_ui(world: &mut World) -> Result {
   let ui = world.get_resource::<Ui>().unwrap();
+  if world.get_resource::<Project>().is_none() {
+    launcher(mutate(world), ui);
+    return Ok(());
+  }
   let panels = world.get_resource::<Vec<Panel>>().unwrap();
   let scene_name = world.get_resource::<SceneName>().unwrap().0.clone();
+  let locale = world.get_resource::<Locale>().unwrap();
   ui.main_menu_bar(|| {
-    ui.menu("File", || {
-      if ui.menu_item_config("Save").shortcut(shortcut("S")).build() {
-        save(mutate(world));
+    ui.menu(locale.tr("File"), || {
+      let clicked = ui
+        .menu_item_config(locale.tr("Save"))
+        .shortcut(shortcut("S"))
+        .build();
+      label(world, Role::MenuItem, &locale.tr("Save"));
+      if clicked {
+        world.commands().add(save);
       }
-      if ui.menu_item_config("Open").shortcut(shortcut("O")).build() {
-        load(mutate(world));
+      let clicked = ui
+        .menu_item_config(locale.tr("Open"))
+        .shortcut(shortcut("O"))
+        .build();
+      label(world, Role::MenuItem, &locale.tr("Open"));
+      if clicked {
+        world.commands().add(load);
+      }
+      let clicked = ui.menu_item(locale.tr("Screenshot"));
+      label(world, Role::MenuItem, &locale.tr("Screenshot"));
+      if clicked {
+        world.commands().add(screenshot);
+      }
+      ui.separator();
+      let clicked = ui.menu_item(locale.tr("Close Project"));
+      label(world, Role::MenuItem, &locale.tr("Close Project"));
+      if clicked {
+        world.commands().add(|world| {
+          world.take_resource::<Project>();
+        });
       }
     });
-    ui.menu("Windows", || {
+    ui.menu(locale.tr("Windows"), || {
       for panel in panels.iter_mut() {
-        ui.menu_item_config(panel.title)
+        ui.menu_item_config(locale.tr(panel.title))
           .build_with_ref(&mut panel.open);
+        label(world, Role::MenuItem, &locale.tr(panel.title));
       }
     });
-    ui.menu("Layout", || {
+    ui.menu(locale.tr("Layout"), || {
       for p in fs::read_dir("phosphor_editor/layouts").unwrap() {
         let name = p.unwrap().file_name().into_string().unwrap();
-        if ui.menu_item(name.clone()) {
+        let clicked = ui.menu_item(name.clone());
+        label(world, Role::MenuItem, &name);
+        if clicked {
           world.add_resource(Layout(name));
         }
       }
@@ -108,6 +170,7 @@ fn draw_ui(world: &mut World) -> Result {
       //   fs::write("layout", s).unwrap();
       // }
     });
+    time_controls(mutate(world), ui);
     let [w, _] = ui.window_size();
     let [tx, _] = ui.calc_text_size(scene_name.clone());
     ui.same_line_with_pos((w - tx) / 2.0);
@@ -116,34 +179,84 @@ fn draw_ui(world: &mut World) -> Result {
     ui.same_line_with_pos(w - tx - 16.0);
     ui.text_disabled(VER);
   });
+  let mut focus = PanelFocus {
+    typing: ui.io().want_text_input,
+    ..PanelFocus::NONE
+  };
   for panel in panels {
     if panel.open {
       let tokens: Vec<StyleStackToken> = panel.vars.iter().map(|v| ui.push_style_var(*v)).collect();
-      ui.window(panel.title)
+      // keep the english title as the window id so layouts still apply
+      let title = match locale.info.code {
+        "en" => panel.title.to_string(),
+        _ => format!("{}###{}", locale.tr(panel.title), panel.title),
+      };
+      ui.window(title)
         .flags(panel.flags)
         .opened(&mut panel.open)
         .build(|| {
           (panel.render)(mutate(world), ui);
+          if ui.is_window_hovered_with_flags(WindowHoveredFlags::CHILD_WINDOWS) {
+            focus.hovered = Some(panel.title);
+          }
+          if ui.is_window_focused_with_flags(WindowFocusedFlags::CHILD_WINDOWS) {
+            focus.focused = Some(panel.title);
+          }
         });
       for token in tokens {
         token.pop();
       }
     }
   }
+  world.add_resource(focus);
   Ok(())
 }
 
+fn time_controls(world: &mut World, ui: &Ui) {
+  let time = world.get_resource::<Time>().unwrap();
+  let locale = world.get_resource::<Locale>().unwrap();
+  ui.separator();
+  // play mode restores the scene when it's stopped, pausing keeps the changes
+  let playing = play::is_playing(world);
+  if ui.small_button(if playing { "\u{f04d}" } else { "\u{f144}" }) {
+    world.commands().add(match playing {
+      true => play::stop,
+      false => play::start,
+    });
+  }
+  hover_tooltip(ui, &locale.tr(if playing { "Stop" } else { "Play" }));
+  if ui.small_button(if time.paused { "\u{f04b}" } else { "\u{f04c}" }) {
+    time.paused = !time.paused;
+  }
+  hover_tooltip(ui, &locale.tr(if time.paused { "Resume" } else { "Pause" }));
+  ui.disabled(!time.paused, || {
+    if ui.small_button("\u{f051}") {
+      time.step();
+    }
+  });
+  hover_tooltip(ui, &locale.tr("Step"));
+  ui.set_next_item_width(96.0);
+  ui.slider_config("##timescale", 0.0, 4.0)
+    .display_format("%.2fx")
+    .build(&mut time.scale);
+  hover_tooltip(ui, &locale.tr("Time scale"));
+}
+
 fn shortcut_handler(world: &mut World) -> Result {
   const M: Modifiers = if cfg!(target_os = "macos") {
     Modifiers::Super
   } else {
     Modifiers::Control
   };
+  if world.get_resource::<PanelFocus>().unwrap().typing || world.get_resource::<Project>().is_none()
+  {
+    return Ok(());
+  }
   match world.get_resource::<WindowEvent>().unwrap() {
-    WindowEvent::Key(Key::S, _, _, M) => {
+    WindowEvent::Key(Key::S, _, Action::Press, M) => {
       save(world);
     }
-    WindowEvent::Key(Key::O, _, _, M) => {
+    WindowEvent::Key(Key::O, _, Action::Press, M) => {
       load(world);
     }
     _ => {}
@@ -152,19 +265,133 @@ fn shortcut_handler(world: &mut World) -> Result {
 }
 
 fn save(world: &mut World) {
-  if let Some(p) = FileDialog::new().set_file_name("test.scene").save_file() {
+  let dir = world.get_resource::<Project>().unwrap().dir();
+  if let Some(p) = FileDialog::new()
+    .set_directory(dir)
+    .set_file_name("main.scene")
+    .save_file()
+  {
     Scene::save(world, p).unwrap();
   }
 }
 
 fn load(world: &mut World) {
-  if let Some(p) = FileDialog::new().pick_file() {
-    world.add_resource(SceneName(p.display().to_string()));
-    world.add_resource(SelectedEntity(None));
-    if let Err(e) = Scene::load(world, p.clone()) {
-      error!("Couldnt load '{}'. {}", p.display(), e);
+  let dir = world.get_resource::<Project>().unwrap().dir();
+  if let Some(p) = FileDialog::new().set_directory(dir).pick_file() {
+    open_scene(world, p);
+  };
+}
+
+fn open_scene(world: &mut World, p: PathBuf) {
+  play::reset(world);
+  world.add_resource(SceneName(p.display().to_string()));
+  world.add_resource(SelectedEntity(None));
+  if let Err(e) = Scene::load(world, p.clone()) {
+    error!("Couldnt load '{}'. {}", p.display(), e);
+  }
+}
+
+// projects dropped on the window open, scenes load and anything else is imported into the asset
+// root
+fn file_drop(world: &mut World) -> Result {
+  let paths = match world.get_resource::<FileDrop>() {
+    Some(drop) => drop.paths.clone(),
+    None => return Ok(()),
+  };
+  for path in paths {
+    match path.extension().and_then(|e| e.to_str()) {
+      Some("project") => open_path(world, &path),
+      _ if world.get_resource::<Project>().is_none() => {
+        warn!("Open a project before importing '{}'.", path.display())
+      }
+      Some("scene") => open_scene(world, path),
+      _ => {
+        if let Err(e) = import(world, &path) {
+          error!("Couldn't import '{}'. {}", path.display(), e);
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+// copied into the asset root unless it's already there, meshes and textures are loaded so they
+// show up in the assets panel
+fn import(world: &mut World, path: &Path) -> Result {
+  let project = world.get_resource::<Project>().unwrap();
+  let root = project.dir().join(&project.asset_root).canonicalize()?;
+  let path = path.canonicalize()?;
+  let name = match path.strip_prefix(&root) {
+    Ok(name) => name.to_path_buf(),
+    Err(_) => {
+      let name = PathBuf::from(path.file_name().ok_or("Not a file.")?);
+      if root.join(&name).exists() {
+        return Err(format!("'{}' is already in the asset root.", name.display()).into());
+      }
+      fs::copy(&path, root.join(&name))?;
+      // a texture's sampling options go with it
+      let tex = PathBuf::from(format!("{}.tex", path.display()));
+      if tex.exists() {
+        fs::copy(&tex, root.join(format!("{}.tex", name.display())))?;
+      }
+      name
     }
   };
+  let name = name
+    .components()
+    .map(|c| c.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/");
+  let assets = world.get_resource::<Assets>().unwrap();
+  let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+  // kept loaded so they show up in asset pickers until something uses them
+  match ext.as_deref() {
+    Some("obj") => {
+      let h = assets.load::<Mesh>(&name)?;
+      assets.keep(h);
+    }
+    Some("png" | "jpg" | "jpeg" | "tga" | "bmp" | "dds" | "ktx2") => {
+      let h = assets.load::<Texture>(&name)?;
+      assets.keep(h);
+    }
+    _ => {}
+  }
+  info!("Imported '{}'.", name);
+  Ok(())
+}
+
+fn screenshot(world: &mut World) {
+  let dir = world.get_resource::<Project>().unwrap().dir();
+  if let Some(path) = FileDialog::new()
+    .set_directory(dir)
+    .set_file_name("screenshot.png")
+    .add_filter("PNG", &["png"])
+    .save_file()
+  {
+    world.add_resource(Screenshot {
+      path,
+      waited: false,
+    });
+  }
+}
+
+// after imgui_draw so the whole editor is in the shot
+fn take_screenshot(world: &mut World) -> Result {
+  if let Some(shot) = world.get_resource::<Screenshot>() {
+    if !shot.waited {
+      shot.waited = true;
+      return Ok(());
+    }
+    let shot = world.take_resource::<Screenshot>().unwrap();
+    if let Err(e) = world
+      .get_resource::<Renderer>()
+      .unwrap()
+      .screenshot(&shot.path)
+    {
+      error!("Couldn't save '{}'. {}", shot.path.display(), e);
+    }
+  }
+  Ok(())
 }
 
 fn shortcut(s: &str) -> String {
@@ -177,7 +404,8 @@ fn shortcut(s: &str) -> String {
     + s
 }
 
-// not very safe, use refcell or something
+// not very safe, changes that don't need to happen straight away should go through
+// `world.commands()` instead
 pub fn mutate<T>(t: &T) -> &mut T {
   unsafe { &mut *(t as *const T as *mut T) }
 }
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+use phosphor::ecs::World;
+use phosphor::gfx::{Texture, gl};
+use phosphor::assets::Assets;
+use phosphor::streaming::{TextureStreamer, texture_budget_mb};
+use phosphor_3d::{Model, Material, Light, Skybox};
+use phosphor_imgui::imgui::{Ui, WindowFlags, TreeNodeFlags, Drag};
+use crate::panels::Panel;
+
+const WARN: [f32; 4] = [0.9, 0.35, 0.3, 1.0];
+
+struct Budget {
+  entities: usize,
+  triangles: usize,
+  texture_mb: f32,
+  lights: usize,
+  shadow_casters: usize,
+}
+
+pub fn init(world: &mut World) -> Panel {
+  world.add_resource(Budget {
+    entities: 1000,
+    triangles: 1_000_000,
+    texture_mb: 512.0,
+    lights: 32,
+    shadow_casters: 500,
+  });
+  Panel {
+    title: "\u{f080} Scene Budget",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn texture_size(tex: &Texture) -> usize {
+  let bpp = match tex.iformat {
+    gl::RGBA16F => 8,
+    gl::RGBA32F => 16,
+    gl::RGB | gl::SRGB => 3,
+    _ => 4,
+  };
+  tex.width as usize * tex.height as usize * bpp
+}
+
+fn stat(ui: &Ui, label: &str, value: String, over: bool) {
+  ui.text(label);
+  ui.next_column();
+  if over {
+    ui.text_colored(WARN, format!("\u{f071} {}", value));
+  } else {
+    ui.text(value);
+  }
+  ui.next_column();
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let entities = world
+    .components
+    .values()
+    .flat_map(|v| v.iter().map(|(e, _)| *e))
+    .collect::<HashSet<_>>()
+    .len();
+  let models = world.query::<Model>();
+  let triangles = models
+    .iter()
+    .map(|(_, m)| m.mesh.triangles().len())
+    .sum::<usize>();
+  let shadow_casters = models.iter().filter(|(_, m)| m.cast_shadows).count();
+  let lights = world.query::<Light>().len();
+
+  // textures referenced by the scene, counted once each
+  let mut textures = HashMap::new();
+  for (_, mat) in world.query::<Material>() {
+    for tex in [
+      &mat.albedo_tex,
+      &mat.metallic_tex,
+      &mat.roughness_tex,
+      &mat.normal_tex,
+      &mat.ao_tex,
+    ]
+    .into_iter()
+    .flatten()
+    {
+      textures.insert(tex.name.clone(), texture_size(tex));
+    }
+  }
+  if let Some(skybox) = world.get_resource::<Skybox>() {
+    let size = skybox.cubemap.size as usize;
+    textures.insert(skybox.cubemap.name.clone(), size * size * 4 * 6);
+  }
+  let texture_mb = textures.values().sum::<usize>() as f32 / (1024.0 * 1024.0);
+  let loaded = world
+    .get_resource::<Assets>()
+    .unwrap()
+    .get::<Texture>()
+    .len();
+
+  let budget = world.get_resource::<Budget>().unwrap();
+  ui.columns(2, "stats", false);
+  stat(
+    ui,
+    "Entities",
+    entities.to_string(),
+    entities > budget.entities,
+  );
+  stat(
+    ui,
+    "Triangles",
+    triangles.to_string(),
+    triangles > budget.triangles,
+  );
+  stat(
+    ui,
+    "Texture memory",
+    format!("{:.1}MB ({} textures)", texture_mb, textures.len()),
+    texture_mb > budget.texture_mb,
+  );
+  if let Some(streamer) = world.get_resource::<TextureStreamer>() {
+    let resident = streamer.resident() as f32 / (1024.0 * 1024.0);
+    let limit = texture_budget_mb.get();
+    stat(
+      ui,
+      "Resident textures",
+      format!("{:.1}MB / {}MB", resident, limit),
+      resident > limit as f32,
+    );
+  }
+  stat(ui, "Lights", lights.to_string(), lights > budget.lights);
+  stat(
+    ui,
+    "Shadow casters",
+    shadow_casters.to_string(),
+    shadow_casters > budget.shadow_casters,
+  );
+  ui.columns(1, "", false);
+  ui.text_disabled(format!("{} textures loaded in total", loaded));
+
+  if ui.collapsing_header("Budgets", TreeNodeFlags::empty()) {
+    Drag::new("entities").build(ui, &mut budget.entities);
+    Drag::new("triangles")
+      .speed(1000.0)
+      .build(ui, &mut budget.triangles);
+    Drag::new("texture memory")
+      .range(0.0, f32::MAX)
+      .display_format("%.0fMB")
+      .build(ui, &mut budget.texture_mb);
+    Drag::new("lights").build(ui, &mut budget.lights);
+    Drag::new("shadow casters").build(ui, &mut budget.shadow_casters);
+  }
+}
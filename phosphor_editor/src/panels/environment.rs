@@ -1,6 +1,10 @@
 use phosphor::ecs::World;
-use phosphor_3d::SkySettings;
-use phosphor_imgui::imgui::{Ui, WindowFlags, Drag};
+use phosphor::assets::Assets;
+use phosphor::gfx::Cubemap;
+use phosphor_3d::{SkySettings, Skybox, PostProcess, PostEffect, Tonemap, WorldEnvironment};
+use phosphor_imgui::hover_tooltip;
+use phosphor_imgui::imgui::{Ui, WindowFlags, TreeNodeFlags, Drag};
+use crate::SelectedEntity;
 use crate::panels::Panel;
 
 pub fn init() -> Panel {
@@ -14,8 +18,143 @@ pub fn init() -> Panel {
 }
 
 fn render(world: &mut World, ui: &Ui) {
+  // the scene's environment is applied over these every frame
+  if let Some((e, _)) = world.query::<WorldEnvironment>().into_iter().next() {
+    ui.text_wrapped("This scene's look is set by its World Environment.");
+    if ui.button("\u{f05a} Select") {
+      world.add_resource(SelectedEntity(Some(e)));
+    }
+    return;
+  }
+  if ui.button("\u{f067} Save with Scene") {
+    let env = WorldEnvironment::from_world(world);
+    let e = world.spawn("World Environment").insert(env);
+    world.add_resource(SelectedEntity(Some(e)));
+  }
+  hover_tooltip(
+    ui,
+    "Move these settings into a World Environment entity, so the scene keeps its look.",
+  );
   let sky = world.get_resource::<SkySettings>().unwrap();
   Drag::new("light dir")
     .speed(0.5)
     .build_array(ui, sky.dir.as_mut());
+  let mut physical = sky.sun_lux.is_some();
+  if ui.checkbox("sun in lux", &mut physical) {
+    // direct sunlight
+    sky.sun_lux = physical.then_some(100000.0);
+  }
+  if let Some(lux) = &mut sky.sun_lux {
+    Drag::new("illuminance")
+      .speed(100.0)
+      .range(0.0, f32::MAX)
+      .display_format("%g lx")
+      .build(ui, lux);
+  }
+  ui.color_edit3("ambient", sky.ambient.as_mut());
+  ui.color_edit3("fog color", sky.fog.color.as_mut());
+  Drag::new("fog density")
+    .range(0.0, f32::MAX)
+    .speed(0.001)
+    .build(ui, &mut sky.fog.density);
+  Drag::new("fog start")
+    .range(0.0, f32::MAX)
+    .speed(0.1)
+    .build(ui, &mut sky.fog.start);
+
+  let current = world
+    .get_resource::<Skybox>()
+    .map_or("none".to_string(), |s| s.cubemap.name.clone());
+  let cubemaps = world.get_resource::<Assets>().unwrap().get::<Cubemap>();
+  if let Some(_) = ui.begin_combo("skybox", &current) {
+    if ui.selectable("none") {
+      world.take_resource::<Skybox>();
+    }
+    for cubemap in cubemaps {
+      if ui
+        .selectable_config(cubemap.name.clone())
+        .selected(current == cubemap.name)
+        .build()
+      {
+        world.add_resource(Skybox::new(cubemap));
+      }
+    }
+  }
+  if let Some(skybox) = world.get_resource::<Skybox>() {
+    Drag::new("intensity")
+      .range(0.0, f32::MAX)
+      .speed(0.01)
+      .build(ui, &mut skybox.intensity);
+  }
+
+  if ui.collapsing_header("post processing", TreeNodeFlags::DEFAULT_OPEN) {
+    post_effects(
+      &mut world.get_resource::<PostProcess>().unwrap().effects,
+      ui,
+    );
+  }
+}
+
+pub fn post_effects(effects: &mut Vec<PostEffect>, ui: &Ui) {
+  let mut remove = None;
+  let mut swap = None;
+  let n = effects.len();
+  for (i, effect) in effects.iter_mut().enumerate() {
+    let _id = ui.push_id_usize(i);
+    let open = ui.tree_node_config(effect.name()).default_open(true).push();
+    ui.same_line();
+    if i > 0 && ui.small_button("\u{f062}") {
+      swap = Some(i - 1);
+    }
+    ui.same_line();
+    if i + 1 < n && ui.small_button("\u{f063}") {
+      swap = Some(i);
+    }
+    ui.same_line();
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+    if open.is_some() {
+      match effect {
+        PostEffect::Bloom {
+          threshold,
+          intensity,
+        } => {
+          Drag::new("threshold")
+            .range(0.0, f32::MAX)
+            .speed(0.01)
+            .build(ui, threshold);
+          Drag::new("intensity")
+            .range(0.0, f32::MAX)
+            .speed(0.01)
+            .build(ui, intensity);
+        }
+        PostEffect::Tonemap(tonemap) => {
+          if let Some(_) = ui.begin_combo("operator", tonemap.name()) {
+            for t in Tonemap::ALL {
+              if ui.selectable(t.name()) {
+                *tonemap = t;
+              }
+            }
+          }
+        }
+        PostEffect::Vignette { strength } => {
+          ui.slider("strength", 0.0, 1.0, strength);
+        }
+      }
+    }
+  }
+  if let Some(i) = swap {
+    effects.swap(i, i + 1);
+  }
+  if let Some(i) = remove {
+    effects.remove(i);
+  }
+  if let Some(_) = ui.begin_combo("##add", "add effect") {
+    for effect in PostEffect::ALL {
+      if ui.selectable(effect.name()) {
+        effects.push(effect);
+      }
+    }
+  }
 }
@@ -0,0 +1,165 @@
+use std::f32::consts::PI;
+use phosphor::ecs::{World, Entity};
+use phosphor::gfx::Cursor;
+use phosphor::image::{RgbaImage, Rgba};
+use phosphor::math::{Vec3, Quat, Mat4, Vec2};
+use phosphor_imgui::imgui::{Ui, Drag, MouseButton};
+use phosphor_3d::Foliage;
+use rand::Rng;
+use crate::panels::gizmos;
+use crate::panels::viewport::Viewport;
+use crate::panels::placement;
+
+pub struct FoliageBrush {
+  pub painting: bool,
+  pub radius: f32,
+  // instances per square unit per second
+  pub density: f32,
+  pub scale: [f32; 2],
+  pub yaw: [f32; 2],
+  pub max_slope: f32,
+  pub height: [f32; 2],
+  pub align: bool,
+}
+
+impl FoliageBrush {
+  pub const DEFAULT: Self = Self {
+    painting: false,
+    radius: 2.0,
+    density: 4.0,
+    scale: [0.8, 1.2],
+    yaw: [0.0, 360.0],
+    max_slope: 30.0,
+    height: [-100.0, 100.0],
+    align: false,
+  };
+
+  pub fn settings(&mut self, ui: &Ui) {
+    ui.checkbox("Paint", &mut self.painting);
+    ui.same_line();
+    ui.text_disabled("(LMB to paint, Shift+LMB to erase)");
+    Drag::new("Radius")
+      .range(0.1, f32::MAX)
+      .speed(0.05)
+      .build(ui, &mut self.radius);
+    Drag::new("Density")
+      .range(0.0, f32::MAX)
+      .speed(0.05)
+      .build(ui, &mut self.density);
+    Drag::new("Scale")
+      .range(0.01, f32::MAX)
+      .speed(0.01)
+      .build_array(ui, &mut self.scale);
+    Drag::new("Rotation")
+      .range(0.0, 360.0)
+      .speed(0.5)
+      .build_array(ui, &mut self.yaw);
+    ui.slider("Max Slope", 0.0, 90.0, &mut self.max_slope);
+    Drag::new("Height")
+      .speed(0.1)
+      .build_array(ui, &mut self.height);
+    ui.checkbox("Align to normal", &mut self.align);
+  }
+}
+
+fn range(rng: &mut impl Rng, [min, max]: [f32; 2]) -> f32 {
+  match min < max {
+    true => rng.gen_range(min..max),
+    false => min,
+  }
+}
+
+fn scatter(
+  world: &World,
+  brush: &FoliageBrush,
+  foliage: &mut Foliage,
+  center: Vec3,
+  dt: f32,
+  ignore: usize,
+) {
+  let mut rng = rand::thread_rng();
+  let expected = brush.density * PI * brush.radius * brush.radius * dt;
+  let count = (expected + rng.gen::<f32>()) as usize;
+  for _ in 0..count {
+    let (a, r) = (
+      rng.gen_range(0.0..PI * 2.0),
+      brush.radius * rng.gen::<f32>().sqrt(),
+    );
+    let origin = center + Vec3::new(a.cos() * r, brush.radius, a.sin() * r);
+    let (hit, normal) = match placement::raycast(world, (origin, Vec3::NEG_Y), ignore) {
+      Some(hit) => hit,
+      None => continue,
+    };
+    if normal.angle_between(Vec3::Y).to_degrees() > brush.max_slope
+      || hit.y < brush.height[0]
+      || hit.y > brush.height[1]
+    {
+      continue;
+    }
+    let up = match brush.align {
+      true => Quat::from_rotation_arc(Vec3::Y, normal),
+      false => Quat::IDENTITY,
+    };
+    let rotation = up * Quat::from_rotation_y(range(&mut rng, brush.yaw).to_radians());
+    let scale = Vec3::splat(range(&mut rng, brush.scale));
+    foliage
+      .instances
+      .push(Mat4::from_scale_rotation_translation(scale, rotation, hit));
+  }
+}
+
+// a small ring with a dot in the middle, outlined so it shows over any scene
+pub fn cursor() -> Cursor {
+  const SIZE: u32 = 24;
+  let c = Vec2::splat(SIZE as f32 / 2.0 - 0.5);
+  let img = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+    let d = Vec2::new(x as _, y as _).distance(c);
+    match d {
+      d if d < 1.0 || (8.0..9.5).contains(&d) => Rgba([255, 255, 255, 255]),
+      d if d < 2.5 || (6.5..11.0).contains(&d) => Rgba([0, 0, 0, 160]),
+      _ => Rgba([0, 0, 0, 0]),
+    }
+  });
+  Cursor::from_image(img, (SIZE / 2, SIZE / 2))
+}
+
+// returns whether the brush is capturing the mouse
+pub fn paint(world: &World, ui: &Ui, vp: &Viewport, selected: Entity) -> bool {
+  let brush = world.get_resource::<FoliageBrush>().unwrap();
+  let foliage = match selected.get_one::<Foliage>() {
+    Some(f) if brush.painting => f,
+    _ => return false,
+  };
+  let (center, normal) = match placement::raycast(
+    world,
+    vp.viewport_to_world_ray(ui.io().mouse_pos),
+    selected.id,
+  ) {
+    Some(hit) => hit,
+    None => return false,
+  };
+  let u = normal.any_orthonormal_vector();
+  gizmos::circle(ui, vp, center, u, normal.cross(u), brush.radius);
+
+  // the window includes its title bar
+  let hovered = ui.is_window_hovered() && vp.contains(ui.io().mouse_pos);
+  if hovered && ui.is_mouse_down(MouseButton::Left) {
+    // so gpu culling uploads the instances again
+    selected.set_changed::<Foliage>();
+    if ui.io().key_shift {
+      foliage
+        .instances
+        .retain(|m| m.w_axis.truncate().distance(center) > brush.radius);
+    } else {
+      scatter(
+        world,
+        brush,
+        foliage,
+        center,
+        ui.io().delta_time,
+        selected.id,
+      );
+    }
+  }
+  hovered
+}
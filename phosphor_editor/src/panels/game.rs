@@ -0,0 +1,163 @@
+use phosphor::Result;
+use phosphor::ecs::{World, stage};
+use phosphor::gfx::{Texture, Framebuffer};
+use phosphor_imgui::imgui::{Ui, Image, TextureId, WindowFlags};
+use phosphor_3d::{Camera, SceneDrawOptions, SceneViews};
+use crate::locale::Locale;
+use crate::panels::Panel;
+use crate::panels::viewport::FB_SCALE;
+
+#[derive(Copy, Clone, PartialEq)]
+enum GameSize {
+  // fills the panel
+  Free,
+  Aspect(u32, u32),
+  Resolution(u32, u32),
+}
+
+impl GameSize {
+  const ALL: [Self; 7] = [
+    Self::Free,
+    Self::Aspect(16, 9),
+    Self::Aspect(16, 10),
+    Self::Aspect(4, 3),
+    Self::Aspect(21, 9),
+    Self::Resolution(1280, 720),
+    Self::Resolution(1920, 1080),
+  ];
+
+  fn name(&self) -> String {
+    match self {
+      Self::Free => "Free".to_string(),
+      Self::Aspect(w, h) => format!("{}:{}", w, h),
+      Self::Resolution(w, h) => format!("{}x{}", w, h),
+    }
+  }
+
+  // pixels to render at when the panel has `area` to show it in
+  fn resolution(&self, area: [f32; 2]) -> [f32; 2] {
+    match *self {
+      Self::Free => [area[0] * FB_SCALE, area[1] * FB_SCALE],
+      Self::Aspect(w, h) => {
+        let [w, h] = fit(area, w as f32 / h as f32);
+        [w * FB_SCALE, h * FB_SCALE]
+      }
+      Self::Resolution(w, h) => [w as _, h as _],
+    }
+  }
+}
+
+// largest size of `aspect` that fits in `area`
+fn fit(area: [f32; 2], aspect: f32) -> [f32; 2] {
+  match area[0] / area[1] > aspect {
+    true => [area[1] * aspect, area[1]],
+    false => [area[0], area[0] / aspect],
+  }
+}
+
+// what the scene's first camera sees, drawn alongside the scene panel's own camera
+struct GameState {
+  size: GameSize,
+  // bars keep the aspect, otherwise the image is stretched over the panel
+  letterbox: bool,
+  area: [f32; 2],
+  // only drawn while the panel is showing
+  visible: bool,
+  fb: Framebuffer,
+  tex: Texture,
+}
+
+pub fn init(world: &mut World) -> Panel {
+  let fb = Framebuffer::new();
+  let tex = Texture::empty();
+  fb.bind_tex(&tex, 0);
+  world.add_resource(GameState {
+    size: GameSize::Free,
+    letterbox: true,
+    area: [1.0, 1.0],
+    visible: false,
+    fb,
+    tex,
+  });
+  world.add_system(stage::PRE_DRAW, predraw);
+  Panel {
+    title: "\u{f11b} Game",
+    flags: WindowFlags::NO_SCROLLBAR | WindowFlags::NO_SCROLL_WITH_MOUSE,
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn predraw(world: &mut World) -> Result {
+  let s = world.get_resource::<GameState>().unwrap();
+  let visible = s.visible && s.area[0] >= 1.0 && s.area[1] >= 1.0;
+  s.visible = false;
+  if !visible || world.query::<Camera>().is_empty() {
+    world.take_resource::<SceneViews>();
+    return Ok(());
+  }
+  let [w, h] = s.size.resolution(s.area);
+  s.tex.resize(w as _, h as _);
+  s.fb.resize(w as _, h as _);
+  world.add_resource(SceneViews(vec![SceneDrawOptions {
+    fb: s.fb,
+    size: [w.floor(), h.floor()],
+    camera: None,
+  }]));
+  Ok(())
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let s = world.get_resource::<GameState>().unwrap();
+  let locale = world.get_resource::<Locale>().unwrap();
+  s.visible = true;
+  ui.set_next_item_width(120.0);
+  if let Some(_) = ui.begin_combo("##size", locale.tr(&s.size.name())) {
+    for size in GameSize::ALL {
+      if ui.selectable(locale.tr(&size.name())) {
+        s.size = size;
+      }
+    }
+  }
+  ui.same_line();
+  ui.checkbox(locale.tr("Letterbox"), &mut s.letterbox);
+
+  let area = ui.content_region_avail();
+  s.area = area;
+  if world.query::<Camera>().is_empty() {
+    let font = ui.push_font(ui.fonts().fonts()[1]);
+    ui.set_window_font_scale(0.65);
+    let msg = locale.tr("\u{e0eb} No camera.");
+    let [x, y] = ui.calc_text_size(&msg);
+    let [cx, cy] = ui.cursor_pos();
+    ui.set_cursor_pos([cx + (area[0] - x) / 2.0, cy + (area[1] - y) / 2.0]);
+    ui.text(&msg);
+    ui.set_window_font_scale(1.0);
+    font.pop();
+    return;
+  }
+  let [w, h] = s.size.resolution(area);
+  let size = match s.letterbox {
+    true => fit(area, w / h),
+    false => area,
+  };
+  let min = ui.cursor_screen_pos();
+  ui.get_window_draw_list()
+    .add_rect(
+      min,
+      [min[0] + area[0], min[1] + area[1]],
+      [0.0, 0.0, 0.0, 1.0],
+    )
+    .filled(true)
+    .build();
+  let [cx, cy] = ui.cursor_pos();
+  ui.set_cursor_pos([
+    cx + (area[0] - size[0]) / 2.0,
+    cy + (area[1] - size[1]) / 2.0,
+  ]);
+  Image::new(TextureId::new(s.tex.id as _), size)
+    .uv0([0.0, 1.0])
+    .uv1([1.0, 0.0])
+    .build(ui);
+}
@@ -0,0 +1,543 @@
+use std::f32::consts::TAU;
+use phosphor::ecs::{World, Entity};
+use phosphor::math::{Vec3, Quat, Mat4};
+use phosphor_imgui::imgui::{Ui, Key, Drag, MouseButton};
+use phosphor_3d::{
+  Camera, Light, LightUnit, Transform, TriggerVolume, TriggerShape, Spline, SplineKind, Folder,
+};
+use phosphor_rapier::Vehicle;
+use crate::locale::Locale;
+use crate::panels::placement;
+use crate::panels::viewport::Viewport;
+
+const COLOR: [f32; 4] = [0.922, 0.796, 0.545, 1.0];
+const HANDLE: [f32; 4] = [0.33, 0.67, 0.86, 1.0];
+const TRIGGER: [f32; 4] = [0.47, 0.75, 0.33, 0.8];
+const SPLINE: [f32; 4] = [0.86, 0.53, 0.33, 0.8];
+const AXES: [(Vec3, [f32; 4]); 3] = [
+  (Vec3::X, [0.86, 0.33, 0.33, 1.0]),
+  (Vec3::Y, [0.47, 0.75, 0.33, 1.0]),
+  (Vec3::Z, [0.33, 0.53, 0.86, 1.0]),
+];
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GizmoMode {
+  Move,
+  Rotate,
+  Scale,
+}
+
+impl GizmoMode {
+  pub const ALL: [Self; 3] = [Self::Move, Self::Rotate, Self::Scale];
+
+  pub fn name(&self) -> &str {
+    match self {
+      Self::Move => "Move",
+      Self::Rotate => "Rotate",
+      Self::Scale => "Scale",
+    }
+  }
+}
+
+struct Drag3 {
+  position: Vec3,
+  rotation: Quat,
+  scale: Vec3,
+  // where the cursor was along the axis, or its angle around the ring
+  start: f32,
+}
+
+pub struct GizmoState {
+  pub mode: GizmoMode,
+  // axes follow the entity's rotation, scaling always does
+  pub local: bool,
+  // also on while ctrl is held
+  pub snap: bool,
+  // units, degrees and scale factor
+  pub steps: [f32; 3],
+  drag: Option<Drag3>,
+}
+
+impl GizmoState {
+  pub const DEFAULT: Self = Self {
+    mode: GizmoMode::Move,
+    local: false,
+    snap: false,
+    steps: [0.5, 15.0, 0.1],
+    drag: None,
+  };
+
+  pub fn settings(&mut self, ui: &Ui, locale: &Locale) {
+    for mode in GizmoMode::ALL {
+      if ui.radio_button_bool(locale.tr(mode.name()), self.mode == mode) {
+        self.mode = mode;
+      }
+      ui.same_line();
+    }
+    ui.checkbox(locale.tr("Local"), &mut self.local);
+    ui.same_line();
+    ui.checkbox(locale.tr("Snap"), &mut self.snap);
+    if self.snap {
+      ui.set_next_item_width(180.0);
+      Drag::new("##steps")
+        .range(0.0, f32::MAX)
+        .speed(0.01)
+        .build_array(ui, &mut self.steps);
+    }
+  }
+}
+
+pub fn ray_plane((o, d): (Vec3, Vec3), p: Vec3, n: Vec3) -> Option<Vec3> {
+  let denom = d.dot(n);
+  if denom.abs() < 1e-5 {
+    return None;
+  }
+  Some(o + d * (p - o).dot(n) / denom)
+}
+
+// parameter along the line (o, d) closest to the ray
+fn ray_line((ro, rd): (Vec3, Vec3), o: Vec3, d: Vec3) -> Option<f32> {
+  let w = ro - o;
+  let (a, b, c) = (rd.dot(rd), rd.dot(d), d.dot(d));
+  let denom = a * c - b * b;
+  if denom.abs() < 1e-5 {
+    return None;
+  }
+  Some((a * d.dot(w) - b * rd.dot(w)) / denom)
+}
+
+fn snap(v: f32, step: f32, on: bool) -> f32 {
+  match on && step > 0.0 {
+    true => (v / step).round() * step,
+    false => v,
+  }
+}
+
+fn line(ui: &Ui, vp: &Viewport, a: Vec3, b: Vec3, color: [f32; 4]) {
+  if let (Some(a), Some(b)) = (vp.world_to_viewport(a), vp.world_to_viewport(b)) {
+    ui.get_window_draw_list()
+      .add_line(a, b, color)
+      .thickness(1.5)
+      .build();
+  }
+}
+
+fn ring(ui: &Ui, vp: &Viewport, center: Vec3, u: Vec3, v: Vec3, r: f32, color: [f32; 4]) {
+  const SEGMENTS: usize = 48;
+  let point = |i: usize| {
+    let a = i as f32 / SEGMENTS as f32 * TAU;
+    center + (u * a.cos() + v * a.sin()) * r
+  };
+  for i in 0..SEGMENTS {
+    line(ui, vp, point(i), point(i + 1), color);
+  }
+}
+
+pub fn circle(ui: &Ui, vp: &Viewport, center: Vec3, u: Vec3, v: Vec3, r: f32) {
+  ring(ui, vp, center, u, v, r, COLOR);
+}
+
+pub fn handle(ui: &Ui, id: &str, pos: [f32; 2], color: [f32; 4]) -> (bool, bool) {
+  ui.set_cursor_screen_pos([pos[0] - 6.0, pos[1] - 6.0]);
+  ui.invisible_button(id, [12.0, 12.0]);
+  let (hovered, active) = (ui.is_item_hovered(), ui.is_item_active());
+  ui.get_window_draw_list()
+    .add_circle(pos, if hovered || active { 6.0 } else { 4.0 }, color)
+    .filled(true)
+    .build();
+  (hovered, active)
+}
+
+// axis handles for the current mode around a center handle that moves in the view plane,
+// hold V while moving to snap the pivot to nearby vertices. `t` is in world space
+fn transform_gizmo(
+  world: &World,
+  ui: &Ui,
+  vp: &Viewport,
+  id: usize,
+  t: &mut Transform,
+  state: &mut GizmoState,
+) -> bool {
+  let center = match vp.world_to_viewport(t.position) {
+    Some(pos) => pos,
+    None => return false,
+  };
+  let snapping = state.snap || ui.io().key_ctrl;
+  let mouse = ui.io().mouse_pos;
+  let ray = vp.viewport_to_world_ray(mouse);
+  // axes stay put while dragging so rotating in local space doesn't spin them
+  let (origin, rotation) = match &state.drag {
+    Some(d) => (d.position, d.rotation),
+    None => (t.position, t.rotation),
+  };
+  let rotation = match state.local || state.mode == GizmoMode::Scale {
+    true => rotation,
+    false => Quat::IDENTITY,
+  };
+  // constant size on screen
+  let len = (origin - vp.view.inverse().w_axis.truncate()).length() * 0.15;
+  let mut interacting = false;
+  let mut dragging = false;
+
+  for (i, (axis, color)) in AXES.into_iter().enumerate() {
+    let axis = rotation * axis;
+    let (u, v) = axis.any_orthonormal_pair();
+    let param = |ray| match state.mode {
+      GizmoMode::Rotate => ray_plane(ray, origin, axis).map(|h| {
+        let h = h - origin;
+        h.dot(v).atan2(h.dot(u))
+      }),
+      _ => ray_line(ray, origin, axis),
+    };
+    let end = match state.mode {
+      GizmoMode::Rotate => {
+        ring(ui, vp, origin, u, v, len, color);
+        origin + u * len
+      }
+      _ => {
+        let end = t.position + axis * len;
+        line(ui, vp, t.position, end, color);
+        end
+      }
+    };
+    let pos = match vp.world_to_viewport(end) {
+      Some(pos) => pos,
+      None => continue,
+    };
+    let (hovered, active) = handle(ui, ["x", "y", "z"][i], pos, color);
+    interacting |= hovered || active;
+    if !active {
+      continue;
+    }
+    dragging = true;
+    if ui.is_item_activated() {
+      state.drag = param(ray).map(|start| Drag3 {
+        position: t.position,
+        rotation: t.rotation,
+        scale: t.scale,
+        start,
+      });
+    }
+    if let (Some(d), Some(p)) = (&state.drag, param(ray)) {
+      match state.mode {
+        GizmoMode::Move => {
+          t.position = d.position + axis * snap(p - d.start, state.steps[0], snapping);
+        }
+        GizmoMode::Rotate => {
+          let angle = snap((p - d.start).to_degrees(), state.steps[1], snapping);
+          t.rotation = Quat::from_axis_angle(axis, angle.to_radians()) * d.rotation;
+        }
+        GizmoMode::Scale => {
+          if d.start.abs() > 1e-5 {
+            t.scale[i] = d.scale[i] * snap(p / d.start, state.steps[2], snapping);
+          }
+        }
+      }
+    }
+  }
+
+  if state.mode != GizmoMode::Rotate {
+    let (hovered, active) = handle(ui, "center", center, HANDLE);
+    interacting |= hovered || active;
+    if active {
+      dragging = true;
+      if ui.is_item_activated() {
+        state.drag = Some(Drag3 {
+          position: t.position,
+          rotation: t.rotation,
+          scale: t.scale,
+          start: mouse[0],
+        });
+      }
+      match (state.mode, &state.drag) {
+        (GizmoMode::Scale, Some(d)) => {
+          let factor = (1.0 + (mouse[0] - d.start) * 0.01).max(0.01);
+          t.scale = d.scale * snap(factor, state.steps[2], snapping);
+        }
+        _ => {
+          let snapped = match ui.is_key_down(Key::V) {
+            true => placement::nearest_vertex(world, vp, mouse, id),
+            false => None,
+          };
+          let step = state.steps[0];
+          let free =
+            ray_plane(ray, t.position, vp.axes().2).map(|p| match snapping && step > 0.0 {
+              true => (p / step).round() * step,
+              false => p,
+            });
+          if let Some(p) = snapped.or(free) {
+            t.position = p;
+          }
+        }
+      }
+    }
+  }
+
+  if !dragging {
+    state.drag = None;
+  }
+  interacting
+}
+
+fn light_gizmo(ui: &Ui, vp: &Viewport, t: &Transform, light: &mut Light) -> bool {
+  let (right, _, forward) = vp.axes();
+  let radius = match light.unit {
+    LightUnit::Relative => light.strength,
+    _ => light.range(),
+  };
+  for (u, v) in [(Vec3::X, Vec3::Y), (Vec3::X, Vec3::Z), (Vec3::Y, Vec3::Z)] {
+    circle(ui, vp, t.position, u, v, radius);
+  }
+  // physical lights reach as far as their intensity does, there's nothing to drag
+  if light.unit != LightUnit::Relative {
+    return false;
+  }
+  let mut interacting = false;
+  if let Some(pos) = vp.world_to_viewport(t.position + right * light.strength) {
+    let (hovered, active) = handle(ui, "range", pos, HANDLE);
+    if active {
+      if let Some(hit) = ray_plane(
+        vp.viewport_to_world_ray(ui.io().mouse_pos),
+        t.position,
+        forward,
+      ) {
+        light.strength = (hit - t.position).length().max(0.01);
+      }
+    }
+    interacting = hovered || active;
+  }
+  interacting
+}
+
+fn camera_gizmo(ui: &Ui, vp: &Viewport, t: &Transform, cam: &mut Camera) -> bool {
+  let (view, proj) = cam.matrices(t, vp.size[0] / vp.size[1]);
+  let inv = (proj * view).inverse();
+  let corners = [-1.0, 1.0].map(|z| {
+    [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+      .map(|[x, y]| inv.project_point3(Vec3::new(x, y, z)))
+  });
+  for i in 0..4 {
+    line(ui, vp, corners[0][i], corners[0][(i + 1) % 4], COLOR);
+    line(ui, vp, corners[1][i], corners[1][(i + 1) % 4], COLOR);
+    line(ui, vp, corners[0][i], corners[1][i], COLOR);
+  }
+
+  let forward = t.rotation * Vec3::NEG_Z;
+  let up = t.rotation * Vec3::Y;
+  let right = t.rotation * Vec3::X;
+  let far = t.position + forward * cam.clip[1];
+  let mut interacting = false;
+  if let Some(pos) = vp.world_to_viewport(far) {
+    let (hovered, active) = handle(ui, "far", pos, HANDLE);
+    if active {
+      if let Some(d) = ray_line(
+        vp.viewport_to_world_ray(ui.io().mouse_pos),
+        t.position,
+        forward,
+      ) {
+        cam.clip[1] = d.max(cam.clip[0] + 0.01);
+      }
+    }
+    interacting |= hovered || active;
+  }
+  let top = far + up * cam.clip[1] * (cam.fov.to_radians() / 2.0).tan();
+  if let Some(pos) = vp.world_to_viewport(top) {
+    let (hovered, active) = handle(ui, "fov", pos, HANDLE);
+    if active {
+      if let Some(hit) = ray_plane(
+        vp.viewport_to_world_ray(ui.io().mouse_pos),
+        t.position,
+        right,
+      ) {
+        let v = hit - t.position;
+        cam.fov = (v.dot(up).atan2(v.dot(forward)).abs() * 2.0)
+          .to_degrees()
+          .clamp(10.0, 170.0);
+      }
+    }
+    interacting |= hovered || active;
+  }
+  // the plane that's in focus, the frustum's edges all meet at the camera
+  if let Some(dof) = &mut cam.dof {
+    let plane = corners[1].map(|c| t.position + (c - t.position) * dof.focus / cam.clip[1]);
+    for i in 0..4 {
+      line(ui, vp, plane[i], plane[(i + 1) % 4], HANDLE);
+    }
+    if let Some(pos) = vp.world_to_viewport(t.position + forward * dof.focus) {
+      let (hovered, active) = handle(ui, "focus", pos, HANDLE);
+      if active {
+        if let Some(d) = ray_line(
+          vp.viewport_to_world_ray(ui.io().mouse_pos),
+          t.position,
+          forward,
+        ) {
+          dof.focus = d.max(0.01);
+        }
+      }
+      interacting |= hovered || active;
+    }
+  }
+  interacting
+}
+
+// the suspension from its attachment with ticks at the ends of its travel, and the wheel where
+// the suspension currently holds it
+fn vehicle_gizmo(ui: &Ui, vp: &Viewport, t: &Transform, vehicle: &Vehicle) {
+  let down = t.rotation * Vec3::NEG_Y;
+  let right = t.rotation * Vec3::X;
+  let forward = t.rotation * Vec3::NEG_Z;
+  for (i, w) in vehicle.wheels.iter().enumerate() {
+    let top = t.position + t.rotation * w.position;
+    let center = top + down * vehicle.suspension_length(i);
+    line(ui, vp, top, center, HANDLE);
+    for length in [w.rest_length - w.max_travel, w.rest_length + w.max_travel] {
+      let p = top + down * length.max(0.0);
+      line(ui, vp, p - forward * 0.05, p + forward * 0.05, HANDLE);
+    }
+    let steer = match w.steered {
+      true => Quat::from_axis_angle(down, vehicle.steer * vehicle.max_steer.to_radians()),
+      false => Quat::IDENTITY,
+    };
+    circle(ui, vp, center, steer * forward, -down, w.radius);
+    line(ui, vp, center - right * 0.05, center + right * 0.05, COLOR);
+  }
+}
+
+// every trigger volume, they can't be seen in game so they're always shown
+pub fn triggers(world: &World, ui: &Ui, vp: &Viewport) {
+  for (e, trigger) in world.query::<TriggerVolume>() {
+    let mat = match e.get_one::<Transform>() {
+      Some(t) if !Folder::hides(e) => t.global_mat4(e),
+      _ => continue,
+    };
+    match trigger.shape {
+      // corners are indexed by which axes are at max, edges join corners one bit apart
+      TriggerShape::Box(half) => {
+        let corner = |i: usize| {
+          let sign = Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+          );
+          mat.transform_point3(half * sign)
+        };
+        for i in 0..8 {
+          for bit in [1, 2, 4] {
+            if i & bit == 0 {
+              line(ui, vp, corner(i), corner(i | bit), TRIGGER);
+            }
+          }
+        }
+      }
+      TriggerShape::Sphere(radius) => {
+        let center = mat.transform_point3(Vec3::ZERO);
+        let [x, y, z] = [Vec3::X, Vec3::Y, Vec3::Z].map(|a| mat.transform_vector3(a));
+        for (u, v) in [(x, y), (x, z), (y, z)] {
+          ring(ui, vp, center, u, v, radius, TRIGGER);
+        }
+      }
+    }
+  }
+}
+
+// every spline's curve, like trigger volumes they only show in the editor
+pub fn splines(world: &World, ui: &Ui, vp: &Viewport) {
+  for (e, spline) in world.query::<Spline>() {
+    let mat = match e.get_one::<Transform>() {
+      Some(t) if !Folder::hides(e) => t.global_mat4(e),
+      _ => continue,
+    };
+    let points = spline.polyline();
+    for pair in points.windows(2) {
+      line(
+        ui,
+        vp,
+        mat.transform_point3(pair[0]),
+        mat.transform_point3(pair[1]),
+        SPLINE,
+      );
+    }
+  }
+}
+
+// control points drag in the view plane and right click removes them. bezier handles are joined
+// to the anchor they belong to
+fn spline_gizmo(ui: &Ui, vp: &Viewport, mat: Mat4, spline: &mut Spline) -> bool {
+  let points = spline
+    .points
+    .iter()
+    .map(|p| mat.transform_point3(*p))
+    .collect::<Vec<_>>();
+  let n = points.len();
+  if spline.kind == SplineKind::Bezier {
+    for i in (0..n).filter(|i| !spline.is_anchor(*i)) {
+      let anchor = match (i % 3, spline.closed) {
+        (1, _) => i - 1,
+        (_, true) => (i + 1) % n,
+        (_, false) => (i + 1).min(n - 1),
+      };
+      line(ui, vp, points[anchor], points[i], HANDLE);
+    }
+  }
+  let ray = vp.viewport_to_world_ray(ui.io().mouse_pos);
+  let mut interacting = false;
+  let mut remove = None;
+  for (i, p) in points.iter().enumerate() {
+    let pos = match vp.world_to_viewport(*p) {
+      Some(pos) => pos,
+      None => continue,
+    };
+    let _id = ui.push_id_usize(i);
+    let color = match spline.is_anchor(i) {
+      true => SPLINE,
+      false => HANDLE,
+    };
+    let (hovered, active) = handle(ui, "point", pos, color);
+    if ui.is_item_clicked_with_button(MouseButton::Right) {
+      remove = Some(i);
+    }
+    if active {
+      if let Some(hit) = ray_plane(ray, *p, vp.axes().2) {
+        spline.points[i] = mat.inverse().transform_point3(hit);
+      }
+    }
+    interacting |= hovered || active;
+  }
+  if let Some(i) = remove {
+    spline.remove_point(i);
+  }
+  interacting
+}
+
+pub fn draw(world: &World, ui: &Ui, vp: &Viewport, selected: Entity) -> bool {
+  let mut interacting = false;
+  if let Some(local) = selected.get_one::<Transform>() {
+    let id = ui.push_id("gizmos");
+    let state = world.get_resource::<GizmoState>().unwrap();
+    // handles are placed and dragged in world space, then converted back under the parent
+    let mut t = local.to_global(selected);
+    let before = t.as_mat4();
+    if transform_gizmo(world, ui, vp, selected.id, &mut t, state) {
+      if t.as_mat4() != before {
+        local.set_global(selected, &t);
+      }
+      selected.set_changed::<Transform>();
+      interacting = true;
+    }
+    let t = &t;
+    if let Some(light) = selected.get_one::<Light>() {
+      interacting |= light_gizmo(ui, vp, t, light);
+    }
+    if let Some(cam) = selected.get_one::<Camera>() {
+      interacting |= camera_gizmo(ui, vp, t, cam);
+    }
+    if let Some(vehicle) = selected.get_one::<Vehicle>() {
+      vehicle_gizmo(ui, vp, t, vehicle);
+    }
+    if let Some(spline) = selected.get_one::<Spline>() {
+      interacting |= spline_gizmo(ui, vp, local.global_mat4(selected), spline);
+    }
+    id.pop();
+  }
+  interacting
+}
@@ -0,0 +1,59 @@
+use phosphor::ecs::World;
+use phosphor::profiling::GpuProfiler;
+use phosphor_imgui::imgui::{Ui, WindowFlags};
+use crate::panels::Panel;
+
+const GRAPH_HEIGHT: f32 = 40.0;
+
+pub fn init() -> Panel {
+  Panel {
+    title: "\u{f201} GPU Profiler",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let profiler = match world.get_resource::<GpuProfiler>() {
+    Some(p) => p,
+    None => {
+      ui.text_disabled("GPU profiling isn't available.");
+      return;
+    }
+  };
+  let scopes = profiler.scopes();
+  if scopes.is_empty() {
+    ui.text_disabled("Nothing has been timed yet.");
+    return;
+  }
+  ui.text(format!(
+    "{} scopes | {:.3}ms",
+    scopes.len(),
+    scopes.iter().filter_map(|s| s.last()).sum::<f32>()
+  ));
+  // every graph shares a scale so passes can be compared at a glance
+  let max = scopes
+    .iter()
+    .filter_map(|s| s.max())
+    .fold(0.0, f32::max)
+    .max(0.01);
+  let width = ui.content_region_avail()[0];
+  for scope in scopes {
+    let history = scope.history.iter().copied().collect::<Vec<_>>();
+    ui.text(scope.name);
+    ui.same_line();
+    ui.text_disabled(format!(
+      "{:.3}ms | avg {:.3}ms | max {:.3}ms",
+      scope.last().unwrap_or(0.0),
+      scope.average().unwrap_or(0.0),
+      scope.max().unwrap_or(0.0)
+    ));
+    ui.plot_lines(format!("##{}", scope.name), &history)
+      .graph_size([width, GRAPH_HEIGHT])
+      .scale_min(0.0)
+      .scale_max(max)
+      .build();
+  }
+}
@@ -0,0 +1,113 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use phosphor::ecs::World;
+use phosphor_3d::FrameGraph;
+use phosphor_imgui::imgui::{Ui, WindowFlags, StyleColor};
+use crate::panels::Panel;
+
+const NODE_WIDTH: f32 = 160.0;
+const NODE_GAP: f32 = 90.0;
+const LINE: f32 = 17.0;
+const PAD: f32 = 6.0;
+
+pub fn init() -> Panel {
+  Panel {
+    title: "\u{f542} Frame Graph",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+// each texture keeps its color across the graph, so reuse stands out
+fn texture_color(name: &str) -> [f32; 4] {
+  let mut h = DefaultHasher::new();
+  name.hash(&mut h);
+  let h = h.finish();
+  let channel = |shift: u64| 0.45 + ((h >> shift) & 0xff) as f32 / 255.0 * 0.5;
+  [channel(0), channel(8), channel(16), 1.0]
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let graph = match world.get_resource::<FrameGraph>() {
+    Some(g) => g,
+    None => {
+      ui.text_disabled("The scene renderer isn't running.");
+      return;
+    }
+  };
+  let passes = graph.passes();
+  ui.text(format!(
+    "{} passes | {:.3}ms",
+    passes.len(),
+    passes.iter().filter_map(|p| p.time).sum::<f32>()
+  ));
+  ui.child_window("graph")
+    .horizontal_scrollbar(true)
+    .build(|| {
+      let origin = ui.cursor_screen_pos();
+      let draw = ui.get_window_draw_list();
+      let text = ui.style_color(StyleColor::Text);
+      let dim = ui.style_color(StyleColor::TextDisabled);
+      let header = ui.style_color(StyleColor::Header);
+      let bg = ui.style_color(StyleColor::FrameBg);
+      let mut height: f32 = 0.0;
+      // where each texture was last written, as the pass and the row it was listed on
+      let mut written: Vec<(&str, usize, f32)> = vec![];
+      for (i, pass) in passes.iter().enumerate() {
+        let x = origin[0] + i as f32 * (NODE_WIDTH + NODE_GAP);
+        let y = origin[1];
+        let rows = 2 + pass.reads.len() + pass.writes.len();
+        let bottom = y + PAD * 2.0 + LINE * (rows + 1) as f32;
+        height = height.max(bottom - origin[1]);
+        draw
+          .add_rect([x, y], [x + NODE_WIDTH, bottom], bg)
+          .filled(true)
+          .rounding(4.0)
+          .build();
+        draw
+          .add_rect([x, y], [x + NODE_WIDTH, y + LINE + PAD], header)
+          .filled(true)
+          .rounding(4.0)
+          .build();
+        draw.add_text([x + PAD, y + PAD / 2.0], text, pass.name);
+        let mut row = y + LINE + PAD * 1.5;
+        let time = match pass.time {
+          Some(t) => format!("{:.3}ms", t),
+          None => "-".to_string(),
+        };
+        draw.add_text(
+          [x + PAD, row],
+          dim,
+          format!("{}x{} | {}", pass.size[0], pass.size[1], time),
+        );
+        row += LINE;
+        for read in &pass.reads {
+          row += LINE;
+          let color = texture_color(read);
+          draw.add_text([x + PAD, row], color, format!("\u{f061} {}", read));
+          // inputs nothing wrote this frame, like noise textures, have no edge
+          if let Some((_, from, from_y)) = written.iter().rev().find(|w| w.0 == *read) {
+            let from_x = origin[0] + *from as f32 * (NODE_WIDTH + NODE_GAP) + NODE_WIDTH;
+            let (a, b) = ([from_x, from_y + LINE / 2.0], [x, row + LINE / 2.0]);
+            let bend = (b[0] - a[0]) * 0.4;
+            draw
+              .add_bezier_curve(a, [a[0] + bend, a[1]], [b[0] - bend, b[1]], b, color)
+              .thickness(1.5)
+              .build();
+          }
+        }
+        for write in &pass.writes {
+          row += LINE;
+          draw.add_text(
+            [x + PAD, row],
+            texture_color(write),
+            format!("{} \u{f061}", write),
+          );
+          written.push((write, i, row));
+        }
+      }
+      ui.dummy([passes.len() as f32 * (NODE_WIDTH + NODE_GAP), height + PAD]);
+    });
+}
@@ -1,18 +1,43 @@
 use std::collections::HashMap;
 use std::any::Any;
-use phosphor::{TypeIdNamed, HashMapExt};
+use std::env;
+use std::f32::consts::PI;
+use phosphor::TypeIdNamed;
 use phosphor::ecs::{World, Name};
 use phosphor::assets::{Handle, Assets};
+use phosphor::gfx::{Texture, Cubemap};
 use phosphor::math::{Vec3, Quat, EulerRot};
+use phosphor::log::{warn, error};
 use phosphor_imgui::hover_tooltip;
 use phosphor_imgui::imgui::{Ui, Drag, WindowFlags, TreeNodeFlags, DragDropFlags};
-use phosphor_3d::{Camera, Transform, Model, Material, Light};
+use phosphor_imgui::access::{Role, label};
+use phosphor_3d::{
+  Camera, CameraController, CameraMode, Exposure, DepthOfField, MotionBlur, FilmLook, Transform,
+  Model, Material, Light, LightUnit, Foliage, Billboard, BillboardMode, ParticleEmitter,
+  ParticleBlend, Curve, Lerp, StreamingVolume, Lod, MaterialAnimation, CustomMaterial,
+  MaterialShaders, UniformValue, MaterialAsset, SharedMaterial, Sequence, SequencePlayer,
+  TriggerVolume, TriggerShape, Spline, SplineKind, SplineFollower, Folder, Locked,
+  WorldEnvironment, Bindings, Binding, Property,
+};
 use phosphor_fmod::AudioSource;
+use phosphor_rapier::{Collider, PhysicsMaterial, ColliderBuilder, Vehicle, Wheel, Ragdoll, ALL_LAYERS};
+use phosphor_rapier::rapier3d::prelude::{
+  ColliderSet, ColliderHandle, CoefficientCombineRule, RigidBodySet,
+};
 use crate::{SelectedEntity, mutate};
+use crate::locale::Locale;
 use crate::panels::Panel;
+use crate::panels::placement::{self, PlacementOptions};
+use crate::panels::foliage::FoliageBrush;
+use crate::panels::spline::{self, SplineTool};
+use crate::panels::environment;
 use super::assets::SelectedAsset;
+use rfd::FileDialog;
 
 pub fn init(world: &mut World) -> Panel {
+  world.add_resource(PlacementOptions { align: false });
+  world.add_resource(FoliageBrush::DEFAULT);
+  world.add_resource(SplineTool::DEFAULT);
   let mut panels = HashMap::new();
   panels.insert(
     TypeIdNamed::of::<Name>(),
@@ -38,6 +63,14 @@ pub fn init(world: &mut World) -> Panel {
       default: camera_default,
     },
   );
+  panels.insert(
+    TypeIdNamed::of::<CameraController>(),
+    InspectorPanel {
+      label: "\u{f05b} Camera Controller",
+      render: inspector_controller,
+      default: controller_default,
+    },
+  );
   panels.insert(
     TypeIdNamed::of::<Model>(),
     InspectorPanel {
@@ -46,6 +79,14 @@ pub fn init(world: &mut World) -> Panel {
       default: model_default,
     },
   );
+  panels.insert(
+    TypeIdNamed::of::<Billboard>(),
+    InspectorPanel {
+      label: "\u{f5fd} Billboard",
+      render: inspector_billboard,
+      default: billboard_default,
+    },
+  );
   panels.insert(
     TypeIdNamed::of::<Material>(),
     InspectorPanel {
@@ -70,6 +111,150 @@ pub fn init(world: &mut World) -> Panel {
       default: light_default,
     },
   );
+  panels.insert(
+    TypeIdNamed::of::<Foliage>(),
+    InspectorPanel {
+      label: "\u{f1b3} Foliage",
+      render: inspector_foliage,
+      default: foliage_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<ParticleEmitter>(),
+    InspectorPanel {
+      label: "\u{f76c} Particle Emitter",
+      render: inspector_particles,
+      default: particles_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Lod>(),
+    InspectorPanel {
+      label: "\u{f247} Level of Detail",
+      render: inspector_lod,
+      default: lod_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<StreamingVolume>(),
+    InspectorPanel {
+      label: "\u{f1b2} Streaming Volume",
+      render: inspector_streaming_volume,
+      default: streaming_volume_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<MaterialAnimation>(),
+    InspectorPanel {
+      label: "\u{f72b} Material Animation",
+      render: inspector_material_animation,
+      default: material_animation_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<CustomMaterial>(),
+    InspectorPanel {
+      label: "\u{f1fc} Custom Material",
+      render: inspector_custom_material,
+      default: custom_material_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<SharedMaterial>(),
+    InspectorPanel {
+      label: "\u{f5c3} Shared Material",
+      render: inspector_shared_material,
+      default: shared_material_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<SequencePlayer>(),
+    InspectorPanel {
+      label: "\u{f008} Sequence Player",
+      render: inspector_sequence_player,
+      default: sequence_player_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Folder>(),
+    InspectorPanel {
+      label: "\u{f07b} Folder",
+      render: inspector_folder,
+      default: folder_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Locked>(),
+    InspectorPanel {
+      label: "\u{f023} Locked",
+      render: inspector_locked,
+      default: locked_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Spline>(),
+    InspectorPanel {
+      label: "\u{f55b} Spline",
+      render: inspector_spline,
+      default: spline_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<SplineFollower>(),
+    InspectorPanel {
+      label: "\u{f55b} Spline Follower",
+      render: inspector_spline_follower,
+      default: spline_follower_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<TriggerVolume>(),
+    InspectorPanel {
+      label: "\u{f192} Trigger Volume",
+      render: inspector_trigger,
+      default: trigger_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Collider>(),
+    InspectorPanel {
+      label: "\u{f6d1} Collider",
+      render: inspector_collider,
+      default: collider_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Vehicle>(),
+    InspectorPanel {
+      label: "\u{f1b9} Vehicle",
+      render: inspector_vehicle,
+      default: vehicle_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Ragdoll>(),
+    InspectorPanel {
+      label: "\u{f183} Ragdoll",
+      render: inspector_ragdoll,
+      default: ragdoll_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<WorldEnvironment>(),
+    InspectorPanel {
+      label: "\u{f765} World Environment",
+      render: inspector_environment,
+      default: environment_default,
+    },
+  );
+  panels.insert(
+    TypeIdNamed::of::<Bindings>(),
+    InspectorPanel {
+      label: "\u{f0c1} Bindings",
+      render: inspector_bindings,
+      default: bindings_default,
+    },
+  );
   world.add_resource(panels);
   Panel {
     title: "\u{f30f} Inspector",
@@ -105,7 +290,7 @@ fn name_default(_: &mut World) -> Box<dyn Any> {
   Box::new(())
 }
 
-fn inspector_transform(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+fn inspector_transform(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
   let transform: &mut Transform = t.downcast_mut().unwrap();
   Drag::new("Position")
     .speed(0.05)
@@ -127,6 +312,21 @@ fn inspector_transform(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
   Drag::new("Scale")
     .speed(0.05)
     .build_array(ui, transform.scale.as_mut());
+
+  let opts = world.get_resource::<PlacementOptions>().unwrap();
+  if ui.button("\u{f063} Drop to ground") {
+    let selected = world.get_resource::<SelectedEntity>().unwrap().0.unwrap();
+    if !placement::drop_to_ground(world, selected, transform, opts.align) {
+      warn!(
+        "Nothing below '{}' to drop onto.",
+        selected
+          .get_one::<Name>()
+          .map_or(selected.id.to_string(), |n| n.0.clone())
+      );
+    }
+  }
+  ui.same_line();
+  ui.checkbox("Align to normal", &mut opts.align);
 }
 
 fn transform_default(_: &mut World) -> Box<dyn Any> {
@@ -143,12 +343,131 @@ fn inspector_camera(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
     .speed(0.05)
     .display_format("%g")
     .build_array(ui, &mut cam.clip);
+  if let Some(_) = ui.begin_combo("Exposure", cam.exposure.name()) {
+    for exposure in Exposure::ALL {
+      if ui.selectable(exposure.name()) {
+        cam.exposure = exposure;
+      }
+    }
+  }
+  match &mut cam.exposure {
+    Exposure::Manual(multiplier) => {
+      Drag::new("Multiplier")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, multiplier);
+    }
+    Exposure::Ev100(ev) => {
+      Drag::new("EV100").speed(0.05).build(ui, ev);
+    }
+    Exposure::Physical {
+      aperture,
+      shutter,
+      iso,
+    } => {
+      Drag::new("Aperture")
+        .speed(0.05)
+        .range(0.5, 64.0)
+        .display_format("f/%g")
+        .build(ui, aperture);
+      Drag::new("Shutter")
+        .speed(0.0005)
+        .range(0.00001, 30.0)
+        .display_format("%gs")
+        .build(ui, shutter);
+      Drag::new("ISO")
+        .speed(5.0)
+        .range(25.0, 102400.0)
+        .build(ui, iso);
+      ui.text_disabled(format!("EV100 {:.2}", cam.exposure.ev100().unwrap()));
+    }
+  }
+  let mut dof = cam.dof.is_some();
+  if ui.checkbox("Depth of Field", &mut dof) {
+    cam.dof = dof.then_some(DepthOfField::DEFAULT);
+  }
+  if let Some(dof) = &mut cam.dof {
+    Drag::new("Focus")
+      .speed(0.05)
+      .range(0.01, f32::MAX)
+      .display_format("%gm")
+      .build(ui, &mut dof.focus);
+    Drag::new("Aperture##dof")
+      .speed(0.05)
+      .range(0.5, 64.0)
+      .display_format("f/%g")
+      .build(ui, &mut dof.aperture);
+    Drag::new("Max Blur")
+      .speed(0.1)
+      .range(0.0, 64.0)
+      .display_format("%gpx")
+      .build(ui, &mut dof.max_radius);
+  }
+  let mut blur = cam.motion_blur.is_some();
+  if ui.checkbox("Motion Blur", &mut blur) {
+    cam.motion_blur = blur.then_some(MotionBlur::DEFAULT);
+  }
+  if let Some(blur) = &mut cam.motion_blur {
+    ui.slider("Shutter", 0.0, 1.0, &mut blur.shutter);
+    hover_tooltip(ui, "How much of each frame the shutter is open for.");
+    ui.slider("Samples", 2, 32, &mut blur.samples);
+  }
+  let mut film = cam.film.is_some();
+  if ui.checkbox("Film Look", &mut film) {
+    cam.film = film.then_some(FilmLook::DEFAULT);
+  }
+  if let Some(film) = &mut cam.film {
+    ui.slider("Vignette", 0.0, 1.0, &mut film.vignette);
+    ui.slider("Vignette Radius", 0.0, 1.0, &mut film.vignette_radius);
+    ui.slider("Grain", 0.0, 0.5, &mut film.grain);
+    Drag::new("Aberration")
+      .speed(0.05)
+      .range(0.0, 32.0)
+      .display_format("%gpx")
+      .build(ui, &mut film.chromatic_aberration);
+    hover_tooltip(
+      ui,
+      "How far apart the red and blue channels are at the edges.",
+    );
+  }
 }
 
 fn camera_default(_: &mut World) -> Box<dyn Any> {
   Box::new(Camera::new(80.0, [0.1, 100.0]))
 }
 
+fn inspector_controller(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let controller: &mut CameraController = t.downcast_mut().unwrap();
+  if let Some(_) = ui.begin_combo("Mode", controller.mode.name()) {
+    for mode in CameraMode::ALL {
+      if ui.selectable(mode.name()) {
+        controller.mode = mode;
+      }
+    }
+  }
+  Drag::new("Speed")
+    .speed(0.05)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut controller.speed);
+  Drag::new("Sensitivity")
+    .speed(0.01)
+    .display_format("%g°")
+    .build(ui, &mut controller.sensitivity);
+  if controller.mode == CameraMode::Orbit {
+    Drag::new("Target")
+      .speed(0.05)
+      .build_array(ui, controller.target.as_mut());
+    Drag::new("Distance")
+      .speed(0.05)
+      .range(0.1, f32::MAX)
+      .build(ui, &mut controller.distance);
+  }
+}
+
+fn controller_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(CameraController::fly())
+}
+
 fn inspector_model(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
   let model: &mut Model = t.downcast_mut().unwrap();
   asset_picker(ui, "Mesh", world, &mut model.mesh);
@@ -161,35 +480,589 @@ fn model_default(world: &mut World) -> Box<dyn Any> {
   Box::new(Model::new(assets.load("cube.obj").unwrap()))
 }
 
+fn inspector_billboard(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let billboard: &mut Billboard = t.downcast_mut().unwrap();
+  if let Some(_) = ui.begin_combo("Mode", billboard.0.name()) {
+    for mode in BillboardMode::ALL {
+      if ui.selectable(mode.name()) {
+        billboard.0 = mode;
+      }
+    }
+  }
+}
+
+fn billboard_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Billboard(BillboardMode::Spherical))
+}
+
+fn inspector_lod(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let lod: &mut Lod = t.downcast_mut().unwrap();
+  Drag::new("Fade")
+    .range(0.0, f32::MAX)
+    .speed(0.05)
+    .build(ui, &mut lod.fade);
+  hover_tooltip(ui, "Distance before each level over which it dithers in.");
+  let mut remove = None;
+  for (i, (distance, mesh)) in lod.levels.iter_mut().enumerate() {
+    let id = ui.push_id_usize(i);
+    ui.set_next_item_width(80.0);
+    Drag::new("##distance")
+      .range(0.0, f32::MAX)
+      .speed(0.1)
+      .display_format("%.1fm")
+      .build(ui, distance);
+    ui.same_line();
+    ui.set_next_item_width(ui.content_region_avail()[0] - 30.0);
+    asset_picker(ui, "##mesh", world, mesh);
+    ui.same_line();
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+    id.pop();
+  }
+  if let Some(i) = remove {
+    lod.levels.remove(i);
+  }
+  if ui.button("Add level") {
+    let assets = world.get_resource::<Assets>().unwrap();
+    let distance = lod.levels.last().map_or(10.0, |l| l.0 + 10.0);
+    lod
+      .levels
+      .push((distance, assets.load("cube.obj").unwrap()));
+  }
+  // keep them nearest first after edits
+  lod.levels.sort_by(|a, b| a.0.total_cmp(&b.0));
+}
+
+fn lod_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Lod::new())
+}
+
+fn inspector_environment(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let env: &mut WorldEnvironment = t.downcast_mut().unwrap();
+  Drag::new("Sun Direction")
+    .speed(0.5)
+    .display_format("%g°")
+    .build_array(ui, env.sun_dir.as_mut());
+  let mut physical = env.sun_lux.is_some();
+  if ui.checkbox("Sun in Lux", &mut physical) {
+    // direct sunlight
+    env.sun_lux = physical.then_some(100000.0);
+  }
+  if let Some(lux) = &mut env.sun_lux {
+    Drag::new("Illuminance")
+      .speed(100.0)
+      .range(0.0, f32::MAX)
+      .display_format("%g lx")
+      .build(ui, lux);
+  }
+  ui.color_edit3("Ambient", env.ambient.as_mut());
+  hover_tooltip(ui, "Light every surface gets, as a fraction of the sun's.");
+
+  let current = env
+    .skybox
+    .as_ref()
+    .map_or("None".to_string(), |s| s.name.clone());
+  let cubemaps = world.get_resource::<Assets>().unwrap().get::<Cubemap>();
+  if let Some(_) = ui.begin_combo("Skybox", &current) {
+    if ui.selectable("None") {
+      env.skybox = None;
+    }
+    for cubemap in cubemaps {
+      if ui
+        .selectable_config(cubemap.name.clone())
+        .selected(current == cubemap.name)
+        .build()
+      {
+        env.skybox = Some(cubemap);
+      }
+    }
+  }
+  ui.disabled(env.skybox.is_none(), || {
+    Drag::new("Skybox Intensity")
+      .range(0.0, f32::MAX)
+      .speed(0.01)
+      .build(ui, &mut env.skybox_intensity);
+  });
+
+  ui.color_edit3("Fog Color", env.fog.color.as_mut());
+  Drag::new("Fog Density")
+    .range(0.0, f32::MAX)
+    .speed(0.001)
+    .build(ui, &mut env.fog.density);
+  hover_tooltip(ui, "0 disables fog.");
+  Drag::new("Fog Start")
+    .range(0.0, f32::MAX)
+    .speed(0.1)
+    .build(ui, &mut env.fog.start);
+
+  if ui.collapsing_header("Post Processing", TreeNodeFlags::DEFAULT_OPEN) {
+    environment::post_effects(&mut env.post, ui);
+  }
+}
+
+// starts from the current look so nothing changes when it's added
+fn environment_default(world: &mut World) -> Box<dyn Any> {
+  Box::new(WorldEnvironment::from_world(world))
+}
+
+fn inspector_bindings(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let bindings: &mut Bindings = t.downcast_mut().unwrap();
+  let e = world.get_resource::<SelectedEntity>().unwrap().0.unwrap();
+  let mut remove = None;
+  for (i, b) in bindings.0.iter_mut().enumerate() {
+    let _id = ui.push_id_usize(i);
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+    ui.same_line();
+    ui.set_next_item_width(100.0);
+    if let Some(_) = ui.begin_combo("##property", b.property.name()) {
+      for p in Property::ALL {
+        if ui.selectable(p.name()) {
+          b.property = p;
+        }
+      }
+    }
+    ui.same_line();
+    ui.input_text("##expr", &mut b.expr).build();
+    match b.error().map(str::to_string) {
+      Some(err) => ui.text_colored([0.749, 0.38, 0.416, 1.0], err),
+      None => match b.value(world, e) {
+        Some(v) => ui.text_disabled(format!("= {:.3}", v)),
+        None => ui.text_disabled("Reads something missing."),
+      },
+    }
+  }
+  if let Some(i) = remove {
+    bindings.0.remove(i);
+  }
+  if ui.small_button("\u{2b} Bind") {
+    bindings.0.push(Binding::new(Property::ALL[0], "0"));
+  }
+  hover_tooltip(
+    ui,
+    "Expressions like 'sin(time) * 2', 'door.y + 1' or '\"main light\".strength', with cvars by name.",
+  );
+}
+
+fn bindings_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Bindings::new())
+}
+
+fn inspector_streaming_volume(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let volume: &mut StreamingVolume = t.downcast_mut().unwrap();
+  ui.input_text("Scene", &mut volume.scene).build();
+  Drag::new("Half Extents")
+    .range(0.0, f32::MAX)
+    .speed(0.05)
+    .build_array(ui, volume.half_extents.as_mut());
+  Drag::new("Margin")
+    .range(0.0, f32::MAX)
+    .speed(0.05)
+    .build(ui, &mut volume.margin);
+  hover_tooltip(ui, "Distance past the box before the scene unloads.");
+  Drag::new("Priority").build(ui, &mut volume.priority);
+}
+
+fn streaming_volume_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(StreamingVolume::new("", Vec3::splat(10.0)))
+}
+
 fn inspector_material(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
   let mat: &mut Material = t.downcast_mut().unwrap();
-  ui.color_edit3("Color", mat.color.as_mut());
-  let mut use_tex = mat.tex.is_some();
-  if ui.checkbox("Texture", &mut use_tex) {
-    mat.tex = use_tex.then(|| {
-      world
-        .get_resource::<Assets>()
-        .unwrap()
-        .load("garfield.png")
-        .unwrap()
-    });
+  ui.color_edit3("Albedo", mat.albedo.as_mut());
+  texture_picker(ui, "Albedo Map", world, &mut mat.albedo_tex);
+  ui.slider("Metallic", 0.0, 1.0, &mut mat.metallic);
+  texture_picker(ui, "Metallic Map", world, &mut mat.metallic_tex);
+  ui.slider("Roughness", 0.0, 1.0, &mut mat.roughness);
+  texture_picker(ui, "Roughness Map", world, &mut mat.roughness_tex);
+  texture_picker(ui, "Normal Map", world, &mut mat.normal_tex);
+  ui.slider("AO", 0.0, 1.0, &mut mat.ao);
+  texture_picker(ui, "AO Map", world, &mut mat.ao_tex);
+  ui.checkbox("Transparent", &mut mat.transparent);
+  ui.disabled(!mat.transparent, || {
+    ui.slider("Alpha", 0.0, 1.0, &mut mat.alpha);
+  });
+  Drag::new("Emissive")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut mat.emissive);
+  Drag::new("UV Offset")
+    .speed(0.01)
+    .build_array(ui, mat.uv_offset.as_mut());
+  if ui.button("\u{f0c7} Save as .mat") {
+    save_material(world, mat);
   }
-  if use_tex {
-    asset_picker(ui, "Texture", world, mat.tex.as_mut().unwrap())
+}
+
+// writes the material, and a custom one on the same entity, then loads it so it can be shared
+fn save_material(world: &mut World, mat: &Material) {
+  let assets_dir = env::current_dir().unwrap().join("assets");
+  let path = match FileDialog::new()
+    .set_directory(&assets_dir)
+    .add_filter("Material", &["mat"])
+    .save_file()
+  {
+    Some(p) => p,
+    None => return,
+  };
+  let selected = world.get_resource::<SelectedEntity>().unwrap().0.unwrap();
+  let custom = selected.get_one::<CustomMaterial>();
+  if let Err(e) = MaterialAsset::save(mat, custom.as_deref(), &path) {
+    error!("Couldn't save '{}'. {}", path.display(), e);
+    return;
   }
-  ui.slider("Specular", 0.0, 1.0, &mut mat.spec);
-  ui.slider("Metallic", 0.0, 1.0, &mut mat.metallic);
+  if let Ok(name) = path.strip_prefix(&assets_dir) {
+    let assets = world.get_resource::<Assets>().unwrap();
+    match assets.load::<MaterialAsset>(&name.to_string_lossy()) {
+      Ok(h) => assets.keep(h),
+      Err(e) => error!("Couldn't load '{}'. {}", path.display(), e),
+    }
+  }
+}
+
+fn inspector_shared_material(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let shared: &mut SharedMaterial = t.downcast_mut().unwrap();
+  asset_picker(ui, "Material", world, &mut shared.0);
+  if let Some(custom) = &shared.0.custom {
+    ui.text_disabled(format!("Shader: {}", custom.shader));
+  }
+  hover_tooltip(
+    ui,
+    "Material and Custom Material on the entity take priority.",
+  );
+}
+
+fn shared_material_default(world: &mut World) -> Box<dyn Any> {
+  let assets = world.get_resource::<Assets>().unwrap();
+  Box::new(SharedMaterial(assets.load("default.mat").unwrap()))
+}
+
+fn inspector_sequence_player(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let player: &mut SequencePlayer = t.downcast_mut().unwrap();
+  asset_picker(ui, "Sequence", world, &mut player.sequence);
+  ui.checkbox("Playing", &mut player.playing);
+  ui.checkbox("Looping", &mut player.looping);
+  Drag::new("Speed")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut player.speed);
+  ui.text_disabled(format!(
+    "{:.2}s / {:.2}s",
+    player.time(),
+    player.sequence.duration
+  ));
+  ui.same_line();
+  if ui.small_button("\u{f2f9} Restart") {
+    player.restart();
+  }
+  hover_tooltip(ui, "Edit the tracks in the Sequencer panel.");
+}
+
+fn sequence_player_default(world: &mut World) -> Box<dyn Any> {
+  let assets = world.get_resource::<Assets>().unwrap();
+  let sequence = match assets.get::<Sequence>().into_iter().next() {
+    Some(s) => s,
+    None => assets.insert("untitled.seq", Sequence::new(5.0)),
+  };
+  Box::new(SequencePlayer::new(sequence))
+}
+
+fn inspector_folder(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let folder: &mut Folder = t.downcast_mut().unwrap();
+  ui.checkbox("Visible", &mut folder.visible);
+  ui.checkbox("Locked", &mut folder.locked);
+  hover_tooltip(ui, "Applies to everything in the folder.");
+}
+
+fn folder_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Folder::DEFAULT)
+}
+
+fn inspector_locked(_: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  ui.text_disabled("Can't be selected or edited, along with its children.");
+}
+
+fn locked_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Locked)
+}
+
+fn inspector_spline(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let spline: &mut Spline = t.downcast_mut().unwrap();
+  if let Some(_) = ui.begin_combo("Kind", spline.kind.name()) {
+    for kind in SplineKind::ALL {
+      if ui.selectable(kind.name()) {
+        spline.kind = kind;
+      }
+    }
+  }
+  ui.checkbox("Closed", &mut spline.closed);
+  let mut remove = None;
+  for i in 0..spline.points.len() {
+    let _id = ui.push_id_usize(i);
+    let label = match spline.is_anchor(i) {
+      true => "Point",
+      false => "Handle",
+    };
+    Drag::new(label)
+      .speed(0.05)
+      .build_array(ui, spline.points[i].as_mut());
+    if spline.is_anchor(i) {
+      ui.same_line();
+      if ui.small_button("\u{f1f8}") {
+        remove = Some(i);
+      }
+    }
+  }
+  if let Some(i) = remove {
+    spline.remove_point(i);
+  }
+  if ui.small_button("\u{2b} Point") {
+    // carries on the way the end was going
+    let next = match spline.points.as_slice() {
+      [.., a, b] => *b + (*b - *a).normalize_or_zero(),
+      [a] => *a + Vec3::X,
+      [] => Vec3::ZERO,
+    };
+    spline.add_point(next);
+  }
+  ui.same_line();
+  ui.text_disabled(format!("{:.2} long", spline.length()));
+  ui.separator();
+  let tool = world.get_resource::<SplineTool>().unwrap();
+  tool.settings(ui);
+  let selected = world.get_resource::<SelectedEntity>().unwrap().0.unwrap();
+  if ui.button("Extrude Road") {
+    spline::extrude(world, selected, spline);
+  }
+  hover_tooltip(
+    ui,
+    "Saves a strip along the curve and puts it on this entity's Model.",
+  );
+  ui.same_line();
+  if ui.button("Scatter") {
+    spline::scatter(world, selected, spline);
+  }
+  hover_tooltip(
+    ui,
+    "Replaces this entity's Foliage with instances along the curve.",
+  );
+}
+
+fn spline_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(
+    Spline::new(SplineKind::CatmullRom)
+      .point(Vec3::ZERO)
+      .point(Vec3::new(0.0, 0.0, -4.0)),
+  )
+}
+
+fn inspector_spline_follower(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let follower: &mut SplineFollower = t.downcast_mut().unwrap();
+  if let Some(_) = ui.begin_combo("Spline", &follower.spline) {
+    for (e, _) in world.query::<Spline>() {
+      if let Some(name) = e.get_one::<Name>() {
+        if ui.selectable(&name.0) {
+          follower.spline = name.0.clone();
+        }
+      }
+    }
+  }
+  Drag::new("Speed")
+    .speed(0.05)
+    .build(ui, &mut follower.speed);
+  Drag::new("Distance")
+    .speed(0.05)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut follower.distance);
+  ui.checkbox("Looping", &mut follower.looping);
+  ui.checkbox("Face along", &mut follower.align);
+}
+
+fn spline_follower_default(world: &mut World) -> Box<dyn Any> {
+  let spline = world
+    .query::<Spline>()
+    .into_iter()
+    .find_map(|(e, _)| e.get_one::<Name>().map(|n| n.0.clone()))
+    .unwrap_or_default();
+  Box::new(SplineFollower::new(&spline))
+}
+
+fn inspector_trigger(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let trigger: &mut TriggerVolume = t.downcast_mut().unwrap();
+  if let Some(_) = ui.begin_combo("Shape", trigger.shape.name()) {
+    for shape in TriggerShape::ALL {
+      if ui.selectable(shape.name()) {
+        trigger.shape = shape;
+      }
+    }
+  }
+  match &mut trigger.shape {
+    TriggerShape::Box(half) => {
+      Drag::new("Half Extents")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build_array(ui, half.as_mut());
+    }
+    TriggerShape::Sphere(radius) => {
+      Drag::new("Radius")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, radius);
+    }
+  }
+  let mut remove = None;
+  for (i, target) in trigger.targets.iter().enumerate() {
+    let _id = ui.push_id_usize(i);
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+    ui.same_line();
+    ui.text(target);
+  }
+  if let Some(i) = remove {
+    trigger.targets.remove(i);
+  }
+  if let Some(_) = ui.begin_combo("##target", "\u{2b} Target") {
+    for (_, name) in world.query::<Name>() {
+      if ui.selectable(&name.0) && !trigger.targets.contains(&name.0) {
+        trigger.targets.push(name.0.clone());
+      }
+    }
+  }
+  hover_tooltip(ui, "Entities that set it off, by name.");
+  ui.input_text("On Enter", &mut trigger.on_enter).build();
+  ui.input_text("On Exit", &mut trigger.on_exit).build();
+  ui.checkbox("Once", &mut trigger.once);
+  ui.text_disabled(format!("{} inside", trigger.inside().len()));
+}
+
+fn trigger_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(TriggerVolume::new(TriggerShape::ALL[0]))
 }
 
 fn material_default(_: &mut World) -> Box<dyn Any> {
   Box::new(Material::DEFAULT)
 }
 
+fn inspector_custom_material(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let custom: &mut CustomMaterial = t.downcast_mut().unwrap();
+  let shaders = world.get_resource::<MaterialShaders>().unwrap();
+  if let Some(_) = ui.begin_combo("Shader", &custom.shader) {
+    for name in shaders.names() {
+      if ui.selectable(name) {
+        custom.shader = name.to_string();
+      }
+    }
+  }
+  let shader = match shaders.get(&custom.shader) {
+    Some(s) => s,
+    None => {
+      ui.text_disabled("Shader isn't registered.");
+      return;
+    }
+  };
+  custom.sync(shader);
+  let names = shader
+    .properties
+    .iter()
+    .map(|p| p.name.clone())
+    .collect::<Vec<_>>();
+  for name in names {
+    match custom.values.get_mut(&name).unwrap() {
+      UniformValue::Float(v) => {
+        Drag::new(&name).speed(0.01).build(ui, v);
+      }
+      UniformValue::Vec2(v) => {
+        Drag::new(&name).speed(0.01).build_array(ui, v.as_mut());
+      }
+      UniformValue::Vec3(v) => {
+        Drag::new(&name).speed(0.01).build_array(ui, v.as_mut());
+      }
+      UniformValue::Vec4(v) => {
+        Drag::new(&name).speed(0.01).build_array(ui, v.as_mut());
+      }
+      UniformValue::Int(v) => {
+        Drag::new(&name).build(ui, v);
+      }
+      UniformValue::Bool(v) => {
+        ui.checkbox(&name, v);
+      }
+      UniformValue::Texture(tex) => texture_picker(ui, &name, world, tex),
+    }
+  }
+}
+
+fn custom_material_default(world: &mut World) -> Box<dyn Any> {
+  let shaders = world.get_resource::<MaterialShaders>().unwrap();
+  Box::new(CustomMaterial::new(
+    shaders.names().first().copied().unwrap_or(""),
+  ))
+}
+
+fn inspector_material_animation(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let anim: &mut MaterialAnimation = t.downcast_mut().unwrap();
+  ui.checkbox("Playing", &mut anim.playing);
+  ui.same_line();
+  ui.checkbox("Loop", &mut anim.looping);
+  ui.same_line();
+  if ui.small_button("Restart") {
+    anim.restart();
+  }
+  Drag::new("Duration")
+    .speed(0.01)
+    .range(0.01, f32::MAX)
+    .display_format("%gs")
+    .build(ui, &mut anim.duration);
+  Drag::new("UV Scroll")
+    .speed(0.01)
+    .build_array(ui, anim.uv_scroll.as_mut());
+  hover_tooltip(ui, "UV units per second.");
+  optional_curve(ui, "Albedo", &mut anim.albedo, Vec3::ONE, |ui, v| {
+    ui.color_edit3("##", v.as_mut());
+  });
+  optional_curve(ui, "Emissive", &mut anim.emissive, 0.0, |ui, v| {
+    Drag::new("##")
+      .speed(0.01)
+      .range(0.0, f32::MAX)
+      .build(ui, v);
+  });
+  optional_curve(ui, "Alpha", &mut anim.alpha, 1.0, |ui, v| {
+    ui.slider("##", 0.0, 1.0, v);
+  });
+}
+
+fn material_animation_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(MaterialAnimation::new(1.0))
+}
+
+// a checkbox to animate the property at all, then its curve
+fn optional_curve<T: Lerp>(
+  ui: &Ui,
+  label: &str,
+  curve: &mut Option<Curve<T>>,
+  default: T,
+  value: fn(&Ui, &mut T),
+) {
+  let mut enabled = curve.is_some();
+  if ui.checkbox(format!("Animate {}", label), &mut enabled) {
+    *curve = enabled.then(|| Curve::constant(default));
+  }
+  if let Some(curve) = curve {
+    curve_editor(ui, label, curve, value);
+  }
+}
+
 fn inspector_audiosource(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
   let audio_source: &mut AudioSource = t.downcast_mut().unwrap();
   asset_picker(ui, "Sound", world, &mut audio_source.sound);
   ui.slider("Pitch", 0.1, 10.0, &mut audio_source.pitch);
   ui.checkbox("Play on start", &mut audio_source.play_on_start);
+  let mut subtitle = audio_source.subtitle.clone().unwrap_or_default();
+  if ui.input_text("Subtitle", &mut subtitle).build() {
+    audio_source.subtitle = (!subtitle.is_empty()).then_some(subtitle);
+  }
 }
 
 fn audiosource_default(world: &mut World) -> Box<dyn Any> {
@@ -200,19 +1073,348 @@ fn audiosource_default(world: &mut World) -> Box<dyn Any> {
 fn inspector_light(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
   let light: &mut Light = t.downcast_mut().unwrap();
   ui.color_edit3("Color", light.color.as_mut());
-  ui.slider("Strength", 0.0, 10.0, &mut light.strength);
+  if let Some(_) = ui.begin_combo("Unit", light.unit.name()) {
+    for unit in LightUnit::ALL {
+      if ui.selectable(unit.name()) {
+        // the same brightness in the new unit
+        match (light.unit, unit) {
+          (LightUnit::Lumens, LightUnit::Candela) => light.intensity /= 4.0 * PI,
+          (LightUnit::Candela, LightUnit::Lumens) => light.intensity *= 4.0 * PI,
+          _ => {}
+        }
+        light.unit = unit;
+      }
+    }
+  }
+  match light.unit {
+    LightUnit::Relative => {
+      ui.slider("Strength", 0.0, 10.0, &mut light.strength);
+    }
+    unit => {
+      Drag::new("Intensity")
+        .speed(1.0)
+        .range(0.0, f32::MAX)
+        .display_format(match unit {
+          LightUnit::Lumens => "%g lm",
+          _ => "%g cd",
+        })
+        .build(ui, &mut light.intensity);
+    }
+  }
+  ui.checkbox("Cast Shadows", &mut light.shadows);
+  if light.shadows {
+    if let Some(_) = ui.begin_combo("Shadow Resolution", light.shadow_res.to_string()) {
+      for res in [256, 512, 1024, 2048] {
+        if ui.selectable(res.to_string()) {
+          light.shadow_res = res;
+        }
+      }
+    }
+    hover_tooltip(ui, "Only the first 4 shadowed lights get shadows.");
+  }
 }
 
 fn light_default(_: &mut World) -> Box<dyn Any> {
   Box::new(Light::new(Vec3::ONE))
 }
 
+fn inspector_collider(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let collider: &mut Collider = t.downcast_mut().unwrap();
+  let m = &mut collider.material;
+  Drag::new("Friction")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut m.friction);
+  combine_rule(ui, "Friction Combine", &mut m.friction_combine);
+  ui.slider("Restitution", 0.0, 1.0, &mut m.restitution);
+  combine_rule(ui, "Restitution Combine", &mut m.restitution_combine);
+  hover_tooltip(
+    ui,
+    "The later of Average, Min, Multiply and Max wins between two colliders.",
+  );
+  layer_mask(ui, "Layers", &mut collider.layers);
+  layer_mask(ui, "Collides With", &mut collider.mask);
+}
+
+fn combine_rule(ui: &Ui, label: &str, rule: &mut CoefficientCombineRule) {
+  if let Some(_) = ui.begin_combo(label, format!("{:?}", rule)) {
+    for r in [
+      CoefficientCombineRule::Average,
+      CoefficientCombineRule::Min,
+      CoefficientCombineRule::Multiply,
+      CoefficientCombineRule::Max,
+    ] {
+      if ui.selectable(format!("{:?}", r)) {
+        *rule = r;
+      }
+    }
+  }
+}
+
+fn layer_mask(ui: &Ui, label: &str, mask: &mut u32) {
+  let preview = match *mask {
+    ALL_LAYERS => "All".to_string(),
+    0 => "None".to_string(),
+    m => (0..32)
+      .filter(|i| m & 1 << i != 0)
+      .map(|i| i.to_string())
+      .collect::<Vec<_>>()
+      .join(", "),
+  };
+  if let Some(_) = ui.begin_combo(label, preview) {
+    if ui.small_button("All") {
+      *mask = ALL_LAYERS;
+    }
+    ui.same_line();
+    if ui.small_button("None") {
+      *mask = 0;
+    }
+    for i in 0..32 {
+      ui.checkbox_flags(format!("Layer {}", i), mask, 1 << i);
+    }
+  }
+}
+
+// without the rapier plugin there's no set to add to, so the collider only holds its settings
+fn collider_default(world: &mut World) -> Box<dyn Any> {
+  Box::new(match world.get_resource::<ColliderSet>() {
+    Some(_) => ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(world),
+    None => Collider {
+      handle: ColliderHandle::invalid(),
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
+    },
+  })
+}
+
+fn inspector_vehicle(t: &mut Box<dyn Any>, ui: &Ui, _: &mut World) {
+  let vehicle: &mut Vehicle = t.downcast_mut().unwrap();
+  Drag::new("Engine Force")
+    .range(0.0, f32::MAX)
+    .build(ui, &mut vehicle.engine_force);
+  Drag::new("Brake Force")
+    .speed(0.05)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut vehicle.brake_force);
+  ui.slider_config("Max Steer", 0.0, 60.0)
+    .display_format("%g°")
+    .build(&mut vehicle.max_steer);
+  let mut remove = None;
+  for (i, wheel) in vehicle.wheels.iter_mut().enumerate() {
+    let id = ui.push_id_usize(i);
+    if let Some(_) = ui.tree_node(format!("Wheel {}", i)) {
+      Drag::new("Position")
+        .speed(0.01)
+        .build_array(ui, wheel.position.as_mut());
+      Drag::new("Radius")
+        .speed(0.01)
+        .range(0.01, f32::MAX)
+        .build(ui, &mut wheel.radius);
+      Drag::new("Rest Length")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.rest_length);
+      Drag::new("Travel")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.max_travel);
+      Drag::new("Stiffness")
+        .speed(0.05)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.stiffness);
+      Drag::new("Compression")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.compression);
+      Drag::new("Relaxation")
+        .speed(0.01)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.relaxation);
+      Drag::new("Friction Slip")
+        .speed(0.1)
+        .range(0.0, f32::MAX)
+        .build(ui, &mut wheel.friction_slip);
+      ui.checkbox("Steered", &mut wheel.steered);
+      ui.same_line();
+      ui.checkbox("Driven", &mut wheel.driven);
+      if ui.small_button("\u{f1f8} Remove") {
+        remove = Some(i);
+      }
+    }
+    id.pop();
+  }
+  if let Some(i) = remove {
+    vehicle.wheels.remove(i);
+  }
+  if ui.button("Add wheel") {
+    let wheel = vehicle
+      .wheels
+      .last()
+      .copied()
+      .unwrap_or(Wheel::new(Vec3::ZERO));
+    vehicle.wheels.push(wheel);
+  }
+}
+
+fn vehicle_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Vehicle::four_wheels(1.6, 2.6))
+}
+
+fn inspector_ragdoll(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let ragdoll: &mut Ragdoll = t.downcast_mut().unwrap();
+  ui.slider("Weight", 0.0, 1.0, &mut ragdoll.weight);
+  hover_tooltip(ui, "0 follows the bones' transforms, 1 is fully simulated.");
+  ui.slider("Thickness", 0.01, 1.0, &mut ragdoll.thickness);
+  ui.slider_config("Joint Limit", 0.0, 180.0)
+    .display_format("%g°")
+    .build(&mut ragdoll.joint_limit);
+  // bodies only exist with the rapier plugin
+  if world.get_resource::<RigidBodySet>().is_some() && ui.button("Rebuild") {
+    ragdoll.clear(world);
+  }
+}
+
+fn ragdoll_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(Ragdoll::new())
+}
+
+fn inspector_foliage(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let foliage: &mut Foliage = t.downcast_mut().unwrap();
+  asset_picker(ui, "Mesh", world, &mut foliage.mesh);
+  ui.checkbox("Cast Shadows", &mut foliage.cast_shadows);
+  ui.text(format!("{} instances", foliage.instances.len()));
+  ui.same_line();
+  if ui.small_button("Clear") {
+    foliage.instances.clear();
+  }
+  ui.separator();
+  world.get_resource::<FoliageBrush>().unwrap().settings(ui);
+}
+
+fn foliage_default(world: &mut World) -> Box<dyn Any> {
+  let assets = world.get_resource::<Assets>().unwrap();
+  Box::new(Foliage::new(assets.load("cube.obj").unwrap()))
+}
+
+fn inspector_particles(t: &mut Box<dyn Any>, ui: &Ui, world: &mut World) {
+  let emitter: &mut ParticleEmitter = t.downcast_mut().unwrap();
+  ui.checkbox("Playing", &mut emitter.playing);
+  ui.same_line();
+  if ui.small_button("Restart") {
+    emitter.clear();
+  }
+  ui.same_line();
+  ui.text_disabled(format!("{} alive", emitter.count()));
+  Drag::new("Rate")
+    .range(0.0, f32::MAX)
+    .build(ui, &mut emitter.rate);
+  let mut max = emitter.max_particles as i32;
+  if Drag::new("Max Particles")
+    .range(0, i32::MAX)
+    .build(ui, &mut max)
+  {
+    emitter.max_particles = max as _;
+  }
+  Drag::new("Lifetime")
+    .speed(0.01)
+    .range(0.01, f32::MAX)
+    .display_format("%gs")
+    .build_array(ui, &mut emitter.lifetime);
+  Drag::new("Radius")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut emitter.radius);
+  Drag::new("Velocity")
+    .speed(0.01)
+    .build_array(ui, emitter.velocity.as_mut());
+  Drag::new("Spread")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut emitter.spread);
+  Drag::new("Gravity")
+    .speed(0.01)
+    .build_array(ui, emitter.gravity.as_mut());
+  if let Some(_) = ui.begin_combo("Blend", emitter.blend.name()) {
+    for blend in ParticleBlend::ALL {
+      if ui.selectable(blend.name()) {
+        emitter.blend = blend;
+      }
+    }
+  }
+  texture_picker(ui, "Texture", world, &mut emitter.texture);
+  Drag::new("Softness")
+    .speed(0.01)
+    .range(0.0, f32::MAX)
+    .build(ui, &mut emitter.softness);
+  curve_editor(ui, "Speed over life", &mut emitter.speed, |ui, v| {
+    Drag::new("##").speed(0.01).build(ui, v);
+  });
+  curve_editor(ui, "Size over life", &mut emitter.size, |ui, v| {
+    Drag::new("##")
+      .speed(0.01)
+      .range(0.0, f32::MAX)
+      .build(ui, v);
+  });
+  curve_editor(ui, "Color over life", &mut emitter.color, |ui, v| {
+    ui.color_edit4("##", v.as_mut());
+  });
+}
+
+fn particles_default(_: &mut World) -> Box<dyn Any> {
+  Box::new(ParticleEmitter::new())
+}
+
+// one row per key, the time on the left and the value on the right
+fn curve_editor<T: Lerp>(ui: &Ui, label: &str, curve: &mut Curve<T>, value: fn(&Ui, &mut T)) {
+  let id = ui.push_id(label);
+  ui.text(label);
+  ui.same_line();
+  if ui.small_button("\u{2b}") {
+    let v = curve.sample(1.0);
+    curve.keys.push((1.0, v));
+  }
+  let mut remove = None;
+  let w = ui.content_region_avail()[0];
+  for (i, (t, v)) in curve.keys.iter_mut().enumerate() {
+    let _id = ui.push_id_usize(i);
+    ui.set_next_item_width(w * 0.25);
+    ui.slider("##t", 0.0, 1.0, t);
+    ui.same_line();
+    ui.set_next_item_width(w * 0.6);
+    value(ui, v);
+    ui.same_line();
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+  }
+  // a curve needs at least one key
+  if let Some(i) = remove.filter(|_| curve.keys.len() > 1) {
+    curve.keys.remove(i);
+  }
+  curve.keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+  id.pop();
+}
+
 fn render(world: &mut World, ui: &Ui) {
+  let locale = world.get_resource::<Locale>().unwrap();
   match world.get_resource::<SelectedEntity>().unwrap().0 {
     Some(e) => {
       let panels = world
         .get_resource::<HashMap<TypeIdNamed, InspectorPanel>>()
         .unwrap();
+      // it could have been selected before being locked, it stays visible but can't be changed
+      let locked = Folder::locks(e);
+      if locked {
+        ui.text_disabled(locale.tr("\u{f023} Locked"));
+        if e.has::<Locked>() {
+          ui.same_line();
+          if ui.small_button(locale.tr("Unlock")) {
+            world.commands().remove::<Locked>(e);
+          }
+        }
+      }
+      let disabled = ui.begin_disabled(locked);
 
       for (t, mut v) in e.get_all() {
         match panels.get(&t) {
@@ -220,18 +1422,24 @@ fn render(world: &mut World, ui: &Ui) {
             for (i, c) in v.iter_mut().enumerate() {
               let id = ui.push_id_usize(i);
               let mut close = true;
-              if ui.collapsing_header_with_close_button(
+              let open = ui.collapsing_header_with_close_button(
                 panel.label,
                 TreeNodeFlags::DEFAULT_OPEN,
                 &mut close,
-              ) {
+              );
+              label(world, Role::Button, panel.label);
+              if open {
                 hover_tooltip(ui, t.name);
-                (panel.render)(c, ui, mutate(world));
+                ui.group(|| (panel.render)(c, ui, mutate(world)));
+                // checkboxes and buttons are only active while held, so they show as deactivated
+                if ui.is_item_active() || ui.is_item_deactivated() {
+                  world.set_changed(t, e.id);
+                }
               } else {
                 hover_tooltip(ui, t.name);
               }
               if !close {
-                world.remove_id(t, e.id);
+                world.commands().remove_id(t, e.id);
               }
               id.pop();
             }
@@ -243,24 +1451,47 @@ fn render(world: &mut World, ui: &Ui) {
       }
       ui.separator();
       let [w, _] = ui.window_size();
-      if ui.button_with_size("\u{2b} Add Component", [w, 0.0]) {
+      if ui.button_with_size(locale.tr("\u{2b} Add Component"), [w, 0.0]) {
         ui.open_popup("addcomponent")
       }
       ui.popup("addcomponent", || {
         for (t, i) in panels.iter() {
           if *t != TypeIdNamed::of::<Name>() && ui.selectable_config(i.label).size([w, 0.0]).build()
           {
-            mutate(world)
-              .components
-              .push_or_insert(*t, (e.id, (i.default)(mutate(world))));
+            let (t, default) = (*t, i.default);
+            world.commands().add(move |world| {
+              let c = default(world);
+              world.insert_id(t, e.id, c);
+            });
           }
         }
       });
+      disabled.end();
     }
-    None => ui.text("\u{f071} No entity selected."),
+    None => ui.text(locale.tr("\u{f071} No entity selected.")),
   }
 }
 
+fn texture_picker(ui: &Ui, label: &str, world: &mut World, tex: &mut Option<Handle<Texture>>) {
+  let id = ui.push_id(label);
+  let mut use_tex = tex.is_some();
+  if ui.checkbox("##", &mut use_tex) {
+    *tex = use_tex.then(|| {
+      world
+        .get_resource::<Assets>()
+        .unwrap()
+        .load("garfield.png")
+        .unwrap()
+    });
+  }
+  ui.same_line();
+  match tex {
+    Some(tex) => asset_picker(ui, label, world, tex),
+    None => ui.text_disabled(label),
+  }
+  id.pop();
+}
+
 fn asset_picker<T: Any>(ui: &Ui, label: &str, world: &mut World, handle: &mut Handle<T>) {
   let id = ui.push_id("##");
   let assets = world.get_resource::<Assets>().unwrap();
@@ -5,11 +5,51 @@ mod inspector;
 mod settings;
 mod log;
 mod assets;
+mod stats;
+mod gizmos;
+mod viewport;
+mod budget;
+mod placement;
+mod foliage;
+mod spline;
+mod tweaks;
+mod game;
+mod graph;
+mod vram;
+mod gpu;
+mod sequencer;
+mod unused;
+pub mod play;
 
 use phosphor::Result;
 use phosphor::ecs::World;
 use phosphor_imgui::imgui::{Ui, WindowFlags, StyleVar};
 
+// which panel has the mouse and which has the keyboard, filled in as panels are drawn so systems
+// running before the ui see the last frame's
+pub struct PanelFocus {
+  pub hovered: Option<&'static str>,
+  pub focused: Option<&'static str>,
+  // a text field has the keyboard, so key shortcuts and movement are ignored
+  pub typing: bool,
+}
+
+impl PanelFocus {
+  pub const NONE: Self = Self {
+    hovered: None,
+    focused: None,
+    typing: false,
+  };
+
+  pub fn is_hovered(&self, title: &str) -> bool {
+    self.hovered == Some(title)
+  }
+
+  pub fn is_focused(&self, title: &str) -> bool {
+    self.focused == Some(title)
+  }
+}
+
 pub struct Panel {
   pub title: &'static str,
   pub flags: WindowFlags,
@@ -20,21 +60,42 @@ pub struct Panel {
 
 // use linkme for this
 pub fn setup_panels(world: &mut World) -> Result {
+  world.add_resource(PanelFocus::NONE);
   let scene = scene::init(world)?;
+  let game = game::init(world);
   let outline = outline::init();
   let environment = environment::init();
   let inspector = inspector::init(world);
   let settings = settings::init(world);
   let log = log::init(world);
   let assets = assets::init(world);
+  let stats = stats::init();
+  let budget = budget::init(world);
+  let tweaks = tweaks::init();
+  let graph = graph::init();
+  let vram = vram::init();
+  let gpu = gpu::init();
+  let sequencer = sequencer::init(world);
+  let unused = unused::init(world);
+  let play = play::init();
   world.add_resource(vec![
     scene,
+    game,
     outline,
     environment,
     inspector,
     settings,
     log,
     assets,
+    stats,
+    budget,
+    tweaks,
+    graph,
+    vram,
+    gpu,
+    sequencer,
+    unused,
+    play,
   ]);
   Ok(())
 }
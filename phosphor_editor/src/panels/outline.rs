@@ -1,7 +1,12 @@
-use phosphor::ecs::{World, Name};
-use phosphor_3d::Transform;
-use phosphor_imgui::imgui::{Ui, WindowFlags};
+use phosphor::ecs::{World, Name, Entity};
+use phosphor_3d::{Transform, Folder, Locked};
+use phosphor_imgui::hover_tooltip;
+use phosphor_imgui::imgui::{
+  Ui, WindowFlags, TreeNodeFlags, DragDropFlags, StyleColor, Key, WindowFocusedFlags,
+};
+use phosphor_imgui::access::{Role, label};
 use crate::SelectedEntity;
+use crate::locale::Locale;
 use crate::panels::Panel;
 
 pub fn init() -> Panel {
@@ -14,22 +19,139 @@ pub fn init() -> Panel {
   }
 }
 
+fn entity_node(world: &World, ui: &Ui, e: Entity, name: &str, selected: &mut SelectedEntity) {
+  let children = e.children();
+  let mut flags = TreeNodeFlags::OPEN_ON_ARROW | TreeNodeFlags::SPAN_AVAIL_WIDTH;
+  if children.is_empty() {
+    flags |= TreeNodeFlags::LEAF;
+  }
+  if selected.0.is_some_and(|s| s.id == e.id) {
+    flags |= TreeNodeFlags::SELECTED;
+  }
+  let id = ui.push_id_usize(e.id);
+  // hidden and locked entities are dimmed, locked ones can't be selected
+  let locked = Folder::locks(e);
+  let dim = (locked || Folder::hides(e))
+    .then(|| ui.push_style_color(StyleColor::Text, ui.style_color(StyleColor::TextDisabled)));
+  let folder = e.get_one::<Folder>();
+  let text = match folder {
+    Some(_) => format!("\u{f07b} {}", name),
+    None => name.to_string(),
+  };
+  let node = ui.tree_node_config(&text).flags(flags).push();
+  drop(dim);
+  label(world, Role::TreeItem, name);
+  if ui.is_item_clicked() && !ui.is_item_toggled_open() && !locked {
+    *selected = SelectedEntity(Some(e));
+  }
+  if !locked {
+    if let Some(tooltip) = ui.drag_drop_source_config("entity").begin_payload(e.id) {
+      ui.text(name);
+      tooltip.end();
+    }
+  }
+  if let Some(target) = ui.drag_drop_target() {
+    if let Some(Ok(payload)) = target.accept_payload::<usize, _>("entity", DragDropFlags::empty()) {
+      Entity { id: payload.data }.set_parent(Some(e));
+    }
+  }
+  toggles(world, ui, e, folder);
+  if let Some(node) = node {
+    for child in children {
+      let name = child
+        .get_one::<Name>()
+        .map_or("?".to_string(), |n| n.0.clone());
+      entity_node(world, ui, child, &name, selected);
+    }
+    node.pop();
+  }
+  id.pop();
+}
+
+fn lock_icon(locked: bool) -> &'static str {
+  match locked {
+    true => "\u{f023}",
+    false => "\u{f09c}",
+  }
+}
+
+// on the right of the row, folders can also be hidden
+fn toggles(world: &World, ui: &Ui, e: Entity, folder: Option<&mut Folder>) {
+  let right = ui.window_content_region_max()[0];
+  match folder {
+    Some(folder) => {
+      ui.same_line_with_pos(right - 44.0);
+      let eye = match folder.visible {
+        true => "\u{f06e}",
+        false => "\u{f070}",
+      };
+      if ui.small_button(eye) {
+        folder.visible = !folder.visible;
+      }
+      hover_tooltip(ui, "Show or hide everything in the folder.");
+      ui.same_line();
+      if ui.small_button(lock_icon(folder.locked)) {
+        folder.locked = !folder.locked;
+      }
+      hover_tooltip(
+        ui,
+        "Stop everything in the folder being selected or edited.",
+      );
+    }
+    None => {
+      ui.same_line_with_pos(right - 20.0);
+      let locked = e.has::<Locked>();
+      if ui.small_button(lock_icon(locked)) {
+        match locked {
+          true => world.commands().remove::<Locked>(e),
+          false => world.commands().insert(e, Locked),
+        }
+      }
+      hover_tooltip(ui, "Stop it and its children being selected or edited.");
+    }
+  }
+}
+
 fn render(world: &mut World, ui: &Ui) {
   let [w, _] = ui.window_size();
   let selected = world.get_resource::<SelectedEntity>().unwrap();
   for (e, n) in world.query::<Name>() {
-    let id = ui.push_id_usize(e.id);
-    if ui
-      .selectable_config(n.0.clone())
-      .selected(selected.0.is_some_and(|s| s.id == e.id))
-      .build()
-    {
-      *selected = SelectedEntity(Some(e));
+    if e.parent().is_none() {
+      entity_node(world, ui, e, &n.0, selected);
     }
-    id.pop();
   }
   ui.separator();
-  if ui.button_with_size("\u{2b} Add Entity", [w, 0.0]) {
+  if ui.button_with_size(
+    world
+      .get_resource::<Locale>()
+      .unwrap()
+      .tr("\u{2b} Add Entity"),
+    [w, 0.0],
+  ) {
     world.spawn("New").insert(Transform::new());
   }
+  // folders have no transform, so moving things into them doesn't move them
+  if ui.button_with_size(
+    world
+      .get_resource::<Locale>()
+      .unwrap()
+      .tr("\u{f07b} Add Folder"),
+    [w, 0.0],
+  ) {
+    world.spawn("Folder").insert(Folder::DEFAULT);
+  }
+  // dropping onto the button area detaches from the parent
+  if let Some(target) = ui.drag_drop_target() {
+    if let Some(Ok(payload)) = target.accept_payload::<usize, _>("entity", DragDropFlags::empty()) {
+      Entity { id: payload.data }.set_parent(None);
+    }
+  }
+  // delete removes the selection and everything under it
+  let deleting = ui.is_window_focused_with_flags(WindowFocusedFlags::CHILD_WINDOWS)
+    && !ui.io().want_text_input
+    && ui.is_key_pressed(Key::Delete);
+  if let Some(e) = selected.0.filter(|e| deleting && !Folder::locks(*e)) {
+    world.commands().despawn(e);
+    selected.0 = None;
+  }
 }
@@ -0,0 +1,109 @@
+use phosphor::ecs::{World, Entity};
+use phosphor::math::{Vec3, Quat};
+use phosphor::raycast::Hit;
+use phosphor_3d::{Model, Transform, Folder};
+use crate::panels::viewport::Viewport;
+
+const SNAP_DIST: f32 = 16.0;
+
+pub struct PlacementOptions {
+  pub align: bool,
+}
+
+// closest model under the ray and where it's hit
+fn closest(world: &World, ray: (Vec3, Vec3), ignore: usize) -> Option<(Entity, Hit)> {
+  let mut closest: Option<(Entity, Hit)> = None;
+  for (e, model) in world.query::<Model>() {
+    if e.id == ignore || Folder::hides(e) {
+      continue;
+    }
+    let t = match e.get_one::<Transform>() {
+      Some(t) => t,
+      None => continue,
+    };
+    if let Some(hit) = model.mesh.raycast(ray, &t.global_mat4(e)) {
+      if closest.map_or(true, |(_, c)| hit.distance < c.distance) {
+        closest = Some((e, hit));
+      }
+    }
+  }
+  closest
+}
+
+// closest hit against every model in the scene, returns the point and surface normal
+pub fn raycast(world: &World, ray: (Vec3, Vec3), ignore: usize) -> Option<(Vec3, Vec3)> {
+  closest(world, ray, ignore).map(|(_, hit)| (hit.point, hit.normal))
+}
+
+// the model clicked on, by its triangles rather than its bounds. locked folders can't be picked
+pub fn pick(world: &World, ray: (Vec3, Vec3)) -> Option<Entity> {
+  closest(world, ray, usize::MAX)
+    .map(|(e, _)| e)
+    .filter(|e| !Folder::locks(*e))
+}
+
+// vertex of any other model closest to the cursor on screen
+pub fn nearest_vertex(
+  world: &World,
+  vp: &Viewport,
+  cursor: [f32; 2],
+  ignore: usize,
+) -> Option<Vec3> {
+  // a corner of the surface under the cursor first, so hidden vertices aren't snapped to
+  let ray = vp.viewport_to_world_ray(cursor);
+  if let Some((e, hit)) = closest(world, ray, ignore) {
+    let model = e.get_one::<Model>().unwrap();
+    let mat = e.get_one::<Transform>().unwrap().global_mat4(e);
+    let corner = hit
+      .vertices
+      .map(|i| mat.transform_point3(model.mesh.positions[i as usize]))
+      .into_iter()
+      .filter_map(|p| {
+        vp.world_to_viewport(p)
+          .map(|s| ((s[0] - cursor[0]).hypot(s[1] - cursor[1]), p))
+      })
+      .filter(|(d, _)| *d < SNAP_DIST)
+      .min_by(|a, b| a.0.total_cmp(&b.0));
+    if let Some((_, p)) = corner {
+      return Some(p);
+    }
+  }
+  let mut closest: Option<(f32, Vec3)> = None;
+  for (e, model) in world.query::<Model>() {
+    if e.id == ignore || Folder::hides(e) {
+      continue;
+    }
+    let t = match e.get_one::<Transform>() {
+      Some(t) => t,
+      None => continue,
+    };
+    let mat = t.global_mat4(e);
+    for v in &model.mesh.positions {
+      let p = mat.transform_point3(*v);
+      if let Some(s) = vp.world_to_viewport(p) {
+        let d = (s[0] - cursor[0]).hypot(s[1] - cursor[1]);
+        if d < SNAP_DIST && closest.map_or(true, |(c, _)| d < c) {
+          closest = Some((d, p));
+        }
+      }
+    }
+  }
+  closest.map(|(_, p)| p)
+}
+
+// straight down in world space, `t` being the entity's own local transform
+pub fn drop_to_ground(world: &World, e: Entity, t: &mut Transform, align: bool) -> bool {
+  let mut global = t.to_global(e);
+  match raycast(world, (global.position + Vec3::Y * 0.01, Vec3::NEG_Y), e.id) {
+    Some((hit, normal)) => {
+      global.position = hit;
+      if align {
+        global.rotation =
+          Quat::from_rotation_arc(global.rotation * Vec3::Y, normal) * global.rotation;
+      }
+      t.set_global(e, &global);
+      true
+    }
+    None => false,
+  }
+}
@@ -0,0 +1,171 @@
+use phosphor::{Time, TypeIdNamed};
+use phosphor::ecs::{World, Entity, Name};
+use phosphor::scene::{Scene, Change};
+use phosphor::log::{info, error};
+use phosphor_imgui::imgui::{Ui, WindowFlags, TableFlags, TableColumnSetup};
+use phosphor_3d::Transform;
+use crate::SelectedEntity;
+use crate::locale::Locale;
+use crate::panels::Panel;
+
+const TITLE: &str = "\u{f1da} Play Changes";
+
+// the scene as it was when play started, there while playing
+struct PlayMode(Scene);
+
+// what changed while playing, after it's stopped
+struct PlayChanges {
+  after: Scene,
+  // each change with its entity's name and whether to keep it
+  changes: Vec<(Change, String, bool)>,
+}
+
+pub fn init() -> Panel {
+  Panel {
+    title: TITLE,
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+pub fn is_playing(world: &World) -> bool {
+  world.get_resource::<PlayMode>().is_some()
+}
+
+pub fn start(world: &mut World) {
+  world.add_resource(PlayMode(Scene::capture(world)));
+  world.take_resource::<PlayChanges>();
+  world.get_resource::<Time>().unwrap().paused = false;
+  info!("Started playing.");
+}
+
+// puts the scene back how it was when play started and lists what changed, so values tuned while
+// playing can be kept
+pub fn stop(world: &mut World) {
+  let before = match world.take_resource::<PlayMode>() {
+    Some(p) => p.0,
+    None => return,
+  };
+  let after = Scene::capture(world);
+  let changes = before.diff(&after);
+  if let Err(e) = before.restore(world) {
+    error!("Couldn't restore the scene. {}", e);
+  }
+  world.get_resource::<Time>().unwrap().paused = true;
+  // named from the restored scene, or the capture for entities spawned while playing
+  let changes = changes
+    .into_iter()
+    .map(|c| {
+      let id = c.entity();
+      let name = match (Entity { id }).get_one::<Name>() {
+        Some(n) => n.0.clone(),
+        None => after
+          .component::<Name>(world, id)
+          .map_or_else(|| id.to_string(), |n| n.0),
+      };
+      (c, name, false)
+    })
+    .collect::<Vec<_>>();
+  let selected = world.get_resource::<SelectedEntity>().unwrap();
+  if selected.0.is_some_and(|e| e.get_all().is_empty()) {
+    selected.0 = None;
+  }
+  info!("Stopped playing, {} changes.", changes.len());
+  world.add_resource(PlayChanges { after, changes });
+  if let Some(panel) = world
+    .get_resource::<Vec<Panel>>()
+    .unwrap()
+    .iter_mut()
+    .find(|p| p.title == TITLE)
+  {
+    panel.open = true;
+  }
+}
+
+// loading another scene ends play mode without restoring
+pub fn reset(world: &mut World) {
+  world.take_resource::<PlayMode>();
+  world.take_resource::<PlayChanges>();
+}
+
+fn describe(change: &Change, locale: &Locale) -> String {
+  let short = |t: &TypeIdNamed| t.name.rsplit("::").next().unwrap().to_string();
+  match change {
+    Change::Spawned(_) => locale.tr("Spawned"),
+    Change::Despawned(_) => locale.tr("Despawned"),
+    Change::Changed(_, t) if *t == TypeIdNamed::of::<Transform>() => locale.tr("Moved"),
+    Change::Added(_, t) => format!("{} {}", locale.tr("Added"), short(t)),
+    Change::Removed(_, t) => format!("{} {}", locale.tr("Removed"), short(t)),
+    Change::Changed(_, t) => format!("{} {}", locale.tr("Changed"), short(t)),
+  }
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let locale = world.get_resource::<Locale>().unwrap();
+  let state = match world.get_resource::<PlayChanges>() {
+    Some(s) => s,
+    None => {
+      ui.text_disabled(locale.tr(match is_playing(world) {
+        true => "Stop playing to see what changed.",
+        false => "Changes made while playing show up here once it's stopped.",
+      }));
+      return;
+    }
+  };
+  if state.changes.is_empty() {
+    ui.text_disabled(locale.tr("Nothing changed while playing."));
+    return;
+  }
+  if ui.small_button(locale.tr("All")) {
+    state.changes.iter_mut().for_each(|c| c.2 = true);
+  }
+  ui.same_line();
+  if ui.small_button(locale.tr("None")) {
+    state.changes.iter_mut().for_each(|c| c.2 = false);
+  }
+  ui.same_line();
+  let keep = ui.small_button(locale.tr("Keep Selected"));
+  ui.same_line();
+  let discard = ui.small_button(locale.tr("Discard"));
+  let flags = TableFlags::RESIZABLE | TableFlags::ROW_BG | TableFlags::BORDERS_INNER_V;
+  if let Some(_t) = ui.begin_table_header_with_flags(
+    "changes",
+    ["", "Entity", "Change"].map(TableColumnSetup::new),
+    flags,
+  ) {
+    for (i, (change, name, kept)) in state.changes.iter_mut().enumerate() {
+      ui.table_next_row();
+      ui.table_next_column();
+      ui.checkbox(format!("##{}", i), kept);
+      ui.table_next_column();
+      let e = Entity {
+        id: change.entity(),
+      };
+      // spawned ones aren't in the scene until they're kept
+      match e.get_all().is_empty() {
+        true => ui.text_disabled(&*name),
+        false => {
+          if ui.selectable(format!("{}##{}", name, i)) {
+            world.add_resource(SelectedEntity(Some(e)));
+          }
+        }
+      }
+      ui.table_next_column();
+      ui.text(describe(change, locale));
+    }
+  }
+  if keep || discard {
+    let state = world.take_resource::<PlayChanges>().unwrap();
+    if keep {
+      let kept = state.changes.iter().filter(|c| c.2).collect::<Vec<_>>();
+      for (change, _, _) in &kept {
+        if let Err(e) = state.after.apply(world, *change) {
+          error!("Couldn't keep a change. {}", e);
+        }
+      }
+      info!("Kept {} changes from playing.", kept.len());
+    }
+  }
+}
@@ -1,40 +1,63 @@
 use std::f32::consts::FRAC_PI_2;
 use phosphor::Result;
 use phosphor::ecs::{World, Name, stage};
-use phosphor::gfx::{Texture, Framebuffer, Renderer};
+use phosphor::gfx::{Texture, Framebuffer, Renderer, Cursor};
 use phosphor::glfw::{Key, Action, CursorMode, MouseButton};
 use phosphor::math::{Vec3, EulerRot, Quat};
-use phosphor_imgui::imgui::{Ui, Image, TextureId, WindowFlags, StyleVar, Condition};
-use phosphor_3d::{Camera, Transform, SceneDrawOptions, scenerenderer_plugin};
-use crate::{SelectedEntity, load};
-use crate::panels::Panel;
+use phosphor_imgui::imgui::{
+  Ui, Image, TextureId, WindowFlags, StyleVar, Condition, MouseButton as UiButton,
+};
+use phosphor_3d::{Camera, Transform, Folder, SceneDrawOptions, scenerenderer_plugin};
+use crate::SelectedEntity;
+use crate::locale::Locale;
+use crate::panels::{Panel, PanelFocus};
+use crate::panels::gizmos::{self, GizmoState};
+use crate::panels::viewport::{Viewport, FB_SCALE};
+use crate::panels::foliage;
+use crate::panels::placement;
+use crate::panels::spline;
 
+const TITLE: &str = "\u{e1c3} Scene";
+// pixels the mouse can move between press and release for it to still be a click
+const CLICK_DIST: f32 = 4.0;
+
+// the scene is edited through its own camera, the scene's cameras are seen in the game panel
 struct SceneState {
   size: [f32; 2],
-  focused: bool,
-  cam: bool,
+  cam: Camera,
+  cam_t: Transform,
+  // moved to the scene's first camera once there is one
+  placed: bool,
+  gizmo: bool,
   fb: Framebuffer,
   tex: Texture,
   last_pos: (f32, f32),
+  brush_cursor: Cursor,
+  // what the tools last set, so a cursor set by the game isn't cleared
+  cursor: Option<Cursor>,
 }
 
 pub fn init(world: &mut World) -> Result<Panel> {
-  load(world);
   let fb = Framebuffer::new();
   let tex = Texture::empty();
   fb.bind_tex(&tex, 0);
   world.add_resource(SceneState {
     size: [0.0, 0.0],
-    focused: false,
-    cam: false,
+    cam: Camera::new(80.0, [0.1, 100.0]),
+    cam_t: Transform::new().pos(Vec3::new(0.0, 2.0, 5.0)),
+    placed: false,
+    gizmo: false,
     fb,
     tex,
     last_pos: (0.0, 0.0),
+    brush_cursor: foliage::cursor(),
+    cursor: None,
   });
+  world.add_resource(GizmoState::DEFAULT);
   scenerenderer_plugin(world)?;
   world.add_system(stage::PRE_DRAW, predraw);
   Ok(Panel {
-    title: "\u{e1c3} Scene",
+    title: TITLE,
     flags: WindowFlags::NO_SCROLLBAR | WindowFlags::NO_SCROLL_WITH_MOUSE,
     vars: &[StyleVar::WindowPadding([0.0, 0.0])],
     open: true,
@@ -45,99 +68,145 @@ pub fn init(world: &mut World) -> Result<Panel> {
 fn predraw(world: &mut World) -> Result {
   let renderer = world.get_resource::<Renderer>().unwrap();
   let s = world.get_resource::<SceneState>().unwrap();
-  if s.focused {
-    match world.query::<Camera>().first() {
-      Some((e, _)) => {
-        s.cam = true;
-        let cam_t = e.get_one::<Transform>().unwrap();
-
-        if renderer.window.get_mouse_button(MouseButton::Button1) == Action::Press {
-          let pos = renderer.window.get_cursor_pos();
-          let (x, y) = (pos.0 as _, pos.1 as _);
-          if renderer.window.get_cursor_mode() != CursorMode::Disabled {
-            s.last_pos = (x, y);
-            renderer.window.set_cursor_mode(CursorMode::Disabled);
-          }
-          let (dx, dy) = (x - s.last_pos.0, y - s.last_pos.1);
-          s.last_pos = (x, y);
-          let mut euler = cam_t.rotation.to_euler(EulerRot::YXZ);
-          euler.0 -= dx * 0.005;
-          euler.1 -= dy * 0.005;
-          euler.1 = euler.1.clamp(-FRAC_PI_2 + 0.1, FRAC_PI_2 - 0.1);
-          cam_t.rotation = Quat::from_euler(EulerRot::YXZ, euler.0, euler.1, euler.2);
-        } else {
-          renderer.window.set_cursor_mode(CursorMode::Normal);
-        }
-
-        let front = cam_t.rotation * Vec3::NEG_Z;
-        let right = front.cross(Vec3::Y);
-        let mut trans = Vec3::ZERO;
-        if renderer.window.get_key(Key::W) == Action::Press {
-          trans += front;
-        }
-        if renderer.window.get_key(Key::S) == Action::Press {
-          trans -= front;
-        }
-        if renderer.window.get_key(Key::A) == Action::Press {
-          trans -= right
-        }
-        if renderer.window.get_key(Key::D) == Action::Press {
-          trans += right;
-        }
-        cam_t.position += trans.normalize_or_zero() * 0.1;
-      }
-      None => {
-        s.cam = false;
+  let focus = world.get_resource::<PanelFocus>().unwrap();
+  // a drag keeps the camera once the cursor leaves the panel
+  let dragging = renderer.window.get_cursor_mode() == CursorMode::Disabled;
+  if !s.placed {
+    if let Some((e, cam)) = world.query::<Camera>().first() {
+      if let Some(t) = e.get_one::<Transform>() {
+        s.cam = Camera::new(cam.fov, cam.clip);
+        s.cam_t = Transform::new().pos(t.position).rot(t.rotation);
+        s.placed = true;
       }
-    };
+    }
+  }
+
+  let cam_t = &mut s.cam_t;
+  if !s.gizmo
+    && (dragging || focus.is_hovered(TITLE))
+    && renderer.window.get_mouse_button(MouseButton::Button1) == Action::Press
+  {
+    let pos = renderer.window.get_cursor_pos();
+    let (x, y) = (pos.0 as _, pos.1 as _);
+    if !dragging {
+      s.last_pos = (x, y);
+      renderer.window.set_cursor_mode(CursorMode::Disabled);
+    }
+    let (dx, dy) = (x - s.last_pos.0, y - s.last_pos.1);
+    s.last_pos = (x, y);
+    let mut euler = cam_t.rotation.to_euler(EulerRot::YXZ);
+    euler.0 -= dx * 0.005;
+    euler.1 -= dy * 0.005;
+    euler.1 = euler.1.clamp(-FRAC_PI_2 + 0.1, FRAC_PI_2 - 0.1);
+    cam_t.rotation = Quat::from_euler(EulerRot::YXZ, euler.0, euler.1, euler.2);
   } else {
     renderer.window.set_cursor_mode(CursorMode::Normal);
   }
+
+  if focus.is_focused(TITLE) && !focus.typing {
+    let front = cam_t.rotation * Vec3::NEG_Z;
+    let right = front.cross(Vec3::Y);
+    let mut trans = Vec3::ZERO;
+    if renderer.window.get_key(Key::W) == Action::Press {
+      trans += front;
+    }
+    if renderer.window.get_key(Key::S) == Action::Press {
+      trans -= front;
+    }
+    if renderer.window.get_key(Key::A) == Action::Press {
+      trans -= right
+    }
+    if renderer.window.get_key(Key::D) == Action::Press {
+      trans += right;
+    }
+    cam_t.position += trans.normalize_or_zero() * 0.1;
+  }
+  // exposed like the game camera, so lighting looks the same in both
+  let exposure = world
+    .query::<Camera>()
+    .first()
+    .map_or(s.cam.exposure, |(_, cam)| cam.exposure);
   world.add_resource(SceneDrawOptions {
     fb: s.fb,
-    size: [s.size[0] * 2.5, s.size[1] * 2.5],
+    size: [s.size[0] * FB_SCALE, s.size[1] * FB_SCALE],
+    camera: Some((
+      Camera::new(s.cam.fov, s.cam.clip).exposure(exposure),
+      Transform::new().pos(s.cam_t.position).rot(s.cam_t.rotation),
+    )),
   });
   Ok(())
 }
 
 fn render(world: &mut World, ui: &Ui) {
   let s = world.get_resource::<SceneState>().unwrap();
+  let locale = world.get_resource::<Locale>().unwrap();
   let selected = world.get_resource::<SelectedEntity>().unwrap();
   s.size = ui.window_size();
-  s.focused = ui.is_window_focused();
-  if s.cam {
-    let pos = ui.cursor_screen_pos();
-    Image::new(TextureId::new(s.tex.id as _), s.size)
-      .uv0([0.0, 1.0])
-      .uv1([1.0, 0.0])
-      .build(ui);
-    let pad = ui.push_style_var(StyleVar::WindowPadding([2.0, 2.0]));
-    let round = ui.push_style_var(StyleVar::WindowRounding(0.0));
-    ui.window("##")
-      .flags(WindowFlags::NO_DECORATION | WindowFlags::ALWAYS_AUTO_RESIZE | WindowFlags::NO_MOVE)
-      .bg_alpha(0.5)
-      .position(pos, Condition::Always)
-      .build(|| {
-        ui.set_window_font_scale(0.8);
-        ui.text(match selected.0 {
-          Some(e) => e.get_one::<Name>().unwrap().0.clone(),
-          None => "No entity selected.".to_string(),
-        });
-        ui.text(format!("{:.1}fps", ui.io().framerate));
-      });
-    pad.pop();
-    round.pop();
-  } else {
-    let font = ui.push_font(ui.fonts().fonts()[1]);
-    ui.set_window_font_scale(0.65);
-    let msg = "\u{e0eb} No camera.";
-    let [w, h] = ui.window_size();
-    let [x, y] = ui.calc_text_size(msg);
-    ui.set_cursor_pos([(w - x) / 2.0, (h - y) / 2.0]);
-    ui.text(msg);
-    ui.set_window_font_scale(1.0);
-    font.pop();
+  let pos = ui.cursor_screen_pos();
+  Image::new(TextureId::new(s.tex.id as _), s.size)
+    .uv0([0.0, 1.0])
+    .uv1([1.0, 0.0])
+    .build(ui);
+  s.gizmo = false;
+  let (view, proj) = s.cam.matrices(&s.cam_t, s.size[0] / s.size[1]);
+  let vp = Viewport {
+    pos,
+    size: s.size,
+    view,
+    proj,
+  };
+  gizmos::triggers(world, ui, &vp);
+  gizmos::splines(world, ui, &vp);
+  let mut painting = false;
+  // it could have been selected before its folder was locked
+  if let Some(e) = selected.0.filter(|e| !Folder::locks(*e)) {
+    s.gizmo = gizmos::draw(world, ui, &vp, e);
+    painting = foliage::paint(world, ui, &vp, e);
+    s.gizmo |= painting;
+    s.gizmo |= spline::place(world, ui, &vp, e);
   }
-  s.tex.resize((2.5 * s.size[0]) as _, (2.5 * s.size[1]) as _);
-  s.fb.resize((2.5 * s.size[0]) as _, (2.5 * s.size[1]) as _);
+  // a click that didn't turn the camera selects what's under it
+  let drag = ui.mouse_drag_delta_with_button(UiButton::Left);
+  if !s.gizmo
+    && ui.is_window_hovered()
+    && ui.is_mouse_released(UiButton::Left)
+    && drag[0].hypot(drag[1]) < CLICK_DIST
+  {
+    let ray = vp.viewport_to_world_ray(ui.io().mouse_pos);
+    if let Some(e) = placement::pick(world, ray) {
+      selected.0 = Some(e);
+    }
+  }
+  let renderer = world.get_resource::<Renderer>().unwrap();
+  let cursor = painting.then(|| s.brush_cursor.clone());
+  if cursor.is_some() || renderer.cursor() == s.cursor.as_ref() {
+    renderer.set_cursor(cursor.clone());
+  }
+  s.cursor = cursor;
+  let pad = ui.push_style_var(StyleVar::WindowPadding([2.0, 2.0]));
+  let round = ui.push_style_var(StyleVar::WindowRounding(0.0));
+  ui.window("##")
+    .flags(WindowFlags::NO_DECORATION | WindowFlags::ALWAYS_AUTO_RESIZE | WindowFlags::NO_MOVE)
+    .bg_alpha(0.5)
+    .position(pos, Condition::Always)
+    .build(|| {
+      ui.set_window_font_scale(0.8);
+      ui.text(match selected.0 {
+        Some(e) => e.get_one::<Name>().unwrap().0.clone(),
+        None => locale.tr("No entity selected."),
+      });
+      ui.text(format!("{:.1}fps", ui.io().framerate));
+      if selected.0.is_some() {
+        world
+          .get_resource::<GizmoState>()
+          .unwrap()
+          .settings(ui, locale);
+      }
+    });
+  pad.pop();
+  round.pop();
+  s.tex
+    .resize((FB_SCALE * s.size[0]) as _, (FB_SCALE * s.size[1]) as _);
+  s.fb
+    .resize((FB_SCALE * s.size[0]) as _, (FB_SCALE * s.size[1]) as _);
 }
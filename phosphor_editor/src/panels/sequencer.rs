@@ -0,0 +1,335 @@
+use std::env;
+use phosphor::Time;
+use phosphor::ecs::{World, Name};
+use phosphor::assets::Assets;
+use phosphor::log::error;
+use phosphor_3d::{Sequence, SequencePlayer, Track, TrackKind};
+use phosphor_fmod::Sound;
+use phosphor_imgui::hover_tooltip;
+use phosphor_imgui::imgui::{Ui, WindowFlags, Drag, StyleColor, MouseButton};
+use crate::SelectedEntity;
+use crate::panels::Panel;
+use rfd::FileDialog;
+
+const LABEL_WIDTH: f32 = 220.0;
+const ROW: f32 = 22.0;
+const KEY_SIZE: f32 = 5.0;
+
+struct SequencerState {
+  // track and key
+  selected: Option<(usize, usize)>,
+  // plays in the editor without running the game
+  preview: bool,
+  // keys snap to frames at this rate when set, eg to match an animation's footsteps
+  frames: Option<f32>,
+}
+
+impl SequencerState {
+  fn snap(&self, time: f32) -> f32 {
+    match self.frames {
+      Some(fps) => (time * fps).round() / fps,
+      None => time,
+    }
+  }
+}
+
+pub fn init(world: &mut World) -> Panel {
+  world.add_resource(SequencerState {
+    selected: None,
+    preview: false,
+    frames: None,
+  });
+  Panel {
+    title: "\u{f008} Sequencer",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+// the player's sequence is edited as a copy, then swapped in for the asset under the same name
+fn render(world: &mut World, ui: &Ui) {
+  let selected = world.get_resource::<SelectedEntity>().unwrap().0;
+  let player = match selected
+    .as_ref()
+    .and_then(|e| e.get_one::<SequencePlayer>())
+  {
+    Some(p) => p,
+    None => {
+      ui.text_disabled("Select an entity with a Sequence Player.");
+      return;
+    }
+  };
+  let state = world.get_resource::<SequencerState>().unwrap();
+  let mut seq = (*player.sequence).clone();
+  let mut changed = false;
+  let mut time = player.time();
+
+  let icon = if state.preview {
+    "\u{f04c}"
+  } else {
+    "\u{f04b}"
+  };
+  if ui.small_button(icon) {
+    state.preview = !state.preview;
+    if time >= seq.duration {
+      player.seek(0.0);
+    }
+  }
+  hover_tooltip(
+    ui,
+    "Preview, audio and events only fire while the game runs.",
+  );
+  if state.preview {
+    time += world.get_resource::<Time>().unwrap().unscaled_delta;
+    state.preview = time < seq.duration;
+    player.seek(time);
+  }
+  ui.same_line();
+  ui.text(format!("{:.2}s", player.time()));
+  ui.same_line();
+  ui.set_next_item_width(80.0);
+  changed |= Drag::new("Duration")
+    .speed(0.05)
+    .range(0.1, f32::MAX)
+    .display_format("%gs")
+    .build(ui, &mut seq.duration);
+  ui.same_line();
+  let mut snap = state.frames.is_some();
+  if ui.checkbox("Frames", &mut snap) {
+    state.frames = snap.then_some(30.0);
+  }
+  if let Some(fps) = &mut state.frames {
+    ui.same_line();
+    ui.set_next_item_width(60.0);
+    Drag::new("##fps")
+      .speed(0.5)
+      .range(1.0, 240.0)
+      .display_format("%g fps")
+      .build(ui, fps);
+  }
+  ui.same_line();
+  if let Some(_) = ui.begin_combo("##add", "\u{2b} Track") {
+    for kind in TrackKind::ALL {
+      if ui.selectable(kind.name()) {
+        seq.tracks.push(Track {
+          target: String::new(),
+          kind,
+        });
+        changed = true;
+      }
+    }
+  }
+  ui.same_line();
+  if ui.small_button("\u{f0c7} Save") {
+    save_sequence(world, player, &seq);
+  }
+  ui.separator();
+
+  let names = world
+    .query::<Name>()
+    .into_iter()
+    .map(|(_, n)| n.0.clone())
+    .collect::<Vec<_>>();
+  let origin = ui.cursor_screen_pos();
+  let x0 = origin[0] + LABEL_WIDTH;
+  let width = (ui.content_region_avail()[0] - LABEL_WIDTH - KEY_SIZE).max(1.0);
+  let scale = width / seq.duration.max(0.1);
+  let mouse_time = state.snap(((ui.io().mouse_pos[0] - x0) / scale).clamp(0.0, seq.duration));
+  let draw = ui.get_window_draw_list();
+  let text = ui.style_color(StyleColor::Text);
+  let dim = ui.style_color(StyleColor::TextDisabled);
+  let bg = ui.style_color(StyleColor::FrameBg);
+  let accent = ui.style_color(StyleColor::PlotLinesHovered);
+
+  // the ruler scrubs
+  let step = if scale > 60.0 { 0.5 } else { 1.0 };
+  for i in 0..=(seq.duration / step) as usize {
+    let x = x0 + i as f32 * step * scale;
+    draw
+      .add_line([x, origin[1] + ROW * 0.6], [x, origin[1] + ROW], dim)
+      .build();
+    if i as f32 * step == (i as f32 * step).floor() {
+      draw.add_text([x + 2.0, origin[1]], dim, format!("{}", i as f32 * step));
+    }
+  }
+  ui.set_cursor_screen_pos([x0, origin[1]]);
+  ui.invisible_button("ruler", [width, ROW]);
+  if ui.is_item_active() {
+    state.preview = false;
+    player.seek(mouse_time);
+  }
+
+  let mut remove = None;
+  for (i, track) in seq.tracks.iter_mut().enumerate() {
+    let y = origin[1] + ROW * (i + 1) as f32;
+    let _id = ui.push_id_usize(i);
+    draw
+      .add_rect([x0, y + 2.0], [x0 + width, y + ROW - 2.0], bg)
+      .filled(true)
+      .build();
+    ui.set_cursor_screen_pos([origin[0], y]);
+    ui.set_next_item_width(100.0);
+    let preview = match track.target.is_empty() {
+      true => "-",
+      false => &track.target,
+    };
+    if let Some(_) = ui.begin_combo("##target", preview) {
+      for name in &names {
+        if ui.selectable(name) {
+          track.target = name.clone();
+          changed = true;
+        }
+      }
+    }
+    ui.same_line();
+    ui.text(track.kind.name());
+    ui.set_cursor_screen_pos([x0 - 48.0, y]);
+    if ui.small_button("\u{f111}") {
+      track
+        .kind
+        .record(world, &track.target, state.snap(player.time()));
+      changed = true;
+    }
+    hover_tooltip(ui, "Key the target's current value at the playhead.");
+    ui.same_line();
+    if ui.small_button("\u{f1f8}") {
+      remove = Some(i);
+    }
+    for (k, t) in track.kind.times().into_iter().enumerate() {
+      let center = [x0 + t * scale, y + ROW / 2.0];
+      let _id = ui.push_id_usize(k);
+      ui.set_cursor_screen_pos([center[0] - KEY_SIZE, center[1] - KEY_SIZE]);
+      ui.invisible_button("key", [KEY_SIZE * 2.0; 2]);
+      let selected = state.selected == Some((i, k));
+      if ui.is_item_clicked() {
+        state.selected = Some((i, k));
+      }
+      // right click removes
+      if ui.is_item_clicked_with_button(MouseButton::Right) {
+        track.kind.remove_key(k);
+        state.selected = None;
+        changed = true;
+        break;
+      }
+      if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+        track.kind.move_key(k, mouse_time);
+        let moved = track.kind.times().iter().position(|t| *t == mouse_time);
+        state.selected = moved.map(|k| (i, k));
+        changed = true;
+      }
+      let r = if selected || ui.is_item_hovered() {
+        KEY_SIZE + 1.0
+      } else {
+        KEY_SIZE
+      };
+      draw
+        .add_polyline(
+          vec![
+            [center[0], center[1] - r],
+            [center[0] + r, center[1]],
+            [center[0], center[1] + r],
+            [center[0] - r, center[1]],
+          ],
+          if selected { accent } else { text },
+        )
+        .filled(true)
+        .build();
+    }
+  }
+  if let Some(i) = remove {
+    seq.tracks.remove(i);
+    state.selected = None;
+    changed = true;
+  }
+  let bottom = origin[1] + ROW * (seq.tracks.len() + 1) as f32;
+  let x = x0 + player.time() * scale;
+  draw
+    .add_line([x, origin[1]], [x, bottom], accent)
+    .thickness(2.0)
+    .build();
+  ui.set_cursor_screen_pos([origin[0], bottom + 4.0]);
+
+  // the selected key's time, its name for events and its sound for sounds
+  let selected = state
+    .selected
+    .and_then(|(i, k)| Some((k, seq.tracks.get_mut(i)?)));
+  if let Some((k, track)) = selected {
+    if let Some(mut t) = track.kind.times().get(k).copied() {
+      ui.set_next_item_width(120.0);
+      let moved = match state.frames {
+        Some(fps) => {
+          let mut frame = (t * fps).round() as i32;
+          let moved = Drag::new("Key Frame")
+            .range(0, (seq.duration * fps) as i32)
+            .build(ui, &mut frame);
+          t = frame as f32 / fps;
+          moved
+        }
+        None => Drag::new("Key Time")
+          .speed(0.01)
+          .range(0.0, seq.duration)
+          .display_format("%.2fs")
+          .build(ui, &mut t),
+      };
+      if moved {
+        track.kind.move_key(k, t);
+        changed = true;
+      }
+      match &mut track.kind {
+        TrackKind::Event(keys) => {
+          ui.same_line();
+          ui.set_next_item_width(160.0);
+          changed |= ui.input_text("Event", &mut keys[k].1).build();
+        }
+        TrackKind::Sound(keys) => {
+          ui.same_line();
+          ui.set_next_item_width(160.0);
+          let sound = &mut keys[k].1;
+          if let Some(_) = ui.begin_combo("Sound", sound.clone()) {
+            for asset in world.get_resource::<Assets>().unwrap().get::<Sound>() {
+              if ui.selectable(&asset.name) {
+                *sound = asset.name.clone();
+                changed = true;
+              }
+            }
+          }
+          hover_tooltip(ui, "Loaded sounds, or type the path of another.");
+          ui.same_line();
+          ui.set_next_item_width(160.0);
+          changed |= ui.input_text("##path", sound).build();
+        }
+        _ => {}
+      }
+    }
+  }
+
+  if changed {
+    let assets = world.get_resource::<Assets>().unwrap();
+    let time = player.time();
+    player.sequence = assets.insert(&player.sequence.name.clone(), seq);
+    player.seek(time);
+  }
+}
+
+fn save_sequence(world: &World, player: &mut SequencePlayer, seq: &Sequence) {
+  let assets_dir = env::current_dir().unwrap().join("assets");
+  let path = match FileDialog::new()
+    .set_directory(&assets_dir)
+    .set_file_name(&player.sequence.name)
+    .add_filter("Sequence", &["seq"])
+    .save_file()
+  {
+    Some(p) => p,
+    None => return,
+  };
+  if let Err(e) = seq.save(&path) {
+    error!("Couldn't save '{}'. {}", path.display(), e);
+    return;
+  }
+  if let Ok(name) = path.strip_prefix(&assets_dir) {
+    let assets = world.get_resource::<Assets>().unwrap();
+    player.sequence = assets.insert(&name.to_string_lossy(), seq.clone());
+  }
+}
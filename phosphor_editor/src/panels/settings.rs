@@ -1,22 +1,28 @@
 use std::env::consts;
 use phosphor::ecs::World;
 use phosphor::gfx::Renderer;
-use phosphor_imgui::imgui::{Context, Ui, WindowFlags, StyleVar, dear_imgui_version};
+use phosphor_imgui::imgui::{Context, Ui, WindowFlags, StyleVar, Drag, dear_imgui_version};
+use phosphor_imgui::access::{Role, label, screen_reader};
 use phosphor_fmod::FmodContext;
+use phosphor_3d::{SceneRendererOptions, AntiAliasing, QualityScaling};
+use phosphor::log::error;
 use crate::panels::Panel;
+use crate::locale::{Locale, LOCALES};
 
 #[derive(PartialEq, Eq)]
 enum SettingsPane {
   Appearance,
+  Graphics,
   About,
 }
 
 impl SettingsPane {
-  const ALL: [Self; 2] = [Self::Appearance, Self::About];
+  const ALL: [Self; 3] = [Self::Appearance, Self::Graphics, Self::About];
 
   fn name(&self) -> &str {
     match self {
       Self::Appearance => "\u{f53f} Appearance",
+      Self::Graphics => "\u{f108} Graphics",
       Self::About => "\u{f05a} About",
     }
   }
@@ -35,6 +41,7 @@ pub fn init(world: &mut World) -> Panel {
 
 fn render(world: &mut World, ui: &Ui) {
   let pane = world.get_resource::<SettingsPane>().unwrap();
+  let locale = world.get_resource::<Locale>().unwrap();
   let pad = ui.push_style_var(StyleVar::WindowPadding([8.0, 8.0]));
   let space = ui.push_style_var(StyleVar::ItemSpacing([8.0, 8.0]));
   ui.child_window("l")
@@ -42,7 +49,11 @@ fn render(world: &mut World, ui: &Ui) {
     .always_use_window_padding(true)
     .build(|| {
       for p in SettingsPane::ALL {
-        if ui.selectable_config(p.name()).selected(*pane == p).build() {
+        if ui
+          .selectable_config(locale.tr(p.name()))
+          .selected(*pane == p)
+          .build()
+        {
           *pane = p
         }
       }
@@ -56,7 +67,7 @@ fn render(world: &mut World, ui: &Ui) {
     match pane {
       SettingsPane::Appearance => unsafe {
         static mut THEME: usize = 0; // too bored for a resource
-        if ui.combo_simple_string("Theme", &mut THEME, &["Dark", "Nord", "Light"]) {
+        if ui.combo_simple_string(locale.tr("Theme"), &mut THEME, &["Dark", "Nord", "Light"]) {
           let style = world.get_resource::<Context>().unwrap().style_mut();
           match THEME {
             0 => phosphor_imgui::theme_dark(style),
@@ -67,7 +78,116 @@ fn render(world: &mut World, ui: &Ui) {
             _ => {}
           }
         }
+        if let Some(_) = ui.begin_combo(locale.tr("Language"), locale.info.name) {
+          for info in LOCALES {
+            if ui
+              .selectable_config(info.name)
+              .selected(info.code == locale.info.code)
+              .build()
+            {
+              match Locale::load(info) {
+                Ok(l) => *locale = l,
+                Err(e) => error!("Couldn't load locale '{}'. {}", info.code, e),
+              }
+            }
+          }
+        }
+        if !locale.info.fonts.is_empty() {
+          ui.text_disabled(locale.tr("Fonts for this language are loaded on startup."));
+        }
+        let mut reader = screen_reader.get();
+        if ui.checkbox(locale.tr("Screen reader"), &mut reader) {
+          screen_reader.set(reader);
+        }
+        label(world, Role::CheckBox, &locale.tr("Screen reader"));
       },
+      SettingsPane::Graphics => {
+        let options = world.get_resource::<SceneRendererOptions>().unwrap();
+        if let Some(_) = ui.begin_combo(locale.tr("Anti-aliasing"), options.aa.name()) {
+          for aa in AntiAliasing::ALL {
+            if ui
+              .selectable_config(aa.name())
+              .selected(options.aa == aa)
+              .build()
+            {
+              options.aa = aa;
+            }
+          }
+        }
+        ui.checkbox(locale.tr("GPU culling"), &mut options.gpu_culling);
+        ui.checkbox(
+          locale.tr("Occlusion culling"),
+          &mut options.occlusion_culling,
+        );
+        ui.slider(
+          locale.tr("Render scale"),
+          0.25,
+          2.0,
+          &mut options.render_scale,
+        );
+        ui.checkbox("SSAO", &mut options.ssao);
+        ui.checkbox(locale.tr("Bloom"), &mut options.bloom);
+        ui.separator();
+        ui.slider(
+          locale.tr("Shadow cascades"),
+          1,
+          4,
+          &mut options.shadow_cascades,
+        );
+        Drag::new(locale.tr("Shadow distance"))
+          .range(1.0, f32::MAX)
+          .speed(0.5)
+          .build(ui, &mut options.shadow_distance);
+        ui.slider(
+          locale.tr("Cascade split"),
+          0.0,
+          1.0,
+          &mut options.shadow_split,
+        );
+        if let Some(_) = ui.begin_combo(
+          locale.tr("Shadow resolution"),
+          options.shadow_res.to_string(),
+        ) {
+          for res in [1024, 2048, 4096, 8192] {
+            if ui
+              .selectable_config(res.to_string())
+              .selected(options.shadow_res == res)
+              .build()
+            {
+              options.shadow_res = res;
+            }
+          }
+        }
+        ui.checkbox(locale.tr("Show cascades"), &mut options.debug_cascades);
+        ui.separator();
+        let mut scaling = world
+          .get_resource::<QualityScaling>()
+          .is_some_and(|q| q.enabled);
+        if ui.checkbox(locale.tr("Quality scaling"), &mut scaling) {
+          match world.get_resource::<QualityScaling>() {
+            Some(q) => {
+              q.enabled = scaling;
+              q.reset();
+            }
+            None => world.add_resource(QualityScaling::new(60.0)),
+          }
+        }
+        if let Some(q) = world.get_resource::<QualityScaling>().filter(|q| q.enabled) {
+          let mut fps = 1000.0 / q.target_ms;
+          if Drag::new(locale.tr("Target framerate"))
+            .range(10.0, 240.0)
+            .build(ui, &mut fps)
+          {
+            q.target_ms = 1000.0 / fps.max(1.0);
+          }
+          ui.text_disabled(format!(
+            "{:.0}% | {}: {}",
+            q.scale() * 100.0,
+            locale.tr("Effects off"),
+            q.level()
+          ));
+        }
+      }
       SettingsPane::About => {
         let font = ui.push_font(ui.fonts().fonts()[1]);
         ui.text("\u{f5d3} Phosphor");
@@ -81,8 +201,10 @@ fn render(world: &mut World, ui: &Ui) {
         item(ui, "Opengl ver:", renderer.version);
         item(ui, "GLFW ver:", &phosphor::glfw::get_version_string());
         item(ui, "Dear ImGui ver:", dear_imgui_version());
-        let fmod = world.get_resource::<FmodContext>().unwrap();
-        item(ui, "FMOD ver:", &fmod.ver);
+        // only running when the project uses it
+        if let Some(fmod) = world.get_resource::<FmodContext>() {
+          item(ui, "FMOD ver:", &fmod.ver);
+        }
         item(ui, "GPU", renderer.renderer);
         item(
           ui,
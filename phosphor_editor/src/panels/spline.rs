@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::fmt::Write as _;
+use std::path::Path;
+use phosphor::Result;
+use phosphor::ecs::{World, Entity};
+use phosphor::assets::Assets;
+use phosphor::gfx::{Mesh, Vertex};
+use phosphor::math::Vec3;
+use phosphor::log::error;
+use phosphor_imgui::imgui::{Ui, Drag, MouseButton};
+use phosphor_3d::{Spline, Transform, Model, Foliage};
+use crate::panels::gizmos;
+use crate::panels::viewport::Viewport;
+use crate::panels::placement;
+use rfd::FileDialog;
+
+pub struct SplineTool {
+  pub placing: bool,
+  // road width, and how far apart road vertices and scattered instances are
+  pub width: f32,
+  pub spacing: f32,
+}
+
+impl SplineTool {
+  pub const DEFAULT: Self = Self {
+    placing: false,
+    width: 4.0,
+    spacing: 2.0,
+  };
+
+  pub fn settings(&mut self, ui: &Ui) {
+    ui.checkbox("Place", &mut self.placing);
+    ui.same_line();
+    ui.text_disabled("(LMB to add points, RMB on one to remove it)");
+    Drag::new("Width")
+      .range(0.01, f32::MAX)
+      .speed(0.05)
+      .build(ui, &mut self.width);
+    Drag::new("Spacing")
+      .range(0.01, f32::MAX)
+      .speed(0.05)
+      .build(ui, &mut self.spacing);
+  }
+}
+
+// returns whether the tool is capturing the mouse
+pub fn place(world: &World, ui: &Ui, vp: &Viewport, selected: Entity) -> bool {
+  let tool = world.get_resource::<SplineTool>().unwrap();
+  let (spline, t) = match (
+    selected.get_one::<Spline>(),
+    selected.get_one::<Transform>(),
+  ) {
+    (Some(s), Some(t)) if tool.placing => (s, t),
+    _ => return false,
+  };
+  let mat = t.global_mat4(selected);
+  let ray = vp.viewport_to_world_ray(ui.io().mouse_pos);
+  // onto the scene, or the flat plane through the spline's origin
+  let hit = match placement::raycast(world, ray, selected.id) {
+    Some((hit, _)) => hit,
+    None => match gizmos::ray_plane(ray, mat.w_axis.truncate(), Vec3::Y) {
+      Some(hit) => hit,
+      None => return false,
+    },
+  };
+  gizmos::circle(ui, vp, hit, Vec3::X, Vec3::Z, 0.25);
+
+  // the window includes its title bar, and clicks on existing points drag them instead
+  let hovered = ui.is_window_hovered() && vp.contains(ui.io().mouse_pos);
+  if hovered && !ui.is_any_item_hovered() && ui.is_mouse_clicked(MouseButton::Left) {
+    spline.add_point(mat.inverse().transform_point3(hit));
+  }
+  hovered
+}
+
+// saves the road as an obj so scenes can load it, then shows it on the spline's entity
+pub fn extrude(world: &World, e: Entity, spline: &Spline) {
+  let tool = world.get_resource::<SplineTool>().unwrap();
+  let (vertices, indices) = spline.extrude(tool.width, tool.spacing);
+  if indices.is_empty() {
+    error!("The spline needs at least one segment to extrude.");
+    return;
+  }
+  let assets_dir = env::current_dir().unwrap().join("assets");
+  let path = match FileDialog::new()
+    .set_directory(&assets_dir)
+    .add_filter("Mesh", &["obj"])
+    .save_file()
+  {
+    Some(p) => p,
+    None => return,
+  };
+  if let Err(e) = write_obj(&path, &vertices, &indices) {
+    error!("Couldn't save '{}'. {}", path.display(), e);
+    return;
+  }
+  if let Ok(name) = path.strip_prefix(&assets_dir) {
+    let name = name.to_string_lossy();
+    let mesh = Mesh::new(&vertices, &indices);
+    mesh.label(&name);
+    let mesh = world.get_resource::<Assets>().unwrap().insert(&name, mesh);
+    match e.get_one::<Model>() {
+      Some(model) => model.mesh = mesh,
+      None => {
+        e.insert(Model::new(mesh));
+      }
+    }
+  }
+}
+
+// replaces the entity's instances with ones along the curve
+pub fn scatter(world: &World, e: Entity, spline: &Spline) {
+  let tool = world.get_resource::<SplineTool>().unwrap();
+  let (foliage, t) = match (e.modify::<Foliage>(), e.get_one::<Transform>()) {
+    (Some(f), Some(t)) => (f, t),
+    _ => return,
+  };
+  let mat = t.global_mat4(e);
+  foliage.instances = spline
+    .scatter(tool.spacing)
+    .into_iter()
+    .map(|m| mat * m)
+    .collect();
+}
+
+fn write_obj(path: &Path, vertices: &[Vertex], indices: &[u32]) -> Result {
+  let mut obj = String::new();
+  for v in vertices {
+    writeln!(obj, "v {} {} {}", v.pos[0], v.pos[1], v.pos[2])?;
+    writeln!(obj, "vt {} {}", v.uv[0], v.uv[1])?;
+    writeln!(obj, "vn {} {} {}", v.normal[0], v.normal[1], v.normal[2])?;
+  }
+  for tri in indices.chunks_exact(3) {
+    let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| i + 1);
+    writeln!(obj, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+  }
+  fs::write(path, obj)?;
+  Ok(())
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashMap, BTreeSet};
+use std::mem;
+use std::any::Any;
+use phosphor::ecs::{World, stage};
+use phosphor_imgui::imgui::{Ui, WindowFlags, TreeNodeFlags};
+use crate::panels::Panel;
+
+pub fn init() -> Panel {
+  Panel {
+    title: "\u{f1c0} ECS",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn stage_name(stage: usize) -> &'static str {
+  match stage {
+    stage::INIT => "Init",
+    stage::START => "Start",
+    stage::PRE_DRAW => "PreDraw",
+    stage::DRAW => "Draw",
+    stage::POST_DRAW => "PostDraw",
+    stage::EVENT => "Event",
+    _ => "?",
+  }
+}
+
+fn table<F: FnOnce()>(ui: &Ui, id: &str, headers: &[&str], f: F) {
+  ui.columns(headers.len() as _, id, true);
+  for h in headers {
+    ui.text_disabled(h);
+    ui.next_column();
+  }
+  ui.separator();
+  f();
+  ui.columns(1, "", false);
+}
+
+fn row(ui: &Ui, cols: &[String]) {
+  for c in cols {
+    ui.text(c);
+    ui.next_column();
+  }
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let mut entities: HashMap<usize, BTreeSet<&str>> = HashMap::new();
+  let mut components = world
+    .components
+    .iter()
+    .map(|(t, v)| {
+      for (e, _) in v {
+        entities.entry(*e).or_default().insert(t.name);
+      }
+      (
+        t.name,
+        v.len(),
+        v.capacity() * mem::size_of::<(usize, Box<dyn Any>)>(),
+      )
+    })
+    .collect::<Vec<_>>();
+  components.sort_by(|a, b| b.1.cmp(&a.1));
+  let mut archetypes: HashMap<&BTreeSet<&str>, usize> = HashMap::new();
+  for set in entities.values() {
+    *archetypes.entry(set).or_default() += 1;
+  }
+  let mut archetypes = archetypes.into_iter().collect::<Vec<_>>();
+  archetypes.sort_by(|a, b| b.1.cmp(&a.1));
+
+  ui.text(format!(
+    "{} entities | {} components | {} archetypes",
+    entities.len(),
+    components.iter().map(|c| c.1).sum::<usize>(),
+    archetypes.len()
+  ));
+  if ui.collapsing_header("Components", TreeNodeFlags::DEFAULT_OPEN) {
+    table(ui, "components", &["Type", "Count", "Storage"], || {
+      for (name, count, size) in components {
+        row(
+          ui,
+          &[name.to_string(), count.to_string(), format!("{}B", size)],
+        );
+      }
+    });
+  }
+  if ui.collapsing_header("Archetypes", TreeNodeFlags::empty()) {
+    table(ui, "archetypes", &["Components", "Entities"], || {
+      for (set, count) in archetypes {
+        row(
+          ui,
+          &[
+            set.iter().copied().collect::<Vec<_>>().join(", "),
+            count.to_string(),
+          ],
+        );
+      }
+    });
+  }
+  if ui.collapsing_header("Systems", TreeNodeFlags::DEFAULT_OPEN) {
+    table(ui, "systems", &["Stage", "System", "Time"], || {
+      for (stage, name) in world.systems() {
+        row(
+          ui,
+          &[
+            stage_name(stage).to_string(),
+            name.to_string(),
+            match world.system_times.get(name) {
+              Some(t) => format!("{:.3}ms", t.as_secs_f32() * 1000.0),
+              None => "-".to_string(),
+            },
+          ],
+        );
+      }
+    });
+  }
+}
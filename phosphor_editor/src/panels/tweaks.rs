@@ -0,0 +1,18 @@
+use phosphor::ecs::World;
+use phosphor_imgui::imgui::{Ui, WindowFlags};
+use phosphor_imgui::cvar_tweaker;
+use crate::panels::Panel;
+
+pub fn init() -> Panel {
+  Panel {
+    title: "\u{f1de} Tweaks",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn render(_: &mut World, ui: &Ui) {
+  cvar_tweaker(ui);
+}
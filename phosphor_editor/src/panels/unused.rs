@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use phosphor::ecs::World;
+use phosphor::assets::Assets;
+use phosphor::log::info;
+use phosphor_imgui::hover_tooltip;
+use phosphor_imgui::imgui::{Ui, WindowFlags, TableFlags, TableColumnSetup, TreeNodeFlags};
+use crate::project::Project;
+use crate::panels::Panel;
+
+// files that refer to assets by name. scenes and sequences are bincode, but it keeps strings as
+// plain bytes so they're searched the same way
+const DOCUMENTS: [&str; 5] = ["scene", "mat", "seq", "rs", "project"];
+// the engine's own files in the asset root
+const ENGINE_DIRS: [&str; 3] = ["shaders", "fonts", "locale"];
+const SKIP_DIRS: [&str; 2] = ["target", ".git"];
+
+struct UnusedAssets {
+  // names and sizes from the last scan, none before the first
+  files: Option<Vec<(String, u64)>>,
+}
+
+pub fn init(world: &mut World) -> Panel {
+  world.add_resource(UnusedAssets { files: None });
+  Panel {
+    title: "\u{f1f8} Unused Assets",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn mb(bytes: u64) -> String {
+  format!("{:.2}MB", bytes as f32 / (1 << 20) as f32)
+}
+
+fn render(world: &mut World, ui: &Ui) {
+  let assets = world.get_resource::<Assets>().unwrap();
+  if ui.collapsing_header("Loaded", TreeNodeFlags::DEFAULT_OPEN) {
+    if ui.small_button("Collect") {
+      let unloaded = assets.collect();
+      info!("Unloaded {} assets.", unloaded.len());
+    }
+    hover_tooltip(ui, "Unload every asset with no references.");
+    let flags = TableFlags::RESIZABLE | TableFlags::ROW_BG | TableFlags::BORDERS_INNER_V;
+    if let Some(_t) = ui.begin_table_header_with_flags(
+      "loaded",
+      ["Type", "Name", "References"].map(TableColumnSetup::new),
+      flags,
+    ) {
+      for (t, v) in assets.handles.iter() {
+        for h in v {
+          ui.table_next_row();
+          ui.table_next_column();
+          ui.text(t.name.rsplit("::").next().unwrap());
+          ui.table_next_column();
+          ui.text(&h.name);
+          ui.table_next_column();
+          match h.refs() {
+            0 => ui.text_disabled("0"),
+            n => ui.text(n.to_string()),
+          }
+        }
+      }
+    }
+  }
+
+  if ui.collapsing_header("On Disk", TreeNodeFlags::DEFAULT_OPEN) {
+    let state = world.get_resource::<UnusedAssets>().unwrap();
+    if ui.small_button("Scan") {
+      if let Some(project) = world.get_resource::<Project>() {
+        state.files = Some(scan(project, assets));
+      }
+    }
+    hover_tooltip(
+      ui,
+      "Find files in the asset root that no scene, code or referenced asset names.",
+    );
+    let files = match &state.files {
+      Some(f) => f,
+      None => return,
+    };
+    let total = files.iter().map(|f| f.1).sum::<u64>();
+    ui.same_line();
+    ui.text_disabled(format!("{} files | {}", files.len(), mb(total)));
+    let flags = TableFlags::RESIZABLE | TableFlags::ROW_BG | TableFlags::BORDERS_INNER_V;
+    if let Some(_t) =
+      ui.begin_table_header_with_flags("unused", ["Name", "Size"].map(TableColumnSetup::new), flags)
+    {
+      for (name, size) in files {
+        ui.table_next_row();
+        ui.table_next_column();
+        ui.text(name);
+        ui.table_next_column();
+        ui.text(mb(*size));
+      }
+    }
+  }
+}
+
+// files in the asset root that nothing names. scenes, code and the project file anywhere in the
+// project are searched, then any asset they name that names others in turn, eg a material's
+// textures. loaded assets with references count as used
+fn scan(project: &Project, assets: &Assets) -> Vec<(String, u64)> {
+  let dir = project.dir();
+  let root = dir.join(&project.asset_root);
+  let mut files = vec![];
+  walk(&root, &mut files);
+  files.retain(|p| {
+    let first = p
+      .strip_prefix(&root)
+      .ok()
+      .and_then(|p| p.components().next());
+    !first.is_some_and(|c| ENGINE_DIRS.iter().any(|d| c.as_os_str() == *d))
+  });
+  let names = files
+    .iter()
+    .map(|p| asset_name(p.strip_prefix(&root).unwrap()))
+    .collect::<Vec<_>>();
+
+  let loaded = assets
+    .handles
+    .values()
+    .flatten()
+    .filter(|h| h.refs() > 0)
+    .map(|h| h.name.clone())
+    .collect::<HashSet<_>>();
+  let mut used = (0..names.len())
+    .filter(|i| loaded.contains(&names[*i]))
+    .collect::<HashSet<_>>();
+  let mut pending = vec![];
+  walk(&dir, &mut pending);
+  pending.retain(|p| document(p) && (!p.starts_with(&root) || extension(p) == "scene"));
+  pending.extend(
+    used
+      .iter()
+      .map(|i| files[*i].clone())
+      .filter(|p| document(p)),
+  );
+  while let Some(doc) = pending.pop() {
+    let bytes = match fs::read(&doc) {
+      Ok(b) => b,
+      Err(_) => continue,
+    };
+    for (i, name) in names.iter().enumerate() {
+      if !used.contains(&i) && (mentions(&bytes, name) || cubemap_face(name, &bytes)) {
+        used.insert(i);
+        if document(&files[i]) {
+          pending.push(files[i].clone());
+        }
+      }
+    }
+  }
+
+  let mut unused = names
+    .iter()
+    .enumerate()
+    .filter(|(i, name)| {
+      // texture options go with their texture
+      let texture = name.strip_suffix(".tex");
+      !used.contains(i)
+        && !texture.is_some_and(|t| {
+          names
+            .iter()
+            .position(|n| n == t)
+            .is_some_and(|j| used.contains(&j))
+        })
+    })
+    .map(|(i, name)| {
+      let size = fs::metadata(&files[i]).map_or(0, |m| m.len());
+      (name.clone(), size)
+    })
+    .collect::<Vec<_>>();
+  unused.sort_by(|a, b| b.1.cmp(&a.1));
+  info!("Found {} unused assets.", unused.len());
+  unused
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+  let entries = match fs::read_dir(dir) {
+    Ok(e) => e,
+    Err(_) => return,
+  };
+  for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+    if path.is_dir() {
+      if !SKIP_DIRS.iter().any(|d| path.ends_with(d)) {
+        walk(&path, files);
+      }
+    } else {
+      files.push(path);
+    }
+  }
+}
+
+fn extension(path: &Path) -> String {
+  path
+    .extension()
+    .map(|e| e.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+fn document(path: &Path) -> bool {
+  DOCUMENTS.contains(&extension(path).as_str())
+}
+
+// what `Assets::load` takes
+fn asset_name(path: &Path) -> String {
+  path
+    .components()
+    .map(|c| c.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+// cubemaps are named by their folder
+fn cubemap_face(name: &str, bytes: &[u8]) -> bool {
+  let path = Path::new(name);
+  let face = path
+    .file_stem()
+    .is_some_and(|s| ["px", "nx", "py", "ny", "pz", "nz"].iter().any(|f| s == *f));
+  match path.parent().map(asset_name) {
+    Some(folder) if face && !folder.is_empty() => mentions(bytes, &folder),
+    _ => false,
+  }
+}
+
+// `name` on its own, not part of a longer name
+fn mentions(bytes: &[u8], name: &str) -> bool {
+  let part = |b: &u8| b.is_ascii_alphanumeric() || b"_-./".contains(b);
+  let name = name.as_bytes();
+  bytes.windows(name.len()).enumerate().any(|(i, w)| {
+    w == name && !(i > 0 && part(&bytes[i - 1])) && !bytes.get(i + name.len()).is_some_and(part)
+  })
+}
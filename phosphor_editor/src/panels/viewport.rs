@@ -0,0 +1,57 @@
+use phosphor::math::{Vec3, Vec2, Mat4};
+
+// the scene is rendered at this many pixels per ui unit
+pub const FB_SCALE: f32 = 2.5;
+
+// the scene panel's image on screen and the camera drawn into it, all positions are in imgui
+// screen space unless they say otherwise
+pub struct Viewport {
+  pub pos: [f32; 2],
+  pub size: [f32; 2],
+  pub view: Mat4,
+  pub proj: Mat4,
+}
+
+impl Viewport {
+  pub fn contains(&self, p: [f32; 2]) -> bool {
+    p[0] >= self.pos[0]
+      && p[1] >= self.pos[1]
+      && p[0] < self.pos[0] + self.size[0]
+      && p[1] < self.pos[1] + self.size[1]
+  }
+
+  // none if `p` is behind the camera
+  pub fn world_to_viewport(&self, p: Vec3) -> Option<[f32; 2]> {
+    let c = self.proj * self.view * p.extend(1.0);
+    if c.w <= 0.0 {
+      return None;
+    }
+    let n = c.truncate() / c.w;
+    Some([
+      self.pos[0] + (n.x * 0.5 + 0.5) * self.size[0],
+      self.pos[1] + (0.5 - n.y * 0.5) * self.size[1],
+    ])
+  }
+
+  // origin on the near plane and normalized direction
+  pub fn viewport_to_world_ray(&self, p: [f32; 2]) -> (Vec3, Vec3) {
+    let ndc = Vec2::new(
+      (p[0] - self.pos[0]) / self.size[0] * 2.0 - 1.0,
+      1.0 - (p[1] - self.pos[1]) / self.size[1] * 2.0,
+    );
+    let inv = (self.proj * self.view).inverse();
+    let near = inv.project_point3(ndc.extend(-1.0));
+    let far = inv.project_point3(ndc.extend(1.0));
+    (near, (far - near).normalize())
+  }
+
+  // right, up and forward of the camera
+  pub fn axes(&self) -> (Vec3, Vec3, Vec3) {
+    let inv = self.view.inverse();
+    (
+      inv.x_axis.truncate(),
+      inv.y_axis.truncate(),
+      -inv.z_axis.truncate(),
+    )
+  }
+}
@@ -0,0 +1,96 @@
+use phosphor::ecs::World;
+use phosphor::vram::{self, GpuKind};
+use phosphor_imgui::imgui::{
+  Ui, WindowFlags, TableFlags, TableColumnSetup, TableColumnFlags, TableSortDirection,
+};
+use crate::panels::Panel;
+
+pub fn init() -> Panel {
+  Panel {
+    title: "\u{f538} GPU Memory",
+    flags: WindowFlags::empty(),
+    vars: &[],
+    open: false,
+    render,
+  }
+}
+
+fn mb(bytes: usize) -> String {
+  format!("{:.2}MB", bytes as f32 / (1 << 20) as f32)
+}
+
+fn render(_: &mut World, ui: &Ui) {
+  let mut objects = vram::objects();
+  let total = objects.iter().map(|o| o.bytes).sum::<usize>();
+  ui.text(format!("{} objects | {}", objects.len(), mb(total)));
+  for kind in [
+    GpuKind::Texture,
+    GpuKind::Buffer,
+    GpuKind::Renderbuffer,
+    GpuKind::Program,
+  ] {
+    let (count, bytes) = objects
+      .iter()
+      .filter(|o| o.kind == kind)
+      .fold((0, 0), |(c, b), o| (c + 1, b + o.bytes));
+    ui.same_line();
+    ui.text_disabled(format!("| {} {} {}", count, kind.name(), mb(bytes)));
+  }
+  let columns = ["Kind", "Id", "Label", "Size", "Creator"].map(|name| {
+    let mut c = TableColumnSetup::new(name);
+    c.flags = match name {
+      "Label" | "Creator" => TableColumnFlags::WIDTH_STRETCH,
+      // biggest first, to find what's using the memory
+      "Size" => TableColumnFlags::DEFAULT_SORT | TableColumnFlags::PREFER_SORT_DESCENDING,
+      _ => TableColumnFlags::empty(),
+    };
+    c
+  });
+  let flags = TableFlags::SORTABLE
+    | TableFlags::RESIZABLE
+    | TableFlags::ROW_BG
+    | TableFlags::SCROLL_Y
+    | TableFlags::BORDERS_INNER_V;
+  if let Some(_t) = ui.begin_table_header_with_flags("vram", columns, flags) {
+    // the list is rebuilt every frame, so it's sorted every frame too
+    let mut sort = (3, false);
+    if let Some(mut specs) = ui.table_sort_specs_mut() {
+      if let Some(s) = specs.specs().iter().next() {
+        sort = (
+          s.column_idx(),
+          s.sort_direction() != Some(TableSortDirection::Descending),
+        );
+      }
+      specs.set_sorted();
+    }
+    objects.sort_by(|a, b| {
+      let order = match sort.0 {
+        0 => a.kind.cmp(&b.kind),
+        1 => a.id.cmp(&b.id),
+        2 => a.label.cmp(&b.label),
+        4 => a.creator.to_string().cmp(&b.creator.to_string()),
+        _ => a.bytes.cmp(&b.bytes),
+      };
+      match sort.1 {
+        true => order,
+        false => order.reverse(),
+      }
+    });
+    for o in objects {
+      ui.table_next_row();
+      ui.table_next_column();
+      ui.text(o.kind.name());
+      ui.table_next_column();
+      ui.text(o.id.to_string());
+      ui.table_next_column();
+      match &o.label {
+        Some(label) => ui.text(label),
+        None => ui.text_disabled("-"),
+      }
+      ui.table_next_column();
+      ui.text(mb(o.bytes));
+      ui.table_next_column();
+      ui.text_disabled(o.creator.to_string());
+    }
+  }
+}
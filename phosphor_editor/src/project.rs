@@ -0,0 +1,244 @@
+use std::fs;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use phosphor::{Result, assets::Assets};
+use phosphor::ecs::World;
+use phosphor::scene::Scene;
+use phosphor::log::{error, info};
+use phosphor_fmod::fmod_plugin;
+use phosphor_imgui::imgui::{Ui, Condition, WindowFlags};
+use rfd::FileDialog;
+use crate::{SceneName, SelectedEntity};
+use crate::locale::Locale;
+use crate::panels::play;
+
+const RECENT_PATH: &str = "phosphor_editor/recent_projects.txt";
+const MAX_RECENT: usize = 10;
+
+// plugins a project can turn on, the rest of the editor always runs
+pub const PLUGINS: &[(&str, fn(&mut World) -> Result)] = &[("fmod", fmod_plugin)];
+
+// a game's editor settings, kept as `key = value` lines in a .project file:
+//
+//   name = "demo"
+//   startup_scene = "main.scene"
+//   asset_root = "assets"
+//   plugins = ["fmod"]
+//
+// paths are relative to the file's directory
+pub struct Project {
+  pub path: PathBuf,
+  pub name: String,
+  // empty for none
+  pub startup_scene: String,
+  pub asset_root: String,
+  pub plugins: Vec<String>,
+}
+
+fn string(value: &str) -> Option<String> {
+  Some(value.strip_prefix('"')?.strip_suffix('"')?.to_string())
+}
+
+impl Project {
+  pub fn new(path: PathBuf) -> Self {
+    Self {
+      name: path
+        .file_stem()
+        .map_or("project".into(), |s| s.to_string_lossy().to_string()),
+      path,
+      startup_scene: String::new(),
+      asset_root: "assets".to_string(),
+      plugins: PLUGINS.iter().map(|p| p.0.to_string()).collect(),
+    }
+  }
+
+  pub fn load(path: &Path) -> Result<Self> {
+    let mut project = Self::new(path.to_path_buf());
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let err = || format!("Invalid line {} in '{}'.", i + 1, path.display());
+      let (key, value) = line.split_once('=').ok_or_else(err)?;
+      let value = value.trim();
+      match key.trim() {
+        "name" => project.name = string(value).ok_or_else(err)?,
+        "startup_scene" => project.startup_scene = string(value).ok_or_else(err)?,
+        "asset_root" => project.asset_root = string(value).ok_or_else(err)?,
+        "plugins" => {
+          project.plugins = value
+            .strip_prefix('[')
+            .and_then(|v| v.strip_suffix(']'))
+            .ok_or_else(err)?
+            .split(',')
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| string(p.trim()))
+            .collect::<Option<_>>()
+            .ok_or_else(err)?
+        }
+        key => {
+          return Err(format!("Unknown project key '{}' in '{}'.", key, path.display()).into())
+        }
+      }
+    }
+    Ok(project)
+  }
+
+  pub fn save(&self) -> Result {
+    let mut s = String::new();
+    writeln!(s, "name = \"{}\"", self.name)?;
+    writeln!(s, "startup_scene = \"{}\"", self.startup_scene)?;
+    writeln!(s, "asset_root = \"{}\"", self.asset_root)?;
+    let plugins = self
+      .plugins
+      .iter()
+      .map(|p| format!("\"{}\"", p))
+      .collect::<Vec<_>>();
+    writeln!(s, "plugins = [{}]", plugins.join(", "))?;
+    fs::write(&self.path, s)?;
+    Ok(())
+  }
+
+  pub fn dir(&self) -> PathBuf {
+    self
+      .path
+      .parent()
+      .map_or(PathBuf::from("."), Path::to_path_buf)
+  }
+
+  // makes this the open project, its startup scene replaces the current one
+  pub fn open(self, world: &mut World) {
+    info!("Opening project '{}'.", self.name);
+    world.add_resource(SelectedEntity(None));
+    play::reset(world);
+    world.components.clear();
+    world.add_resource(SceneName("".to_string()));
+    // the old project's assets are unloaded once nothing holds them, engine ones stay loaded
+    let assets = world.get_resource::<Assets>().unwrap();
+    assets.release();
+    assets.collect();
+    assets.root = self.dir().join(&self.asset_root).display().to_string();
+    let loaded = &mut world.get_resource::<LoadedPlugins>().unwrap().0;
+    let start = PLUGINS
+      .iter()
+      .filter(|(name, _)| self.plugins.iter().any(|p| p == name) && !loaded.contains(name))
+      .collect::<Vec<_>>();
+    loaded.extend(start.iter().map(|p| p.0));
+    for (name, plugin) in start {
+      if let Err(e) = plugin(world) {
+        error!("Couldn't start plugin '{}'. {}", name, e);
+      }
+    }
+    if !self.startup_scene.is_empty() {
+      let path = self.dir().join(&self.startup_scene);
+      match Scene::load(world, path.clone()) {
+        Ok(_) => world.add_resource(SceneName(path.display().to_string())),
+        Err(e) => error!("Couldn't load '{}'. {}", path.display(), e),
+      }
+    }
+    let recent = world.get_resource::<RecentProjects>().unwrap();
+    recent.push(self.path.clone());
+    world.add_resource(self);
+  }
+}
+
+// plugins stay running when another project is opened
+pub struct LoadedPlugins(pub Vec<&'static str>);
+
+// project files opened before, newest first
+pub struct RecentProjects(pub Vec<PathBuf>);
+
+impl RecentProjects {
+  pub fn load() -> Self {
+    Self(
+      fs::read_to_string(RECENT_PATH)
+        .unwrap_or_default()
+        .lines()
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .collect(),
+    )
+  }
+
+  fn push(&mut self, path: PathBuf) {
+    let path = path.canonicalize().unwrap_or(path);
+    self.0.retain(|p| *p != path);
+    self.0.insert(0, path);
+    self.0.truncate(MAX_RECENT);
+    let s = self
+      .0
+      .iter()
+      .map(|p| p.display().to_string())
+      .collect::<Vec<_>>()
+      .join("\n");
+    if let Err(e) = fs::write(RECENT_PATH, s) {
+      error!("Couldn't save recent projects. {}", e);
+    }
+  }
+}
+
+pub fn open_path(world: &mut World, path: &Path) {
+  match Project::load(path) {
+    Ok(project) => project.open(world),
+    Err(e) => error!("Couldn't open project '{}'. {}", path.display(), e),
+  }
+}
+
+// shown in place of the panels until a project is open
+pub fn launcher(world: &mut World, ui: &Ui) {
+  let locale = world.get_resource::<Locale>().unwrap();
+  let [w, h] = ui.io().display_size;
+  ui.window(format!("{}###Projects", locale.tr("\u{f07c} Projects")))
+    .position([w / 2.0, h / 2.0], Condition::Always)
+    .position_pivot([0.5, 0.5])
+    .size([480.0, 360.0], Condition::Always)
+    .flags(
+      WindowFlags::NO_RESIZE
+        | WindowFlags::NO_MOVE
+        | WindowFlags::NO_COLLAPSE
+        | WindowFlags::NO_DOCKING,
+    )
+    .build(|| {
+      if ui.button(locale.tr("\u{f067} New Project")) {
+        if let Some(path) = FileDialog::new().set_file_name("game.project").save_file() {
+          let project = Project::new(path);
+          let created = fs::create_dir_all(project.dir().join(&project.asset_root))
+            .map_err(|e| e.into())
+            .and_then(|_| project.save());
+          match created {
+            Ok(_) => world.commands().add(move |world| project.open(world)),
+            Err(e) => error!(
+              "Couldn't create project '{}'. {}",
+              project.path.display(),
+              e
+            ),
+          }
+        }
+      }
+      ui.same_line();
+      if ui.button(locale.tr("\u{f07c} Open Project")) {
+        if let Some(path) = FileDialog::new()
+          .add_filter("Project", &["project"])
+          .pick_file()
+        {
+          world.commands().add(move |world| open_path(world, &path));
+        }
+      }
+      ui.separator();
+      ui.text_disabled(locale.tr("Recent"));
+      let recent = world.get_resource::<RecentProjects>().unwrap().0.clone();
+      if recent.is_empty() {
+        ui.text(locale.tr("No recent projects."));
+      }
+      for (i, path) in recent.iter().enumerate() {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        if ui.selectable(format!("{}##{}", name, i)) {
+          let path = path.clone();
+          world.commands().add(move |world| open_path(world, &path));
+        }
+        ui.same_line();
+        ui.text_disabled(path.display().to_string());
+      }
+    });
+}
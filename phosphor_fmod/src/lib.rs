@@ -2,15 +2,16 @@ use std::ptr;
 use std::ffi::CString;
 use libfmod::{System, Sound as FmodSound, Channel};
 use libfmod::ffi::{
-  FMOD_INIT_3D_RIGHTHANDED, FMOD_3D, FMOD_VECTOR, FMOD_System_GetDriverInfo,
+  FMOD_INIT_3D_RIGHTHANDED, FMOD_3D, FMOD_VECTOR, FMOD_TIMEUNIT_MS, FMOD_System_GetDriverInfo,
   FMOD_System_Set3DListenerAttributes, FMOD_Channel_Set3DAttributes,
 };
-use phosphor::assets::Handle;
+use phosphor::assets::{Handle, Assets};
+use phosphor::accessibility::Subtitles;
 use phosphor::ecs::{World, stage};
 use phosphor::{Result, asset, component};
-use phosphor::log::debug;
+use phosphor::log::{debug, warn};
 use phosphor::math::Vec3;
-use phosphor_3d::{Camera, Transform};
+use phosphor_3d::{Camera, Transform, SequencePlayer, SequenceEvent};
 use serde::{Serialize, Deserialize};
 
 pub use libfmod as fmod;
@@ -28,6 +29,8 @@ impl FmodOptions {
 pub struct FmodContext {
   pub system: System,
   pub ver: String,
+  // kept loaded until they finish
+  one_shots: Vec<(Handle<Sound>, Channel)>,
 }
 
 pub fn fmod_plugin(world: &mut World) -> Result {
@@ -49,7 +52,11 @@ pub fn fmod_plugin(world: &mut World) -> Result {
       ptr::null_mut(),
     );
     debug!("Initialized FMOD {} system on '{}'. ", ver, name.to_str()?);
-    world.add_resource(FmodContext { system, ver });
+    world.add_resource(FmodContext {
+      system,
+      ver,
+      one_shots: vec![],
+    });
   }
 
   let options = match world.get_resource::<FmodOptions>() {
@@ -97,11 +104,37 @@ fn fmod_predraw(world: &mut World) -> Result {
       }
     }
   }
+  // audio keys sequences passed over last frame
+  for (_, p) in world.query::<SequencePlayer>() {
+    for event in p.events() {
+      match event {
+        SequenceEvent::Audio(target) => {
+          if let Some(e) = world.get_name(target) {
+            if let Some(a) = e.get_one::<AudioSource>() {
+              a.play(world);
+            }
+          }
+        }
+        SequenceEvent::Sound(sound, target) => {
+          let pos = world
+            .get_name(target)
+            .and_then(|e| Some(e.get_one::<Transform>()?.global_mat4(e).w_axis.truncate()));
+          if let Err(e) = play_one_shot(world, sound, pos) {
+            warn!("Couldn't play '{}'. {}", sound, e);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+  fmod
+    .one_shots
+    .retain(|(_, c)| c.is_playing().unwrap_or(false));
   fmod.system.update().unwrap();
   Ok(())
 }
 
-#[asset(load_sound)]
+#[asset(load_sound, unload_sound)]
 pub struct Sound(pub FmodSound);
 
 fn load_sound(world: &mut World, path: &str) -> Result<Sound> {
@@ -115,12 +148,18 @@ fn load_sound(world: &mut World, path: &str) -> Result<Sound> {
   ))
 }
 
+fn unload_sound(_: &mut World, sound: &Sound) {
+  let _ = sound.0.release();
+}
+
 #[derive(Serialize, Deserialize)]
 #[component]
 pub struct AudioSource {
   pub sound: Handle<Sound>,
   pub pitch: f32,
   pub play_on_start: bool,
+  // shown for as long as the sound plays when subtitles are on
+  pub subtitle: Option<String>,
   #[serde(skip)]
   channel: Option<Channel>,
 }
@@ -131,6 +170,7 @@ impl AudioSource {
       sound,
       pitch: 1.0,
       play_on_start: true,
+      subtitle: None,
       channel: None,
     }
   }
@@ -144,9 +184,30 @@ impl AudioSource {
       .unwrap();
     channel.set_pitch(self.pitch).unwrap();
     self.channel = Some(channel);
+    if let (Some(text), Some(subtitles)) = (&self.subtitle, world.get_resource::<Subtitles>()) {
+      let ms = self.sound.0.get_length(FMOD_TIMEUNIT_MS).unwrap_or(0);
+      subtitles.show(text, ms as f32 / 1000.0 / self.pitch.max(0.01));
+    }
   }
 }
 
+// plays a sound asset to the end without an AudioSource, at `pos` or wherever the listener is
+pub fn play_one_shot(world: &World, path: &str, pos: Option<Vec3>) -> Result {
+  let sound = world
+    .get_resource::<Assets>()
+    .unwrap()
+    .load::<Sound>(path)?;
+  let fmod = world.get_resource::<FmodContext>().unwrap();
+  let channel = fmod.system.play_sound(sound.0, None, false)?;
+  if let Some(pos) = pos {
+    unsafe {
+      FMOD_Channel_Set3DAttributes(channel.as_mut_ptr(), &fvec(pos), &fvec(Vec3::ZERO));
+    }
+  }
+  fmod.one_shots.push((sound, channel));
+  Ok(())
+}
+
 fn fvec(v: Vec3) -> FMOD_VECTOR {
   FMOD_VECTOR {
     x: v.x,
@@ -0,0 +1,252 @@
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use accesskit::{
+  Action, ActionHandler, ActionRequest, Node, NodeBuilder, NodeClassSet, NodeId, Rect, Tree,
+  TreeUpdate,
+};
+use imgui::sys;
+use imgui::{Context, ConfigFlags};
+use phosphor::{Result, cvar};
+use phosphor::ecs::{World, stage};
+use phosphor::log::debug;
+
+pub use accesskit::Role;
+
+// publishes the ui to the os accessibility api and turns on keyboard navigation, read each frame
+cvar!(pub screen_reader: bool = false);
+
+const ROOT: NodeId = NodeId(0);
+
+fn window_node(id: u32) -> NodeId {
+  NodeId(1 << 32 | id as u64)
+}
+
+fn item_node(id: u32) -> NodeId {
+  NodeId(2 << 32 | id as u64)
+}
+
+// imgui ids hide after ## in window names
+fn visible_name(name: &str) -> &str {
+  name.split("##").next().unwrap()
+}
+
+struct Item {
+  id: u32,
+  window: u32,
+  root: u32,
+  role: Role,
+  name: String,
+  bounds: Rect,
+}
+
+// requests from the screen reader, handled at the start of the next frame
+struct Actions(Arc<Mutex<Vec<ActionRequest>>>);
+
+impl ActionHandler for Actions {
+  fn do_action(&mut self, request: ActionRequest) {
+    self.0.lock().unwrap().push(request);
+  }
+}
+
+// windows are found from imgui itself, but it keeps no labels for widgets so anything a screen
+// reader should announce is described with `label` straight after it's drawn
+pub struct AccessTree {
+  items: Vec<Item>,
+  focus: NodeId,
+  actions: Arc<Mutex<Vec<ActionRequest>>>,
+  classes: NodeClassSet,
+  #[cfg(target_os = "linux")]
+  adapter: Option<accesskit_unix::Adapter>,
+}
+
+impl AccessTree {
+  fn new() -> Self {
+    let actions = Arc::new(Mutex::new(vec![]));
+    #[cfg(target_os = "linux")]
+    let adapter = accesskit_unix::Adapter::new(
+      "phosphor".to_string(),
+      "imgui".to_string(),
+      imgui::dear_imgui_version().to_string(),
+      || TreeUpdate {
+        nodes: vec![(
+          ROOT,
+          NodeBuilder::new(Role::Window).build(&mut NodeClassSet::new()),
+        )],
+        tree: Some(Tree::new(ROOT)),
+        focus: ROOT,
+      },
+      Box::new(Actions(actions.clone())),
+    );
+    #[cfg(not(target_os = "linux"))]
+    phosphor::log::warn!("There's no accessibility adapter for this platform yet.");
+    Self {
+      items: vec![],
+      focus: ROOT,
+      actions,
+      classes: NodeClassSet::new(),
+      #[cfg(target_os = "linux")]
+      adapter,
+    }
+  }
+
+  #[cfg(target_os = "linux")]
+  fn publish(&mut self) {
+    if let Some(adapter) = self.adapter.take() {
+      adapter.update_if_active(|| self.tree_update());
+      self.adapter = Some(adapter);
+    }
+  }
+
+  #[cfg(not(target_os = "linux"))]
+  fn publish(&mut self) {}
+
+  // describes the widget drawn last
+  pub fn label(&mut self, role: Role, name: &str) {
+    unsafe {
+      let window = sys::igGetCurrentWindow();
+      if window.is_null() {
+        return;
+      }
+      let (mut min, mut max) = (sys::ImVec2::zero(), sys::ImVec2::zero());
+      sys::igGetItemRectMin(&mut min);
+      sys::igGetItemRectMax(&mut max);
+      let id = sys::igGetItemID();
+      if sys::igIsItemFocused() {
+        self.focus = item_node(id);
+      }
+      self.items.push(Item {
+        id,
+        window: (*window).ID,
+        root: (*(*window).RootWindow).ID,
+        role,
+        name: name.to_string(),
+        bounds: Rect::new(min.x as _, min.y as _, max.x as _, max.y as _),
+      });
+    }
+  }
+
+  // the whole tree, for platform adapters
+  pub fn tree_update(&mut self) -> TreeUpdate {
+    let mut nodes = vec![];
+    let mut windows = vec![];
+    let g = unsafe { &*sys::igGetCurrentContext() };
+    let all = unsafe { std::slice::from_raw_parts(g.Windows.Data, g.Windows.Size as _) };
+    for &w in all {
+      let w = unsafe { &*w };
+      let flags = w.Flags as u32;
+      if !w.Active
+        || w.Hidden
+        || w.IsFallbackWindow
+        || flags & (sys::ImGuiWindowFlags_ChildWindow | sys::ImGuiWindowFlags_Tooltip) != 0
+      {
+        continue;
+      }
+      let name = unsafe { CStr::from_ptr(w.Name) }.to_string_lossy();
+      let role = match (name.as_ref(), flags & sys::ImGuiWindowFlags_Popup != 0) {
+        ("##MainMenuBar", _) => Role::MenuBar,
+        (_, true) => Role::Menu,
+        _ => Role::Window,
+      };
+      let mut node = NodeBuilder::new(role);
+      node.set_name(visible_name(&name));
+      node.set_bounds(Rect::new(
+        w.Pos.x as _,
+        w.Pos.y as _,
+        (w.Pos.x + w.Size.x) as _,
+        (w.Pos.y + w.Size.y) as _,
+      ));
+      node.add_action(Action::Focus);
+      for item in self.items.iter().filter(|i| i.root == w.ID) {
+        node.push_child(item_node(item.id));
+      }
+      windows.push(window_node(w.ID));
+      nodes.push((window_node(w.ID), node));
+    }
+    for item in &self.items {
+      let mut node = NodeBuilder::new(item.role);
+      node.set_name(item.name.as_str());
+      node.set_bounds(item.bounds);
+      node.add_action(Action::Focus);
+      nodes.push((item_node(item.id), node));
+    }
+    let mut root = NodeBuilder::new(Role::Window);
+    root.set_name("phosphor");
+    root.set_children(windows);
+    nodes.push((ROOT, root));
+    // an unlabelled widget keeps focus on its window
+    if self.focus == ROOT && !g.NavWindow.is_null() {
+      self.focus = window_node(unsafe { (*(*g.NavWindow).RootWindow).ID });
+    }
+    let classes = &mut self.classes;
+    TreeUpdate {
+      nodes: nodes
+        .into_iter()
+        .map(|(id, node)| (id, node.build(classes)))
+        .collect::<Vec<(NodeId, Node)>>(),
+      tree: Some(Tree::new(ROOT)),
+      focus: self.focus,
+    }
+  }
+}
+
+// describes the widget drawn last, does nothing while the screen reader mode is off
+pub fn label(world: &World, role: Role, name: &str) {
+  if screen_reader.get() {
+    if let Some(tree) = world.get_resource::<AccessTree>() {
+      tree.label(role, name);
+    }
+  }
+}
+
+pub fn access_plugin(world: &mut World) -> Result {
+  world.add_resource(AccessTree::new());
+  world.add_system(stage::PRE_DRAW, access_predraw);
+  world.add_system(stage::POST_DRAW, access_postdraw);
+  debug!("Initialized accessibility tree.");
+  Ok(())
+}
+
+fn access_predraw(world: &mut World) -> Result {
+  let tree = world.get_resource::<AccessTree>().unwrap();
+  let ctx = world.get_resource::<Context>().unwrap();
+  ctx
+    .io_mut()
+    .config_flags
+    .set(ConfigFlags::NAV_ENABLE_KEYBOARD, screen_reader.get());
+  // targets are from last frame's items, so they're handled before those are cleared
+  for request in tree.actions.lock().unwrap().drain(..) {
+    if request.action != Action::Focus {
+      continue;
+    }
+    let (kind, id) = (request.target.0 >> 32, request.target.0 as u32);
+    unsafe {
+      match kind {
+        1 => {
+          let window = sys::igFindWindowByID(id);
+          if !window.is_null() {
+            sys::igFocusWindow(window);
+          }
+        }
+        _ => {
+          if let Some(item) = tree.items.iter().find(|i| i.id == id) {
+            let window = sys::igFindWindowByID(item.window);
+            if !window.is_null() {
+              sys::igSetFocusID(id, window);
+            }
+          }
+        }
+      }
+    }
+  }
+  tree.items.clear();
+  tree.focus = ROOT;
+  Ok(())
+}
+
+// after imgui_draw so every window of the frame has been submitted
+fn access_postdraw(world: &mut World) -> Result {
+  if screen_reader.get() {
+    world.get_resource::<AccessTree>().unwrap().publish();
+  }
+  Ok(())
+}
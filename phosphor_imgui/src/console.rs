@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+use imgui::{
+  Ui, WindowFlags, Condition, InputTextCallback, InputTextCallbackHandler, TextCallbackData,
+  HistoryDirection,
+};
+use phosphor::{Result, Time};
+use phosphor::ecs::{World, stage};
+use phosphor::scene::Scene;
+use phosphor::cvars::{self, CVARS_PATH};
+use phosphor::replay::Replay;
+use phosphor::glfw::{WindowEvent, Key, Action};
+
+const INFO: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const INPUT: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const ERROR: [f32; 4] = [0.9, 0.35, 0.3, 1.0];
+
+pub type Command = fn(&mut World, &[&str]) -> Result<String>;
+
+pub struct Console {
+  pub open: bool,
+  input: String,
+  lines: Vec<(String, [f32; 4])>,
+  history: Vec<String>,
+  history_pos: Option<usize>,
+  commands: BTreeMap<&'static str, (&'static str, Command)>,
+  scroll: bool,
+}
+
+impl Console {
+  pub fn new() -> Self {
+    let mut console = Self {
+      open: false,
+      input: String::new(),
+      lines: vec![],
+      history: vec![],
+      history_pos: None,
+      commands: BTreeMap::new(),
+      scroll: false,
+    };
+    console.register("help", "list commands", help);
+    console.register("clear", "clear the console", clear);
+    console.register("spawn", "spawn <name>", spawn);
+    console.register("set_timescale", "set_timescale <scale>", set_timescale);
+    console.register("load_scene", "load_scene <path>", load_scene);
+    console.register("cvars", "list tuning variables", list_cvars);
+    console.register("set", "set <cvar> <value>", set_cvar);
+    console.register("reset", "reset <cvar>", reset_cvar);
+    console.register("record", "record <path>, records input until stop", record);
+    console.register("replay", "replay <path>", replay);
+    console.register("stop", "stop recording or replaying input", stop);
+    console
+  }
+
+  pub fn register(&mut self, name: &'static str, help: &'static str, cmd: Command) {
+    self.commands.insert(name, (help, cmd));
+  }
+
+  pub fn print<S: ToString>(&mut self, s: S) {
+    self.lines.push((s.to_string(), INFO));
+    self.scroll = true;
+  }
+
+  fn push(&mut self, s: String, color: [f32; 4]) {
+    self.lines.push((s, color));
+    self.scroll = true;
+  }
+}
+
+fn help(world: &mut World, _: &[&str]) -> Result<String> {
+  let console = world.get_resource::<Console>().unwrap();
+  Ok(
+    console
+      .commands
+      .iter()
+      .map(|(name, (help, _))| format!("{:<16}{}", name, help))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+fn clear(world: &mut World, _: &[&str]) -> Result<String> {
+  world.get_resource::<Console>().unwrap().lines.clear();
+  Ok(String::new())
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> Result<String> {
+  let name = args.first().ok_or("Usage: spawn <name>")?;
+  let e = world.spawn(name);
+  Ok(format!("Spawned '{}' ({}).", name, e.id))
+}
+
+fn set_timescale(world: &mut World, args: &[&str]) -> Result<String> {
+  let scale = args
+    .first()
+    .ok_or("Usage: set_timescale <scale>")?
+    .parse::<f32>()?;
+  world
+    .get_resource::<Time>()
+    .ok_or("No Time resource.")?
+    .scale = scale.max(0.0);
+  Ok(format!("Time scale set to {}.", scale.max(0.0)))
+}
+
+fn load_scene(world: &mut World, args: &[&str]) -> Result<String> {
+  let path = args.first().ok_or("Usage: load_scene <path>")?;
+  Scene::load(world, path.into())?;
+  Ok(format!("Loaded '{}'.", path))
+}
+
+fn record(world: &mut World, args: &[&str]) -> Result<String> {
+  let path = args.first().ok_or("Usage: record <path>")?;
+  Replay::record(world, path.into())?;
+  Ok(format!("Recording input to '{}'.", path))
+}
+
+fn replay(world: &mut World, args: &[&str]) -> Result<String> {
+  let path = args.first().ok_or("Usage: replay <path>")?;
+  Replay::play(world, path.into())?;
+  Ok(format!("Replaying '{}'.", path))
+}
+
+fn stop(world: &mut World, _: &[&str]) -> Result<String> {
+  Replay::stop(world)?;
+  Ok(String::new())
+}
+
+fn list_cvars(_: &mut World, _: &[&str]) -> Result<String> {
+  Ok(
+    cvars::all()
+      .iter()
+      .map(|c| format!("{:<24}{}", c.name(), c.value()))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+fn set_cvar(_: &mut World, args: &[&str]) -> Result<String> {
+  let (name, value) = match args {
+    [name, value] => (name, value),
+    _ => return Err("Usage: set <cvar> <value>".into()),
+  };
+  let cvar = cvars::find(name).ok_or(format!("Unknown cvar '{}'.", name))?;
+  cvar.parse(value)?;
+  cvars::save(CVARS_PATH)?;
+  Ok(format!("{} = {}", name, cvar.value()))
+}
+
+fn reset_cvar(_: &mut World, args: &[&str]) -> Result<String> {
+  let name = args.first().ok_or("Usage: reset <cvar>")?;
+  let cvar = cvars::find(name).ok_or(format!("Unknown cvar '{}'.", name))?;
+  cvar.reset();
+  cvars::save(CVARS_PATH)?;
+  Ok(format!("{} = {}", name, cvar.value()))
+}
+
+struct Callbacks<'a> {
+  commands: Vec<&'static str>,
+  history: &'a [String],
+  pos: &'a mut Option<usize>,
+  candidates: &'a mut Vec<&'static str>,
+}
+
+impl InputTextCallbackHandler for Callbacks<'_> {
+  fn on_completion(&mut self, mut data: TextCallbackData) {
+    let text = data.str().to_string();
+    // complete command names, or cvar names as the argument to set/reset
+    let (cmd, text, names) = match text.split_once(' ') {
+      None => ("", text.as_str(), self.commands.clone()),
+      Some((cmd @ ("set" | "reset"), arg)) if !arg.contains(' ') => {
+        (cmd, arg, cvars::all().iter().map(|c| c.name()).collect())
+      }
+      _ => return,
+    };
+    let matches = names
+      .into_iter()
+      .filter(|c| c.starts_with(text))
+      .collect::<Vec<_>>();
+    let line = |s: &str| match cmd {
+      "" => s.to_string(),
+      _ => format!("{} {}", cmd, s),
+    };
+    match matches.len() {
+      0 => {}
+      1 => {
+        data.clear();
+        data.push_str(&format!("{} ", line(matches[0])));
+      }
+      _ => {
+        // extend to the longest shared prefix and list the options
+        let mut prefix = matches[0].to_string();
+        for m in &matches[1..] {
+          while !m.starts_with(&prefix) {
+            prefix.pop();
+          }
+        }
+        data.clear();
+        data.push_str(&line(&prefix));
+        *self.candidates = matches;
+      }
+    }
+  }
+
+  fn on_history(&mut self, dir: HistoryDirection, mut data: TextCallbackData) {
+    if self.history.is_empty() {
+      return;
+    }
+    *self.pos = match (dir, *self.pos) {
+      (HistoryDirection::Up, None) => Some(self.history.len() - 1),
+      (HistoryDirection::Up, Some(p)) => Some(p.saturating_sub(1)),
+      (HistoryDirection::Down, Some(p)) if p + 1 < self.history.len() => Some(p + 1),
+      (HistoryDirection::Down, _) => None,
+    };
+    data.clear();
+    if let Some(p) = *self.pos {
+      data.push_str(&self.history[p]);
+    }
+  }
+}
+
+fn execute(world: &mut World, line: &str) {
+  let console = world.get_resource::<Console>().unwrap();
+  console.push(format!("> {}", line), INPUT);
+  if console.history.last().map_or(true, |l| l != line) {
+    console.history.push(line.to_string());
+  }
+  let args = line.split_whitespace().collect::<Vec<_>>();
+  let cmd = console.commands.get(args[0]).map(|c| c.1);
+  let result = match cmd {
+    Some(cmd) => cmd(world, &args[1..]),
+    None => Err(format!("Unknown command '{}', try 'help'.", args[0]).into()),
+  };
+  let console = world.get_resource::<Console>().unwrap();
+  match result {
+    Ok(s) if s.is_empty() => {}
+    Ok(s) => console.push(s, INFO),
+    Err(e) => console.push(e.to_string(), ERROR),
+  }
+}
+
+fn console_draw(world: &mut World) -> Result {
+  let ui = world.get_resource::<Ui>().unwrap();
+  let console = world.get_resource::<Console>().unwrap();
+  if !console.open {
+    return Ok(());
+  }
+  let [w, h] = ui.io().display_size;
+  let mut submitted = None;
+  ui.window("##console")
+    .flags(WindowFlags::NO_DECORATION | WindowFlags::NO_MOVE | WindowFlags::NO_SAVED_SETTINGS)
+    .position([0.0, 0.0], Condition::Always)
+    .size([w, h * 0.4], Condition::Always)
+    .bg_alpha(0.9)
+    .build(|| {
+      let footer = ui.frame_height_with_spacing();
+      ui.child_window("lines").size([0.0, -footer]).build(|| {
+        for (line, color) in &console.lines {
+          ui.text_colored(*color, line);
+        }
+        if console.scroll {
+          ui.set_scroll_here_y_with_ratio(1.0);
+          console.scroll = false;
+        }
+      });
+      ui.set_next_item_width(-1.0);
+      let mut candidates = vec![];
+      let callbacks = Callbacks {
+        commands: console.commands.keys().copied().collect(),
+        history: &console.history,
+        pos: &mut console.history_pos,
+        candidates: &mut candidates,
+      };
+      let entered = ui
+        .input_text("##input", &mut console.input)
+        .enter_returns_true(true)
+        .callback(
+          InputTextCallback::COMPLETION | InputTextCallback::HISTORY,
+          callbacks,
+        )
+        .build();
+      if !candidates.is_empty() {
+        console.push(candidates.join("  "), INPUT);
+      }
+      // the toggle key also arrives as a typed character
+      console.input.retain(|c| c != '`' && c != '~');
+      if entered && !console.input.trim().is_empty() {
+        submitted = Some(console.input.trim().to_string());
+        console.input.clear();
+        console.history_pos = None;
+      }
+      if ui.is_window_appearing() || entered {
+        ui.set_keyboard_focus_here_with_offset(imgui::FocusedWidget::Previous);
+      }
+    });
+  if let Some(line) = submitted {
+    execute(world, &line);
+  }
+  Ok(())
+}
+
+fn console_event(world: &mut World) -> Result {
+  if let WindowEvent::Key(Key::GraveAccent, _, Action::Press, _) =
+    world.get_resource::<WindowEvent>().unwrap()
+  {
+    let console = world.get_resource::<Console>().unwrap();
+    console.open = !console.open;
+  }
+  Ok(())
+}
+
+pub fn console_plugin(world: &mut World) -> Result {
+  if world.get_resource::<Console>().is_none() {
+    world.add_resource(Console::new());
+  }
+  world.add_system(stage::DRAW, console_draw);
+  world.add_system(stage::EVENT, console_event);
+  Ok(())
+}
@@ -1,38 +1,70 @@
+mod console;
+pub mod access;
+
 use std::fs;
 use std::time::Instant;
 use imgui::{
   Context, Ui, Style, StyleColor, ConfigFlags, MouseCursor, BackendFlags, Key, FontConfig,
-  FontGlyphRanges, TextureId, sys,
+  FontGlyphRanges, TextureId, Condition, WindowFlags, sys,
 };
 use phosphor::glfw::{
   Cursor, StandardCursor, CursorMode, WindowEvent, Action, Modifiers, MouseButton, Key as GlfwKey,
 };
 use phosphor::Result;
-use phosphor::gfx::{Renderer, Shader, Texture, gl};
+use phosphor::gfx::{
+  Renderer, RenderState, BlendFunc, Shader, Texture, TextureOptions, StreamBuffer, VertexLayout,
+  AttribType, gl,
+};
 use phosphor::ecs::{World, stage};
 use phosphor::math::Mat4;
-use phosphor::log::debug;
+use phosphor::log::{debug, warn, error};
+use phosphor::cvars::{self, CVarValue, CVARS_PATH};
+use phosphor::accessibility::{Subtitles, subtitles, ui_scale};
 
 pub use imgui;
+pub use console::{Console, Command, console_plugin};
 
 pub struct UiRendererOptions {
   pub docking: bool,
   pub fonts: &'static [&'static [(&'static str, f32, Option<&'static [u32]>)]],
+  // merged into every font at its size, for scripts the main fonts dont cover
+  pub fallback: &'static [(&'static str, &'static [u32])],
 }
 
 impl UiRendererOptions {
   const DEFAULT: Self = Self {
     docking: false,
     fonts: &[&[("assets/fonts/roboto.ttf", 16.0, None)]],
+    fallback: &[],
   };
 }
 
+// zero terminated codepoint ranges, matching the ones built into imgui
+pub mod glyphs {
+  pub const CYRILLIC: &[u32] = &[
+    0x0020, 0x00ff, 0x0400, 0x052f, 0x2de0, 0x2dff, 0xa640, 0xa69f, 0,
+  ];
+  pub const GREEK: &[u32] = &[0x0020, 0x00ff, 0x0370, 0x03ff, 0];
+  pub const JAPANESE: &[u32] = &[
+    0x0020, 0x00ff, 0x3000, 0x30ff, 0x31f0, 0x31ff, 0xff00, 0xffef, 0x4e00, 0x9faf, 0,
+  ];
+  pub const KOREAN: &[u32] = &[0x0020, 0x00ff, 0x3131, 0x3163, 0xac00, 0xd7a3, 0];
+  pub const CHINESE: &[u32] = &[
+    0x0020, 0x00ff, 0x2000, 0x206f, 0x3000, 0x30ff, 0x31f0, 0x31ff, 0xff00, 0xffef, 0x4e00, 0x9faf,
+    0,
+  ];
+}
+
 struct UiRenderer {
   shader: Shader,
   vert_arr: u32,
-  vert_buf: u32,
-  idx_buf: u32,
+  vert_buf: StreamBuffer,
+  idx_buf: StreamBuffer,
+  layout: VertexLayout,
   last_frame: Instant,
+  // unscaled, `ui_scale` is applied to a copy whenever it changes
+  style: Style,
+  scale: f32,
 }
 
 pub fn imgui_plugin(world: &mut World) -> Result {
@@ -78,20 +110,42 @@ pub fn imgui_plugin(world: &mut World) -> Result {
   io[Key::Z] = GlfwKey::Z as _;
 
   let mut fonts = ctx.fonts();
+  let fallback = options
+    .fallback
+    .iter()
+    .filter_map(|(path, glyphs)| match fs::read(path) {
+      Ok(data) => Some((&*Box::leak(data.into_boxed_slice()), *glyphs)),
+      Err(e) => {
+        warn!("Couldn't load fallback font '{}': {}", path, e);
+        None
+      }
+    })
+    .collect::<Vec<_>>();
   for font in options.fonts {
-    fonts.add_font(
-      &font
+    let mut sources = font
+      .iter()
+      .map(|f| imgui::FontSource::TtfData {
+        data: Box::leak(fs::read(f.0).unwrap().into_boxed_slice()),
+        size_pixels: f.1,
+        config: f.2.map(|g| FontConfig {
+          glyph_ranges: FontGlyphRanges::from_slice(g),
+          ..FontConfig::default()
+        }),
+      })
+      .collect::<Vec<_>>();
+    sources.extend(
+      fallback
         .iter()
-        .map(|f| imgui::FontSource::TtfData {
-          data: Box::leak(fs::read(f.0).unwrap().into_boxed_slice()),
-          size_pixels: f.1,
-          config: f.2.map(|g| FontConfig {
-            glyph_ranges: FontGlyphRanges::from_slice(g),
+        .map(|(data, glyphs)| imgui::FontSource::TtfData {
+          data,
+          size_pixels: font[0].1,
+          config: Some(FontConfig {
+            glyph_ranges: FontGlyphRanges::from_slice(glyphs),
             ..FontConfig::default()
           }),
-        })
-        .collect::<Vec<_>>(),
+        }),
     );
+    fonts.add_font(&sources);
   }
   let font_tex = fonts.build_rgba32_texture();
   fonts.tex_id = TextureId::new(
@@ -102,6 +156,7 @@ pub fn imgui_plugin(world: &mut World) -> Result {
       gl::SRGB_ALPHA,
       gl::RGBA,
       gl::UNSIGNED_BYTE,
+      TextureOptions::DEFAULT,
     )
     .id as _,
   );
@@ -110,38 +165,27 @@ pub fn imgui_plugin(world: &mut World) -> Result {
   style.window_rounding = 4.0;
   style.popup_rounding = 4.0;
   style.frame_rounding = 2.0;
+  let style = *style;
 
   let shader = Shader::new("imgui.vert", "imgui.frag")?;
   let mut vert_arr = 0;
-  let mut vert_buf = 0;
-  let mut idx_buf = 0;
   unsafe {
     gl::GenVertexArrays(1, &mut vert_arr);
-    gl::BindVertexArray(vert_arr);
-    gl::GenBuffers(1, &mut vert_buf);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vert_buf);
-    gl::GenBuffers(1, &mut idx_buf);
-    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, idx_buf);
-    gl::EnableVertexAttribArray(0);
-    gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 20, 0 as _);
-    gl::EnableVertexAttribArray(1);
-    gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, 20, 8 as _);
-    gl::EnableVertexAttribArray(2);
-    gl::VertexAttribPointer(2, 4, gl::UNSIGNED_BYTE, gl::TRUE, 20, 16 as _);
-    gl::BlendFuncSeparate(
-      gl::SRC_ALPHA,
-      gl::ONE_MINUS_SRC_ALPHA,
-      gl::ONE,
-      gl::ONE_MINUS_SRC_ALPHA,
-    );
   }
   world.add_resource(ctx);
   world.add_resource(UiRenderer {
     shader,
     vert_arr,
-    vert_buf,
-    idx_buf,
+    vert_buf: StreamBuffer::new(gl::ARRAY_BUFFER, 1 << 20),
+    idx_buf: StreamBuffer::new(gl::ELEMENT_ARRAY_BUFFER, 1 << 18),
+    // imgui's DrawVert
+    layout: VertexLayout::new()
+      .attrib(0, 2, AttribType::F32)
+      .attrib(1, 2, AttribType::F32)
+      .attrib(2, 4, AttribType::U8Norm),
     last_frame: Instant::now(),
+    style,
+    scale: 1.0,
   });
   world.add_system(stage::PRE_DRAW, imgui_predraw);
   world.add_system(stage::POST_DRAW, imgui_draw);
@@ -166,8 +210,15 @@ fn imgui_event(world: &mut World) -> Result {
       io.key_alt = modifiers.contains(Modifiers::Alt);
       io.key_super = modifiers.contains(Modifiers::Super);
     }
-    WindowEvent::Size(width, height) => {
-      io.display_size = [width as _, height as _];
+    // the framebuffer scale changes when fullscreen moves the window to another monitor
+    WindowEvent::Size(..) | WindowEvent::FramebufferSize(..) => {
+      let window = &world.get_resource::<Renderer>().unwrap().window;
+      let (w, h) = window.get_size();
+      let (fb_w, fb_h) = window.get_framebuffer_size();
+      if w > 0 && h > 0 {
+        io.display_size = [w as _, h as _];
+        io.display_framebuffer_scale = [fb_w as f32 / w as f32, fb_h as f32 / h as f32];
+      }
     }
     WindowEvent::Char(ch) => {
       if ch != '\u{7f}' {
@@ -198,6 +249,15 @@ fn imgui_event(world: &mut World) -> Result {
 fn imgui_predraw(world: &mut World) -> Result {
   let renderer = world.get_resource::<Renderer>().unwrap();
   let ctx = world.get_resource::<Context>().unwrap();
+  let r = world.get_resource::<UiRenderer>().unwrap();
+  let scale = ui_scale.get().clamp(0.25, 4.0);
+  if scale != r.scale {
+    let style = ctx.style_mut();
+    *style = r.style;
+    style.scale_all_sizes(scale);
+    ctx.io_mut().font_global_scale = scale;
+    r.scale = scale;
+  }
   let io = ctx.io();
   if io.want_set_mouse_pos {
     let [x, y] = io.mouse_pos;
@@ -218,22 +278,63 @@ fn imgui_predraw(world: &mut World) -> Result {
   Ok(())
 }
 
+// centered along the bottom over everything else
+fn draw_subtitles(world: &World, ui: &Ui) {
+  if !subtitles.get() {
+    return;
+  }
+  let lines = match world.get_resource::<Subtitles>() {
+    Some(s) => s.current(),
+    None => return,
+  };
+  if lines.is_empty() {
+    return;
+  }
+  let [w, h] = ui.io().display_size;
+  ui.window("##subtitles")
+    .position([w / 2.0, h - 32.0 * ui_scale.get()], Condition::Always)
+    .position_pivot([0.5, 1.0])
+    .bg_alpha(0.6)
+    .flags(
+      WindowFlags::NO_DECORATION
+        | WindowFlags::NO_INPUTS
+        | WindowFlags::ALWAYS_AUTO_RESIZE
+        | WindowFlags::NO_FOCUS_ON_APPEARING
+        | WindowFlags::NO_NAV,
+    )
+    .build(|| {
+      for line in lines {
+        ui.text(line);
+      }
+    });
+}
+
 fn imgui_draw(world: &mut World) -> Result {
   if let Some(ui) = world.take_resource::<imgui::Ui>() {
+    draw_subtitles(world, &ui);
     let renderer = world.get_resource::<Renderer>().unwrap();
     let r = world.get_resource::<UiRenderer>().unwrap();
     let ctx = world.get_resource::<Context>().unwrap();
+    // put back afterwards, whatever draws next shouldn't have to know the ui ran
+    let prev = renderer.state();
+    let state = RenderState {
+      depth_test: false,
+      blend: Some(BlendFunc::ALPHA),
+      cull: None,
+      ..prev
+    };
+    renderer.apply_state(state);
     unsafe {
-      gl::Enable(gl::BLEND);
-      gl::Disable(gl::DEPTH_TEST);
       gl::BindVertexArray(r.vert_arr);
       let io = ctx.io_mut();
       let now = Instant::now();
       io.update_delta_time(now - r.last_frame);
       r.last_frame = now;
+      // a cursor set on the renderer, eg an editor tool's, is shown over the ui's
       if !io
         .config_flags
         .contains(ConfigFlags::NO_MOUSE_CURSOR_CHANGE)
+        && renderer.cursor().is_none()
       {
         match ui.mouse_cursor() {
           Some(mouse_cursor) if !io.mouse_draw_cursor => {
@@ -264,42 +365,36 @@ fn imgui_draw(world: &mut World) -> Result {
 
       let draw_data = ctx.render();
       for draw_list in draw_data.draw_lists() {
-        gl::BindBuffer(gl::ARRAY_BUFFER, r.vert_buf);
-        gl::BufferData(
-          gl::ARRAY_BUFFER,
-          (draw_list.vtx_buffer().len() * 20) as _,
-          draw_list.vtx_buffer().as_ptr() as _,
-          gl::DYNAMIC_DRAW,
-        );
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, r.idx_buf);
-        gl::BufferData(
-          gl::ELEMENT_ARRAY_BUFFER,
-          (draw_list.idx_buffer().len() * 2) as _,
-          draw_list.idx_buffer().as_ptr() as _,
-          gl::DYNAMIC_DRAW,
-        );
+        // the attributes point at wherever this list landed in the ring
+        let vtx = r.vert_buf.write(draw_list.vtx_buffer(), 4);
+        r.layout.bind(vtx);
+        let idx = r.idx_buf.write(draw_list.idx_buffer(), 4);
         for cmd in draw_list.commands() {
           if let imgui::DrawCmd::Elements { count, cmd_params } = cmd {
             gl::ActiveTexture(gl::TEXTURE0);
             gl::BindTexture(gl::TEXTURE_2D, cmd_params.texture_id.id() as _);
-            gl::Scissor(
-              (cmd_params.clip_rect[0] * scale_w) as _,
-              (h * scale_h - cmd_params.clip_rect[3] * scale_h) as _,
-              ((cmd_params.clip_rect[2] - cmd_params.clip_rect[0]) * scale_w) as _,
-              ((cmd_params.clip_rect[3] - cmd_params.clip_rect[1]) * scale_h) as _,
-            );
+            renderer.apply_state(RenderState {
+              scissor: Some([
+                (cmd_params.clip_rect[0] * scale_w) as _,
+                (h * scale_h - cmd_params.clip_rect[3] * scale_h) as _,
+                ((cmd_params.clip_rect[2] - cmd_params.clip_rect[0]) * scale_w) as _,
+                ((cmd_params.clip_rect[3] - cmd_params.clip_rect[1]) * scale_h) as _,
+              ]),
+              ..state
+            });
             gl::DrawElements(
               gl::TRIANGLES,
               count as _,
               gl::UNSIGNED_SHORT,
-              (cmd_params.idx_offset * 2) as _,
+              (idx + cmd_params.idx_offset * 2) as _,
             );
           }
         }
       }
-      gl::Enable(gl::DEPTH_TEST);
-      gl::Disable(gl::BLEND);
+      r.vert_buf.fence();
+      r.idx_buf.fence();
     }
+    renderer.apply_state(prev);
   }
   Ok(())
 }
@@ -310,6 +405,95 @@ pub fn hover_tooltip(ui: &imgui::Ui, text: &str) {
   }
 }
 
+// `border` is the left, top, right and bottom edge in texels, edges keep their size and the
+// middle stretches, so a small texture can back a panel or button of any size
+pub fn nine_slice(ui: &imgui::Ui, tex: &Texture, size: [f32; 2], border: [f32; 4]) {
+  let pos = ui.cursor_screen_pos();
+  ui.dummy(size);
+  draw_nine_slice(ui, tex, pos, size, border);
+}
+
+pub fn nine_slice_button(
+  ui: &imgui::Ui,
+  id: &str,
+  tex: &Texture,
+  size: [f32; 2],
+  border: [f32; 4],
+) -> bool {
+  let pressed = ui.invisible_button(id, size);
+  draw_nine_slice(ui, tex, ui.item_rect_min(), size, border);
+  pressed
+}
+
+fn draw_nine_slice(ui: &imgui::Ui, tex: &Texture, pos: [f32; 2], size: [f32; 2], border: [f32; 4]) {
+  let (w, h) = (tex.width as f32, tex.height as f32);
+  // edges shrink evenly when the target is smaller than them
+  let sx = (size[0] / (border[0] + border[2]).max(1.0)).min(1.0);
+  let sy = (size[1] / (border[1] + border[3]).max(1.0)).min(1.0);
+  let xs = [0.0, border[0] * sx, size[0] - border[2] * sx, size[0]];
+  let ys = [0.0, border[1] * sy, size[1] - border[3] * sy, size[1]];
+  let us = [0.0, border[0] / w, 1.0 - border[2] / w, 1.0];
+  // loaded textures are stored bottom row first
+  let vs = [1.0, 1.0 - border[1] / h, border[3] / h, 0.0];
+  let draw_list = ui.get_window_draw_list();
+  for x in 0..3 {
+    for y in 0..3 {
+      draw_list
+        .add_image(
+          TextureId::new(tex.id as _),
+          [pos[0] + xs[x], pos[1] + ys[y]],
+          [pos[0] + xs[x + 1], pos[1] + ys[y + 1]],
+        )
+        .uv_min([us[x], vs[y]])
+        .uv_max([us[x + 1], vs[y + 1]])
+        .build();
+    }
+  }
+}
+
+// widgets for every declared cvar, saved to the project when an edit finishes
+pub fn cvar_tweaker(ui: &imgui::Ui) {
+  let cvars = cvars::all();
+  if cvars.is_empty() {
+    ui.text_disabled("No cvars declared.");
+    return;
+  }
+  ui.text_disabled("Right click a value to reset it.");
+  let mut edited = false;
+  for cvar in cvars {
+    let _id = ui.push_id(cvar.name());
+    match cvar.value() {
+      CVarValue::Bool(mut v) => {
+        if ui.checkbox(cvar.name(), &mut v) {
+          cvar.set_value(CVarValue::Bool(v));
+          edited = true;
+        }
+      }
+      CVarValue::I32(mut v) => {
+        if imgui::Drag::new(cvar.name()).build(ui, &mut v) {
+          cvar.set_value(CVarValue::I32(v));
+        }
+        edited |= ui.is_item_deactivated_after_edit();
+      }
+      CVarValue::F32(mut v) => {
+        if imgui::Drag::new(cvar.name()).speed(0.01).build(ui, &mut v) {
+          cvar.set_value(CVarValue::F32(v));
+        }
+        edited |= ui.is_item_deactivated_after_edit();
+      }
+    }
+    if ui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+      cvar.reset();
+      edited = true;
+    }
+  }
+  if edited {
+    if let Err(e) = cvars::save(CVARS_PATH) {
+      error!("Couldn't save cvars. {}", e);
+    }
+  }
+}
+
 pub fn theme_dark(style: &mut Style) {
   style[StyleColor::Text] = [1.00, 1.00, 1.00, 1.00];
   style[StyleColor::TextDisabled] = [0.50, 0.50, 0.50, 1.00];
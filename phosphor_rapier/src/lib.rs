@@ -1,16 +1,18 @@
-#![feature(vec_into_raw_parts)]
 use phosphor::{Result, DeltaTime, component};
 use phosphor::ecs::{World, stage};
-use phosphor::math::{Vec3, Mat4};
+use phosphor::math::Vec3;
 use phosphor::log::debug;
 use phosphor::gfx::Mesh;
-use phosphor_3d::{Transform, Camera};
-use phosphor_imgui::imgui::{Ui, draw_list::DrawListMut};
+use phosphor_3d::{Transform, DebugDraw};
 use rapier3d::prelude::*;
 use rapier3d::dynamics::{RigidBody as RapierRigidBody, RigidBodyBuilder as RapierRigidBodyBuilder};
 use rapier3d::geometry::{Collider as RapierCollider, ColliderBuilder as RapierColliderBuilder};
 use serde::{Serialize, Deserialize};
 
+mod vehicle;
+mod ragdoll;
+pub use vehicle::{Vehicle, Wheel};
+pub use ragdoll::Ragdoll;
 pub use rapier3d;
 
 pub struct Gravity(pub Vec3);
@@ -58,9 +60,46 @@ impl RigidBody {
   }
 }
 
+// how a collider's surface behaves in contacts, when two colliders' combine rules differ the later
+// one in Average, Min, Multiply, Max is used, so Min lets ice stay slippery on anything
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct PhysicsMaterial {
+  pub friction: f32,
+  pub restitution: f32,
+  pub friction_combine: CoefficientCombineRule,
+  pub restitution_combine: CoefficientCombineRule,
+}
+
+impl PhysicsMaterial {
+  // rapier's defaults
+  pub const DEFAULT: Self = Self {
+    friction: 0.5,
+    restitution: 0.0,
+    friction_combine: CoefficientCombineRule::Average,
+    restitution_combine: CoefficientCombineRule::Average,
+  };
+
+  pub fn friction(mut self, friction: f32, combine: CoefficientCombineRule) -> Self {
+    self.friction = friction;
+    self.friction_combine = combine;
+    self
+  }
+
+  pub fn restitution(mut self, restitution: f32, combine: CoefficientCombineRule) -> Self {
+    self.restitution = restitution;
+    self.restitution_combine = combine;
+    self
+  }
+}
+
+pub const ALL_LAYERS: u32 = u32::MAX;
+
 pub struct ColliderBuilder {
   b: RapierColliderBuilder,
   rb: Option<RigidBody>,
+  material: PhysicsMaterial,
+  layers: u32,
+  mask: u32,
 }
 
 impl ColliderBuilder {
@@ -68,6 +107,9 @@ impl ColliderBuilder {
     ColliderBuilder {
       b: RapierColliderBuilder::ball(r),
       rb: None,
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
     }
   }
 
@@ -75,6 +117,9 @@ impl ColliderBuilder {
     ColliderBuilder {
       b: RapierColliderBuilder::cuboid(x, y, z),
       rb: None,
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
     }
   }
 
@@ -82,6 +127,9 @@ impl ColliderBuilder {
     ColliderBuilder {
       b: RapierColliderBuilder::cone(h, r),
       rb: None,
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
     }
   }
 
@@ -89,29 +137,34 @@ impl ColliderBuilder {
     Self {
       b: RapierColliderBuilder::convex_hull(
         mesh
-          .vertices
+          .positions
           .iter()
-          .map(|v| v.pos.into())
+          .map(|v| v.to_array().into())
           .collect::<Vec<_>>()
           .as_slice(),
       )
       .unwrap(),
       rb: None,
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
     }
   }
 
   pub fn trimesh(mesh: &Mesh) -> Self {
-    let v = mesh.indices.clone().into_raw_parts();
     Self {
       b: RapierColliderBuilder::trimesh(
         mesh
-          .vertices
+          .positions
           .iter()
-          .map(|v| v.pos.into())
+          .map(|v| v.to_array().into())
           .collect::<Vec<_>>(),
-        unsafe { Vec::from_raw_parts(v.0 as _, v.1 / 3, v.2 / 3) },
+        mesh.triangles(),
       ),
       rb: None,
+      material: PhysicsMaterial::DEFAULT,
+      layers: ALL_LAYERS,
+      mask: ALL_LAYERS,
     }
   }
 
@@ -125,18 +178,35 @@ impl ColliderBuilder {
     self
   }
 
+  pub fn material(mut self, material: PhysicsMaterial) -> Self {
+    self.material = material;
+    self
+  }
+
+  // see Collider::layers
+  pub fn layers(mut self, layers: u32, mask: u32) -> Self {
+    self.layers = layers;
+    self.mask = mask;
+    self
+  }
+
   pub fn build(self, world: &World) -> Collider {
     let set = world.get_resource::<ColliderSet>().unwrap();
-    Collider {
-      handle: match self.rb {
-        Some(rb) => set.insert_with_parent(
-          self.b.build(),
-          rb.handle,
-          world.get_resource::<RigidBodySet>().unwrap(),
-        ),
-        None => set.insert(self.b.build()),
-      },
-    }
+    let mut collider = Collider {
+      handle: ColliderHandle::invalid(),
+      material: self.material,
+      layers: self.layers,
+      mask: self.mask,
+    };
+    let mut c = self.b.build();
+    collider.apply(&mut c);
+    collider.handle = match self.rb {
+      Some(rb) => {
+        set.insert_with_parent(c, rb.handle, world.get_resource::<RigidBodySet>().unwrap())
+      }
+      None => set.insert(c),
+    };
+    collider
   }
 }
 
@@ -144,9 +214,39 @@ impl ColliderBuilder {
 #[component]
 pub struct Collider {
   pub handle: ColliderHandle,
+  pub material: PhysicsMaterial,
+  // two colliders touch when each one's layers overlap the other's mask, so a projectile can
+  // leave the trigger layer out of its mask to pass through triggers
+  pub layers: u32,
+  pub mask: u32,
 }
 
 impl Collider {
+  // copies the material and layers onto rapier's collider, only what changed is set so contacts
+  // aren't recomputed every frame
+  fn apply(&self, c: &mut RapierCollider) {
+    let m = &self.material;
+    if c.friction() != m.friction {
+      c.set_friction(m.friction);
+    }
+    if c.restitution() != m.restitution {
+      c.set_restitution(m.restitution);
+    }
+    if c.friction_combine_rule() != m.friction_combine {
+      c.set_friction_combine_rule(m.friction_combine);
+    }
+    if c.restitution_combine_rule() != m.restitution_combine {
+      c.set_restitution_combine_rule(m.restitution_combine);
+    }
+    let groups = InteractionGroups::new(
+      Group::from_bits_truncate(self.layers),
+      Group::from_bits_truncate(self.mask),
+    );
+    if c.collision_groups() != groups {
+      c.set_collision_groups(groups);
+    }
+  }
+
   pub fn get<'s>(&'s self, world: &'s World) -> &mut RapierCollider {
     world
       .get_resource::<ColliderSet>()
@@ -167,7 +267,9 @@ pub fn rapier_plugin(world: &mut World) -> Result {
   world.add_resource(ImpulseJointSet::new());
   world.add_resource(MultibodyJointSet::new());
   world.add_resource(CCDSolver::new());
+  world.add_resource(QueryPipeline::new());
   world.add_system(stage::PRE_DRAW, rapier_update);
+  world.add_system(stage::PRE_DRAW, ragdoll::ragdoll_update);
   debug!("Initialized Rapier {}.", rapier3d::VERSION);
   Ok(())
 }
@@ -191,12 +293,17 @@ fn rapier_update(world: &mut World) -> Result {
   let impulse_joint_set = world.get_resource::<ImpulseJointSet>().unwrap();
   let multibody_joint_set = world.get_resource::<MultibodyJointSet>().unwrap();
   let ccd_solver = world.get_resource::<CCDSolver>().unwrap();
+  let query_pipeline = world.get_resource::<QueryPipeline>().unwrap();
+  let dt = world.get_resource::<DeltaTime>().unwrap().0;
   for (e, collider) in world.query::<Collider>() {
-    if e.get_one::<RigidBody>().is_none() {
-      if let Some(t) = e.get_one::<Transform>() {
-        let collider = collider.get(world);
-        collider.set_translation(t.position.into());
-        collider.set_rotation(t.rotation.into());
+    // colliders added in the editor without the plugin have no rapier collider
+    if let Some(c) = collider_set.get_mut(collider.handle) {
+      collider.apply(c);
+      if !e.has::<RigidBody>() {
+        if let Some(t) = e.get_one::<Transform>() {
+          c.set_translation(t.position.into());
+          c.set_rotation(t.rotation.into());
+        }
       }
     }
   }
@@ -207,10 +314,16 @@ fn rapier_update(world: &mut World) -> Result {
       rb.set_rotation(t.rotation.into(), true);
     }
   }
+  // wheels are cast against last step's colliders
+  for (e, vehicle) in world.query::<Vehicle>() {
+    if let Some(rb) = e.get_one::<RigidBody>() {
+      vehicle.update(dt, rb.handle, rb_set, collider_set, query_pipeline);
+    }
+  }
   physics_pipeline.step(
     &gravity.0.into(),
     &IntegrationParameters {
-      dt: world.get_resource::<DeltaTime>().unwrap().0,
+      dt,
       ..Default::default()
     },
     island_manager,
@@ -221,12 +334,12 @@ fn rapier_update(world: &mut World) -> Result {
     impulse_joint_set,
     multibody_joint_set,
     ccd_solver,
-    None,
+    Some(query_pipeline),
     &(),
     &(),
   );
   for (e, rb) in world.query::<RigidBody>() {
-    if let Some(t) = e.get_one::<Transform>() {
+    if let Some(t) = e.modify::<Transform>() {
       let rb = rb.get(world);
       t.position = (*rb.translation()).into();
       t.rotation = (*rb.rotation()).into();
@@ -235,35 +348,30 @@ fn rapier_update(world: &mut World) -> Result {
   Ok(())
 }
 
-struct DebugRenderer<'d> {
-  ui: DrawListMut<'d>,
-  size: [f32; 2],
-  view: Mat4,
-  proj: Mat4,
-}
+struct DebugRenderer<'d>(&'d mut DebugDraw);
 
 impl DebugRenderBackend for DebugRenderer<'_> {
   fn draw_line(&mut self, _: DebugRenderObject, a: Point<Real>, b: Point<Real>, color: [f32; 4]) {
-    let mut a = self
-      .proj
-      .project_point3(self.view.transform_point3(a.into()));
-    if a.x > 1.0 || a.x < -1.0 || a.y > 1.0 || a.y < -1.0 || a.z > 1.0 {
-      return;
-    }
-    let mut b = self
-      .proj
-      .project_point3(self.view.transform_point3(b.into()));
-    if b.x > 1.0 || b.x < -1.0 || b.y > 1.0 || b.y < -1.0 || b.z > 1.0 {
-      return;
-    }
-    a.x = (a.x / 2.0 + 0.5) * self.size[0];
-    a.y = (a.y / -2.0 + 0.5) * self.size[1];
-    b.x = (b.x / 2.0 + 0.5) * self.size[0];
-    b.y = (b.y / -2.0 + 0.5) * self.size[1];
-    self.ui.add_line([a.x, a.y], [b.x, b.y], color).build();
+    self.0.line(a.into(), b.into(), hsl_to_rgb(color));
   }
 }
 
+// rapier's debug colors are hue in degrees, saturation, lightness and alpha
+fn hsl_to_rgb([h, s, l, _]: [f32; 4]) -> Vec3 {
+  let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+  let h = h.rem_euclid(360.0) / 60.0;
+  let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+  let (r, g, b) = match h as u32 {
+    0 => (c, x, 0.0),
+    1 => (x, c, 0.0),
+    2 => (0.0, c, x),
+    3 => (0.0, x, c),
+    4 => (x, 0.0, c),
+    _ => (c, 0.0, x),
+  };
+  Vec3::new(r, g, b) + Vec3::splat(l - c / 2.0)
+}
+
 fn debug_update(world: &mut World) -> Result {
   let debug_pipeline = world.get_resource::<DebugRenderPipeline>().unwrap();
   let rb_set = world.get_resource::<RigidBodySet>().unwrap();
@@ -271,18 +379,8 @@ fn debug_update(world: &mut World) -> Result {
   let impulse_joint_set = world.get_resource::<ImpulseJointSet>().unwrap();
   let multibody_joint_set = world.get_resource::<MultibodyJointSet>().unwrap();
   let narrow_phase = world.get_resource::<NarrowPhase>().unwrap();
-  let ui = world.get_resource::<Ui>().unwrap();
-  let size = ui.io().display_size;
-  let (e, cam) = &world.query::<Camera>()[0];
-  let cam_t = e.get_one::<Transform>().unwrap();
-  let (view, proj) = cam.matrices(cam_t, size[0] / size[1]);
   debug_pipeline.render(
-    &mut DebugRenderer {
-      ui: ui.get_background_draw_list(),
-      size,
-      view,
-      proj,
-    },
+    &mut DebugRenderer(world.get_resource::<DebugDraw>().unwrap()),
     rb_set,
     collider_set,
     impulse_joint_set,
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use phosphor::{Result, component};
+use phosphor::ecs::{World, Entity};
+use phosphor::math::{Vec3, Quat, Mat4};
+use phosphor_3d::Transform;
+use rapier3d::prelude::*;
+use serde::{Serialize, Deserialize};
+
+struct Bone {
+  entity: Entity,
+  // index of the parent bone, none for bones directly under the ragdoll
+  parent: Option<usize>,
+  body: RigidBodyHandle,
+  // the animated local position and rotation, held while the ragdoll has any weight
+  pose: (Vec3, Quat),
+}
+
+// turns every entity below this one into a bone, each gets a capsule reaching to its children and
+// a ball joint to its parent bone. bones follow their transforms kinematically at 0 weight, as
+// weight rises they blend towards the simulation from the last animated pose
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Ragdoll {
+  // 0 is animated, 1 is fully simulated
+  pub weight: f32,
+  // capsule radius as a fraction of the bone's length
+  pub thickness: f32,
+  // degrees each joint can bend from its rest pose on every axis
+  pub joint_limit: f32,
+  #[serde(skip)]
+  bones: Vec<Bone>,
+}
+
+// drops scale, bodies can't have any
+fn isometry(m: Mat4) -> Isometry<Real> {
+  let (_, rotation, position) = m.to_scale_rotation_translation();
+  Isometry::from_parts(Vector::from(position).into(), rotation.into())
+}
+
+fn bone_mat4(e: Entity) -> Mat4 {
+  match e.get_one::<Transform>() {
+    Some(t) => t.global_mat4(e),
+    None => Mat4::IDENTITY,
+  }
+}
+
+impl Ragdoll {
+  pub fn new() -> Self {
+    Self {
+      weight: 0.0,
+      thickness: 0.2,
+      joint_limit: 45.0,
+      bones: vec![],
+    }
+  }
+
+  pub fn weight(mut self, weight: f32) -> Self {
+    self.weight = weight;
+    self
+  }
+
+  // pushes a bone, eg where a projectile hit, does nothing until the ragdoll has weight
+  pub fn impulse(&self, world: &World, bone: Entity, impulse: Vec3, point: Vec3) {
+    if let Some(b) = self.bones.iter().find(|b| b.entity.id == bone.id) {
+      let bodies = world.get_resource::<RigidBodySet>().unwrap();
+      bodies[b.body].apply_impulse_at_point(impulse.into(), point.into(), true);
+    }
+  }
+
+  // removes the bodies, they're built again from the current hierarchy on the next update
+  pub fn clear(&mut self, world: &World) {
+    let bodies = world.get_resource::<RigidBodySet>().unwrap();
+    for b in self.bones.drain(..) {
+      bodies.remove(
+        b.body,
+        world.get_resource::<IslandManager>().unwrap(),
+        world.get_resource::<ColliderSet>().unwrap(),
+        world.get_resource::<ImpulseJointSet>().unwrap(),
+        world.get_resource::<MultibodyJointSet>().unwrap(),
+        true,
+      );
+    }
+  }
+
+  fn build(&mut self, world: &World, root: Entity) {
+    let bodies = world.get_resource::<RigidBodySet>().unwrap();
+    let colliders = world.get_resource::<ColliderSet>().unwrap();
+    let joints = world.get_resource::<ImpulseJointSet>().unwrap();
+    let limit = self.joint_limit.to_radians();
+    let mut radii = vec![];
+    // breadth first so parents come before their children
+    let mut queue = root
+      .children()
+      .into_iter()
+      .map(|e| (e, None))
+      .collect::<VecDeque<_>>();
+    while let Some((e, parent)) = queue.pop_front() {
+      let pose = match e.get_one::<Transform>() {
+        Some(t) => (t.position, t.rotation),
+        None => continue,
+      };
+      let mat = bone_mat4(e);
+      let iso = isometry(mat);
+      let body = bodies.insert(RigidBodyBuilder::kinematic_position_based().position(iso));
+      // children's positions in the bone's unscaled space
+      let ends = e
+        .children()
+        .iter()
+        .map(|c| iso.inverse_transform_point(&bone_mat4(*c).w_axis.truncate().into()))
+        .collect::<Vec<_>>();
+      let end = match ends.is_empty() {
+        true => None,
+        false => Some(ends.iter().fold(Point::origin(), |a, p| a + p.coords) / ends.len() as f32),
+      };
+      let shape = match end {
+        Some(end) if end.coords.norm() > 0.001 => {
+          radii.push(end.coords.norm() * self.thickness);
+          SharedShape::capsule(Point::origin(), end, radii[radii.len() - 1])
+        }
+        // leaves get a ball as thick as their parent
+        _ => {
+          radii.push(parent.map_or(0.1, |p: usize| radii[p]));
+          SharedShape::ball(radii[radii.len() - 1])
+        }
+      };
+      colliders.insert_with_parent(ColliderBuilder::new(shape).build(), body, bodies);
+      if let Some(p) = parent {
+        let parent_iso = *bodies[self.bones[p].body].position();
+        let anchor = parent_iso.inverse_transform_point(&iso.translation.vector.into());
+        let mut joint = SphericalJointBuilder::new()
+          .local_anchor1(anchor)
+          .local_anchor2(Point::origin())
+          .contacts_enabled(false);
+        for axis in [JointAxis::AngX, JointAxis::AngY, JointAxis::AngZ] {
+          joint = joint.limits(axis, [-limit, limit]);
+        }
+        joints.insert(self.bones[p].body, body, joint, true);
+      }
+      let index = self.bones.len();
+      self.bones.push(Bone {
+        entity: e,
+        parent,
+        body,
+        pose,
+      });
+      queue.extend(e.children().into_iter().map(|c| (c, Some(index))));
+    }
+  }
+
+  fn update(&mut self, world: &World, root: Entity) {
+    if self.bones.is_empty() {
+      self.build(world, root);
+    }
+    let bodies = world.get_resource::<RigidBodySet>().unwrap();
+    let weight = self.weight.clamp(0.0, 1.0);
+    if weight == 0.0 {
+      // animated, the bodies chase the bones so they start from the right pose and speed
+      for b in &mut self.bones {
+        if let Some(t) = b.entity.get_one::<Transform>() {
+          b.pose = (t.position, t.rotation);
+        }
+        let body = &mut bodies[b.body];
+        if body.body_type() != RigidBodyType::KinematicPositionBased {
+          body.set_body_type(RigidBodyType::KinematicPositionBased, true);
+        }
+        body.set_next_kinematic_position(isometry(bone_mat4(b.entity)));
+      }
+      return;
+    }
+    let root_mat = bone_mat4(root);
+    for i in 0..self.bones.len() {
+      let b = &self.bones[i];
+      let body = &mut bodies[b.body];
+      if body.body_type() != RigidBodyType::Dynamic {
+        body.set_body_type(RigidBodyType::Dynamic, true);
+      }
+      let parent = match b.parent {
+        Some(p) => body_mat4(bodies, self.bones[p].body),
+        None => root_mat,
+      };
+      let local = parent.inverse() * body_mat4(bodies, b.body);
+      let (_, rotation, position) = local.to_scale_rotation_translation();
+      if let Some(t) = b.entity.modify::<Transform>() {
+        t.position = b.pose.0.lerp(position, weight);
+        t.rotation = b.pose.1.slerp(rotation, weight);
+      }
+    }
+  }
+}
+
+fn body_mat4(bodies: &RigidBodySet, body: RigidBodyHandle) -> Mat4 {
+  let iso = bodies[body].position();
+  Mat4::from_rotation_translation(Quat::from(iso.rotation), Vec3::from(iso.translation.vector))
+}
+
+pub(crate) fn ragdoll_update(world: &mut World) -> Result {
+  for (e, ragdoll) in world.query::<Ragdoll>() {
+    ragdoll.update(world, e);
+  }
+  Ok(())
+}
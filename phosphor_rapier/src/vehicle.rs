@@ -0,0 +1,189 @@
+use phosphor::component;
+use phosphor::math::Vec3;
+use rapier3d::prelude::*;
+use rapier3d::control::{DynamicRayCastVehicleController, WheelTuning};
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Wheel {
+  // chassis space, where the top of the suspension is attached
+  pub position: Vec3,
+  pub radius: f32,
+  // how far below `position` the wheel's center rests
+  pub rest_length: f32,
+  // how far the suspension can compress or extend from rest
+  pub max_travel: f32,
+  pub stiffness: f32,
+  pub compression: f32,
+  pub relaxation: f32,
+  // higher grips harder, too high can flip the vehicle under braking
+  pub friction_slip: f32,
+  pub steered: bool,
+  pub driven: bool,
+}
+
+impl Wheel {
+  pub fn new(position: Vec3) -> Self {
+    let tuning = WheelTuning::default();
+    Self {
+      position,
+      radius: 0.35,
+      rest_length: 0.3,
+      max_travel: 0.2,
+      stiffness: tuning.suspension_stiffness,
+      compression: tuning.suspension_compression,
+      relaxation: tuning.suspension_damping,
+      friction_slip: tuning.friction_slip,
+      steered: false,
+      driven: false,
+    }
+  }
+
+  pub fn steered(mut self, steered: bool) -> Self {
+    self.steered = steered;
+    self
+  }
+
+  pub fn driven(mut self, driven: bool) -> Self {
+    self.driven = driven;
+    self
+  }
+}
+
+// a raycast vehicle driving the entity's RigidBody, wheels are rays cast down the chassis' -y and
+// it drives towards -z. game code sets the inputs each frame
+#[derive(Serialize, Deserialize)]
+#[component]
+pub struct Vehicle {
+  pub wheels: Vec<Wheel>,
+  // at full throttle, split between driven wheels
+  pub engine_force: f32,
+  pub brake_force: f32,
+  // degrees
+  pub max_steer: f32,
+  // -1 to 1, negative reverses
+  pub throttle: f32,
+  // 0 to 1
+  pub brake: f32,
+  // -1 to 1, positive turns right
+  pub steer: f32,
+  #[serde(skip)]
+  controller: Option<DynamicRayCastVehicleController>,
+  #[serde(skip)]
+  lengths: Vec<f32>,
+  // rapier only takes friction slip when adding a wheel
+  #[serde(skip)]
+  built_slip: Vec<f32>,
+}
+
+impl Vehicle {
+  pub fn new(wheels: Vec<Wheel>) -> Self {
+    Self {
+      wheels,
+      engine_force: 30.0,
+      brake_force: 1.0,
+      max_steer: 30.0,
+      throttle: 0.0,
+      brake: 0.0,
+      steer: 0.0,
+      controller: None,
+      lengths: vec![],
+      built_slip: vec![],
+    }
+  }
+
+  // front wheels steer and rear wheels drive, measured between wheel centers
+  pub fn four_wheels(width: f32, length: f32) -> Self {
+    let (x, z) = (width / 2.0, length / 2.0);
+    Self::new(vec![
+      Wheel::new(Vec3::new(-x, 0.0, -z)).steered(true),
+      Wheel::new(Vec3::new(x, 0.0, -z)).steered(true),
+      Wheel::new(Vec3::new(-x, 0.0, z)).driven(true),
+      Wheel::new(Vec3::new(x, 0.0, z)).driven(true),
+    ])
+  }
+
+  // forward speed in units per second, negative when reversing
+  pub fn speed(&self) -> f32 {
+    self
+      .controller
+      .as_ref()
+      .map_or(0.0, |c| -c.current_vehicle_speed)
+  }
+
+  // current distance from the wheel's attachment to its center, rest length until simulated
+  pub fn suspension_length(&self, wheel: usize) -> f32 {
+    match self.lengths.get(wheel) {
+      Some(l) => *l,
+      None => self.wheels[wheel].rest_length,
+    }
+  }
+
+  pub(crate) fn update(
+    &mut self,
+    dt: f32,
+    chassis: RigidBodyHandle,
+    bodies: &mut RigidBodySet,
+    colliders: &ColliderSet,
+    queries: &QueryPipeline,
+  ) {
+    // rebuilt when the wheels or their friction slip change, everything else is copied each frame
+    let slip = self
+      .wheels
+      .iter()
+      .map(|w| w.friction_slip)
+      .collect::<Vec<_>>();
+    let controller = match &mut self.controller {
+      Some(c) if c.chassis == chassis && self.built_slip == slip => c,
+      c => {
+        let mut controller = DynamicRayCastVehicleController::new(chassis);
+        controller.index_forward_axis = 2;
+        for w in &self.wheels {
+          controller.add_wheel(
+            w.position.into(),
+            -Vector::y(),
+            Vector::x(),
+            w.rest_length,
+            w.radius,
+            &WheelTuning {
+              friction_slip: w.friction_slip,
+              ..Default::default()
+            },
+          );
+        }
+        self.built_slip = slip;
+        c.insert(controller)
+      }
+    };
+    let driven = self.wheels.iter().filter(|w| w.driven).count().max(1) as f32;
+    let steer = -self.steer.clamp(-1.0, 1.0) * self.max_steer.to_radians();
+    for (wheel, w) in controller.wheels_mut().iter_mut().zip(&self.wheels) {
+      wheel.chassis_connection_point_cs = w.position.into();
+      wheel.radius = w.radius;
+      wheel.suspension_rest_length = w.rest_length;
+      wheel.max_suspension_travel = w.max_travel;
+      wheel.suspension_stiffness = w.stiffness;
+      wheel.damping_compression = w.compression;
+      wheel.damping_relaxation = w.relaxation;
+      wheel.steering = if w.steered { steer } else { 0.0 };
+      wheel.engine_force = match w.driven {
+        true => self.throttle.clamp(-1.0, 1.0) * self.engine_force / driven,
+        false => 0.0,
+      };
+      wheel.brake = self.brake.clamp(0.0, 1.0) * self.brake_force;
+    }
+    controller.update_vehicle(
+      dt,
+      bodies,
+      colliders,
+      queries,
+      QueryFilter::new().exclude_rigid_body(chassis),
+    );
+    let position = bodies[chassis].position();
+    self.lengths = controller
+      .wheels()
+      .iter()
+      .map(|w| (w.center() - position * w.chassis_connection_point_cs).norm())
+      .collect();
+  }
+}
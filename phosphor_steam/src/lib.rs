@@ -0,0 +1,126 @@
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use steamworks::{Client, SingleClient, AppId, CallbackHandle, GameOverlayActivated};
+use phosphor::Result;
+use phosphor::ecs::{World, stage};
+use phosphor::log::{debug, warn};
+
+pub use steamworks;
+
+pub struct Steam {
+  pub client: Client,
+  single: SingleClient,
+  overlay: Arc<AtomicBool>,
+  _overlay_cb: CallbackHandle,
+}
+
+impl Steam {
+  // has to happen before the engine runs, the overlay hooks the gl context as it's created so
+  // anything made earlier never shows it. returns none when the game wasn't launched through steam
+  // and steam is starting it again, the process should exit straight away
+  pub fn init(app_id: u32) -> Result<Option<Self>> {
+    if Client::restart_app_if_necessary(AppId(app_id)) {
+      return Ok(None);
+    }
+    let (client, single) = Client::init_app(app_id)?;
+    let overlay = Arc::new(AtomicBool::new(false));
+    let active = overlay.clone();
+    let _overlay_cb = client.register_callback(move |e: GameOverlayActivated| {
+      active.store(e.active, Ordering::Relaxed);
+    });
+    client.user_stats().request_current_stats();
+    debug!(
+      "Initialized Steam for app {} as '{}'.",
+      app_id,
+      client.friends().name()
+    );
+    Ok(Some(Self {
+      client,
+      single,
+      overlay,
+      _overlay_cb,
+    }))
+  }
+
+  // games should pause and free the cursor while this is true
+  pub fn overlay_active(&self) -> bool {
+    self.overlay.load(Ordering::Relaxed)
+  }
+
+  // stats are stored straight away so the unlock popup shows
+  pub fn unlock(&self, achievement: &str) -> Result {
+    let stats = self.client.user_stats();
+    if stats.achievement(achievement).set().is_err() || stats.store_stats().is_err() {
+      return Err(format!("Couldn't unlock achievement '{}'.", achievement).into());
+    }
+    Ok(())
+  }
+
+  pub fn unlocked(&self, achievement: &str) -> bool {
+    let stats = self.client.user_stats();
+    stats.achievement(achievement).get().unwrap_or(false)
+  }
+
+  pub fn clear_achievement(&self, achievement: &str) -> Result {
+    let stats = self.client.user_stats();
+    if stats.achievement(achievement).clear().is_err() || stats.store_stats().is_err() {
+      return Err(format!("Couldn't clear achievement '{}'.", achievement).into());
+    }
+    Ok(())
+  }
+
+  // keys are set up on the steamworks site, `steam_display` picks the localized string shown
+  pub fn set_presence(&self, key: &str, value: &str) {
+    if !self.client.friends().set_rich_presence(key, Some(value)) {
+      warn!("Rich presence key '{}' was rejected.", key);
+    }
+  }
+
+  pub fn clear_presence(&self) {
+    self.client.friends().clear_rich_presence();
+  }
+
+  pub fn cloud_enabled(&self) -> bool {
+    let storage = self.client.remote_storage();
+    storage.is_cloud_enabled_for_account() && storage.is_cloud_enabled_for_app()
+  }
+
+  pub fn save(&self, name: &str, data: &[u8]) -> Result {
+    let mut file = self.client.remote_storage().file(name).write();
+    file.write_all(data)?;
+    Ok(())
+  }
+
+  // none when the file hasn't been saved
+  pub fn load(&self, name: &str) -> Result<Option<Vec<u8>>> {
+    let file = self.client.remote_storage().file(name);
+    if !file.exists() {
+      return Ok(None);
+    }
+    let mut data = vec![];
+    file.read().read_to_end(&mut data)?;
+    Ok(Some(data))
+  }
+
+  pub fn delete(&self, name: &str) -> bool {
+    self.client.remote_storage().file(name).delete()
+  }
+}
+
+pub fn steam_plugin(world: &mut World) -> Result {
+  if world.get_resource::<Steam>().is_none() {
+    return Err("Steam has to be added as a resource before the engine runs.".into());
+  }
+  world.add_system(stage::PRE_DRAW, steam_predraw);
+  Ok(())
+}
+
+fn steam_predraw(world: &mut World) -> Result {
+  world
+    .get_resource::<Steam>()
+    .unwrap()
+    .single
+    .run_callbacks();
+  Ok(())
+}